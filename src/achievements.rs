@@ -0,0 +1,81 @@
+//! Feats the player is congratulated on once per run, and which are listed in
+//! the morgue file.
+use crate::{events::GameEvent, stats::Stats};
+use serde::{Deserialize, Serialize};
+
+// kills it takes to earn the slaughter feat
+const SLAUGHTER_KILLS: i32 = 100;
+// experience level it takes to earn the veteran feat
+const VETERAN_LEVEL: i32 = 10;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Achievement {
+    FirstBlood,
+    Slaughter,
+    Bossbane,
+    Veteran,
+    Freefall,
+}
+
+impl Achievement {
+    pub fn name(self) -> &'static str {
+        match self {
+            Achievement::FirstBlood => "First Blood: killed a monster",
+            Achievement::Slaughter => "Slaughter: killed a hundred monsters",
+            Achievement::Bossbane => "Bossbane: slew the guardian of a branch",
+            Achievement::Veteran => "Veteran: reached experience level 10",
+            Achievement::Freefall => "Freefall: fell down a chasm",
+        }
+    }
+
+    /// whether the event, with the run statistics it was recorded in, earns this
+    fn earned_by(self, event: &GameEvent, stats: &Stats) -> bool {
+        match (self, event) {
+            (Achievement::FirstBlood, GameEvent::MonsterDied { by_player, .. }) => *by_player,
+            (Achievement::Slaughter, GameEvent::MonsterDied { .. }) => {
+                stats.total_kills() >= SLAUGHTER_KILLS
+            }
+            (
+                Achievement::Bossbane,
+                GameEvent::MonsterDied {
+                    by_player, boss, ..
+                },
+            ) => *by_player && *boss,
+            (Achievement::Veteran, GameEvent::LevelUp(level)) => *level >= VETERAN_LEVEL,
+            (Achievement::Freefall, GameEvent::FellDown) => true,
+            _ => false,
+        }
+    }
+}
+
+const ALL: &[Achievement] = &[
+    Achievement::FirstBlood,
+    Achievement::Slaughter,
+    Achievement::Bossbane,
+    Achievement::Veteran,
+    Achievement::Freefall,
+];
+
+/// The achievements earned so far in the run
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Achievements {
+    earned: Vec<Achievement>,
+}
+
+impl Achievements {
+    /// the achievements the event earns for the first time
+    pub fn record(&mut self, event: &GameEvent, stats: &Stats) -> Vec<Achievement> {
+        let new: Vec<Achievement> = ALL
+            .iter()
+            .cloned()
+            .filter(|&achievement| !self.earned.contains(&achievement))
+            .filter(|&achievement| achievement.earned_by(event, stats))
+            .collect();
+        self.earned.extend(&new);
+        new
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Achievement> + '_ {
+        self.earned.iter().cloned()
+    }
+}
@@ -1,9 +1,110 @@
+//! How monsters behave. A monster has a behaviour of its own (`Basic`, `Ranged`,
+//! `Patrol` or `Wander`) and may be put in a passing state on top of it (`Confused`,
+//! `Asleep`, `Flee`, `Ally`), which remembers the behaviour to go back to. The rules for
+//! moving between states live here, so passing states never pile up on each other
+//! and a save always holds at most one of them.
+use crate::rect::Rect;
 use serde::{Deserialize, Serialize};
+
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Ai {
     Basic,
+    /// keeps its distance and shoots from behind the melee fighters
+    Ranged,
+    /// walks a round between waypoints until it spots an enemy
+    Patrol {
+        waypoints: Vec<(i32, i32)>,
+        /// index of the waypoint being walked to
+        next: usize,
+    },
+    /// ambles about the room it keeps to until it spots an enemy
+    Wander {
+        home: Rect,
+    },
     Confused {
         previous_ai: Box<Ai>,
         num_turns: i32,
+        /// whether the player did the confusing, and so shares in its kills
+        #[serde(default)]
+        by_player: bool,
+    },
+    /// sleeps until the player is noticed or a noise wakes it
+    Asleep {
+        previous_ai: Box<Ai>,
+    },
+    /// runs away from the player until its nerve returns
+    Flee {
+        previous_ai: Box<Ai>,
+        num_turns: i32,
     },
+    /// charmed into fighting for the player until the charm wears off
+    Ally {
+        previous_ai: Box<Ai>,
+        /// the turn the charm wears off on
+        until: i32,
+    },
+}
+
+impl Ai {
+    /// the behaviour underneath any passing state
+    pub fn base(self) -> Ai {
+        match self {
+            Ai::Confused { previous_ai, .. }
+            | Ai::Asleep { previous_ai }
+            | Ai::Flee { previous_ai, .. }
+            | Ai::Ally { previous_ai, .. } => previous_ai.base(),
+            ai => ai,
+        }
+    }
+
+    pub fn is_asleep(&self) -> bool {
+        matches!(self, Ai::Asleep { .. })
+    }
+
+    /// fall asleep, to wake up behaving as before
+    pub fn fall_asleep(self) -> Ai {
+        Ai::Asleep {
+            previous_ai: Box::new(self.base()),
+        }
+    }
+
+    /// stumble around for a while, waking whoever was asleep. Confusing the
+    /// already confused only prolongs it
+    pub fn confuse(self, num_turns: i32, by_player: bool) -> Ai {
+        let num_turns = match &self {
+            Ai::Confused {
+                num_turns: left, ..
+            } => num_turns.max(*left),
+            _ => num_turns,
+        };
+        Ai::Confused {
+            previous_ai: Box::new(self.base()),
+            num_turns,
+            by_player,
+        }
+    }
+
+    /// turn tail for a while
+    pub fn frighten(self, num_turns: i32) -> Ai {
+        Ai::Flee {
+            previous_ai: Box::new(self.base()),
+            num_turns,
+        }
+    }
+
+    /// side with the player until the turn given
+    pub fn charm(self, until: i32) -> Ai {
+        Ai::Ally {
+            previous_ai: Box::new(self.base()),
+            until,
+        }
+    }
+
+    /// wake from sleep, leaving any other state alone
+    pub fn wake(self) -> Ai {
+        match self {
+            Ai::Asleep { previous_ai } => *previous_ai,
+            ai => ai,
+        }
+    }
 }
@@ -0,0 +1,144 @@
+//! Short animations drawn over the map, such as the damage a blow dealt floating
+//! up from whoever took it. They play out over a few frames while the game goes
+//! on, nothing ever waits for them to finish. They are saved along with the game,
+//! so a game loaded plays out the animations it was saved in the middle of.
+use crate::{events::GameEvent, palette::Palette, renderer::Renderer};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use tcod::{BackgroundFlag, Color, TextAlignment};
+
+// frames a number floats for, and the frames it takes to rise by a tile
+const FLOAT_FRAMES: i32 = 16;
+const FLOAT_RISE_FRAMES: i32 = 8;
+// frames the tile under a floating number flashes for
+const FLASH_FRAMES: i32 = 3;
+// a blow dealing this much damage shakes the screen, for this many frames
+const HEAVY_HIT_DAMAGE: i32 = 10;
+const SHAKE_FRAMES: i32 = 4;
+
+/// A number floating up from a tile
+#[derive(Debug, Serialize, Deserialize)]
+struct Floater {
+    x: i32,
+    y: i32,
+    text: String,
+    color: Color,
+    /// frames drawn so far
+    age: i32,
+}
+
+/// The animations playing on the map
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Animations {
+    floaters: Vec<Floater>,
+    /// tiles to flash on the next frame only
+    flashes: Vec<(i32, i32)>,
+    /// frames the screen has left to shake for
+    shake_frames: i32,
+    /// where heavy blows landed since the last frame, shaking the screen if the
+    /// player sees any of them
+    #[serde(default)]
+    heavy_hits: Vec<(i32, i32)>,
+}
+
+impl Animations {
+    /// float the text up from the tile, the tile flashing in the same color first
+    pub fn float(&mut self, x: i32, y: i32, text: String, color: Color) {
+        self.floaters.push(Floater {
+            x,
+            y,
+            text,
+            color,
+            age: 0,
+        });
+    }
+
+    /// flash the player when they are hit, and shake the screen on heavy blows
+    /// the player sees
+    pub fn record(&mut self, event: &GameEvent, player_pos: (i32, i32)) {
+        let (damage, to_player, at) = match *event {
+            GameEvent::Attack {
+                damage,
+                to_player,
+                at,
+                ..
+            }
+            | GameEvent::Hurt {
+                damage,
+                to_player,
+                at,
+                ..
+            } => (damage, to_player, at),
+            _ => return,
+        };
+        if to_player && damage > 0 {
+            self.flashes.push(player_pos);
+        }
+        if damage >= HEAVY_HIT_DAMAGE {
+            self.heavy_hits.push(at);
+        }
+    }
+
+    pub fn shake(&mut self) {
+        self.shake_frames = SHAKE_FRAMES;
+    }
+
+    /// draw this frame's flashes, each lasting for the one frame
+    pub fn draw_flashes(&mut self, con: &mut dyn Renderer, color: Color) {
+        for (x, y) in self.flashes.drain(..) {
+            con.set_char_background(x, y, color, BackgroundFlag::Set);
+        }
+    }
+
+    /// how far to move the map this frame, a tile or none in each direction while
+    /// the screen shakes
+    pub fn shake_offset(&mut self) -> (i32, i32) {
+        if self.shake_frames <= 0 {
+            return (0, 0);
+        }
+        self.shake_frames -= 1;
+        let mut rng = rand::thread_rng();
+        (rng.gen_range(0, 2), rng.gen_range(0, 2))
+    }
+
+    /// forget the flashes and the shaking, for when they are turned off
+    pub fn clear_effects(&mut self) {
+        self.flashes.clear();
+        self.shake_frames = 0;
+        self.heavy_hits.clear();
+    }
+
+    /// draw the next frame of every animation on a tile `visible` lets through,
+    /// in the colors of the palette, dropping those that are over
+    pub fn draw(
+        &mut self,
+        con: &mut dyn Renderer,
+        palette: Palette,
+        visible: impl Fn(i32, i32) -> bool,
+    ) {
+        if self.heavy_hits.drain(..).any(|(x, y)| visible(x, y)) {
+            self.shake();
+        }
+        for floater in &mut self.floaters {
+            if visible(floater.x, floater.y) {
+                let color = palette.object_color(floater.color);
+                if floater.age < FLASH_FRAMES {
+                    con.set_char_background(floater.x, floater.y, color, BackgroundFlag::Set);
+                }
+                let y = floater.y - 1 - floater.age / FLOAT_RISE_FRAMES;
+                if y >= 0 {
+                    con.set_default_foreground(color);
+                    con.print_ex(
+                        floater.x,
+                        y,
+                        BackgroundFlag::None,
+                        TextAlignment::Center,
+                        &floater.text,
+                    );
+                }
+            }
+            floater.age += 1;
+        }
+        self.floaters.retain(|floater| floater.age < FLOAT_FRAMES);
+    }
+}
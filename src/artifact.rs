@@ -0,0 +1,176 @@
+//! Artifacts: the odd armor or bow that comes out of the dungeon under a name of
+//! its own, with affixes on top of what its kind does. Every artifact has a
+//! suffix raising a stat or a resistance of whoever has it equipped, and many
+//! have a prefix too, doing something to whatever the wearer hits.
+use crate::{
+    fighter::{DamageType, Fighter, DAMAGE_TYPES},
+    object::Object,
+};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use tcod::colors::{self, Color};
+
+// chance a piece of armor or a bow turns up as an artifact, and that an artifact
+// has a prefix as well as a suffix
+pub const ARTIFACT_CHANCE: f32 = 0.05;
+const PREFIX_CHANCE: f32 = 0.6;
+// the most a stat affix raises its stat by, and the hit points a bonus of one
+// to the most hit points is worth
+const MAX_BONUS: i32 = 3;
+const HP_PER_BONUS: i32 = 5;
+// the resistance, in percent, a bonus of one to a resistance is worth
+const RESISTANCE_PER_BONUS: i32 = 15;
+// the most damage a flaming artifact adds to a hit, and the most a vampiric one
+// drains from it
+const MAX_FLAME_DAMAGE: i32 = 4;
+const MAX_DRAIN: i32 = 3;
+// artifacts stand out from their plain kind
+const ARTIFACT_COLOR: Color = colors::LIGHT_MAGENTA;
+
+/// One thing an artifact does besides what its kind does
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Affix {
+    Power(i32),
+    Defense(i32),
+    MaxHp(i32),
+    /// shrugs off this much, in percent, of a type of damage
+    Resistance(DamageType, i32),
+    /// burns whatever the wearer hits for this much more damage
+    Flaming(i32),
+    /// heals the wearer by this much whenever they hit
+    Vampiric(i32),
+}
+
+impl Affix {
+    /// the word the affix puts before the name of the item, if it is a prefix
+    fn prefix(self) -> Option<&'static str> {
+        match self {
+            Affix::Flaming(_) => Some("Flaming"),
+            Affix::Vampiric(_) => Some("Vampiric"),
+            _ => None,
+        }
+    }
+
+    /// the words the affix puts after the name of the item, if it is a suffix
+    fn suffix(self) -> Option<&'static str> {
+        match self {
+            Affix::Power(_) => Some("of the Bear"),
+            Affix::Defense(_) => Some("of the Tortoise"),
+            Affix::MaxHp(_) => Some("of the Ox"),
+            Affix::Resistance(DamageType::Physical, _) => Some("of the Rhino"),
+            Affix::Resistance(DamageType::Fire, _) => Some("of the Salamander"),
+            Affix::Resistance(DamageType::Lightning, _) => Some("of Grounding"),
+            Affix::Resistance(DamageType::Poison, _) => Some("of the Viper"),
+            _ => None,
+        }
+    }
+
+    pub fn description(self) -> String {
+        match self {
+            Affix::Power(bonus) => format!("+{} power", bonus),
+            Affix::Defense(bonus) => format!("+{} defense", bonus),
+            Affix::MaxHp(bonus) => format!("+{} max HP", bonus),
+            Affix::Resistance(kind, resistance) => {
+                format!("resists {} by {}%", kind.name(), resistance)
+            }
+            Affix::Flaming(damage) => format!("burns on hit for {}", damage),
+            Affix::Vampiric(drain) => format!("drains {} HP on hit", drain),
+        }
+    }
+}
+
+/// the name in title case, "chain mail" becoming "Chain Mail"
+fn title_case(name: &str) -> String {
+    name.split(' ')
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+fn random_suffix(rng: &mut impl Rng) -> Affix {
+    let bonus = rng.gen_range(1, MAX_BONUS + 1);
+    match rng.gen_range(0, 4) {
+        0 => Affix::Power(bonus),
+        1 => Affix::Defense(bonus),
+        2 => Affix::MaxHp(bonus * HP_PER_BONUS),
+        _ => {
+            let kind = DAMAGE_TYPES[rng.gen_range(0, DAMAGE_TYPES.len())];
+            Affix::Resistance(kind, bonus * RESISTANCE_PER_BONUS)
+        }
+    }
+}
+
+fn random_prefix(rng: &mut impl Rng) -> Affix {
+    if rng.gen() {
+        Affix::Flaming(rng.gen_range(1, MAX_FLAME_DAMAGE + 1))
+    } else {
+        Affix::Vampiric(rng.gen_range(1, MAX_DRAIN + 1))
+    }
+}
+
+/// turn the item into an artifact, giving it a suffix, maybe a prefix, and the
+/// name they make up together with the name of its kind
+pub fn make_artifact(object: &mut Object, rng: &mut impl Rng) {
+    let mut affixes = vec![];
+    if rng.gen::<f32>() < PREFIX_CHANCE {
+        affixes.push(random_prefix(rng));
+    }
+    affixes.push(random_suffix(rng));
+
+    let mut name = title_case(&object.name);
+    if let Some(prefix) = affixes.iter().find_map(|affix| affix.prefix()) {
+        name = format!("{} {}", prefix, name);
+    }
+    if let Some(suffix) = affixes.iter().find_map(|affix| affix.suffix()) {
+        name = format!("{} {}", name, suffix);
+    }
+    object.name = name;
+    object.color = ARTIFACT_COLOR;
+    object.affixes = affixes;
+}
+
+/// what the affixes do, as shown in the description of the artifact
+pub fn describe_affixes(affixes: &[Affix]) -> String {
+    affixes
+        .iter()
+        .map(|affix| affix.description())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// add the stats the affixes raise to the fighter equipping them, or with a
+/// `sign` of -1 take them away again from the fighter taking them off
+pub fn apply_affixes(affixes: &[Affix], fighter: &mut Fighter, sign: i32) {
+    for affix in affixes {
+        match *affix {
+            Affix::Power(bonus) => fighter.power += sign * bonus,
+            Affix::Defense(bonus) => fighter.defense += sign * bonus,
+            Affix::MaxHp(bonus) => {
+                fighter.max_hp += sign * bonus;
+                fighter.hp = fighter.hp.min(fighter.max_hp);
+            }
+            Affix::Resistance(kind, resistance) => {
+                *fighter.resistances.get_mut(kind) += sign * resistance
+            }
+            Affix::Flaming(_) | Affix::Vampiric(_) => {}
+        }
+    }
+}
+
+/// the extra damage and the healing the affixes of everything equipped in the
+/// inventory bring to a hit
+pub fn on_hit(inventory: &[Object]) -> (i32, i32) {
+    let equipped = inventory.iter().filter(|object| object.equipped);
+    let affixes = equipped.flat_map(|object| &object.affixes);
+    affixes.fold((0, 0), |(burn, drain), affix| match *affix {
+        Affix::Flaming(damage) => (burn + damage, drain),
+        Affix::Vampiric(heal) => (burn, drain + heal),
+        _ => (burn, drain),
+    })
+}
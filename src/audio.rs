@@ -0,0 +1,202 @@
+//! Sound effects and music. A hook on the event bus picks the sound for each
+//! event, and the game names the branch the player is in so the ambient loop
+//! fits it. Whatever makes the noise sits behind a trait, as the drawing sits
+//! behind the renderer. Built with the `ffplay` feature the sounds are played
+//! through the ffplay program, which has to be installed; without it the game
+//! stays silent. The sounds played are the files named here.
+use crate::{dungeon::Branch, events::GameEvent, options::Options};
+use std::{
+    cell::RefCell,
+    io,
+    process::{Child, Command, Stdio},
+};
+
+/// Something that can play sound files, at a volume from 0 to 1
+pub trait AudioBackend {
+    /// play the sound once, over whatever else is playing
+    fn play(&mut self, file: &str, volume: f32);
+    /// loop the music in place of what played before, or stop it for None
+    fn set_music(&mut self, file: Option<&str>, volume: f32);
+}
+
+/// the backend for when there is nothing to play sounds with
+struct Silent;
+
+impl AudioBackend for Silent {
+    fn play(&mut self, _file: &str, _volume: f32) {}
+
+    fn set_music(&mut self, _file: Option<&str>, _volume: f32) {}
+}
+
+/// The backend playing through ffplay, a process for every sound and one looping
+/// the music until it changes
+#[derive(Default)]
+struct Ffplay {
+    sounds: Vec<Child>,
+    music: Option<Child>,
+}
+
+impl AudioBackend for Ffplay {
+    fn play(&mut self, file: &str, volume: f32) {
+        // forget the sounds that are over
+        self.sounds
+            .retain_mut(|sound| matches!(sound.try_wait(), Ok(None)));
+        if let Ok(sound) = ffplay(file, volume, false) {
+            self.sounds.push(sound);
+        }
+    }
+
+    fn set_music(&mut self, file: Option<&str>, volume: f32) {
+        if let Some(mut music) = self.music.take() {
+            let _ = music.kill();
+            let _ = music.wait();
+        }
+        if volume > 0.0 {
+            self.music = file.and_then(|file| ffplay(file, volume, true).ok());
+        }
+    }
+}
+
+/// start ffplay on the file without a window, over and over if `looping`
+fn ffplay(file: &str, volume: f32, looping: bool) -> io::Result<Child> {
+    let mut command = Command::new("ffplay");
+    command
+        .args(["-nodisp", "-autoexit", "-loglevel", "quiet", "-volume"])
+        .arg(((volume * 100.0) as i32).to_string());
+    if looping {
+        command.args(["-loop", "0"]);
+    }
+    command
+        .arg(file)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+}
+
+/// the backend the game was built to play sounds through
+fn default_backend() -> Box<dyn AudioBackend> {
+    if cfg!(feature = "ffplay") {
+        Box::new(Ffplay::default())
+    } else {
+        Box::new(Silent)
+    }
+}
+
+/// The sound being played through, how loud, and the music looping
+struct Audio {
+    backend: Box<dyn AudioBackend>,
+    /// 0 when muted
+    volume: f32,
+    music: Option<&'static str>,
+}
+
+thread_local! {
+    // the hooks on the event bus are plain functions, so what they play through
+    // lives here
+    static AUDIO: RefCell<Audio> = RefCell::new(Audio {
+        backend: default_backend(),
+        volume: 0.0,
+        music: None,
+    });
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Sound {
+    Hit,
+    Miss,
+    /// the player getting hit
+    Hurt,
+    Kill,
+    Death,
+    PickUp,
+    /// the jingle for reaching a new level
+    LevelUp,
+}
+
+impl Sound {
+    fn file(self) -> &'static str {
+        match self {
+            Sound::Hit => "sounds/hit.ogg",
+            Sound::Miss => "sounds/miss.ogg",
+            Sound::Hurt => "sounds/hurt.ogg",
+            Sound::Kill => "sounds/kill.ogg",
+            Sound::Death => "sounds/death.ogg",
+            Sound::PickUp => "sounds/pickup.ogg",
+            Sound::LevelUp => "sounds/levelup.ogg",
+        }
+    }
+}
+
+/// the sound an event makes, if any
+fn sound_for(event: &GameEvent) -> Option<Sound> {
+    match *event {
+        GameEvent::Attack { damage: 0, .. } => Some(Sound::Miss),
+        GameEvent::Attack {
+            to_player: true, ..
+        } => Some(Sound::Hurt),
+        GameEvent::Attack { .. } => Some(Sound::Hit),
+        GameEvent::Hurt {
+            damage, to_player, ..
+        } if damage > 0 => Some(if to_player { Sound::Hurt } else { Sound::Hit }),
+        GameEvent::MonsterDied { .. } => Some(Sound::Kill),
+        GameEvent::PlayerDied => Some(Sound::Death),
+        GameEvent::PickedUp(_) | GameEvent::PickedUpGold(_) => Some(Sound::PickUp),
+        GameEvent::LevelUp(_) => Some(Sound::LevelUp),
+        GameEvent::Hurt { .. }
+        | GameEvent::Resisted { .. }
+        | GameEvent::Afflicted { .. }
+        | GameEvent::FellDown => None,
+    }
+}
+
+/// the ambient loop played in the branch
+fn ambient_music(branch: Branch) -> &'static str {
+    match branch {
+        Branch::Town => "music/town.ogg",
+        Branch::Main => "music/dungeon.ogg",
+        Branch::Mines => "music/mines.ogg",
+        Branch::Crypt => "music/crypt.ogg",
+        Branch::Abyss => "music/abyss.ogg",
+    }
+}
+
+/// the hook playing the sound of each event
+pub fn play_event_sound(event: &GameEvent) {
+    if let Some(sound) = sound_for(event) {
+        AUDIO.with(|audio| {
+            let audio = &mut *audio.borrow_mut();
+            if audio.volume > 0.0 {
+                audio.backend.play(sound.file(), audio.volume);
+            }
+        });
+    }
+}
+
+/// loop the ambient music of the branch, or stop the music outside the dungeon
+pub fn play_ambient(branch: Option<Branch>) {
+    let music = branch.map(ambient_music);
+    AUDIO.with(|audio| {
+        let audio = &mut *audio.borrow_mut();
+        if audio.music != music {
+            audio.music = music;
+            audio.backend.set_music(music, audio.volume);
+        }
+    });
+}
+
+/// take up the volume in the options, the music going on at the new volume
+pub fn configure(options: &Options) {
+    let volume = if options.mute {
+        0.0
+    } else {
+        options.volume as f32 / 100.0
+    };
+    AUDIO.with(|audio| {
+        let audio = &mut *audio.borrow_mut();
+        if audio.volume != volume {
+            audio.volume = volume;
+            audio.backend.set_music(audio.music, volume);
+        }
+    });
+}
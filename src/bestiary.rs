@@ -0,0 +1,121 @@
+//! What the player can tell about a monster by looking it over: how hurt it is,
+//! how hard it hits and how well it is protected, what else it is known to do,
+//! and how many of its kind have fallen to the player this run.
+use crate::{
+    ai::Ai,
+    effects::EffectKind,
+    fighter::{DeathCallback, Resistances},
+    groundeffects::GroundKind,
+    monsters::{Ability, Faction},
+    object::Object,
+    spells::MonsterSpell,
+};
+
+// characters in the health bar of the details screen
+const HP_BAR_LENGTH: usize = 20;
+
+/// the monster's health as a bar of characters, followed by the hit points
+fn hp_bar(hp: i32, max_hp: i32) -> String {
+    let filled = (hp.max(0) as usize * HP_BAR_LENGTH / max_hp.max(1) as usize).min(HP_BAR_LENGTH);
+    format!(
+        "[{}{}] {}/{}",
+        "#".repeat(filled),
+        "-".repeat(HP_BAR_LENGTH - filled),
+        hp,
+        max_hp
+    )
+}
+
+/// what sets the monster apart from a plain brute
+fn abilities(monster: &Object) -> Vec<&'static str> {
+    let mut abilities = vec![];
+    let base = monster.ai.clone().map(Ai::base);
+    match base {
+        Some(Ai::Ranged) => abilities.push("shoots from behind the front line"),
+        Some(Ai::Patrol { .. }) => abilities.push("walks a round, keeping watch"),
+        Some(Ai::Wander { .. }) => abilities.push("wanders its room, keeping watch"),
+        _ => {}
+    }
+    if let Some(effect) = monster.fighter.and_then(|fighter| fighter.on_hit) {
+        abilities.push(match effect.kind {
+            EffectKind::Confused => "its blows leave the victim confused",
+            EffectKind::Paralyzed => "its blows leave the victim paralyzed",
+            EffectKind::Blind => "its blows leave the victim blinded",
+            EffectKind::Poisoned => "its bite poisons the victim",
+            EffectKind::Empowered => "its blows drive the victim into a frenzy",
+            EffectKind::Invisible => "its blows make the victim fade from sight",
+            EffectKind::SeeInvisible => "its blows open the victim's eyes to the unseen",
+        });
+    }
+    for ability in &monster.abilities {
+        abilities.push(match ability {
+            Ability::Regenerate(_) => "its wounds close as you watch",
+            Ability::Shaman { .. } => "mends the wounds of its allies and spurs them on",
+            Ability::DeathBurst {
+                kind: GroundKind::Fire,
+                ..
+            } => "bursts into flames as it dies",
+            Ability::DeathBurst {
+                kind: GroundKind::PoisonGas,
+                ..
+            } => "gives off a cloud of poison gas as it dies",
+            Ability::Cast(MonsterSpell::Curse { .. }) => "curses its foes, withering their health",
+            Ability::Cast(MonsterSpell::Summon(_)) => "whistles up giant rats to its side",
+            Ability::Cast(MonsterSpell::Blink) => "blinks away once badly hurt",
+        });
+    }
+    if monster.capabilities.smashes_doors {
+        abilities.push("smashes doors to splinters");
+    } else if monster.capabilities.opens_doors {
+        abilities.push("opens doors");
+    }
+    if monster.faction == Faction::Vermin {
+        abilities.push("bites anything that comes near, orcs included");
+    }
+    if monster.fighter.map(|fighter| fighter.on_death) == Some(DeathCallback::Boss) {
+        abilities.push("guards the bottom of its branch");
+    }
+    abilities
+}
+
+/// the text of the details screen of a monster, `kills` being how many of its
+/// kind the player has slain
+pub fn monster_details(monster: &Object, kills: i32) -> String {
+    let mut text = format!("{}\n", monster.name);
+    if let Some(fighter) = monster.fighter {
+        text += &format!(
+            "\nHP: {}\nAttack: {}\nDefense: {}",
+            hp_bar(fighter.hp, fighter.max_hp),
+            fighter.power,
+            fighter.defense
+        );
+        if fighter.resistances != Resistances::default() {
+            text += &format!("\nResistances: {}", fighter.resistances.describe());
+        }
+    }
+    // every monster moves once a turn, only the player's armor and burden change that
+    text += "\nSpeed: normal";
+    if monster.has_effect(EffectKind::Empowered) {
+        text += "\nIt is spurred on, hitting harder.";
+    }
+    let state = match &monster.ai {
+        Some(Ai::Asleep { .. }) => Some("asleep"),
+        Some(Ai::Confused { .. }) => Some("confused"),
+        Some(Ai::Flee { .. }) => Some("fleeing"),
+        Some(Ai::Ally { .. }) => Some("charmed, fighting at your side"),
+        _ => None,
+    };
+    if let Some(state) = state {
+        text += &format!("\nIt is {}.", state);
+    }
+
+    let abilities = abilities(monster);
+    text += "\n\nKnown abilities:";
+    if abilities.is_empty() {
+        text += "\nnone, it just hits you";
+    }
+    for ability in abilities {
+        text += &format!("\n- {}", ability);
+    }
+    text + &format!("\n\nSlain by you this run: {}", kills)
+}
@@ -0,0 +1,177 @@
+//! Blessed and cursed items. Every item is blessed, uncursed or cursed, which the
+//! player only learns once the item is identified, worn, or laid on an altar.
+//! Cursed armor and bows can't be taken off or put away again until the curse is
+//! lifted. Blessed armor turns blows better, and a blessed potion heals more.
+//! Altars lie about the dungeon, telling the state of everything carried and, for
+//! an offering, blessing an item; but the gods are fickle, kinder to the lucky,
+//! and their favour or displeasure makes the player luckier or less so.
+use crate::{
+    game::{Game, PLAYER},
+    inventoryui::inventory_menu,
+    item::{Item, Slot},
+    luck::lucky,
+    menu,
+    object::Object,
+    tcoder::{Tcod, INVENTORY_WIDTH},
+    world::Service,
+};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use tcod::colors::{self, LIGHT_BLUE, LIGHT_CYAN, RED};
+
+// chance for a piece of equipment to turn up cursed, or blessed
+const CURSED_CHANCE: f32 = 0.1;
+const BLESSED_CHANCE: f32 = 0.1;
+// gold an altar asks for a prayer, and the chance a prayer over an uncursed item
+// curses it instead of blessing it, or over a cursed item does nothing
+const ALTAR_OFFERING: i32 = 50;
+const ALTAR_CURSE_CHANCE: f32 = 0.25;
+const ALTAR_FAIL_CHANCE: f32 = 0.3;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Beatitude {
+    Blessed,
+    #[default]
+    Uncursed,
+    Cursed,
+}
+
+impl Beatitude {
+    pub fn name(self) -> &'static str {
+        match self {
+            Beatitude::Blessed => "blessed",
+            Beatitude::Uncursed => "uncursed",
+            Beatitude::Cursed => "cursed",
+        }
+    }
+
+    /// what the state adds to the defense of armor and the damage of a bow
+    pub fn bonus(self) -> i32 {
+        match self {
+            Beatitude::Blessed => 1,
+            Beatitude::Uncursed => 0,
+            Beatitude::Cursed => -1,
+        }
+    }
+}
+
+/// bless or curse a new piece of equipment with a small chance
+pub fn random_beatitude(item: Item, rng: &mut impl Rng) -> Beatitude {
+    if item.slot().is_none() || item.slot() == Some(Slot::Light) {
+        return Beatitude::Uncursed;
+    }
+    let roll = rng.gen::<f32>();
+    if roll < CURSED_CHANCE {
+        Beatitude::Cursed
+    } else if roll < CURSED_CHANCE + BLESSED_CHANCE {
+        Beatitude::Blessed
+    } else {
+        Beatitude::Uncursed
+    }
+}
+
+pub fn create_altar(x: i32, y: i32) -> Object {
+    let mut altar = Object::new(x, y, '_', "altar", colors::LIGHTER_GREY, true);
+    altar.always_visible = true;
+    altar.service = Some(Service::Altar);
+    altar
+}
+
+/// change the state of the carried item, what it does to whoever wears it
+/// changing along with it
+pub fn set_beatitude(game: &mut Game, inventory_id: usize, beatitude: Beatitude) {
+    let object = &mut game.inventory[inventory_id];
+    let change = beatitude.bonus() - object.beatitude.bonus();
+    object.beatitude = beatitude;
+    object.beatitude_known = true;
+    let worn_armor = object.equipped && object.item.and_then(Item::armor).is_some();
+    if let Some(fighter) = game.objects[PLAYER].fighter.as_mut().filter(|_| worn_armor) {
+        fighter.defense += change;
+    }
+}
+
+/// lift the curse off everything carried; returns how many items it was lifted from
+pub fn remove_curses(game: &mut Game) -> usize {
+    let cursed: Vec<_> = (0..game.inventory.len())
+        .filter(|&id| game.inventory[id].beatitude == Beatitude::Cursed)
+        .collect();
+    for &id in &cursed {
+        set_beatitude(game, id, Beatitude::Uncursed);
+    }
+    cursed.len()
+}
+
+/// the player stands before an altar: it shows them the state of everything they
+/// carry, and for an offering they may pray over an item
+pub fn visit_altar(game: &mut Game, tcod: &mut Tcod) {
+    let unknown = game.inventory.iter().filter(|o| !o.beatitude_known).count();
+    for object in game.inventory.iter_mut() {
+        object.beatitude_known = true;
+    }
+    if unknown > 0 {
+        game.messages.add(
+            "You lay your belongings on the altar, and they flash black, amber or not at all.",
+            LIGHT_CYAN,
+        );
+    }
+
+    let header = format!(
+        "The altar hums softly. An offering of {} gold would carry a prayer over an \
+         item of yours. You have {} gold.\n",
+        ALTAR_OFFERING, game.gold
+    );
+    let choices = &["Pray over an item", "Leave"];
+    if menu(&header, choices, INVENTORY_WIDTH, &mut tcod.root) != Some(0) {
+        return;
+    }
+    if game.gold < ALTAR_OFFERING {
+        game.messages.add("You have too little gold to offer.", RED);
+        return;
+    }
+    let inventory_id = match inventory_menu(
+        &game.inventory,
+        "Which item do you pray over?\n",
+        &mut tcod.root,
+    ) {
+        Some(id) => id,
+        None => return,
+    };
+    let name = game.inventory[inventory_id].name.clone();
+    if game.inventory[inventory_id].beatitude == Beatitude::Blessed {
+        game.messages
+            .add(format!("The {} is blessed already.", name), LIGHT_BLUE);
+        return;
+    }
+    game.gold -= ALTAR_OFFERING;
+    let luck = game.luck();
+    let (beatitude, favour, message, color) = match game.inventory[inventory_id].beatitude {
+        Beatitude::Blessed => return,
+        Beatitude::Uncursed if !lucky(1.0 - ALTAR_CURSE_CHANCE, luck) => (
+            Beatitude::Cursed,
+            -1,
+            format!("The gods are displeased! The {} flashes black.", name),
+            RED,
+        ),
+        Beatitude::Uncursed => (
+            Beatitude::Blessed,
+            1,
+            format!("The {} glows amber. It is blessed!", name),
+            LIGHT_BLUE,
+        ),
+        Beatitude::Cursed if !lucky(1.0 - ALTAR_FAIL_CHANCE, luck) => (
+            Beatitude::Cursed,
+            0,
+            format!("The {} stays dark. Your prayer goes unheard.", name),
+            RED,
+        ),
+        Beatitude::Cursed => (
+            Beatitude::Uncursed,
+            0,
+            format!("The darkness lifts from the {}.", name),
+            LIGHT_BLUE,
+        ),
+    };
+    set_beatitude(game, inventory_id, beatitude);
+    game.change_favour(favour);
+    game.messages.add(message, color);
+}
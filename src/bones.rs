@@ -0,0 +1,143 @@
+//! Bones: what is left of permadeath characters, kept across runs in the `bones`
+//! file. A character who dies in the dungeon leaves their ghost behind on the
+//! level they died on, sitting on their gear. When a later run first generates
+//! that level, the bones may be taken from the file and the ghost put on it.
+use crate::{
+    blessings::Beatitude,
+    dungeon::LevelId,
+    game::{is_blocked, Game, PLAYER},
+    item::Item,
+    map::Map,
+    monsters::monster_by_name,
+    object::Object,
+    profile::Class,
+    tcoder::{MAP_HEIGHT, MAP_WIDTH},
+    world::TOWN,
+    write_atomically,
+};
+use rand::{thread_rng, Rng};
+use serde::{Deserialize, Serialize};
+use std::{error::Error, fs};
+
+const BONES_FILE: &str = "bones";
+// the most bones kept, the oldest going first
+const MAX_BONES: usize = 10;
+// chance for a level with bones to be haunted when it is first generated
+const HAUNT_CHANCE: f32 = 0.33;
+// chance for each piece of the dead character's gear to be cursed
+const CURSE_CHANCE: f32 = 0.8;
+// how the ghost grows with the level of the character it was
+const GHOST_HP_PER_LEVEL: i32 = 4;
+const GHOST_POWER_PER_LEVEL: i32 = 1;
+const GHOST_XP_PER_LEVEL: i32 = 40;
+// tries at finding a free tile for the ghost, and how far from the player
+const PLACEMENT_TRIES: i32 = 100;
+const GHOST_DISTANCE: f32 = 8.0;
+
+/// A dead character's ghost and gear, waiting for the level they died on
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Bones {
+    /// the run the character died in, whose own levels are never haunted by them
+    run_id: u64,
+    level: LevelId,
+    class: Class,
+    character_level: i32,
+    gear: Vec<Object>,
+}
+
+fn load() -> Vec<Bones> {
+    fs::read_to_string(BONES_FILE)
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+fn save(bones: &[Bones]) -> Result<(), Box<dyn Error>> {
+    write_atomically(BONES_FILE, serde_json::to_string(bones)?.as_bytes())
+}
+
+/// Leave the bones of the character who just died on the current level. The
+/// amulet and the keys, which belong to their own run, are left out
+pub fn leave_bones(game: &Game) -> Result<(), Box<dyn Error>> {
+    if game.level == TOWN {
+        return Ok(());
+    }
+    let gear: Vec<&Object> = game
+        .inventory
+        .iter()
+        .filter(|object| !matches!(object.item, Some(Item::Amulet) | Some(Item::Key)))
+        .collect();
+    // the gear is copied through its JSON, the morgue file still being written
+    // from the inventory
+    let gear = serde_json::from_value(serde_json::to_value(gear)?)?;
+    let mut bones = load();
+    bones.push(Bones {
+        run_id: game.run_id,
+        level: game.level,
+        class: game.class,
+        character_level: game.objects[PLAYER].level,
+        gear,
+    });
+    if bones.len() > MAX_BONES {
+        bones.remove(0);
+    }
+    save(&bones)
+}
+
+/// Maybe take bones left on the level by the character of another run out of the
+/// file, to haunt the level with. They haunt one run only, so if the file can't
+/// be rewritten without them they are left in it for another time
+pub fn take_bones(level: LevelId, run_id: u64) -> Result<Option<Bones>, Box<dyn Error>> {
+    if rand::random::<f32>() >= HAUNT_CHANCE {
+        return Ok(None);
+    }
+    let mut bones = load();
+    let index = bones
+        .iter()
+        .position(|bones| bones.level == level && bones.run_id != run_id);
+    let taken = match index {
+        Some(index) => bones.remove(index),
+        None => return Ok(None),
+    };
+    save(&bones)?;
+    Ok(Some(taken))
+}
+
+/// Put the ghost of the bones somewhere on the level away from the player, on top
+/// of its gear, most of it cursed. Returns false if no room was found for it
+pub fn haunt(bones: Bones, map: &Map, objects: &mut Vec<Object>) -> bool {
+    let mut rng = thread_rng();
+    let player = &objects[PLAYER];
+    let spot = (0..PLACEMENT_TRIES)
+        .map(|_| (rng.gen_range(0, MAP_WIDTH), rng.gen_range(0, MAP_HEIGHT)))
+        .find(|&(x, y)| {
+            player.distance(x, y) >= GHOST_DISTANCE
+                && !map[x as usize][y as usize].terrain.is_perilous()
+                && !is_blocked(x, y, map, &objects[..])
+        });
+    let (x, y) = match spot {
+        Some(spot) => spot,
+        None => return false,
+    };
+    for mut object in bones.gear {
+        object.set_pos(x, y);
+        object.equipped = false;
+        object.off_hand = false;
+        if rng.gen::<f32>() < CURSE_CHANCE {
+            object.beatitude = Beatitude::Cursed;
+        }
+        object.beatitude_known = false;
+        objects.push(object);
+    }
+    let mut ghost = monster_by_name("ghost", x, y).unwrap();
+    ghost.name = format!("fallen {}'s ghost", bones.class.name());
+    let levels = bones.character_level - 1;
+    if let Some(fighter) = ghost.fighter.as_mut() {
+        fighter.max_hp += levels * GHOST_HP_PER_LEVEL;
+        fighter.hp = fighter.max_hp;
+        fighter.power += levels * GHOST_POWER_PER_LEVEL;
+        fighter.xp += levels * GHOST_XP_PER_LEVEL;
+    }
+    objects.push(ghost);
+    true
+}
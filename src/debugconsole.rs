@@ -0,0 +1,131 @@
+use crate::{
+    dungeon::{Branch, LevelId, SIDE_BRANCHES},
+    item::{Item, ITEMS},
+    monsters::monster_names,
+};
+use tcod::colors::{self, Color};
+use tcod::console::*;
+use tcod::input::KeyCode;
+
+const CONSOLE_HEIGHT: i32 = 3;
+const CONSOLE_BACKGROUND: Color = colors::DARKEST_GREY;
+const PROMPT: &str = "> ";
+
+/// A command typed into the debug console.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Command {
+    /// `spawn <monster> <x> <y>`: put a monster on the map
+    Spawn { name: String, x: i32, y: i32 },
+    /// `give <item> [count]`: hand items to the player
+    Give { item: Item, count: u32 },
+    /// `goto [branch] <depth>`: take a shortcut to another level
+    Goto(LevelId),
+    /// `seed`: show what the levels are generated from
+    Seed,
+}
+
+pub fn parse_command(line: &str) -> Result<Command, String> {
+    let words: Vec<_> = line.split_whitespace().collect();
+    let number = |word: &str| {
+        word.parse::<i32>()
+            .map_err(|_| format!("'{}' is not a number", word))
+    };
+    match words.as_slice() {
+        ["spawn", name @ .., x, y] if !name.is_empty() => {
+            let name = name.join(" ");
+            if !monster_names().contains(&name.as_str()) {
+                return Err(format!("there is no monster called '{}'", name));
+            }
+            Ok(Command::Spawn {
+                name,
+                x: number(x)?,
+                y: number(y)?,
+            })
+        }
+        ["give", id] => parse_give(id, "1"),
+        ["give", id, count] => parse_give(id, count),
+        ["goto", depth] => parse_goto("dungeon", depth),
+        ["goto", branch, depth] => parse_goto(branch, depth),
+        ["seed"] => Ok(Command::Seed),
+        [] => Err("type a command: spawn, give, goto or seed".into()),
+        [command, ..] => Err(format!("unknown command or arguments for '{}'", command)),
+    }
+}
+
+fn parse_give(id: &str, count: &str) -> Result<Command, String> {
+    let item = ITEMS
+        .iter()
+        .cloned()
+        .find(|item| item.id() == id)
+        .ok_or_else(|| {
+            let ids: Vec<_> = ITEMS.iter().map(|item| item.id()).collect();
+            format!("there is no item '{}', try one of {}", id, ids.join(", "))
+        })?;
+    let count = count
+        .parse()
+        .map_err(|_| format!("'{}' is not a count", count))?;
+    Ok(Command::Give { item, count })
+}
+
+fn parse_goto(branch: &str, depth: &str) -> Result<Command, String> {
+    let branch = [Branch::Main]
+        .iter()
+        .chain(SIDE_BRANCHES)
+        .cloned()
+        .find(|b| b.name().eq_ignore_ascii_case(branch))
+        .ok_or_else(|| format!("there is no branch '{}'", branch))?;
+    let depth = depth
+        .parse()
+        .ok()
+        .filter(|&depth| depth >= 1 && depth <= branch.max_depth())
+        .ok_or_else(|| {
+            format!(
+                "the {} has levels 1 to {}",
+                branch.name(),
+                branch.max_depth()
+            )
+        })?;
+    Ok(Command::Goto(LevelId::new(branch, depth)))
+}
+
+/// Drop down a one-line console over the top of the screen and read a command,
+/// returns None if it was dismissed with Escape
+pub fn read_command(last_output: &str, root: &mut Root) -> Option<String> {
+    let mut line = String::new();
+    loop {
+        let mut window = Offscreen::new(root.width(), CONSOLE_HEIGHT);
+        window.set_default_background(CONSOLE_BACKGROUND);
+        window.clear();
+        window.set_default_foreground(colors::LIGHT_GREY);
+        window.print_ex(0, 0, BackgroundFlag::None, TextAlignment::Left, last_output);
+        window.set_default_foreground(colors::WHITE);
+        window.print_ex(
+            0,
+            CONSOLE_HEIGHT - 1,
+            BackgroundFlag::None,
+            TextAlignment::Left,
+            format!("{}{}_", PROMPT, line),
+        );
+        blit(
+            &window,
+            (0, 0),
+            (root.width(), CONSOLE_HEIGHT),
+            root,
+            (0, 0),
+            1.0,
+            0.9,
+        );
+        root.flush();
+
+        let key = root.wait_for_keypress(true);
+        match key.code {
+            KeyCode::Enter | KeyCode::NumPadEnter => return Some(line),
+            KeyCode::Escape => return None,
+            KeyCode::Backspace => {
+                line.pop();
+            }
+            KeyCode::Text => line += key.text(),
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,84 @@
+//! How hard the dungeon is, chosen when the character is created. Every way the
+//! difficulty changes the game goes through the multipliers here: the health of
+//! the monsters and the damage they deal, how many of them turn up, and how well
+//! the player heals.
+use crate::object::Object;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+/// every difficulty there is, in the order the new game menu lists them
+pub const DIFFICULTIES: &[Difficulty] = &[Difficulty::Easy, Difficulty::Normal, Difficulty::Hard];
+
+impl Difficulty {
+    pub fn name(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Normal => "normal",
+            Difficulty::Hard => "hard",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "Easy: fewer and weaker monsters, better healing",
+            Difficulty::Normal => "Normal: the dungeon as it was meant to be",
+            Difficulty::Hard => "Hard: more and tougher monsters, poorer healing",
+        }
+    }
+
+    /// the percentages of the usual monster health, monster damage, monsters
+    /// spawned and healing
+    fn percentages(self) -> (i32, i32, i32, i32) {
+        match self {
+            Difficulty::Easy => (75, 75, 75, 125),
+            Difficulty::Normal => (100, 100, 100, 100),
+            Difficulty::Hard => (125, 125, 125, 75),
+        }
+    }
+
+    /// give a freshly spawned monster the health this difficulty asks for
+    pub fn toughen(self, monster: &mut Object) {
+        let percent = self.percentages().0;
+        if let Some(fighter) = monster.fighter.as_mut() {
+            fighter.max_hp = scale(fighter.max_hp, percent);
+            fighter.hp = fighter.max_hp;
+        }
+    }
+
+    /// the power of a monster's blow
+    pub fn monster_damage(self, power: i32) -> i32 {
+        scale(power, self.percentages().1)
+    }
+
+    /// the number of monsters where there would usually be `count`
+    pub fn monster_count(self, count: i32) -> i32 {
+        scale(count, self.percentages().2)
+    }
+
+    /// the chance of a monster spawning where it would usually be `chance`
+    pub fn spawn_chance(self, chance: f32) -> f32 {
+        chance * self.percentages().2 as f32 / 100.0
+    }
+
+    /// the hit points healed where it would usually be `amount`
+    pub fn healing(self, amount: i32) -> i32 {
+        scale(amount, self.percentages().3)
+    }
+}
+
+/// the percentage of the value, rounded, but never taking all of a positive value
+fn scale(value: i32, percent: i32) -> i32 {
+    let scaled = (value * percent + 50) / 100;
+    if value > 0 {
+        scaled.max(1)
+    } else {
+        scaled
+    }
+}
@@ -0,0 +1,147 @@
+use crate::{map::Map, object::Object, stats::LevelStats, theme::Theme, world};
+use serde::{Deserialize, Serialize};
+
+/// A line of levels stacked below each other. Side branches split off the main
+/// dungeon at an entrance level.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Branch {
+    /// the town above the dungeon, a single level the player can come back up to
+    Town,
+    Main,
+    Mines,
+    Crypt,
+    /// the extra-hard postgame branch, opened by slaying the Ancient King
+    Abyss,
+}
+
+pub const SIDE_BRANCHES: &[Branch] = &[Branch::Mines, Branch::Crypt, Branch::Abyss];
+
+impl Branch {
+    pub fn name(self) -> &'static str {
+        match self {
+            Branch::Town => "Town",
+            Branch::Main => "Dungeon",
+            Branch::Mines => "Mines",
+            Branch::Crypt => "Crypt",
+            Branch::Abyss => "Abyss",
+        }
+    }
+
+    /// number of levels in the branch
+    pub fn max_depth(self) -> u32 {
+        match self {
+            Branch::Town => 0,
+            Branch::Main => 10,
+            Branch::Mines => 4,
+            Branch::Crypt => 3,
+            Branch::Abyss => 3,
+        }
+    }
+
+    /// the level holding the stairs into this branch
+    pub fn entrance(self) -> Option<LevelId> {
+        match self {
+            Branch::Town => None,
+            Branch::Main => Some(world::TOWN),
+            Branch::Mines => Some(LevelId::new(Branch::Main, 2)),
+            Branch::Crypt => Some(LevelId::new(Branch::Main, 4)),
+            Branch::Abyss => Some(LevelId::new(Branch::Main, Branch::Main.max_depth())),
+        }
+    }
+
+    /// branches only reachable once the game has been won
+    pub fn is_postgame(self) -> bool {
+        self == Branch::Abyss
+    }
+
+    /// the unique monster guarding the bottom of the branch, if any
+    pub fn boss(self) -> Option<&'static str> {
+        match self {
+            Branch::Main => Some("Ancient King"),
+            Branch::Abyss => Some("Eater of Kings"),
+            Branch::Town | Branch::Mines | Branch::Crypt => None,
+        }
+    }
+}
+
+/// A node of the level graph: one level of one branch.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct LevelId {
+    pub branch: Branch,
+    /// depth inside the branch, starting at 1, the town being at depth 0
+    pub depth: u32,
+}
+
+impl LevelId {
+    pub fn new(branch: Branch, depth: u32) -> Self {
+        LevelId { branch, depth }
+    }
+
+    pub fn name(self) -> String {
+        if self == world::TOWN {
+            return self.branch.name().into();
+        }
+        format!("{} level {}", self.branch.name(), self.depth)
+    }
+
+    /// how far below the surface this level is, counting the levels above the
+    /// branch entrance
+    pub fn absolute_depth(self) -> u32 {
+        self.branch
+            .entrance()
+            .map_or(0, |entrance| entrance.absolute_depth())
+            + self.depth
+    }
+
+    /// What the level is generated from in a run with the given seed, after the
+    /// dungeon has been regenerated `generation` times
+    pub fn seed(self, run_seed: u64, generation: u32) -> [u32; 4] {
+        [
+            run_seed as u32,
+            (run_seed >> 32) as u32,
+            (self.branch as u32) << 16 | self.depth,
+            // never all zeroes, which the generator can't start from
+            0x9e37_79b9 ^ generation,
+        ]
+    }
+
+    /// the level the up-stairs lead to, if any
+    pub fn up(self) -> Option<LevelId> {
+        if self.depth > 1 {
+            Some(LevelId::new(self.branch, self.depth - 1))
+        } else {
+            self.branch.entrance()
+        }
+    }
+
+    /// the level the down-stairs lead to, if any
+    pub fn down(self) -> Option<LevelId> {
+        if self.depth < self.branch.max_depth() {
+            Some(LevelId::new(self.branch, self.depth + 1))
+        } else {
+            None
+        }
+    }
+
+    /// the first levels of the side branches entered from this level
+    pub fn branches(self) -> Vec<LevelId> {
+        SIDE_BRANCHES
+            .iter()
+            .filter(|branch| branch.entrance() == Some(self))
+            .map(|&branch| LevelId::new(branch, 1))
+            .collect()
+    }
+}
+
+/// A level the player has left, kept for when they come back, until a death in
+/// explorer mode shifts the dungeon below.
+#[derive(Serialize, Deserialize)]
+pub struct LevelState {
+    pub map: Map,
+    pub memory: Vec<Vec<Option<char>>>,
+    /// everything on the level but the player
+    pub objects: Vec<Object>,
+    pub stats: LevelStats,
+    #[serde(default)]
+    pub theme: Theme,
+}
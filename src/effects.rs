@@ -0,0 +1,63 @@
+use serde::{Deserialize, Serialize};
+
+// hit points poison burns away every turn, and the power a spurred on fighter gains
+pub const POISON_DAMAGE_PER_TURN: i32 = 1;
+pub const EMPOWERED_POWER: i32 = 2;
+
+/// A temporary condition to put an object under, for some turns.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Effect {
+    pub kind: EffectKind,
+    pub turns: i32,
+}
+
+/// A temporary condition an object is under, until the turn it wears off on.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ActiveEffect {
+    pub kind: EffectKind,
+    pub until: i32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum EffectKind {
+    /// moves in random directions
+    Confused,
+    /// can't act at all
+    Paralyzed,
+    /// sees only what is right next to it
+    Blind,
+    /// loses hit points every turn
+    Poisoned,
+    /// hits harder, spurred on by a shaman
+    Empowered,
+    /// unseen by monsters that aren't right next to it
+    Invisible,
+    /// sees the invisible
+    SeeInvisible,
+}
+
+impl EffectKind {
+    pub fn onset_message(self, name: &str) -> String {
+        match self {
+            EffectKind::Confused => format!("{} is confused!", name),
+            EffectKind::Paralyzed => format!("{} is paralyzed!", name),
+            EffectKind::Blind => format!("{} is blinded!", name),
+            EffectKind::Poisoned => format!("{} is poisoned!", name),
+            EffectKind::Empowered => format!("{} is spurred on!", name),
+            EffectKind::Invisible => format!("{} fades from sight!", name),
+            EffectKind::SeeInvisible => format!("{} sees the unseen!", name),
+        }
+    }
+
+    pub fn expiry_message(self, name: &str) -> String {
+        match self {
+            EffectKind::Confused => format!("{} is no longer confused.", name),
+            EffectKind::Paralyzed => format!("{} can move again.", name),
+            EffectKind::Blind => format!("{} can see again.", name),
+            EffectKind::Poisoned => format!("{} is no longer poisoned.", name),
+            EffectKind::Empowered => format!("{} is no longer spurred on.", name),
+            EffectKind::Invisible => format!("{} is visible again.", name),
+            EffectKind::SeeInvisible => format!("{} no longer sees the unseen.", name),
+        }
+    }
+}
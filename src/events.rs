@@ -0,0 +1,86 @@
+//! What happens in the game, announced as events instead of being written
+//! straight into the message log. Combat, deaths, pickups and level-ups emit
+//! into a queue, and the game hands each event in turn to the message log, the
+//! statistics, the achievements and whatever hooks are registered, such as the
+//! sounds to play.
+use crate::{effects::EffectKind, fighter::DamageType};
+use serde::{Deserialize, Serialize};
+use std::mem;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum GameEvent {
+    /// a melee blow, glancing off when it deals no damage
+    Attack {
+        attacker: String,
+        target: String,
+        damage: i32,
+        by_player: bool,
+        to_player: bool,
+        /// where the target stands
+        #[serde(default)]
+        at: (i32, i32),
+    },
+    /// damage dealt other than by a melee blow, which the cause describes itself
+    Hurt {
+        damage: i32,
+        by_player: bool,
+        to_player: bool,
+        #[serde(default)]
+        at: (i32, i32),
+    },
+    /// the target's resistance, or weakness, to the damage changed how much of
+    /// it was dealt, leaving `damage`
+    Resisted {
+        target: String,
+        kind: DamageType,
+        damage: i32,
+        weak: bool,
+    },
+    /// a blow left a lasting effect on its target
+    Afflicted {
+        target: String,
+        kind: EffectKind,
+    },
+    PlayerDied,
+    /// a monster died, the player earning `xp` experience for their part in it
+    MonsterDied {
+        name: String,
+        xp: i32,
+        by_player: bool,
+        boss: bool,
+    },
+    /// the player fell down a chasm to the level below
+    FellDown,
+    PickedUp(String),
+    PickedUpGold(i32),
+    /// the player reached a new experience level
+    LevelUp(i32),
+}
+
+/// something to be told of every event, after the log and the statistics
+pub type Hook = fn(&GameEvent);
+
+/// The events emitted since the game last went through them
+#[derive(Debug, Default)]
+pub struct Events {
+    queue: Vec<GameEvent>,
+    /// told of every event, for a sound player and the like to register with
+    pub hooks: Vec<Hook>,
+}
+
+impl Events {
+    pub fn emit(&mut self, event: GameEvent) {
+        self.queue.push(event);
+    }
+
+    /// take the queued events, oldest first
+    pub fn take(&mut self) -> Vec<GameEvent> {
+        mem::take(&mut self.queue)
+    }
+
+    pub fn run_hooks(&self, event: &GameEvent) {
+        for hook in &self.hooks {
+            hook(event);
+        }
+    }
+}
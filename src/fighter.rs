@@ -1,6 +1,17 @@
-use crate::{messages::Messages, object::Object};
+use crate::{
+    effects::Effect,
+    events::{Events, GameEvent},
+    food::meat_of,
+    object::Object,
+};
 use serde::{Deserialize, Serialize};
-use tcod::colors::{DARK_RED, ORANGE, RED};
+use tcod::colors::DARK_RED;
+
+// how long a monster's remains litter the floor
+const CORPSE_DECAY_TURNS: i32 = 300;
+// the part of the damage dealt by a monster the player confused that counts as
+// the player's own
+const CONFUSED_SHARE_PERCENT: i32 = 50;
 
 // combat-related properties and methods (monster, player, NPC).
 #[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
@@ -10,50 +21,208 @@ pub struct Fighter {
     pub defense: i32,
     pub power: i32,
     pub xp: i32,
+    pub mana: i32,
+    pub max_mana: i32,
+    /// effect inflicted on whoever this fighter hits
+    #[serde(default)]
+    pub on_hit: Option<Effect>,
     pub on_death: DeathCallback,
+    #[serde(default)]
+    pub damage_taken: DamageTally,
+    #[serde(default)]
+    pub resistances: Resistances,
+}
+
+/// What kind of harm is done, which decides how much of it a fighter resists
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum DamageType {
+    /// blows, arrows, falling rocks and the like
+    #[default]
+    Physical,
+    Fire,
+    Lightning,
+    Poison,
+}
+
+impl DamageType {
+    pub fn name(self) -> &'static str {
+        match self {
+            DamageType::Physical => "physical",
+            DamageType::Fire => "fire",
+            DamageType::Lightning => "lightning",
+            DamageType::Poison => "poison",
+        }
+    }
+}
+
+/// every damage type there is
+pub const DAMAGE_TYPES: &[DamageType] = &[
+    DamageType::Physical,
+    DamageType::Fire,
+    DamageType::Lightning,
+    DamageType::Poison,
+];
+
+/// How much of every type of damage a fighter shrugs off, in percent. A negative
+/// resistance is a weakness, making the damage worse instead
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Resistances {
+    pub physical: i32,
+    pub fire: i32,
+    pub lightning: i32,
+    pub poison: i32,
+}
+
+impl Resistances {
+    pub fn get_mut(&mut self, kind: DamageType) -> &mut i32 {
+        match kind {
+            DamageType::Physical => &mut self.physical,
+            DamageType::Fire => &mut self.fire,
+            DamageType::Lightning => &mut self.lightning,
+            DamageType::Poison => &mut self.poison,
+        }
+    }
+
+    pub fn get(mut self, kind: DamageType) -> i32 {
+        *self.get_mut(kind)
+    }
+
+    /// what is left of the damage once resisted; nothing is resisted more than
+    /// all of it
+    pub fn reduce(self, kind: DamageType, damage: i32) -> i32 {
+        damage * (100 - self.get(kind).min(100)) / 100
+    }
+
+    /// the resistances and weaknesses there are, "none" if there are none
+    pub fn describe(self) -> String {
+        let described: Vec<_> = DAMAGE_TYPES
+            .iter()
+            .filter(|&&kind| self.get(kind) != 0)
+            .map(|&kind| format!("{} {}%", kind.name(), self.get(kind)))
+            .collect();
+        if described.is_empty() {
+            "none".into()
+        } else {
+            described.join(", ")
+        }
+    }
+}
+
+/// The damage a fighter has taken, by where it came from, so that the XP of its
+/// death goes to the player in proportion to the part they had in it
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct DamageTally {
+    pub player: i32,
+    /// allies of the player and other monsters
+    pub monsters: i32,
+    pub environment: i32,
+    /// dealt by monsters stumbling about confused by the player
+    #[serde(default)]
+    pub confused: i32,
+}
+
+impl DamageTally {
+    pub fn add(&mut self, source: DamageSource, damage: i32) {
+        match source {
+            DamageSource::Player => self.player += damage,
+            DamageSource::Monster => self.monsters += damage,
+            DamageSource::Environment => self.environment += damage,
+            DamageSource::Confused => self.confused += damage,
+        }
+    }
+
+    /// the player's share of `xp`
+    pub fn player_share(self, xp: i32) -> i32 {
+        let total = self.player + self.monsters + self.environment + self.confused;
+        if total > 0 {
+            xp * (self.player * 100 + self.confused * CONFUSED_SHARE_PERCENT) / (total * 100)
+        } else {
+            0
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum DeathCallback {
     Player,
     Monster,
+    /// a unique monster guarding the bottom of a branch
+    Boss,
+}
+
+/// Who or what dealt a blow, deciding who the kill goes to and whose share of the
+/// XP the damage counts towards.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DamageSource {
+    Player,
+    Monster,
+    /// traps, hazards and the like, crediting no one
+    Environment,
+    /// a monster the player confused, lashing out at whoever it stumbles into
+    Confused,
 }
 
 impl DeathCallback {
-    pub fn callback(self, object: &mut Object, messages: &mut Messages) {
+    pub fn callback(
+        self,
+        object: &mut Object,
+        source: DamageSource,
+        now: i32,
+        events: &mut Events,
+    ) {
         use DeathCallback::*;
-        let callback: fn(&mut Object, messages: &mut Messages) = match self {
+        let callback: fn(&mut Object, DamageSource, i32, &mut Events) = match self {
             Player => player_death,
             Monster => monster_death,
+            Boss => boss_death,
         };
-        callback(object, messages);
+        callback(object, source, now, events);
     }
 }
 
-fn player_death(player: &mut Object, messages: &mut Messages) {
+fn player_death(player: &mut Object, _source: DamageSource, _now: i32, events: &mut Events) {
     // the game ended!
-    messages.add("You died!", RED);
+    events.emit(GameEvent::PlayerDied);
 
     // for added effect, transform the player into a corpse!
     player.char = '%';
     player.color = DARK_RED;
 }
 
-fn monster_death(monster: &mut Object, messages: &mut Messages) {
-    // transform it into a nasty corpse! it doesn't block, can't be
-    // attacked and doesn't move
-    messages.add(
-        format!(
-            "{} is dead! You gain {} experience points.",
-            monster.name,
-            monster.fighter.unwrap().xp
-        ),
-        ORANGE,
-    );
+fn monster_death(monster: &mut Object, source: DamageSource, now: i32, events: &mut Events) {
+    let fighter = monster.fighter.unwrap();
+    events.emit(GameEvent::MonsterDied {
+        name: monster.name.clone(),
+        xp: fighter.damage_taken.player_share(fighter.xp),
+        by_player: source == DamageSource::Player,
+        boss: false,
+    });
+    become_remains(monster, now);
+}
+
+fn boss_death(boss: &mut Object, source: DamageSource, now: i32, events: &mut Events) {
+    let fighter = boss.fighter.unwrap();
+    events.emit(GameEvent::MonsterDied {
+        name: boss.name.clone(),
+        xp: fighter.damage_taken.player_share(fighter.xp),
+        by_player: source == DamageSource::Player,
+        boss: true,
+    });
+    become_remains(boss, now);
+}
+
+/// transform a monster into a nasty corpse! it doesn't block, can't be
+/// attacked and doesn't move, rotting away some turns after `now`
+fn become_remains(monster: &mut Object, now: i32) {
+    if let Some(fighter) = monster.fighter {
+        monster.meat = meat_of(&monster.name, fighter.max_hp);
+    }
     monster.char = '%';
+    monster.tile_index = None;
     monster.color = DARK_RED;
     monster.blocks = false;
     monster.fighter = None;
     monster.ai = None;
+    monster.decays_at = Some(now + CORPSE_DECAY_TURNS);
     monster.name = format!("remains of {}", monster.name);
 }
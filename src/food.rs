@@ -0,0 +1,145 @@
+//! Hunger, and the meat that keeps it at bay. The player grows hungrier every
+//! turn and starves once they run out of nourishment. The corpses monsters leave
+//! can be butchered for chunks of meat, which go rotten as the corpse they were
+//! cut from would have, and some of which are poisonous however fresh.
+use serde::{Deserialize, Serialize};
+use tcod::colors::{self, Color};
+
+// nourishment the player sets out with, and the most they can hold
+const START_NOURISHMENT: i32 = 1500;
+const MAX_NOURISHMENT: i32 = 2500;
+// below this much nourishment the player is hungry, then weak
+const HUNGRY_BELOW: i32 = 300;
+const WEAK_BELOW: i32 = 100;
+// turns between the hit points a starving player loses
+pub const STARVE_TURNS: i32 = 5;
+
+// nourishment in a chunk of meat
+pub const CHUNK_NOURISHMENT: i32 = 400;
+// hit points of the monster for every chunk its corpse gives, and the most it
+// gives
+const HP_PER_CHUNK: i32 = 8;
+const MAX_CHUNKS: i32 = 4;
+// meat with fewer turns than this left before it rots away has gone bad
+pub const ROTTEN_BELOW: i32 = 150;
+// turns eating rotten meat leaves the player retching and confused, and the
+// damage poisonous meat does
+pub const NAUSEA_TURNS: i32 = 8;
+pub const POISON_DAMAGE: i32 = 6;
+
+// monsters that leave nothing to eat, and those whose flesh is poisonous
+const INEDIBLE: &[&str] = &["skeleton", "shade", "lich", "ghost"];
+const POISONOUS: &[&str] = &["ghoul", "spitting cobra", "giant spider", "abyssal horror"];
+
+/// What a corpse, or a chunk cut from one, is the meat of
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Meat {
+    /// the monster it came from
+    pub of: String,
+    pub poisonous: bool,
+    /// chunks still to be cut from a corpse
+    pub chunks: i32,
+    /// whether it has gone bad, as the clock last told
+    #[serde(default)]
+    pub rotten: bool,
+}
+
+/// the meat a monster with the given name and health leaves behind, none for the
+/// monsters made of bone or of shadow. Elites go by their kind
+pub fn meat_of(name: &str, max_hp: i32) -> Option<Meat> {
+    if INEDIBLE.iter().any(|kind| name.ends_with(kind)) {
+        return None;
+    }
+    Some(Meat {
+        of: name.into(),
+        poisonous: POISONOUS.iter().any(|kind| name.ends_with(kind)),
+        chunks: (max_hp / HP_PER_CHUNK).clamp(1, MAX_CHUNKS),
+        rotten: false,
+    })
+}
+
+/// whether meat that rots away on turn `decays_at` has gone bad by turn `now`
+pub fn is_rotten(decays_at: i32, now: i32) -> bool {
+    decays_at - now < ROTTEN_BELOW
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HungerState {
+    Fed,
+    Hungry,
+    Weak,
+    /// losing hit points every few turns
+    Starving,
+}
+
+impl HungerState {
+    /// what the panel shows, nothing while the player is fed
+    pub fn label(self) -> Option<(&'static str, Color)> {
+        match self {
+            HungerState::Fed => None,
+            HungerState::Hungry => Some(("Hungry", colors::YELLOW)),
+            HungerState::Weak => Some(("Weak", colors::ORANGE)),
+            HungerState::Starving => Some(("Starving", colors::RED)),
+        }
+    }
+
+    /// the message for the player growing this hungry
+    pub fn onset_message(self) -> &'static str {
+        match self {
+            HungerState::Fed => "You feel sated.",
+            HungerState::Hungry => "You are getting hungry.",
+            HungerState::Weak => "You feel weak with hunger.",
+            HungerState::Starving => "You are starving!",
+        }
+    }
+}
+
+/// How long what the player has eaten lasts them, by the clock
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Hunger {
+    /// the turn the player starts starving on, unless they eat before
+    pub starves_at: i32,
+}
+
+impl Default for Hunger {
+    fn default() -> Self {
+        Hunger::starting_at(0)
+    }
+}
+
+impl Hunger {
+    /// the hunger of a player setting out on turn `now`
+    pub fn starting_at(now: i32) -> Self {
+        Hunger {
+            starves_at: now + START_NOURISHMENT,
+        }
+    }
+
+    /// the nourishment the player has left on turn `now`
+    fn nourishment(self, now: i32) -> i32 {
+        (self.starves_at - now).max(0)
+    }
+
+    pub fn state(self, now: i32) -> HungerState {
+        match self.nourishment(now) {
+            n if n <= 0 => HungerState::Starving,
+            n if n < WEAK_BELOW => HungerState::Weak,
+            n if n < HUNGRY_BELOW => HungerState::Hungry,
+            _ => HungerState::Fed,
+        }
+    }
+
+    /// the state the player grew into on turn `now`, if it just changed
+    pub fn onset(self, now: i32) -> Option<HungerState> {
+        Some(self.state(now)).filter(|&state| state != self.state(now - 1))
+    }
+
+    /// whether there is room for the given nourishment on turn `now`
+    pub fn can_eat(self, nourishment: i32, now: i32) -> bool {
+        self.nourishment(now) + nourishment / 2 <= MAX_NOURISHMENT
+    }
+
+    pub fn eat(&mut self, nourishment: i32, now: i32) {
+        self.starves_at = now + (self.nourishment(now) + nourishment).min(MAX_NOURISHMENT);
+    }
+}
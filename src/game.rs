@@ -1,117 +1,442 @@
 use rand::{thread_rng, Rng};
 
 use crate::{
+    achievements::Achievements,
     ai::Ai,
-    fighter::{DeathCallback, Fighter},
-    get_names_under_mouse, inventory_menu,
-    item::{cast_confuse, cast_heal, cast_lightning, Item, UseResult},
-    map::Map,
-    mapbuilder::MapBuilder,
+    animations::Animations,
+    artifact::on_hit,
+    audio::{play_ambient, play_event_sound},
+    autosave,
+    bestiary::monster_details,
+    bones::{haunt, leave_bones, take_bones},
+    bury,
+    debugconsole::{parse_command, read_command, Command},
+    delete_save,
+    difficulty::Difficulty,
+    dungeon::{Branch, LevelId, LevelState},
+    effects::{EffectKind, POISON_DAMAGE_PER_TURN},
+    events::{Events, GameEvent},
+    fighter::{DamageSource, DamageType, DeathCallback, Fighter},
+    food::{is_rotten, Hunger, HungerState, STARVE_TURNS},
+    get_names_under_mouse,
+    glyphaudit::glyph_audit,
+    groundeffects::{Ground, GroundKind},
+    hazards::{Hazard, HazardKind},
+    hints::give_hint,
+    intrinsics::{describe_intrinsics, Intrinsic, REGENERATION_TURNS},
+    inventoryui::inventory_menu,
+    item::{
+        cast_blink, cast_charm, cast_confuse, cast_elixir, cast_fire, cast_heal, cast_identify,
+        cast_lightning, cast_remove_curse, cast_teleport, eat, equip, fire_bow, identify,
+        item_object, quaff_experience, quaff_invisibility, quaff_might, quaff_resilience,
+        quaff_see_invisible, quaff_toughness, quaff_vigor, reveal_kind, rub_charm, take_off,
+        use_amulet, use_key, zap_digging, zap_lightning, Armor, Category, Item, Slot, UseResult,
+    },
+    keys::{binding_for, help_text, KeyCommand, Macro, HELP_WIDTH},
+    levelevents::{meet_sage, meet_trader, roll_level_event, spring_ambush, LevelEvent},
+    luck::lucky,
+    map::{Door, Map, Terrain},
+    mapbuilder::{create_stairs, MapBuilder},
     menu,
+    menuui::Menu,
     messages::Messages,
-    msgbox, mut_two,
+    monsters::{create_monster, kind_of, monster_by_name, Ability, Faction},
+    morgue::{morgue_text, write_morgue},
+    msgbox,
     object::Object,
-    render_bar, save_game,
+    options::{options_menu, AutoPickup},
+    palette::Colors,
+    pathfinding::{DijkstraMap, PathFinder, ScentMap},
+    pickupui::pickup_menu,
+    profile::{record_run_end, Class},
+    quests::Journal,
+    record_score,
+    rect::Rect,
+    render_bar, renderer,
+    runlog::{export_summary, RunLog},
+    save_game,
+    sight::Awareness,
+    skills::{WeaponClass, WeaponSkills},
+    spells::{cast_monster_spell, cast_spell, spell_learned_at, Spell},
+    stats::{LevelStats, Stats},
     tcoder::{
-        Tcod, BAR_WIDTH, CHARACTER_SCREEN_WIDTH, LEVEL_SCREEN_WIDTH, MAP_HEIGHT, MAP_WIDTH,
-        MSG_HEIGHT, MSG_WIDTH, MSG_X, PANEL_HEIGHT, PANEL_Y, SCREEN_WIDTH,
+        Aim, Layout, Tcod, BAR_WIDTH, CHARACTER_SCREEN_WIDTH, INVENTORY_WIDTH, LEVEL_SCREEN_WIDTH,
+        MAP_HEIGHT, MAP_WIDTH,
     },
+    theme::Theme,
+    tiles::glyph,
+    transition::descent_screen,
+    visibility::{hidden_from, in_sight, sees},
+    world::{build_town, offer_service, TOWN},
+};
+use colors::{
+    BLACK, LIGHT_BLUE, LIGHT_CYAN, LIGHT_GREEN, LIGHT_GREY, LIGHT_RED, ORANGE, RED, VIOLET, WHITE,
+    YELLOW,
 };
-use colors::{BLACK, DARKER_RED, GREEN, LIGHT_GREY, LIGHT_RED, RED, VIOLET, WHITE, YELLOW};
 use input::Event;
 use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, mem};
 use tcod::{
     colors,
-    console::blit,
-    input::{self, Key},
-    map::FovAlgorithm,
-    BackgroundFlag, Color, Console, TextAlignment,
+    console::{blit, Offscreen},
+    input,
+    line::Line,
+    map::Map as FovMap,
+    BackgroundFlag, Console, TextAlignment,
 };
 
-//parameters for dungeon generator
-const ROOM_MAX_SIZE: i32 = 10;
-const ROOM_MIN_SIZE: i32 = 6;
-const MAX_ROOMS: i32 = 30;
-
 pub const PLAYER: usize = 0;
 
-const MAX_ROOM_MONSTERS: i32 = 3;
-const MAX_ROOM_ITEMS: i32 = 2;
-
-const FOV_ALGO: FovAlgorithm = FovAlgorithm::Basic; // default FOV algorithm
 const FOV_LIGHT_WALLS: bool = true; // light walls or not
-const TORCH_RADIUS: i32 = 10;
+const BLIND_RADIUS: i32 = 1;
+// how far the player sees in the dark, without a light
+const DARK_RADIUS: i32 = 2;
+// turns of fuel left when a burning light gives its warning
+const FUEL_WARNING_TURNS: i32 = 50;
+
+// how strongly the paused game is darkened
+const PAUSE_DIM: f32 = 0.6;
 
 // experience and level-ups
 const LEVEL_UP_BASE: i32 = 200;
 const LEVEL_UP_FACTOR: i32 = 150;
 
-const COLOR_DARK_WALL: Color = Color { r: 0, g: 0, b: 100 };
-const COLOR_LIGHT_WALL: Color = Color {
-    r: 130,
-    g: 110,
-    b: 50,
-};
+// explorer mode: a checkpoint every few levels, and the price paid for dying
+const CHECKPOINT_INTERVAL: u32 = 3;
+const DEATH_XP_PENALTY_PERCENT: i32 = 50;
 
-const COLOR_DARK_GROUND: Color = Color {
-    r: 50,
-    g: 50,
-    b: 150,
-};
-const COLOR_LIGHT_GROUND: Color = Color {
-    r: 200,
-    g: 180,
-    b: 50,
-};
+// wearing heavy armor, the monsters get an extra move every this many turns
+const ENCUMBRANCE_TURNS: i32 = 3;
+
+// items the player can carry, the inventory screen pages through them
+pub const INVENTORY_SIZE: usize = 52;
+// pounds the player can carry before being overburdened, plus as much again for
+// each point of power
+const CARRY_BASE: i32 = 50;
+const CARRY_PER_POWER: i32 = 10;
+
+// decaying clutter allowed on a level before the oldest is reclaimed early
+const CLUTTER_BUDGET: usize = 40;
+
+// monsters that shoot stop closing in once the player is this close
+const RANGED_ATTACK_RANGE: f32 = 5.0;
+
+// morale: badly hurt monsters may turn tail, and each turn may find their courage
+const FLEE_HP_PERCENT: i32 = 25;
+const FLEE_CHANCE: f32 = 0.3;
+const FLEE_TURNS: i32 = 10;
+const RALLY_CHANCE: f32 = 0.1;
+
+// how close monsters of other factions must be for a monster to go after them
+const FACTION_SIGHT: f32 = 6.0;
+// how close a charmed monster keeps to the player
+const ALLY_FOLLOW_DISTANCE: f32 = 2.5;
+
+// noise wakes sleeping monsters within its radius, seen or not; a sleeping monster
+// otherwise notices the player with this chance per turn, divided by the distance
+const ATTACK_NOISE: f32 = 6.0;
+const DOOR_SMASH_NOISE: f32 = 10.0;
+const NOTICE_CHANCE: f32 = 0.5;
+// chance a wandering monster takes a step on its turn
+const WANDER_STEP_CHANCE: f32 = 0.5;
+
+// chance of finding each hidden door next to the player when searching for it,
+// and the chance in percent per point of perception of noticing it in passing
+const SEARCH_CHANCE: f32 = 0.35;
+const NOTICE_PERCENT_PER_PERCEPTION: i32 = 2;
+
+// the most turns a rest lasts
+const REST_TURNS: i32 = 100;
+
+// the chance of each blow landing when fighting with a weapon in either hand
+const DUAL_WIELD_ACCURACY: f32 = 0.75;
+
+// environmental hazards: chance per turn to brew up, the turns of warning given and
+// how far from the player they strike
+const HAZARD_CHANCE: f32 = 0.01;
+const HAZARD_WARNING_TURNS: i32 = 3;
+const HAZARD_REACH: i32 = 4;
+const SPORE_CONFUSION_TURNS: i32 = 4;
+const SPORE_GAS_TURNS: i32 = 8;
+const RUBBLE_DECAY_TURNS: i32 = 500;
+const HAZARD_DODGE_CHANCE: f32 = 0.2;
+
+// terrain: the damage lava does every turn, the chance deep water soaks a scroll
+// every turn, and the fall down a chasm
+const LAVA_DAMAGE: i32 = 5;
+const SOAK_CHANCE: f32 = 0.1;
+const FALL_DAMAGE: i32 = 5;
+const FALL_LANDING_TRIES: i32 = 100;
+
+// new monsters wander onto the level now and then, out of the player's sight and
+// some way off: chance per turn, plus as much again per level of depth, until
+// the level is this crowded
+const REPOPULATE_CHANCE: f32 = 0.002;
+const REPOPULATE_DEPTH_CHANCE: f32 = 0.0005;
+const REPOPULATE_DISTANCE: f32 = 12.0;
+const REPOPULATE_MAX_MONSTERS: usize = 30;
+const REPOPULATE_TRIES: i32 = 20;
+
+// escaping with the amulet: monsters wander in this many times as often, and may
+// lie in wait at the top of the stairs
+const ESCAPE_REPOPULATE_FACTOR: f32 = 4.0;
+const ESCAPE_AMBUSH_CHANCE: f32 = 0.25;
+
+// luck given by each lucky charm carried, and the most the gods grant or take
+// away at altars
+const CHARM_LUCK: i32 = 1;
+const MAX_FAVOUR: i32 = 2;
+
+// spellcasting
+const MANA_REGEN_TURNS: i32 = 5;
+const LEVEL_UP_MANA: i32 = 5;
+
+// end-of-level grading, bonus XP is multiplied by the dungeon level
+const FULL_EXPLORATION_PERCENT: i32 = 90;
+const EXPLORATION_BONUS_XP: i32 = 25;
+const SPEED_PAR_TURNS: i32 = 150;
+const SPEED_BONUS_XP: i32 = 25;
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 enum PlayerAction {
     TookTurn,
     DidntTakeTurn,
     Exit,
+    /// the run was given up, there is nothing left to save
+    Abandon,
+}
+
+/// How death is handled, chosen when the character is created.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum GameMode {
+    /// death ends the run
+    Permadeath,
+    /// death sends the player back to the last checkpoint level, for exploring
+    /// the dungeon without losing the character
+    #[serde(alias = "Casual")]
+    Explorer,
+}
+
+impl GameMode {
+    pub fn name(self) -> &'static str {
+        match self {
+            GameMode::Permadeath => "permadeath",
+            GameMode::Explorer => "explorer",
+        }
+    }
+}
+
+/// Something the player can do with a feature on or next to their tile
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Interaction {
+    PickUp(usize),
+    /// choose from several items lying here
+    PickUpPile,
+    TakeStairs(LevelId),
+    OpenDoor(i32, i32),
+    CloseDoor(i32, i32),
+    /// open a locked door with a key
+    UnlockDoor(i32, i32),
+    /// take up the services of a townsperson standing next to the player
+    Visit(usize),
+    /// cut a corpse lying here up into chunks of meat
+    Butcher(usize),
+}
+
+/// A journey across the level to a tile the player clicked, taken a step a turn
+#[derive(Clone, Debug, Default)]
+struct Travel {
+    /// the steps left, the next one last
+    path: Vec<(i32, i32)>,
+    /// whether to pick up what lies at the destination
+    pick_up: bool,
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct Game {
-    map: Map,
+    pub map: Map,
+    /// glyph of the object last seen on each tile, drawn while it is out of sight
+    memory: Vec<Vec<Option<char>>>,
     pub messages: Messages,
     pub inventory: Vec<Object>,
-    dungeon_level: u32,
+    /// where in the level graph the player is
+    pub level: LevelId,
     pub objects: Vec<Object>,
-    map_builder: MapBuilder,
+    pub level_stats: LevelStats,
+    pub spells: Vec<Spell>,
+    pub mode: GameMode,
+    /// how hard the dungeon is, chosen along with the mode
+    #[serde(default)]
+    pub difficulty: Difficulty,
+    /// what the character set out as
+    #[serde(default)]
+    pub class: Class,
+    checkpoint: LevelId,
+    pub deaths: u32,
+    /// branches whose boss the player has slain
+    #[serde(default)]
+    bosses_slain: Vec<Branch>,
+    #[serde(default)]
+    pub gold: i32,
+    /// the kinds of item the player has identified one of, told at a glance since
+    #[serde(default)]
+    known_kinds: Vec<Item>,
+    /// the luck the gods granted or took away at altars
+    #[serde(default)]
+    favour: i32,
+    /// environmental hazard about to strike the current level
+    #[serde(default)]
+    hazard: Option<Hazard>,
+    /// fire and gas lingering on the current level
+    #[serde(default)]
+    pub ground: Ground,
+    /// the levels the player has left, restored when they return
+    #[serde(default, with = "crate::savefile::level_map")]
+    levels: HashMap<LevelId, LevelState>,
+    #[serde(default)]
+    pub stats: Stats,
+    /// every event of the run, for the run summary
+    #[serde(default)]
+    pub run_log: RunLog,
+    /// the turns taken since the run began, the clock everything that takes time
+    /// goes by
+    #[serde(default)]
+    pub turn: i32,
+    #[serde(skip)]
+    travel: Option<Travel>,
+    /// the turns left to rest for, while resting
+    #[serde(skip)]
+    rest: Option<i32>,
+    /// the last run or rest, for the player to do again
+    #[serde(skip)]
+    last_errand: Option<KeyCommand>,
+    #[serde(skip)]
+    keyboard_macro: Macro,
+    #[serde(skip)]
+    events: Events,
+    /// what is playing over the map, floating numbers, flashes and shaking
+    #[serde(default)]
+    animations: Animations,
+    #[serde(default)]
+    pub achievements: Achievements,
+    /// what the player left in the chest in town
+    #[serde(default)]
+    pub stash: Vec<Object>,
+    #[serde(default)]
+    pub journal: Journal,
+    /// whether the player has taken the amulet and must now carry it up to town
+    #[serde(default)]
+    escaping: bool,
+    /// what the levels of the run are generated from
+    #[serde(default)]
+    pub seed: u64,
+    /// tells the saves of this run from those of any other, 0 in saves from before
+    /// runs had one
+    #[serde(default)]
+    pub run_id: u64,
+    #[serde(default)]
+    pub hunger: Hunger,
+    #[serde(default)]
+    pub weapon_skills: WeaponSkills,
+    /// how far every tile is from the player, for the monsters to close in by
+    #[serde(skip)]
+    player_map: Option<DijkstraMap>,
+    /// the way away from the player, worked out once a monster needs it
+    #[serde(skip)]
+    flee_map: DijkstraMap,
+    /// whether the flee map is still to be worked out from this turn's player map
+    #[serde(skip)]
+    flee_map_stale: bool,
+    /// the buffers the ways around the level are found in
+    #[serde(skip)]
+    paths: PathFinder,
+    /// where the player walked lately on the current level
+    #[serde(skip)]
+    scent: ScentMap,
+    /// the colors the current level is drawn in
+    #[serde(default)]
+    theme: Theme,
 }
 
 impl Game {
-    pub fn new(tcod: &mut Tcod) -> Game {
+    pub fn new(
+        tcod: &mut Tcod,
+        mode: GameMode,
+        difficulty: Difficulty,
+        class: Class,
+        seed: u64,
+    ) -> Game {
         // create object representing the player
         let mut player = Object::new(0, 0, '@', "player", WHITE, true);
         player.alive = true;
+        player.faction = Faction::Player;
         player.fighter = Some(Fighter {
             max_hp: 30,
             hp: 30,
             defense: 2,
             power: 5,
             xp: 0,
+            mana: 10,
+            max_mana: 10,
+            on_hit: None,
             on_death: DeathCallback::Player, // <1>
+            damage_taken: Default::default(),
+            resistances: Default::default(),
         });
 
         let mut game = Game {
             // generate map (at this point it's not drawn to the screen)
             map: vec![],
+            memory: vec![],
             messages: Messages::new(),
             inventory: vec![], // <1>
-            dungeon_level: 1,
+            level: LevelId::new(Branch::Main, 1),
             objects: vec![player],
-            map_builder: MapBuilder {
-                max_rooms: MAX_ROOMS,
-                room_min_size: ROOM_MIN_SIZE,
-                room_max_size: ROOM_MAX_SIZE,
-                max_room_monsters: MAX_ROOM_MONSTERS,
-                max_room_items: MAX_ROOM_ITEMS,
-            },
+            level_stats: Default::default(),
+            spells: vec![],
+            mode,
+            difficulty,
+            class,
+            checkpoint: LevelId::new(Branch::Main, 1),
+            deaths: 0,
+            bosses_slain: vec![],
+            gold: 0,
+            known_kinds: vec![],
+            favour: 0,
+            hazard: None,
+            ground: Default::default(),
+            levels: HashMap::new(),
+            stats: Default::default(),
+            run_log: Default::default(),
+            turn: 0,
+            travel: None,
+            rest: None,
+            last_errand: None,
+            keyboard_macro: Default::default(),
+            events: Default::default(),
+            animations: Default::default(),
+            achievements: Default::default(),
+            stash: vec![],
+            journal: Default::default(),
+            escaping: false,
+            seed,
+            run_id: rand::random(),
+            hunger: Default::default(),
+            weapon_skills: Default::default(),
+            player_map: None,
+            flee_map: Default::default(),
+            flee_map_stale: false,
+            paths: Default::default(),
+            scent: Default::default(),
+            theme: Default::default(),
         };
+        // the player sets out with a lit torch
+        let mut torch = item_object(Item::Torch, 0, 0, 0, &mut thread_rng());
+        torch.equipped = true;
+        game.inventory.push(torch);
+        class.outfit(&mut game);
 
-        game.initialize_map();
+        game.initialize_map(None);
         game.initialise_fov(tcod);
 
         // a warm welcoming message!
@@ -123,15 +448,85 @@ impl Game {
         game
     }
 
-    fn initialize_map(&mut self) {
-        self.map = self.map_builder.build(&mut self.objects);
+    /// restore the current level as the player left it, or generate it on a first
+    /// visit, putting the player on the stairs leading back to the level they came
+    /// from, if any. Returns the feelings a newly generated level gives
+    fn initialize_map(&mut self, arrived_from: Option<LevelId>) -> Vec<&'static str> {
+        let mut feelings = vec![];
+        self.hazard = None;
+        self.ground.clear();
+        self.scent = Default::default();
+        if let Some(state) = self.levels.remove(&self.level) {
+            self.map = state.map;
+            self.memory = state.memory;
+            self.objects.truncate(1);
+            self.objects.extend(state.objects);
+            self.level_stats = state.stats;
+            self.theme = state.theme;
+        } else if self.level == TOWN {
+            self.map = build_town(&mut self.objects);
+            self.theme = Theme::Stone;
+            self.memory = vec![vec![None; MAP_HEIGHT as usize]; MAP_WIDTH as usize];
+            self.level_stats = Default::default();
+        } else {
+            let builder = MapBuilder::for_level(self.level);
+            let builder = MapBuilder {
+                max_room_monsters: self.difficulty.monster_count(builder.max_room_monsters),
+                bosses_slain: self.bosses_slain.clone(),
+                luck: self.luck(),
+                // a death in explorer mode regenerates the dungeon below, differently
+                seed: self.level.seed(self.seed, self.deaths),
+                ..builder
+            };
+            let (map, level_feelings) = builder.build(&mut self.objects);
+            self.map = map;
+            self.theme = builder.theme;
+            feelings = level_feelings;
+            for monster in self.objects.iter_mut().skip(1) {
+                if monster.ai.is_some() {
+                    self.difficulty.toughen(monster);
+                }
+            }
+            // a character of another run may have died here
+            match take_bones(self.level, self.run_id) {
+                Ok(Some(bones)) => {
+                    if haunt(bones, &self.map, &mut self.objects) {
+                        feelings.push("You feel a chill, as if someone met their end here.");
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => self.messages.add(
+                    format!("Could not take the bones from the file: {}", e),
+                    RED,
+                ),
+            }
+            self.memory = vec![vec![None; MAP_HEIGHT as usize]; MAP_WIDTH as usize];
+            self.level_stats = Default::default();
+            for &feeling in &feelings {
+                self.messages.add(feeling, LIGHT_CYAN);
+            }
+        }
+
+        let stairs_back = self
+            .objects
+            .iter()
+            .find(|object| object.stairs.is_some() && object.stairs == arrived_from)
+            .map(|stairs| stairs.pos());
+        if let Some((x, y)) = stairs_back {
+            self.objects[PLAYER].set_pos(x, y);
+        }
+        self.recognise_kinds();
+        feelings
     }
 
     pub fn play(&mut self, tcod: &mut Tcod) {
         self.initialise_fov(tcod);
+        self.events.hooks.push(play_event_sound);
 
         // force FOV "recompute" first time through the game loop
         let mut previous_player_position = (-1, -1);
+        let mut previous_fov_radius = -1;
+        let mut previous_options = tcod.options;
 
         while !tcod.root.window_closed() {
             // clear the screen of the previous frame
@@ -144,289 +539,3036 @@ impl Game {
             }
 
             // render the screen
-            let fov_recompute = previous_player_position != (self.objects[PLAYER].pos()); // <1>
+            let fov_recompute = previous_player_position != (self.objects[PLAYER].pos()) // <1>
+                || previous_fov_radius != self.fov_radius()
+                || previous_options != tcod.options;
             self.render_all(tcod, fov_recompute);
+            previous_fov_radius = self.fov_radius();
+            previous_options = tcod.options;
+            // the music follows the player from branch to branch
+            play_ambient(Some(self.level.branch));
 
             tcod.root.flush();
 
+            // nothing happens while the player is away from the window
+            if !tcod.root.has_focus() {
+                self.pause(tcod);
+                continue;
+            }
+
             // level up if needed
             self.level_up(tcod);
 
             // handle keys and exit game if needed
             previous_player_position = self.objects[PLAYER].pos();
-            let player_action = self.handle_keys(tcod);
+            let player = &self.objects[PLAYER];
+            let player_action = if player.alive && player.has_effect(EffectKind::Paralyzed) {
+                // a paralyzed player can only watch the monsters take their turn
+                PlayerAction::TookTurn
+            } else if let Some(action) = self.travel_step(tcod) {
+                action
+            } else if let Some(action) = self.rest_step(tcod) {
+                action
+            } else if let Some(action) = self.macro_step(tcod) {
+                action
+            } else {
+                self.handle_keys(tcod)
+            };
             if player_action == PlayerAction::Exit {
                 save_game(self).unwrap();
                 break;
             }
+            if player_action == PlayerAction::Abandon {
+                self.abandon_run();
+                break;
+            }
+
+            if player_action == PlayerAction::TookTurn {
+                self.pass_turn(tcod);
+            }
 
             // let monsters take their turn
             if self.objects[PLAYER].alive && player_action != PlayerAction::DidntTakeTurn {
-                for id in 0..self.objects.len() {
-                    if self.objects[id].ai.is_some() {
-                        self.ai_take_turn(id, tcod);
+                // heavy armor slows the player, the monsters get an extra move now
+                // and then, and an overburdened player moves at half speed
+                let slowed = (self.worn_armor().is_some_and(|armor| armor.slows)
+                    && self.turn % ENCUMBRANCE_TURNS == 0)
+                    || self.overburdened()
+                    || self.player_terrain().slows();
+                self.refresh_ai_maps();
+                for _ in 0..(1 + slowed as usize) {
+                    for id in 0..self.objects.len() {
+                        if self.objects[id].ai.is_some() && self.objects[PLAYER].alive {
+                            self.ai_take_turn(id, tcod);
+                        }
+                    }
+                }
+                self.tick_hazards(tcod);
+                self.tick_ground(tcod);
+                self.digest();
+                self.regenerate(PLAYER);
+                self.suffer_poison(PLAYER);
+                self.tread_terrain(tcod);
+                // no monster wanders into town
+                if self.level != TOWN {
+                    self.repopulate(tcod);
+                }
+
+                if !self.objects[PLAYER].alive && self.player_died(tcod) {
+                    break;
+                }
+            }
+
+            self.drop_loot();
+            self.burst_remains();
+            self.recognise_kinds();
+            give_hint(self, tcod);
+            let told = self.messages.take_unlogged();
+            self.run_log.record_messages(self.turn, self.level, told);
+
+            let autosave_turns = tcod.options.autosave_turns;
+            if player_action == PlayerAction::TookTurn
+                && autosave_turns > 0
+                && self.turn % autosave_turns == 0
+                && self.objects[PLAYER].alive
+            {
+                self.autosave();
+            }
+
+            if let Some(branch) = self.slain_boss() {
+                self.bosses_slain.push(branch);
+                if self.boss_slain(branch, tcod) {
+                    break;
+                }
+            }
+            if self.escaping && self.level == TOWN && self.carries(Item::Amulet) {
+                self.ascend(tcod);
+                break;
+            }
+        }
+        play_ambient(None);
+    }
+
+    /// now and then bring a new monster onto the level somewhere out of sight, so
+    /// that a cleared level doesn't stay safe for ever
+    fn repopulate(&mut self, tcod: &Tcod) {
+        let depth = self.level.absolute_depth() as f32;
+        let mut chance = self
+            .difficulty
+            .spawn_chance(REPOPULATE_CHANCE + depth * REPOPULATE_DEPTH_CHANCE);
+        if self.escaping {
+            chance *= ESCAPE_REPOPULATE_FACTOR;
+        }
+        if rand::random::<f32>() >= chance {
+            return;
+        }
+        let monsters = self.objects.iter().filter(|o| o.ai.is_some()).count();
+        if monsters >= REPOPULATE_MAX_MONSTERS {
+            return;
+        }
+        let player = &self.objects[PLAYER];
+        let mut rng = thread_rng();
+        let spot = (0..REPOPULATE_TRIES)
+            .map(|_| (rng.gen_range(0, MAP_WIDTH), rng.gen_range(0, MAP_HEIGHT)))
+            .find(|&(x, y)| {
+                !in_sight(&tcod.fov, x, y)
+                    && player.distance(x, y) >= REPOPULATE_DISTANCE
+                    && !self.map[x as usize][y as usize].terrain.is_perilous()
+                    && !is_blocked(x, y, &self.map, &self.objects)
+            });
+        if let Some((x, y)) = spot {
+            let mut monster = create_monster(x, y, self.level, &mut thread_rng());
+            self.difficulty.toughen(&mut monster);
+            self.objects.push(monster);
+        }
+    }
+
+    /// Count down the hazard about to strike, or maybe announce a new one near the
+    /// player on a branch prone to them
+    fn tick_hazards(&mut self, tcod: &Tcod) {
+        let mut hazard = match self.hazard.take() {
+            Some(hazard) => hazard,
+            None => {
+                if let Some(kind) = HazardKind::of_branch(self.level.branch) {
+                    if rand::random::<f32>() < HAZARD_CHANCE {
+                        self.announce_hazard(kind);
                     }
                 }
+                return;
+            }
+        };
+        hazard.turns -= 1;
+        if hazard.turns > 0 {
+            self.hazard = Some(hazard);
+            return;
+        }
+
+        let kind = hazard.kind;
+        let seen = in_sight(&tcod.fov, hazard.x, hazard.y);
+        if seen || self.objects[PLAYER].distance(hazard.x, hazard.y) <= kind.radius() {
+            self.messages.add(kind.message(), LIGHT_RED);
+        }
+        if seen && kind == HazardKind::CaveIn {
+            self.animations.shake();
+        }
+        // the environment credits no one with the kills
+        for id in 0..self.objects.len() {
+            let object = &self.objects[id];
+            if object.fighter.is_none() || object.distance(hazard.x, hazard.y) > kind.radius() {
+                continue;
+            }
+            if id == PLAYER && lucky(HAZARD_DODGE_CHANCE, self.luck()) {
+                self.messages
+                    .add("You leap clear just in time!", LIGHT_CYAN);
+                continue;
+            }
+            let msg = format!("The {} is caught in it!", object.name);
+            self.messages.add(msg, LIGHT_RED);
+            self.damage(
+                id,
+                kind.damage(),
+                kind.damage_type(),
+                DamageSource::Environment,
+            );
+            if id == PLAYER && kind == HazardKind::SporeRelease && self.objects[PLAYER].alive {
+                let turn = self.turn;
+                self.objects[PLAYER].add_effect(EffectKind::Confused, SPORE_CONFUSION_TURNS, turn);
+                let msg = EffectKind::Confused.onset_message(&self.objects[PLAYER].name);
+                self.messages.add(msg, LIGHT_CYAN);
+            }
+        }
+        if kind == HazardKind::SporeRelease {
+            // the spores hang in the air a while
+            let map = &self.map;
+            self.ground.place_area(
+                GroundKind::PoisonGas,
+                (hazard.x, hazard.y),
+                kind.radius(),
+                SPORE_GAS_TURNS,
+                |kind, x, y| ground_open(map, kind, x, y),
+            );
+        }
+        if kind == HazardKind::CaveIn {
+            let mut rubble =
+                Object::new(hazard.x, hazard.y, ':', "rubble", colors::DARK_SEPIA, false);
+            rubble.decays_at = Some(self.turn + RUBBLE_DECAY_TURNS);
+            self.objects.push(rubble);
+        }
+    }
+
+    /// burn and choke whoever stands in fire or gas, then let the fire spread and
+    /// the gas drift
+    fn tick_ground(&mut self, tcod: &Tcod) {
+        for id in 0..self.objects.len() {
+            let object = &self.objects[id];
+            if !object.alive || object.fighter.is_none() {
+                continue;
+            }
+            let resisted = |kind| match kind {
+                GroundKind::Fire => object.has_intrinsic(Intrinsic::FireResistance),
+                GroundKind::PoisonGas => object.has_intrinsic(Intrinsic::PoisonResistance),
+            };
+            let effect = self.ground.at(object.x, object.y);
+            if let Some(effect) = effect.filter(|effect| !resisted(effect.kind)) {
+                if sees(&tcod.fov, &self.objects[PLAYER], object) {
+                    self.messages
+                        .add(effect.kind.hurt_message(&object.name), ORANGE);
+                }
+                self.damage(
+                    id,
+                    effect.kind.damage(),
+                    effect.kind.damage_type(),
+                    DamageSource::Environment,
+                );
+            }
+        }
+        let map = &self.map;
+        self.ground
+            .tick(|kind, x, y| ground_open(map, kind, x, y), &mut thread_rng());
+    }
+
+    /// pick a floor tile near the player for a hazard to strike in a few turns
+    fn announce_hazard(&mut self, kind: HazardKind) {
+        let (player_x, player_y) = self.objects[PLAYER].pos();
+        let x = player_x + thread_rng().gen_range(-HAZARD_REACH, HAZARD_REACH + 1);
+        let y = player_y + thread_rng().gen_range(-HAZARD_REACH, HAZARD_REACH + 1);
+        let in_map = x >= 0 && y >= 0 && x < MAP_WIDTH && y < MAP_HEIGHT;
+        if !in_map || self.map[x as usize][y as usize].blocked {
+            return;
+        }
+        self.messages.add(kind.warning_message(), ORANGE);
+        self.hazard = Some(Hazard {
+            kind,
+            x,
+            y,
+            turns: HAZARD_WARNING_TURNS,
+        });
+    }
+
+    /// leave the loot of monsters that died this turn on the floor
+    fn drop_loot(&mut self) {
+        let luck = self.luck();
+        let mut dropped = vec![];
+        for object in self
+            .objects
+            .iter_mut()
+            .filter(|object| object.fighter.is_none())
+        {
+            if let Some(item) = object.loot.take() {
+                dropped.push(item_object(
+                    item,
+                    object.x,
+                    object.y,
+                    luck,
+                    &mut thread_rng(),
+                ));
+            }
+        }
+        self.objects.extend(dropped);
+    }
+
+    /// learn the kinds of the items the player has identified, then tell what they
+    /// are the items of every kind learned, in the pack and on the level
+    fn recognise_kinds(&mut self) {
+        for object in &self.inventory {
+            match object.item {
+                Some(item)
+                    if !object.unidentified
+                        && item.unidentified_name().is_some()
+                        && !self.known_kinds.contains(&item) =>
+                {
+                    self.known_kinds.push(item)
+                }
+                _ => {}
+            }
+        }
+        let known = &self.known_kinds;
+        for object in self.inventory.iter_mut().chain(self.objects.iter_mut()) {
+            if object.unidentified && object.item.is_some_and(|item| known.contains(&item)) {
+                reveal_kind(object);
+            }
+        }
+    }
+
+    /// leave fire or gas where the monsters that go up in it died this turn
+    fn burst_remains(&mut self) {
+        let map = &self.map;
+        for object in self.objects.iter_mut().filter(|o| o.fighter.is_none()) {
+            let bursts = object.abilities.iter().find_map(|ability| match *ability {
+                Ability::DeathBurst {
+                    kind,
+                    radius,
+                    turns,
+                } => Some((kind, radius, turns)),
+                _ => None,
+            });
+            if let Some((kind, radius, turns)) = bursts {
+                object.abilities.clear();
+                self.ground
+                    .place_area(kind, object.pos(), radius, turns, |kind, x, y| {
+                        ground_open(map, kind, x, y)
+                    });
+            }
+        }
+    }
+
+    /// the branch whose boss has just died on this level, if any
+    fn slain_boss(&self) -> Option<Branch> {
+        let branch = self.level.branch;
+        let bottom = self.level.down().is_none() && branch.boss().is_some();
+        let boss_alive = self.objects.iter().any(|object| {
+            object
+                .fighter
+                .is_some_and(|f| f.on_death == DeathCallback::Boss)
+        });
+        if bottom && !boss_alive && !self.bosses_slain.contains(&branch) {
+            Some(branch)
+        } else {
+            None
+        }
+    }
+
+    /// Celebrate a slain boss, returns whether the run is over. The Ancient King
+    /// leaves the amulet behind, to be carried up to town to win the game, and
+    /// opens the Abyss; the Eater of Kings below it gives the true ending.
+    fn boss_slain(&mut self, branch: Branch, tcod: &mut Tcod) -> bool {
+        if branch == Branch::Main {
+            msgbox(
+                "\nThe Ancient King is no more and the tombs fall silent. His amulet \
+                 lies among his remains: carry it up to the town and the tombs are \
+                 beaten. Yet from beneath the throne, something older stirs...\n",
+                LEVEL_SCREEN_WIDTH,
+                &mut tcod.root,
+            );
+            let (x, y) = self.objects[PLAYER].pos();
+            let abyss = LevelId::new(Branch::Abyss, 1);
+            self.objects
+                .push(create_stairs(x, y, abyss, '>', "stairs to the Abyss"));
+            self.messages
+                .add("A stairway into the Abyss opens beneath your feet.", VIOLET);
+            return false;
+        }
+
+        msgbox(
+            "\nThe Eater of Kings is slain. No ruler will ever again be \
+             devoured in the dark, and the world above will sing of \
+             you forever.\n",
+            LEVEL_SCREEN_WIDTH,
+            &mut tcod.root,
+        );
+        self.record_victory("reached the true ending");
+        true
+    }
+
+    /// win the game by bringing the amulet up to town
+    fn ascend(&mut self, tcod: &mut Tcod) {
+        msgbox(
+            "\nYou step out into the daylight with the Amulet of the Ancient Kings. \
+             The whole town turns out to cheer your name, and you retire in glory.\n",
+            LEVEL_SCREEN_WIDTH,
+            &mut tcod.root,
+        );
+        self.record_victory("retired in glory with the amulet");
+    }
+
+    fn record_victory(&mut self, ending: &str) {
+        let score = format!(
+            "{} mode: {} on {} at character level {} after {} turns (deaths: {})",
+            self.mode.name(),
+            ending,
+            self.level.name(),
+            self.objects[PLAYER].level,
+            self.turn,
+            self.deaths
+        );
+        if let Err(e) = record_score(&score) {
+            self.messages
+                .add(format!("Could not record the score: {}", e), RED);
+        }
+        record_run_end(self, true);
+        delete_save().ok();
+    }
+
+    /// Dim the screen under a "Paused" overlay and ignore all input until a key is
+    /// pressed with the window focused
+    fn pause(&mut self, tcod: &mut Tcod) {
+        let (width, height) = (tcod.layout.screen_width, tcod.layout.screen_height);
+        let mut shade = Offscreen::new(width, height);
+        shade.set_default_background(BLACK);
+        shade.clear();
+        blit(
+            &shade,
+            (0, 0),
+            (width, height),
+            &mut tcod.root,
+            (0, 0),
+            PAUSE_DIM,
+            PAUSE_DIM,
+        );
+        tcod.root.set_default_foreground(WHITE);
+        tcod.root.print_ex(
+            width / 2,
+            height / 2 - 1,
+            BackgroundFlag::None,
+            TextAlignment::Center,
+            "Paused",
+        );
+        tcod.root.set_default_foreground(LIGHT_GREY);
+        tcod.root.print_ex(
+            width / 2,
+            height / 2 + 1,
+            BackgroundFlag::None,
+            TextAlignment::Center,
+            "Press any key to resume",
+        );
+
+        while !tcod.root.window_closed() {
+            tcod.root.flush();
+            // keys pressed while the window is in the background don't count
+            if let Some((_, Event::Key(_))) = input::check_for_event(input::KEY_PRESS) {
+                if tcod.root.has_focus() {
+                    break;
+                }
+            }
+        }
+        tcod.key = Default::default();
+    }
+
+    /// read and run debug console commands until the console is dismissed
+    fn debug_console(&mut self, tcod: &mut Tcod) {
+        let mut output = String::from("Debug console, Escape to close.");
+        while let Some(line) = read_command(&output, &mut tcod.root) {
+            output = match parse_command(&line) {
+                Ok(command) => self.run_command(command, tcod),
+                Err(e) => e,
+            };
+            self.render_all(tcod, true);
+        }
+    }
+
+    /// carry out a debug console command through the same actions the player uses,
+    /// returns what happened
+    fn run_command(&mut self, command: Command, tcod: &mut Tcod) -> String {
+        match command {
+            Command::Spawn { name, x, y } => {
+                let in_map = x >= 0 && y >= 0 && x < MAP_WIDTH && y < MAP_HEIGHT;
+                if !in_map || is_blocked(x, y, &self.map, &self.objects) {
+                    return format!("({}, {}) is not free", x, y);
+                }
+                let mut monster = monster_by_name(&name, x, y).unwrap();
+                self.difficulty.toughen(&mut monster);
+                self.objects.push(monster);
+                format!("Spawned a {} at ({}, {}).", name, x, y)
+            }
+            Command::Give { item, count } => {
+                let (x, y) = self.objects[PLAYER].pos();
+                for _ in 0..count {
+                    self.objects
+                        .push(item_object(item, x, y, self.luck(), &mut thread_rng()));
+                    let id = self.objects.len() - 1;
+                    self.pick_item_up(id);
+                }
+                format!("Gave {} x {}.", count, item.id())
             }
+            Command::Goto(level) => {
+                self.change_level(level, tcod);
+                format!("Went to {}.", self.level.name())
+            }
+            Command::Seed => format!("Levels are generated from seed {}.", self.seed),
         }
     }
 
-    /// return the position of a tile left-clicked in player's FOV (optionally in a
-    /// range), or (None,None) if right-clicked.
+    /// return the position of a tile picked in player's FOV (optionally in a
+    /// range), or None if cancelled. A tile is picked by left-clicking it or by
+    /// moving the cursor over it with the arrow keys, or Tab to jump between the
+    /// enemies in sight, and pressing Enter. The cursor follows the mouse too, so
+    /// the path of the shot is drawn to wherever the player points.
     pub fn target_tile(&mut self, tcod: &mut Tcod, max_range: Option<f32>) -> Option<(i32, i32)> {
-        use tcod::input::KeyCode::Escape;
-        loop {
+        tcod.aim = Some(Aim { max_range });
+        // accept the target if it's in FOV, and in case a range
+        // is specified, if it's in that range
+        self.pick_tile(tcod, |game, tcod, x, y| {
+            let in_range =
+                max_range.is_none_or(|range| game.objects[PLAYER].distance(x, y) <= range);
+            in_sight(&tcod.fov, x, y) && in_range
+        })
+    }
+
+    /// return the position of an explored tile free and safe to stand on, outside
+    /// any vault, in sight or not, or None if cancelled
+    pub fn target_explored_tile(&mut self, tcod: &mut Tcod) -> Option<(i32, i32)> {
+        self.pick_tile(tcod, |game, _tcod, x, y| {
+            let tile = &game.map[x as usize][y as usize];
+            tile.explored
+                && !tile.vault
+                && !tile.terrain.is_perilous()
+                && !is_blocked(x, y, &game.map, &game.objects)
+        })
+    }
+
+    /// let the player pick a tile on the map that `accept` approves of
+    fn pick_tile<F>(&mut self, tcod: &mut Tcod, accept: F) -> Option<(i32, i32)>
+    where
+        F: Fn(&Game, &Tcod, i32, i32) -> bool,
+    {
+        use tcod::input::KeyCode::*;
+        tcod.cursor = Some(self.objects[PLAYER].pos());
+        let target = loop {
             // render the screen. this erases the inventory and shows the names of
             // objects under the mouse.
             tcod.root.flush();
             let event = input::check_for_event(input::KEY_PRESS | input::MOUSE).map(|e| e.1);
+            let mut pointed = None;
             match event {
-                Some(Event::Mouse(m)) => tcod.mouse = m,
+                Some(Event::Mouse(m)) => {
+                    let moved = (m.cx, m.cy) != (tcod.mouse.cx, tcod.mouse.cy);
+                    let (x, y) = (m.cx as i32, m.cy as i32);
+                    if moved && x < MAP_WIDTH && y < MAP_HEIGHT {
+                        pointed = Some((x, y));
+                    }
+                    tcod.mouse = m;
+                    tcod.key = Default::default();
+                }
                 Some(Event::Key(k)) => tcod.key = k,
                 None => tcod.key = Default::default(),
             }
+
+            // move the cursor, keeping it on the map, or follow the mouse over it so
+            // the aim is drawn to whichever was moved last
+            let (cursor_x, cursor_y) = pointed.or(tcod.cursor).unwrap();
+            let (dx, dy) = match tcod.key.code {
+                Up => (0, -1),
+                Down => (0, 1),
+                Left => (-1, 0),
+                Right => (1, 0),
+                _ => (0, 0),
+            };
+            let cursor = if tcod.key.code == Tab {
+                self.next_enemy_in_sight(tcod, (cursor_x, cursor_y), tcod.key.shift)
+                    .unwrap_or((cursor_x, cursor_y))
+            } else {
+                (
+                    (cursor_x + dx).clamp(0, MAP_WIDTH - 1),
+                    (cursor_y + dy).clamp(0, MAP_HEIGHT - 1),
+                )
+            };
+            tcod.cursor = Some(cursor);
             self.render_all(tcod, false);
 
-            let (x, y) = (tcod.mouse.cx as i32, tcod.mouse.cy as i32);
+            let picked = if tcod.mouse.lbutton_pressed {
+                Some((tcod.mouse.cx as i32, tcod.mouse.cy as i32))
+            } else if tcod.key.code == Enter || tcod.key.code == NumPadEnter {
+                Some(cursor)
+            } else {
+                None
+            };
 
-            // accept the target if the player clicked in FOV, and in case a range
-            // is specified, if it's in that range
-            let in_fov = (x < MAP_WIDTH) && (y < MAP_HEIGHT) && tcod.fov.is_in_fov(x, y);
-            let in_range =
-                max_range.map_or(true, |range| self.objects[PLAYER].distance(x, y) <= range);
-            if tcod.mouse.lbutton_pressed && in_fov && in_range {
-                return Some((x, y));
+            if let Some((x, y)) = picked {
+                if x < MAP_WIDTH && y < MAP_HEIGHT && accept(self, tcod, x, y) {
+                    break Some((x, y));
+                }
             }
 
             if tcod.mouse.rbutton_pressed || tcod.key.code == Escape {
-                return None; // cancel if the player right-clicked or pressed Escape
+                break None; // cancel if the player right-clicked or pressed Escape
+            }
+        };
+        // the click was spent on the target, don't let it walk the player there
+        tcod.mouse.lbutton_pressed = false;
+        tcod.mouse.rbutton_pressed = false;
+        tcod.cursor = None;
+        tcod.aim = None;
+        target
+    }
+
+    /// The position of the enemy in sight after the one at `cursor`, nearest to
+    /// the player first, or before it if `backwards`. The first enemy if there is
+    /// none at the cursor, None if none are in sight.
+    fn next_enemy_in_sight(
+        &self,
+        tcod: &Tcod,
+        cursor: (i32, i32),
+        backwards: bool,
+    ) -> Option<(i32, i32)> {
+        let player = &self.objects[PLAYER];
+        let mut enemies: Vec<_> = self
+            .objects
+            .iter()
+            .filter(|object| {
+                object.alive
+                    && object.fighter.is_some()
+                    && !object.is_player()
+                    && object.allegiance() != Faction::Player
+                    && sees(&tcod.fov, &self.objects[PLAYER], object)
+            })
+            .collect();
+        if enemies.is_empty() {
+            return None;
+        }
+        enemies.sort_by(|a, b| {
+            player
+                .distance_to(a)
+                .partial_cmp(&player.distance_to(b))
+                .unwrap()
+        });
+        let next = match enemies.iter().position(|enemy| enemy.pos() == cursor) {
+            Some(current) if backwards => (current + enemies.len() - 1) % enemies.len(),
+            Some(current) => (current + 1) % enemies.len(),
+            None => 0,
+        };
+        Some(enemies[next].pos())
+    }
+
+    /// a short description of what the player knows to be on a tile
+    fn describe_tile(&self, tcod: &Tcod, x: i32, y: i32) -> String {
+        let tile = &self.map[x as usize][y as usize];
+        if !tile.explored {
+            return "Unexplored darkness.".into();
+        }
+        if in_sight(&tcod.fov, x, y) {
+            let player = &self.objects[PLAYER];
+            let descriptions: Vec<_> = self
+                .objects
+                .iter()
+                .filter(|object| object.pos() == (x, y) && sees(&tcod.fov, player, object))
+                .map(|object| object.describe())
+                .collect();
+            if !descriptions.is_empty() {
+                return descriptions.join("; ");
             }
+        } else if let Some(char) = self.memory[x as usize][y as usize] {
+            return match tile.last_seen.map(|turn| self.turn - turn) {
+                Some(turns) if turns > 0 => format!(
+                    "You remember a '{}' here, last seen {} turns ago.",
+                    char, turns
+                ),
+                _ => format!("You remember a '{}' here, out of sight.", char),
+            };
+        }
+        match tile.door {
+            Some(Door::Closed) => return "A closed door.".into(),
+            Some(Door::Locked) => return "A locked door.".into(),
+            Some(Door::Open) => return "An open door.".into(),
+            Some(Door::Broken) => return "The splintered remains of a door.".into(),
+            // only ever looked at as the wall it passes for
+            Some(Door::Secret) | None => {}
+        }
+        if tile.blocked {
+            "A stone wall.".into()
+        } else {
+            tile.terrain.description().into()
         }
     }
 
     fn handle_keys(&mut self, tcod: &mut Tcod) -> PlayerAction {
-        use tcod::input::KeyCode::*;
         use PlayerAction::*;
 
         let player_alive = self.objects[PLAYER].alive;
-        match (tcod.key, tcod.key.text(), player_alive) {
-            (
-                Key {
-                    code: Enter,
-                    alt: true,
-                    ..
-                },
-                _,
-                _,
-            ) => {
-                // Alt+Enter: toggle fullscreen
-                let fullscreen = tcod.root.is_fullscreen();
-                tcod.root.set_fullscreen(!fullscreen);
+        if tcod.mouse.lbutton_pressed && player_alive {
+            // the click is handled once, not again every frame until the mouse moves
+            tcod.mouse.lbutton_pressed = false;
+            return self.click(tcod);
+        }
+        let binding = match binding_for(tcod.key) {
+            Some(binding) if player_alive || binding.while_dead => binding,
+            _ => return DidntTakeTurn,
+        };
+        self.keyboard_macro.record(binding.command);
+        self.do_command(binding.command, tcod)
+    }
+
+    /// carry out what a key was pressed for
+    fn do_command(&mut self, command: KeyCommand, tcod: &mut Tcod) -> PlayerAction {
+        use PlayerAction::*;
+
+        match command {
+            KeyCommand::Fullscreen => {
+                tcod.options.fullscreen = !tcod.root.is_fullscreen();
+                tcod.root.set_fullscreen(tcod.options.fullscreen);
+                // not being able to write the file only costs the choice on the next start
+                let _ = tcod.options.save();
                 DidntTakeTurn
             }
-            (Key { code: Escape, .. }, _, _) => return Exit, // exit game
-            // movement keys
-            (Key { code: Up, .. }, _, true) => {
-                self.player_move_or_attack(0, -1);
-                TookTurn
+            KeyCommand::Menu => {
+                let choices = &[
+                    "Resume",
+                    "Save",
+                    "Export run summary",
+                    "Options",
+                    "Quit to menu",
+                    "Abandon run",
+                ];
+                match menu("Paused\n", choices, 24, &mut tcod.root) {
+                    Some(1) => match save_game(self) {
+                        Ok(()) => self.messages.add("Game saved.", LIGHT_CYAN),
+                        Err(e) => self
+                            .messages
+                            .add(format!("Could not save the game: {}", e), RED),
+                    },
+                    Some(2) => match export_summary(self) {
+                        Ok(name) => self.messages.add(
+                            format!("Run summary written to {}.md and {}.json.", name, name),
+                            LIGHT_CYAN,
+                        ),
+                        Err(e) => self
+                            .messages
+                            .add(format!("Could not export the run summary: {}", e), RED),
+                    },
+                    Some(3) => options_menu(tcod),
+                    Some(4) => return Exit, // saved on the way out
+                    Some(5) => {
+                        let header = "Abandon this run? The character and the save \
+                                      will be lost for good.\n";
+                        let choices = &["Keep playing", "Abandon the run"];
+                        if menu(header, choices, 24, &mut tcod.root) == Some(1) {
+                            return Abandon;
+                        }
+                    }
+                    _ => {}
+                }
+                DidntTakeTurn
             }
-            (Key { code: Down, .. }, _, true) => {
-                self.player_move_or_attack(0, 1);
-                TookTurn
+            KeyCommand::Pause => {
+                self.pause(tcod);
+                DidntTakeTurn
             }
-            (Key { code: Left, .. }, _, true) => {
-                self.player_move_or_attack(-1, 0);
-                TookTurn
+            KeyCommand::Help => {
+                msgbox(&help_text(), HELP_WIDTH, &mut tcod.root);
+                DidntTakeTurn
+            }
+            KeyCommand::DebugConsole => {
+                self.debug_console(tcod);
+                DidntTakeTurn
+            }
+            KeyCommand::GlyphAudit => {
+                glyph_audit(&mut tcod.root);
+                DidntTakeTurn
             }
-            (Key { code: Right, .. }, _, true) => {
-                self.player_move_or_attack(1, 0);
+            KeyCommand::Move(dx, dy) => {
+                self.player_move_or_attack(dx, dy, tcod);
                 TookTurn
             }
-            (Key { code: Text, .. }, "g", true) => {
-                // pick up an item
-                let item_id = self.objects.iter().position(|object| {
-                    object.pos() == self.objects[PLAYER].pos() && object.item.is_some()
-                });
-                if let Some(item_id) = item_id {
-                    self.pick_item_up(item_id);
+            KeyCommand::Run(dx, dy) => {
+                self.last_errand = Some(command);
+                self.run(dx, dy);
+                DidntTakeTurn
+            }
+            KeyCommand::Rest => {
+                self.last_errand = Some(command);
+                if self.rested() {
+                    self.messages
+                        .add("You are as rested as you will get.", LIGHT_GREY);
+                } else {
+                    self.messages.add("You sit down to rest.", WHITE);
+                    self.rest = Some(REST_TURNS);
                 }
                 DidntTakeTurn
             }
-            (Key { code: Text, .. }, "i", true) => {
-                // show the inventory
-                let inventory_index = inventory_menu(
-                    &self.inventory,
-                    "Press the key next to an item to use it, or any other to cancel.\n",
-                    &mut tcod.root,
+            KeyCommand::Repeat => match self.last_errand {
+                Some(errand) => self.do_command(errand, tcod),
+                None => {
+                    self.messages.add("There is nothing to repeat.", LIGHT_GREY);
+                    DidntTakeTurn
+                }
+            },
+            KeyCommand::RecordMacro => {
+                if self.keyboard_macro.toggle_recording() {
+                    self.messages.add(
+                        "Recording a macro. Press the key again to stop.",
+                        LIGHT_CYAN,
+                    );
+                } else {
+                    self.messages.add("Macro recorded.", LIGHT_CYAN);
+                }
+                DidntTakeTurn
+            }
+            KeyCommand::PlayMacro => {
+                if !self.keyboard_macro.play() {
+                    self.messages.add("There is no macro to play.", LIGHT_GREY);
+                }
+                DidntTakeTurn
+            }
+            KeyCommand::PickUp => {
+                // pick up an item, asking which if there's a pile of them
+                self.pick_up_here(tcod);
+                DidntTakeTurn
+            }
+            KeyCommand::Inventory => {
+                let header = format!(
+                    "{}\nPress the key next to an item to use it, or Escape to cancel.\n",
+                    self.burden()
                 );
+                let inventory_index = inventory_menu(&self.inventory, &header, &mut tcod.root);
                 if let Some(inventory_index) = inventory_index {
                     self.use_item(inventory_index, tcod);
                 }
                 DidntTakeTurn
             }
-            (Key { code: Text, .. }, "d", true) => {
-                // show the inventory; if an item is selected, drop it
-                let inventory_index = inventory_menu(
-                    &self.inventory,
-                    "Press the key next to an item to drop it, or any other to cancel.\n'",
-                    &mut tcod.root,
+            KeyCommand::Drop => {
+                let header = format!(
+                    "{}\nPress the key next to an item to drop it, or Escape to cancel.\n",
+                    self.burden()
                 );
+                let inventory_index = inventory_menu(&self.inventory, &header, &mut tcod.root);
                 if let Some(inventory_index) = inventory_index {
                     self.drop_item(inventory_index);
                 }
                 DidntTakeTurn
             }
-            (Key { code: Text, .. }, "v", true) => {
-                // go down stairs, if the player is on them
-                let player_on_stairs = self.objects.iter().any(|object| {
-                    object.pos() == self.objects[PLAYER].pos() && object.name == "stairs"
-                });
-                if player_on_stairs {
-                    self.next_level(tcod);
+            KeyCommand::TakeStairs => {
+                // go down or up the stairs the player stands on, if any
+                let player_pos = self.objects[PLAYER].pos();
+                let destination = self
+                    .objects
+                    .iter()
+                    .skip(PLAYER + 1)
+                    .filter(|object| object.pos() == player_pos)
+                    .find_map(|object| object.stairs);
+                if let Some(destination) = destination {
+                    self.change_level(destination, tcod);
                 }
                 DidntTakeTurn
             }
-            (Key { code: Text, .. }, "c", true) => {
-                // show character information
-                let player = &self.objects[PLAYER];
-                let level = player.level;
-                let level_up_xp = LEVEL_UP_BASE + player.level * LEVEL_UP_FACTOR;
-                if let Some(fighter) = player.fighter.as_ref() {
-                    let msg = format!(
-                        "Character information
-            
-            Level: {}
-            Experience: {}
-            Experience to level up: {}
-            
-            Maximum HP: {}
+            KeyCommand::Interact => {
+                // interact with what's here or next to the player, asking which if
+                // there's a choice
+                let interactions = self.interactions();
+                let chosen = match interactions.len() {
+                    0 => {
+                        self.messages
+                            .add("There is nothing around to interact with.", WHITE);
+                        None
+                    }
+                    1 => Some(interactions[0]),
+                    _ => {
+                        let options: Vec<_> = interactions
+                            .iter()
+                            .map(|&interaction| self.describe_interaction(interaction))
+                            .collect();
+                        menu(
+                            "What do you want to do?\n",
+                            &options,
+                            INVENTORY_WIDTH,
+                            &mut tcod.root,
+                        )
+                        .map(|index| interactions[index])
+                    }
+                };
+                match chosen {
+                    Some(Interaction::PickUp(item_id)) => self.pick_item_up(item_id),
+                    Some(Interaction::PickUpPile) => self.pick_up_here(tcod),
+                    Some(Interaction::TakeStairs(destination)) => {
+                        self.change_level(destination, tcod)
+                    }
+                    Some(Interaction::OpenDoor(x, y)) => {
+                        self.set_door(x, y, Door::Open, tcod);
+                        self.messages.add("You open the door.", WHITE);
+                        return TookTurn;
+                    }
+                    Some(Interaction::CloseDoor(x, y)) => {
+                        self.set_door(x, y, Door::Closed, tcod);
+                        self.messages.add("You close the door.", WHITE);
+                        return TookTurn;
+                    }
+                    Some(Interaction::UnlockDoor(x, y)) => {
+                        self.unlock_door(x, y, tcod);
+                        return TookTurn;
+                    }
+                    Some(Interaction::Visit(id)) => {
+                        if let Some(service) = self.objects[id].service {
+                            offer_service(service, self, tcod);
+                        }
+                    }
+                    Some(Interaction::Butcher(corpse_id)) => {
+                        self.butcher(corpse_id);
+                        return TookTurn;
+                    }
+                    None => {}
+                }
+                DidntTakeTurn
+            }
+            KeyCommand::Examine => {
+                // examine things with a keyboard cursor
+                self.messages.add(
+                    "Move the cursor with the arrow keys, or Tab between enemies, to examine \
+                     things, Enter to look a monster over. Escape to stop.",
+                    LIGHT_CYAN,
+                );
+                if let Some((x, y)) = self.target_tile(tcod, None) {
+                    self.inspect_at(x, y, tcod);
+                }
+                DidntTakeTurn
+            }
+            KeyCommand::Travel => {
+                self.travel_to_landmark(tcod);
+                DidntTakeTurn
+            }
+            KeyCommand::Fire => match fire_bow(0, tcod, self) {
+                UseResult::Cancelled => DidntTakeTurn,
+                UseResult::UsedUp | UseResult::Kept => TookTurn,
+            },
+            KeyCommand::Cast => {
+                // cast one of the known spells
+                let options = if self.spells.is_empty() {
+                    vec!["You don't know any spells yet.".into()]
+                } else {
+                    self.spells
+                        .iter()
+                        .map(|spell| format!("{} ({} mana)", spell.name(), spell.cost()))
+                        .collect()
+                };
+                let spell_index = menu(
+                    "Press the key next to a spell to cast it, or any other to cancel.\n",
+                    &options,
+                    INVENTORY_WIDTH,
+                    &mut tcod.root,
+                );
+                match spell_index {
+                    Some(spell_index) if spell_index < self.spells.len() => {
+                        if self.cast_spell(spell_index, tcod) {
+                            TookTurn
+                        } else {
+                            DidntTakeTurn
+                        }
+                    }
+                    _ => DidntTakeTurn,
+                }
+            }
+            KeyCommand::Search => {
+                if !self.find_secret_doors(SEARCH_CHANCE, tcod) {
+                    self.messages
+                        .add("You search the walls around you, but find nothing.", WHITE);
+                }
+                TookTurn
+            }
+            KeyCommand::Statistics => {
+                let mut msg = format!("Statistics\n\n{}\n", self.stats.summary(self.turn));
+                for (name, count) in self.stats.kill_list() {
+                    msg += &format!("\n{:4} {}", count, name);
+                }
+                msgbox(&msg, CHARACTER_SCREEN_WIDTH, &mut tcod.root);
+                DidntTakeTurn
+            }
+            KeyCommand::Journal => {
+                msgbox(&self.journal.text(), CHARACTER_SCREEN_WIDTH, &mut tcod.root);
+                DidntTakeTurn
+            }
+            KeyCommand::Character => {
+                let player = &self.objects[PLAYER];
+                let level = player.level;
+                let level_up_xp = self.level_up_xp();
+                if let Some(fighter) = player.fighter.as_ref() {
+                    let msg = format!(
+                        "Character information
+            
+            Level: {}
+            Experience: {}
+            Experience to level up: {}
+            
+            Maximum HP: {}
+            Maximum mana: {}
             Attack: {}
-            Defense: {}",
+            Defense: {}
+            Resistances: {}
+            Weapon skills: {}
+            
+            Gold: {}
+            Luck: {}
+            Class: {}
+            Difficulty: {}
+
+            Intrinsics: {}",
                         level,
                         fighter.xp,
                         level_up_xp,
                         fighter.max_hp,
+                        fighter.max_mana,
                         fighter.power,
-                        fighter.defense
+                        fighter.defense,
+                        fighter.resistances.describe(),
+                        self.weapon_skills.describe(),
+                        self.gold,
+                        self.luck(),
+                        self.class.name(),
+                        self.difficulty.name(),
+                        describe_intrinsics(&player.intrinsics)
                     );
                     msgbox(&msg, CHARACTER_SCREEN_WIDTH, &mut tcod.root);
                 }
 
-                DidntTakeTurn
+                DidntTakeTurn
+            }
+        }
+    }
+
+    fn player_move_or_attack(&mut self, dx: i32, dy: i32, tcod: &mut Tcod) {
+        // a confused player stumbles in a random direction instead
+        let (dx, dy) = if self.objects[PLAYER].has_effect(EffectKind::Confused) {
+            self.messages
+                .add("You stumble around in confusion.", LIGHT_CYAN);
+            (thread_rng().gen_range(-1, 2), thread_rng().gen_range(-1, 2))
+        } else {
+            (dx, dy)
+        };
+        if (dx, dy) == (0, 0) {
+            return;
+        }
+
+        // the coordinates the player is moving to/attacking
+        let x = self.objects[PLAYER].x + dx;
+        let y = self.objects[PLAYER].y + dy;
+
+        // the townsfolk offer their services instead of being attacked
+        let service = self
+            .objects
+            .iter()
+            .find(|object| object.pos() == (x, y))
+            .and_then(|object| object.service);
+        if let Some(service) = service {
+            offer_service(service, self, tcod);
+            return;
+        }
+
+        // try to find an attackable object there
+        let target_id = self
+            .objects
+            .iter()
+            .position(|object| object.fighter.is_some() && object.pos() == (x, y));
+
+        // attack if target found, move otherwise
+        match target_id {
+            Some(target_id) if self.objects[target_id].allegiance() == Faction::Player => {
+                // a charmed monster makes way, trading places with the player
+                let (player_x, player_y) = self.objects[PLAYER].pos();
+                self.objects[target_id].set_pos(player_x, player_y);
+                self.objects[PLAYER].set_pos(x, y);
+            }
+            Some(target_id) => {
+                self.melee(PLAYER, target_id);
+                self.make_noise(x, y, ATTACK_NOISE, tcod);
+            }
+            None if self.map[x as usize][y as usize].door == Some(Door::Closed) => {
+                self.set_door(x, y, Door::Open, tcod);
+                self.messages.add("You open the door.", WHITE);
+            }
+            None if self.map[x as usize][y as usize].door == Some(Door::Locked) => {
+                self.unlock_door(x, y, tcod)
+            }
+            None => {
+                self.move_by(PLAYER, dx, dy);
+                if self.objects[PLAYER].pos() == (x, y) {
+                    self.stats.steps += 1;
+                    // heavy armor clatters with every step
+                    let noise = self.worn_armor().map_or(0.0, |armor| armor.noise);
+                    if noise > 0.0 {
+                        self.make_noise(x, y, noise, tcod);
+                    }
+                    // a sharp eye notices a hidden door in passing
+                    if !self.objects[PLAYER].has_effect(EffectKind::Blind) {
+                        let chance =
+                            (self.perception() * NOTICE_PERCENT_PER_PERCEPTION) as f32 / 100.0;
+                        self.find_secret_doors(chance, tcod);
+                    }
+                    self.auto_pick_up(tcod.options.auto_pickup);
+                }
+            }
+        }
+    }
+
+    /// Let one object attack another, keeping the run statistics. The player
+    /// strikes with the weapon they hold, or less surely with a melee weapon in
+    /// each hand, harder for their skill with it, and their shield may stop the blows aimed
+    /// at them. A single weapon never misses, so the skill only makes the blows
+    /// of the two weapons land more surely, and only blows that hurt train it
+    fn melee(&mut self, attacker_id: usize, target_id: usize) {
+        if target_id == PLAYER && self.blocks_blow() {
+            let msg = format!(
+                "You block the {}'s blow with your shield.",
+                self.objects[attacker_id].name
+            );
+            self.messages.add(msg, LIGHT_GREY);
+            return;
+        }
+        if attacker_id != PLAYER {
+            self.strike(attacker_id, target_id, 0, DamageSource::Monster);
+            return;
+        }
+        self.alert_to_player(target_id);
+        let weapon_power = |object: &Object| {
+            let power = object.item.and_then(Item::melee_power)?;
+            Some(power + object.beatitude.bonus())
+        };
+        let skills = self.weapon_skills;
+        let main_weapon = self.held(Slot::Weapon);
+        let main_class = WeaponClass::melee(main_weapon.and_then(|object| object.item));
+        let main_power = main_weapon.and_then(weapon_power);
+        let main = main_power.unwrap_or(0) + skills.damage_bonus(main_class);
+        // only a melee weapon in the main hand fights alongside the off-hand one
+        let off_weapon = self.held(Slot::OffHand);
+        let off = match main_power.and(off_weapon.and_then(weapon_power)) {
+            Some(power) => {
+                let class = WeaponClass::melee(off_weapon.and_then(|object| object.item));
+                (power + skills.damage_bonus(class), class)
+            }
+            None => {
+                if self.strike(PLAYER, target_id, main, DamageSource::Player) > 0 {
+                    self.train(main_class);
+                }
+                return;
+            }
+        };
+        for &(power, class) in &[(main, main_class), off] {
+            if !self.objects[target_id].alive {
+                break;
+            }
+            if rand::random::<f32>() < DUAL_WIELD_ACCURACY + skills.accuracy_bonus(class) {
+                if self.strike(PLAYER, target_id, power, DamageSource::Player) > 0 {
+                    self.train(class);
+                }
+            } else {
+                let msg = format!("You miss the {}.", self.objects[target_id].name);
+                self.messages.add(msg, LIGHT_GREY);
+            }
+        }
+    }
+
+    /// count a blow the player landed towards their skill with the weapon class,
+    /// telling them when it brings them to a new rank
+    pub fn train(&mut self, class: WeaponClass) {
+        if let Some(rank) = self.weapon_skills.train(class) {
+            self.messages.add(
+                format!(
+                    "Your {} skill has grown: you are now {}.",
+                    class.name(),
+                    rank
+                ),
+                LIGHT_CYAN,
+            );
+        }
+    }
+
+    /// whether the shield the player holds, if any, stops the blow aimed at them
+    fn blocks_blow(&self) -> bool {
+        let chance = self
+            .held(Slot::OffHand)
+            .and_then(|object| object.item)
+            .and_then(Item::block_chance);
+        matches!(chance, Some(chance) if rand::random::<f32>() < chance)
+    }
+
+    /// what the player holds in the hand, if anything
+    fn held(&self, slot: Slot) -> Option<&Object> {
+        self.inventory
+            .iter()
+            .find(|object| object.equipped && object.worn_slot() == Some(slot))
+    }
+
+    /// the blow of a confused monster at whoever it stumbled into, counting
+    /// towards the player's share of the kill if they did the confusing
+    fn confused_melee(&mut self, attacker_id: usize, target_id: usize, by_player: bool) {
+        if target_id == PLAYER || !by_player {
+            self.melee(attacker_id, target_id);
+            return;
+        }
+        self.strike(attacker_id, target_id, 0, DamageSource::Confused);
+    }
+
+    /// land a blow on the target, as hard as the attacker hits plus `bonus`,
+    /// returning the damage it dealt
+    fn strike(
+        &mut self,
+        attacker_id: usize,
+        target_id: usize,
+        bonus: i32,
+        source: DamageSource,
+    ) -> i32 {
+        let hp = self.objects[target_id].fighter.map(|f| f.hp);
+        let mut blow = self.objects[attacker_id].blow();
+        blow.power += bonus;
+        if attacker_id != PLAYER {
+            blow.power = self.difficulty.monster_damage(blow.power);
+        }
+        let (damage, xp) =
+            self.objects[target_id].suffer_blow(&blow, source, self.turn, &mut self.events);
+        if let Some(fighter) = self.objects[PLAYER].fighter.as_mut() {
+            fighter.xp += xp.unwrap_or(0);
+        }
+        self.float_hp_change(target_id, hp);
+        self.handle_events();
+        if attacker_id == PLAYER && damage > 0 {
+            self.strike_with_affixes(target_id);
+        }
+        damage
+    }
+
+    /// burn the monster the player just hit, and drain its life into the player,
+    /// as much as the affixes of the artifacts they have equipped do
+    pub fn strike_with_affixes(&mut self, target_id: usize) {
+        let (burn, drain) = on_hit(&self.inventory);
+        if burn > 0 && self.objects[target_id].alive {
+            self.messages.add(
+                format!(
+                    "The {} is burnt by the flames.",
+                    self.objects[target_id].name
+                ),
+                ORANGE,
+            );
+            self.damage(target_id, burn, DamageType::Fire, DamageSource::Player);
+        }
+        if drain > 0 {
+            self.heal(PLAYER, drain);
+        }
+    }
+
+    /// Act on a click on the map: attack the monster next to the player, pick up
+    /// the item underfoot, or set off towards the tile, picking up what lies there
+    fn click(&mut self, tcod: &mut Tcod) -> PlayerAction {
+        let (x, y) = (tcod.mouse.cx as i32, tcod.mouse.cy as i32);
+        if x >= MAP_WIDTH || y >= MAP_HEIGHT || !self.map[x as usize][y as usize].explored {
+            return PlayerAction::DidntTakeTurn;
+        }
+        let (player_x, player_y) = self.objects[PLAYER].pos();
+        let (dx, dy) = (x - player_x, y - player_y);
+        let item = self
+            .objects
+            .iter()
+            .position(|object| object.pos() == (x, y) && object.item.is_some());
+        let player = &self.objects[PLAYER];
+        let monster = self.objects.iter().any(|object| {
+            object.pos() == (x, y)
+                && object.fighter.is_some()
+                && object.alive
+                && object.ai.is_some()
+                && sees(&tcod.fov, player, object)
+        });
+
+        if monster && dx.abs() <= 1 && dy.abs() <= 1 {
+            self.player_move_or_attack(dx, dy, tcod);
+            return PlayerAction::TookTurn;
+        }
+        // a monster further off is looked over rather than walked to
+        if monster && self.inspect_at(x, y, tcod) {
+            return PlayerAction::DidntTakeTurn;
+        }
+        if (dx, dy) == (0, 0) {
+            if let Some(item_id) = item {
+                self.pick_item_up(item_id);
+            }
+            return PlayerAction::DidntTakeTurn;
+        }
+        self.set_out((x, y), item.is_some());
+        PlayerAction::DidntTakeTurn
+    }
+
+    /// Set off towards the tile, a step a turn, picking up what lies there if
+    /// `pick_up`. Only the way through known ground is sought, closed doors get
+    /// opened; whatever stands on the tile itself is bumped into on the last step
+    fn set_out(&mut self, to: (i32, i32), pick_up: bool) {
+        let map = &self.map;
+        let path = self
+            .paths
+            .find_path(self.objects[PLAYER].pos(), to, |x, y| {
+                let tile = &map[x as usize][y as usize];
+                tile.explored
+                    && (!tile.blocked || tile.door == Some(Door::Closed))
+                    && !tile.terrain.is_perilous()
+            });
+        match path {
+            Some(path) => self.travel = Some(Travel { path, pick_up }),
+            None => self
+                .messages
+                .add("You see no way to get there.", LIGHT_GREY),
+        }
+    }
+
+    /// What the player knows of on the level to travel to: the stairs, the
+    /// townsfolk and altars, and the items last seen lying about, the nearest
+    /// first within each. Every landmark comes under a heading, with its place
+    fn landmarks(&self) -> Vec<(&'static str, String, (i32, i32))> {
+        let player = &self.objects[PLAYER];
+        let mut landmarks: Vec<_> = self.objects[PLAYER + 1..]
+            .iter()
+            .filter(|object| object.pos() != player.pos())
+            .filter_map(|object| {
+                let (x, y) = object.pos();
+                let explored = self.map[x as usize][y as usize].explored;
+                let remembered = self.memory[x as usize][y as usize] == Some(object.char);
+                let heading = if object.stairs.is_some() && explored {
+                    "Stairs"
+                } else if object.service.is_some() && explored {
+                    "Places"
+                } else if object.item.is_some() && remembered {
+                    "Items"
+                } else {
+                    return None;
+                };
+                Some((heading, object.name.clone(), (x, y)))
+            })
+            .collect();
+        let order = ["Stairs", "Places", "Items"];
+        landmarks.sort_by_key(|&(heading, _, (x, y))| {
+            let heading = order.iter().position(|&h| h == heading);
+            (heading, (x - player.x).pow(2) + (y - player.y).pow(2))
+        });
+        landmarks
+    }
+
+    /// let the player pick a known landmark and set off towards it
+    fn travel_to_landmark(&mut self, tcod: &mut Tcod) {
+        let landmarks = self.landmarks();
+        if landmarks.is_empty() {
+            self.messages.add(
+                "You know of nothing on this level to travel to.",
+                LIGHT_GREY,
+            );
+            return;
+        }
+        let menu = landmarks.iter().fold(
+            Menu::new("Travel where?\n", INVENTORY_WIDTH),
+            |menu, (heading, name, _)| menu.entry(name.as_str()).group(*heading),
+        );
+        if let Some(index) = menu.show(&mut tcod.root) {
+            let (heading, _, to) = landmarks[index];
+            self.set_out(to, heading == "Items");
+        }
+    }
+
+    /// show the details of the monster on the tile, returns whether there was one
+    fn inspect_at(&mut self, x: i32, y: i32, tcod: &mut Tcod) -> bool {
+        let monster = self.objects.iter().enumerate().find(|&(id, object)| {
+            id != PLAYER && object.pos() == (x, y) && object.alive && object.fighter.is_some()
+        });
+        match monster {
+            Some((_, monster)) => {
+                let kills = self
+                    .stats
+                    .kills
+                    .get(kind_of(&monster.name))
+                    .cloned()
+                    .unwrap_or(0);
+                let details = monster_details(monster, kills);
+                msgbox(&details, CHARACTER_SCREEN_WIDTH, &mut tcod.root);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Take the next step of the journey the player clicked, if one is under way.
+    /// Any input, danger coming into view, or someone harmless or unaware standing
+    /// in the way ends it.
+    fn travel_step(&mut self, tcod: &mut Tcod) -> Option<PlayerAction> {
+        use tcod::input::KeyCode::NoKey;
+
+        let mut travel = self.travel.take()?;
+        if tcod.key.code != NoKey || tcod.mouse.lbutton_pressed || tcod.mouse.rbutton_pressed {
+            return None;
+        }
+        if self.danger_in_sight(tcod) {
+            self.messages.add("You stop, danger is near.", LIGHT_RED);
+            return Some(PlayerAction::DidntTakeTurn);
+        }
+        let (x, y) = travel.path.pop()?;
+        // only a foe on the lookout gets walked into, anyone else stops the journey
+        let blocker = self.objects.iter().skip(PLAYER + 1).find(|object| {
+            object.pos() == (x, y) && object.blocks && object.alive && object.fighter.is_some()
+        });
+        if let Some(blocker) = blocker {
+            let unaware = blocker.ai.as_ref().is_some_and(Ai::is_asleep)
+                || blocker.sight.awareness == Awareness::Unaware;
+            if unaware || !blocker.allegiance().is_hostile_to(Faction::Player) {
+                let msg = format!("You stop, the {} is in the way.", blocker.name);
+                self.messages.add(msg, LIGHT_GREY);
+                return Some(PlayerAction::DidntTakeTurn);
+            }
+        }
+
+        let (player_x, player_y) = self.objects[PLAYER].pos();
+        let door = self.map[x as usize][y as usize].door == Some(Door::Closed);
+        self.player_move_or_attack(x - player_x, y - player_y, tcod);
+        if self.objects[PLAYER].pos() == (x, y) {
+            if travel.path.is_empty() {
+                // arrived
+                let item = self
+                    .objects
+                    .iter()
+                    .position(|object| object.pos() == (x, y) && object.item.is_some());
+                if let Some(item_id) = item.filter(|_| travel.pick_up) {
+                    self.pick_item_up(item_id);
+                }
+            } else {
+                self.travel = Some(travel);
+            }
+        } else if door {
+            // the door was opened, step through it next turn
+            travel.path.push((x, y));
+            self.travel = Some(travel);
+        }
+        Some(PlayerAction::TookTurn)
+    }
+
+    /// Set out walking in a direction, up to whatever is in the way or worth a
+    /// look: a wall, perilous ground, a doorway or something lying about
+    fn run(&mut self, dx: i32, dy: i32) {
+        let (mut x, mut y) = self.objects[PLAYER].pos();
+        let mut path = vec![];
+        loop {
+            x += dx;
+            y += dy;
+            let in_map = x >= 0 && y >= 0 && x < MAP_WIDTH && y < MAP_HEIGHT;
+            if !in_map {
+                break;
+            }
+            let tile = &self.map[x as usize][y as usize];
+            if tile.blocked || tile.terrain.is_perilous() {
+                break;
+            }
+            path.push((x, y));
+            let objects_here = self.objects.iter().any(|object| object.pos() == (x, y));
+            if tile.door.is_some() || objects_here {
+                break;
+            }
+        }
+        if path.is_empty() {
+            self.messages
+                .add("There is no room to run that way.", LIGHT_GREY);
+        } else {
+            path.reverse();
+            self.travel = Some(Travel {
+                path,
+                pick_up: false,
+            });
+        }
+    }
+
+    /// whether resting would do the player no more good: their mana is full, and
+    /// so are their hit points or they can't regenerate them
+    fn rested(&self) -> bool {
+        let player = &self.objects[PLAYER];
+        player.fighter.is_none_or(|fighter| {
+            fighter.mana >= fighter.max_mana
+                && (fighter.hp >= fighter.max_hp || !player.has_intrinsic(Intrinsic::Regeneration))
+        })
+    }
+
+    /// Wait out the next turn of a rest, if the player is resting. Any input, or
+    /// danger coming into view, ends it
+    fn rest_step(&mut self, tcod: &mut Tcod) -> Option<PlayerAction> {
+        use tcod::input::KeyCode::NoKey;
+
+        let turns = self.rest.take()?;
+        if tcod.key.code != NoKey || tcod.mouse.lbutton_pressed || tcod.mouse.rbutton_pressed {
+            return None;
+        }
+        if self.danger_in_sight(tcod) {
+            self.messages
+                .add("You stop resting, danger is near.", LIGHT_RED);
+            return Some(PlayerAction::DidntTakeTurn);
+        }
+        if turns == 0 || self.rested() {
+            self.messages.add("You get up, rested.", WHITE);
+            return Some(PlayerAction::DidntTakeTurn);
+        }
+        self.rest = Some(turns - 1);
+        Some(PlayerAction::TookTurn)
+    }
+
+    /// Carry out the next command of the macro being played, if any. Any input,
+    /// or danger coming into view, stops it
+    fn macro_step(&mut self, tcod: &mut Tcod) -> Option<PlayerAction> {
+        use tcod::input::KeyCode::NoKey;
+
+        let command = self.keyboard_macro.next_command()?;
+        if tcod.key.code != NoKey || tcod.mouse.lbutton_pressed || tcod.mouse.rbutton_pressed {
+            self.keyboard_macro.stop();
+            return None;
+        }
+        if self.danger_in_sight(tcod) {
+            self.keyboard_macro.stop();
+            self.messages
+                .add("You stop the macro, danger is near.", LIGHT_RED);
+            return Some(PlayerAction::DidntTakeTurn);
+        }
+        Some(self.do_command(command, tcod))
+    }
+
+    /// whether anything calls for the player's attention: an awake monster in
+    /// view, or a hazard about to strike
+    fn danger_in_sight(&self, tcod: &Tcod) -> bool {
+        self.hazard.is_some()
+            || self.objects.iter().any(|object| {
+                object.alive
+                    && object.fighter.is_some()
+                    && object.allegiance().is_hostile_to(Faction::Player)
+                    && sees(&tcod.fov, &self.objects[PLAYER], object)
+                    && !object.ai.as_ref().is_some_and(Ai::is_asleep)
+            })
+    }
+
+    /// move by the given amount, if the destination is not blocked
+    fn move_by(&mut self, id: usize, dx: i32, dy: i32) {
+        let (x, y) = self.objects[id].pos();
+        // the player goes where they please, monsters keep out of harm's way
+        let perilous = self.map[(x + dx) as usize][(y + dy) as usize]
+            .terrain
+            .is_perilous();
+        if id != PLAYER && perilous {
+            return;
+        }
+        if !is_blocked(x + dx, y + dy, &self.map, &self.objects) {
+            self.objects[id].set_pos(x + dx, y + dy);
+        }
+        // a monster looks the way it goes, even when it bumps into something
+        self.objects[id].sight.face((x, y), (x + dx, y + dy));
+    }
+
+    /// the single step that leads an object most directly towards the target
+    fn direction_towards(&self, id: usize, target_x: i32, target_y: i32) -> (i32, i32) {
+        // vector from this object to the target, and distance
+        let dx = target_x - self.objects[id].x;
+        let dy = target_y - self.objects[id].y;
+        let distance = ((dx.pow(2) + dy.pow(2)) as f32).sqrt();
+
+        // normalize it to length 1 (preserving direction), then round it and
+        // convert to integer so the movement is restricted to the map grid
+        let dx = (dx as f32 / distance).round() as i32;
+        let dy = (dy as f32 / distance).round() as i32;
+        (dx, dy)
+    }
+
+    /// work out the maps the monsters find their way by, now the player has moved,
+    /// in the buffers of the turn before
+    fn refresh_ai_maps(&mut self) {
+        let map = &self.map;
+        let goals = [self.objects[PLAYER].pos()];
+        let passable = |x: i32, y: i32| map[x as usize][y as usize].monster_passable();
+        match self.player_map.as_mut() {
+            Some(player_map) => player_map.fill(&goals, passable),
+            None => self.player_map = Some(DijkstraMap::new(&goals, passable)),
+        }
+        self.flee_map_stale = true;
+    }
+
+    /// the tile a monster heads for on its way to the target: downhill on the
+    /// player map when going for the player, around walls and crowds
+    fn step_towards(&self, id: usize, target_id: usize) -> (i32, i32) {
+        let (x, y) = self.objects[id].pos();
+        let objects = &self.objects;
+        let step = match &self.player_map {
+            Some(map) if target_id == PLAYER => map.downhill(x, y, |x, y| {
+                !objects
+                    .iter()
+                    .any(|object| object.blocks && object.pos() == (x, y))
+            }),
+            _ => None,
+        };
+        match step {
+            Some((dx, dy)) => (x + dx, y + dy),
+            None => self.objects[target_id].pos(),
+        }
+    }
+
+    fn move_towards(&mut self, id: usize, target_x: i32, target_y: i32, tcod: &mut Tcod) {
+        let (dx, dy) = self.direction_towards(id, target_x, target_y);
+        let (x, y) = self.objects[id].pos();
+        if self.map[(x + dx) as usize][(y + dy) as usize].door == Some(Door::Closed) {
+            // a closed door is in the way, deal with it if able
+            let capabilities = self.objects[id].capabilities;
+            let seen = in_sight(&tcod.fov, x, y);
+            if capabilities.smashes_doors {
+                self.set_door(x + dx, y + dy, Door::Broken, tcod);
+                let msg = if seen {
+                    format!(
+                        "The {} smashes the door to splinters!",
+                        self.objects[id].name
+                    )
+                } else {
+                    "You hear wood splintering in the distance.".into()
+                };
+                self.messages.add(msg, LIGHT_RED);
+                self.make_noise(x + dx, y + dy, DOOR_SMASH_NOISE, tcod);
+                return;
+            } else if capabilities.opens_doors {
+                self.set_door(x + dx, y + dy, Door::Open, tcod);
+                if seen {
+                    let msg = format!("The {} opens the door.", self.objects[id].name);
+                    self.messages.add(msg, WHITE);
+                }
+                return;
+            }
+        }
+        if !is_blocked(x + dx, y + dy, &self.map, &self.objects) {
+            self.move_by(id, dx, dy);
+            return;
+        }
+
+        // something (most likely an ally) is in the way, try to step around it
+        // without falling back
+        let sidesteps = if dx != 0 && dy != 0 {
+            [(dx, 0), (0, dy)]
+        } else if dx != 0 {
+            [(dx, 1), (dx, -1)]
+        } else {
+            [(1, dy), (-1, dy)]
+        };
+        if let Some(&(dx, dy)) = sidesteps
+            .iter()
+            .find(|&&(dx, dy)| !is_blocked(x + dx, y + dy, &self.map, &self.objects))
+        {
+            self.move_by(id, dx, dy);
+        }
+    }
+
+    /// everything the player could interact with from where they stand
+    fn interactions(&self) -> Vec<Interaction> {
+        let player_pos = self.objects[PLAYER].pos();
+        let mut interactions = vec![];
+        match self.items_here()[..] {
+            [] => {}
+            [id] => interactions.push(Interaction::PickUp(id)),
+            _ => interactions.push(Interaction::PickUpPile),
+        }
+        for (id, object) in self.objects.iter().enumerate() {
+            if object.pos() != player_pos {
+                continue;
+            }
+            if let Some(destination) = object.stairs {
+                interactions.push(Interaction::TakeStairs(destination));
+            }
+            if object.meat.is_some() && object.item.is_none() {
+                interactions.push(Interaction::Butcher(id));
+            }
+        }
+
+        // the doors and the townsfolk next to the player, open doors only with
+        // nothing in the doorway
+        let (player_x, player_y) = player_pos;
+        for dx in -1..2 {
+            for dy in -1..2 {
+                let (x, y) = (player_x + dx, player_y + dy);
+                if (dx, dy) == (0, 0) {
+                    continue;
+                }
+                let objects_there = || {
+                    self.objects
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, object)| object.pos() == (x, y))
+                };
+                match self.map[x as usize][y as usize].door {
+                    Some(Door::Open) if objects_there().next().is_none() => {
+                        interactions.push(Interaction::CloseDoor(x, y))
+                    }
+                    Some(Door::Closed) => interactions.push(Interaction::OpenDoor(x, y)),
+                    Some(Door::Locked) => interactions.push(Interaction::UnlockDoor(x, y)),
+                    _ => {}
+                }
+                if let Some((id, _)) = objects_there().find(|(_, object)| object.service.is_some())
+                {
+                    interactions.push(Interaction::Visit(id));
+                }
+            }
+        }
+        interactions
+    }
+
+    fn describe_interaction(&self, interaction: Interaction) -> String {
+        match interaction {
+            Interaction::PickUp(id) => format!("Pick up the {}", self.objects[id].name),
+            Interaction::PickUpPile => "Pick up from the pile".into(),
+            Interaction::TakeStairs(destination) => {
+                format!("Take the stairs to {}", destination.name())
+            }
+            Interaction::OpenDoor(..) => "Open the door".into(),
+            Interaction::CloseDoor(..) => "Close the door".into(),
+            Interaction::UnlockDoor(..) => "Unlock the door".into(),
+            Interaction::Visit(id) => format!("Visit the {}", self.objects[id].name),
+            Interaction::Butcher(id) => format!("Butcher the {}", self.objects[id].name),
+        }
+    }
+
+    /// the items and gold lying on the player's tile
+    fn items_here(&self) -> Vec<usize> {
+        let player_pos = self.objects[PLAYER].pos();
+        (0..self.objects.len())
+            .filter(|&id| {
+                let object = &self.objects[id];
+                object.pos() == player_pos && (object.item.is_some() || object.gold.is_some())
+            })
+            .collect()
+    }
+
+    /// pick up what lies on the player's tile, letting them choose from a pile
+    fn pick_up_here(&mut self, tcod: &mut Tcod) {
+        let here = self.items_here();
+        match here[..] {
+            [] => {}
+            [id] => self.pick_item_up(id),
+            _ => {
+                let pile: Vec<&Object> = here.iter().map(|&id| &self.objects[id]).collect();
+                let chosen = pickup_menu(&pile, &mut tcod.root);
+                self.pick_items_up(chosen.into_iter().map(|index| here[index]).collect());
+            }
+        }
+    }
+
+    /// pick up what the player walked onto, as far as the auto-pickup settings
+    /// ask for it
+    fn auto_pick_up(&mut self, auto_pickup: AutoPickup) {
+        let wanted: Vec<usize> = self
+            .items_here()
+            .into_iter()
+            .filter(|&id| {
+                let object = &self.objects[id];
+                match (object.gold, object.item) {
+                    (Some(_), _) => auto_pickup.gold,
+                    (None, Some(item)) => auto_pickup.picks_up(item.category()),
+                    (None, None) => false,
+                }
+            })
+            .collect();
+        if !wanted.is_empty() {
+            self.pick_items_up(wanted);
+        }
+    }
+
+    /// pick up several objects, for as long as there is room for them
+    fn pick_items_up(&mut self, mut ids: Vec<usize>) {
+        // from the highest id down, so removing one doesn't move the others
+        ids.sort_unstable_by(|a, b| b.cmp(a));
+        let mut left_behind = 0;
+        for id in ids {
+            if self.objects[id].gold.is_none() && self.inventory.len() >= INVENTORY_SIZE {
+                left_behind += 1;
+            } else {
+                self.pick_item_up(id);
+            }
+        }
+        if left_behind > 0 {
+            self.messages.add(
+                format!(
+                    "Your inventory is full, you leave {} things behind.",
+                    left_behind
+                ),
+                RED,
+            );
+        }
+    }
+
+    /// open a locked door with a key from the inventory, which stays in the lock
+    fn unlock_door(&mut self, x: i32, y: i32, tcod: &mut Tcod) {
+        let key = self
+            .inventory
+            .iter()
+            .position(|object| object.item == Some(Item::Key));
+        match key {
+            Some(key) => {
+                self.inventory.remove(key);
+                self.set_door(x, y, Door::Open, tcod);
+                self.messages.add(
+                    "You unlock the door, and leave the key in the lock.",
+                    YELLOW,
+                );
+            }
+            None => self.messages.add(
+                "The door is locked. The key must be somewhere on this level.",
+                WHITE,
+            ),
+        }
+    }
+
+    /// add to the player's inventory and remove from the map
+    fn pick_item_up(&mut self, object_id: usize) {
+        if let Some(gold) = self.objects[object_id].gold {
+            // gold goes into the purse, not the inventory
+            self.objects.swap_remove(object_id);
+            self.gold += gold;
+            self.events.emit(GameEvent::PickedUpGold(gold));
+        } else if self.inventory.len() >= INVENTORY_SIZE {
+            self.messages.add(
+                format!(
+                    "Your inventory is full, cannot pick up {}.",
+                    self.objects[object_id].name
+                ),
+                RED,
+            );
+        } else {
+            let was_overburdened = self.overburdened();
+            let item = self.objects.swap_remove(object_id);
+            self.events.emit(GameEvent::PickedUp(item.name.clone()));
+            if item.item == Some(Item::Amulet) && !self.escaping {
+                self.escaping = true;
+                self.messages.add(
+                    "As you lift the amulet the tombs shudder, and all around you \
+                     the dead begin to stir. Flee up to the town!",
+                    RED,
+                );
+            }
+            // arrows go into the bundle already carried, if any
+            let bundle = self
+                .inventory
+                .iter_mut()
+                .find(|object| object.item == Some(Item::Arrows));
+            match (bundle, item.count) {
+                (Some(bundle), Some(count)) if item.item == Some(Item::Arrows) => {
+                    *bundle.count.get_or_insert(0) += count;
+                }
+                _ => self.inventory.push(item),
+            }
+            if self.overburdened() && !was_overburdened {
+                self.messages.add(
+                    "You are overburdened! You will move at half speed until you drop something.",
+                    LIGHT_RED,
+                );
+            }
+        }
+        self.handle_events();
+    }
+
+    /// put the current level away for when the player returns: its map, what the
+    /// player remembers of it and everything on it are kept, while the ground
+    /// effects, the scent and any hazard brewing fade the moment they leave
+    fn store_level(&mut self) {
+        let state = LevelState {
+            map: mem::take(&mut self.map),
+            memory: mem::take(&mut self.memory),
+            objects: self.objects.drain(PLAYER + 1..).collect(),
+            stats: self.level_stats,
+            theme: self.theme,
+        };
+        self.levels.insert(self.level, state);
+    }
+
+    /// Take the stairs to another level of the level graph
+    fn change_level(&mut self, destination: LevelId, tcod: &mut Tcod) {
+        let arrived_from = self.level;
+        let descending = destination.absolute_depth() > arrived_from.absolute_depth();
+        let first_visit = !self.levels.contains_key(&destination);
+        // there is nothing to grade about the town
+        if descending && !self.level_stats.graded && arrived_from != TOWN {
+            self.grade_level(tcod);
+        }
+        if descending && first_visit {
+            self.messages.add(
+                "You take a moment to rest, and recover your strength.",
+                VIOLET,
+            );
+            let heal_hp = self.objects[PLAYER].fighter.map_or(0, |f| f.max_hp / 2);
+            self.heal(PLAYER, heal_hp);
+
+            self.messages.add(
+                "After a rare moment of peace, you descend deeper into \
+             the heart of the dungeon...",
+                RED,
+            );
+        } else if descending {
+            self.messages
+                .add("You descend the familiar stairs.", VIOLET);
+        } else {
+            self.messages.add("You climb the stairs back up.", VIOLET);
+        }
+        self.store_level();
+        self.level = destination;
+        self.reach_checkpoint();
+
+        // something unusual may happen on the way down to a new level
+        let event = if descending && first_visit {
+            roll_level_event()
+        } else {
+            LevelEvent::Nothing
+        };
+        let shaft = destination
+            .down()
+            .filter(|deeper| !self.levels.contains_key(deeper));
+        if let (LevelEvent::CollapsedShaft, Some(deeper)) = (event, shaft) {
+            self.messages.add(
+                "The stairs collapse beneath you and you tumble down a shaft, \
+             past an entire level!",
+                RED,
+            );
+            self.level = deeper;
+            self.reach_checkpoint();
+        }
+
+        let feelings = self.initialize_map(Some(arrived_from));
+        self.initialise_fov(tcod);
+        if descending {
+            let flavor = if first_visit {
+                "The air grows stale as you go deeper."
+            } else {
+                "You know these halls."
+            };
+            descent_screen(
+                &mut tcod.root,
+                &self.level.name(),
+                self.level.absolute_depth(),
+                &feelings,
+                flavor,
+                tcod.options.screen_effects,
+            );
+        }
+
+        // with the amulet, the way up is watched
+        let event = if self.escaping
+            && !descending
+            && self.level != TOWN
+            && rand::random::<f32>() < ESCAPE_AMBUSH_CHANCE
+        {
+            LevelEvent::Ambush
+        } else {
+            event
+        };
+        match event {
+            LevelEvent::Ambush => spring_ambush(self),
+            LevelEvent::Trader => meet_trader(self, tcod),
+            LevelEvent::Sage => meet_sage(self, tcod),
+            LevelEvent::Nothing | LevelEvent::CollapsedShaft => {}
+        }
+        if descending {
+            self.autosave();
+        }
+    }
+
+    /// save to the autosave slot, a failure only costs the safety net
+    fn autosave(&mut self) {
+        if let Err(e) = autosave(self) {
+            self.messages
+                .add(format!("Could not autosave the game: {}", e), RED);
+        }
+    }
+
+    /// Move the clock on by the turn the player just took. Whatever lasts for some
+    /// turns counts down here, once a turn whoever it is on, however often they
+    /// get to act in it
+    fn pass_turn(&mut self, tcod: &mut Tcod) {
+        self.turn += 1;
+        self.level_stats.turns += 1;
+        let (player_x, player_y) = self.objects[PLAYER].pos();
+        self.scent.mark(player_x, player_y, self.turn);
+        self.burn_fuel();
+        self.decay_clutter(&mut tcod.scratch.decay_turns);
+        for id in 0..self.objects.len() {
+            let expired = self.objects[id].expire_effects(self.turn);
+            if id != PLAYER {
+                continue;
+            }
+            for expired in expired {
+                let msg = expired.expiry_message(&self.objects[PLAYER].name);
+                self.messages.add(msg, LIGHT_CYAN);
+            }
+        }
+        if self.turn % MANA_REGEN_TURNS == 0 {
+            self.regenerate_mana(1);
+        }
+    }
+
+    /// let worthless clutter rot away once its time comes, and reclaim the oldest
+    /// of it early when a level holds more than its budget
+    fn decay_clutter(&mut self, decays_at: &mut Vec<i32>) {
+        decays_at.clear();
+        decays_at.extend(self.objects.iter().filter_map(|o| o.decays_at));
+        let reclaim_before = if decays_at.len() > CLUTTER_BUDGET {
+            decays_at.sort();
+            decays_at[decays_at.len() - CLUTTER_BUDGET].max(self.turn + 1)
+        } else {
+            self.turn + 1
+        };
+
+        self.objects
+            .retain(|object| object.decays_at.is_none_or(|turn| turn >= reclaim_before));
+    }
+
+    /// explorer mode remembers every few levels as the place to respawn
+    fn reach_checkpoint(&mut self) {
+        let depth = self.level.absolute_depth();
+        if depth >= self.checkpoint.absolute_depth() + CHECKPOINT_INTERVAL {
+            self.checkpoint = self.level;
+            if self.mode == GameMode::Explorer {
+                self.messages
+                    .add("You have reached a checkpoint.", LIGHT_BLUE);
+            }
+        }
+    }
+
+    /// give up the run, recording it among the scores and deleting the save
+    fn abandon_run(&mut self) {
+        let score = format!(
+            "{} mode: abandoned on {} at character level {} after {} turns",
+            self.mode.name(),
+            self.level.name(),
+            self.objects[PLAYER].level,
+            self.turn
+        );
+        if let Err(e) = record_score(&score) {
+            self.messages
+                .add(format!("Could not record the score: {}", e), RED);
+        }
+        record_run_end(self, false);
+        delete_save().ok();
+    }
+
+    /// Record the death and, in explorer mode, bring the player back at the last
+    /// checkpoint. Returns whether the run is over.
+    fn player_died(&mut self, tcod: &mut Tcod) -> bool {
+        self.deaths += 1;
+        let player = &self.objects[PLAYER];
+        let score = format!(
+            "{} mode: died on {} at character level {} after {} turns (death #{})",
+            self.mode.name(),
+            self.level.name(),
+            player.level,
+            self.turn,
+            self.deaths
+        );
+        if let Err(e) = record_score(&score) {
+            self.messages
+                .add(format!("Could not record the score: {}", e), RED);
+        }
+
+        if self.mode == GameMode::Permadeath {
+            // the save goes before anything else can happen, and the character is
+            // buried so that a copy of it kept somewhere won't bring them back
+            delete_save().ok();
+            if let Err(e) = bury(self.run_id) {
+                self.messages
+                    .add(format!("Could not bury the character: {}", e), RED);
+            }
+            if let Err(e) = leave_bones(self) {
+                self.messages
+                    .add(format!("Could not leave the character's bones: {}", e), RED);
+            }
+            record_run_end(self, false);
+            self.death_screen(tcod);
+            return true;
+        }
+
+        let player = &mut self.objects[PLAYER];
+        let fighter = player.fighter.as_mut().unwrap();
+        let lost_xp = fighter.xp * DEATH_XP_PENALTY_PERCENT / 100;
+        fighter.xp -= lost_xp;
+        fighter.hp = fighter.max_hp;
+        fighter.mana = fighter.max_mana;
+        player.alive = true;
+        player.char = '@';
+        player.color = WHITE;
+
+        msgbox(
+            &format!(
+                "\nYou have died... but your spirit is not \
+                 yet free. You awaken on {}, \
+                 having lost {} experience points.\n",
+                self.checkpoint.name(),
+                lost_xp
+            ),
+            LEVEL_SCREEN_WIDTH,
+            &mut tcod.root,
+        );
+        self.messages
+            .add("The dungeon below has shifted while you were gone.", VIOLET);
+        self.level = self.checkpoint;
+        self.levels.clear();
+        self.initialize_map(None);
+        self.initialise_fov(tcod);
+        false
+    }
+
+    /// write the morgue file and sum up the run that just ended
+    fn death_screen(&mut self, tcod: &mut Tcod) {
+        let morgue = match write_morgue(&morgue_text(self)) {
+            Ok(name) => format!("The tale of your demise was written to {}.", name),
+            Err(e) => format!("Could not write the morgue file: {}", e),
+        };
+        // the last look at the dungeon, with the final blow in the message log
+        self.render_all(tcod, false);
+        let kills = self.stats.total_kills();
+        let player = &self.objects[PLAYER];
+        let msg = format!(
+            "\nYou died on {} at character level {}, having slain {} monsters and \
+             gathered {} gold.\n\n{}\n",
+            self.level.name(),
+            player.level,
+            kills,
+            self.gold,
+            morgue
+        );
+        msgbox(&msg, LEVEL_SCREEN_WIDTH, &mut tcod.root);
+    }
+
+    /// Summarize the level being left in the message log, and on a screen unless
+    /// the player turned it off, awarding bonus XP for exploring it fully or for
+    /// leaving it quickly
+    fn grade_level(&mut self, tcod: &mut Tcod) {
+        let (explored, floor) = self
+            .map
+            .iter()
+            .flatten()
+            .filter(|tile| !tile.blocked)
+            .fold((0, 0), |(explored, floor), tile| {
+                (explored + tile.explored as i32, floor + 1)
+            });
+        let explored_percent = explored * 100 / floor.max(1);
+        let stats = self.level_stats;
+        let depth = self.level.absolute_depth() as i32;
+
+        let mut bonus_xp = 0;
+        let mut bonuses = String::new();
+        if explored_percent >= FULL_EXPLORATION_PERCENT {
+            let xp = EXPLORATION_BONUS_XP * depth;
+            bonus_xp += xp;
+            bonuses += &format!("\nExploration bonus: {} XP", xp);
+        }
+        if stats.turns <= SPEED_PAR_TURNS {
+            let xp = SPEED_BONUS_XP * depth;
+            bonus_xp += xp;
+            bonuses += &format!("\nSpeed bonus: {} XP", xp);
+        }
+
+        let msg = format!(
+            "{} complete\n\nMonsters slain: {}\nItems found: {}\n\
+             Turns taken: {}\nExplored: {}%\n{}",
+            self.level.name(),
+            stats.kills,
+            stats.items_found,
+            stats.turns,
+            explored_percent,
+            bonuses
+        );
+        self.messages.add(
+            format!(
+                "{} complete: {} monsters slain, {} items found, {} turns taken, {}% explored.",
+                self.level.name(),
+                stats.kills,
+                stats.items_found,
+                stats.turns,
+                explored_percent
+            ),
+            LIGHT_CYAN,
+        );
+        if tcod.options.level_summary {
+            msgbox(&msg, LEVEL_SCREEN_WIDTH, &mut tcod.root);
+        }
+
+        if bonus_xp > 0 {
+            self.messages.add(
+                format!("You gain {} bonus experience points.", bonus_xp),
+                YELLOW,
+            );
+            if let Some(fighter) = self.objects[PLAYER].fighter.as_mut() {
+                fighter.xp += bonus_xp;
+            }
+        }
+        self.level_stats.graded = true;
+    }
+
+    fn ai_take_turn(&mut self, monster_id: usize, tcod: &mut Tcod) {
+        self.suffer_poison(monster_id);
+        if self.use_abilities(monster_id, tcod) {
+            return;
+        }
+        if let Some(ai) = self.objects[monster_id].ai.take() {
+            let new_ai = match ai {
+                Ai::Basic => self.ai_basic(monster_id, tcod),
+                Ai::Ranged => self.ai_ranged(monster_id, tcod),
+                Ai::Patrol { waypoints, next } => self.ai_patrol(monster_id, tcod, waypoints, next),
+                Ai::Wander { home } => self.ai_wander(monster_id, tcod, home),
+                Ai::Confused {
+                    previous_ai,
+                    num_turns,
+                    by_player,
+                } => self.ai_confused(monster_id, tcod, previous_ai, num_turns, by_player),
+                Ai::Flee {
+                    previous_ai,
+                    num_turns,
+                } => self.ai_flee(monster_id, previous_ai, num_turns),
+                Ai::Asleep { previous_ai } => self.ai_asleep(monster_id, tcod, previous_ai),
+                Ai::Ally { previous_ai, until } => {
+                    self.ai_ally(monster_id, tcod, previous_ai, until)
+                }
+            };
+            self.objects[monster_id].ai = Some(new_ai);
+        }
+    }
+
+    /// let the monster use the abilities of its kind, returning whether that took
+    /// its turn. A sleeping monster only regenerates
+    fn use_abilities(&mut self, monster_id: usize, tcod: &Tcod) -> bool {
+        let awake = matches!(&self.objects[monster_id].ai, Some(ai) if !ai.is_asleep());
+        let alerted = self.objects[monster_id].sight.awareness == Awareness::Alerted;
+        let mut spells = vec![];
+        for ability in self.objects[monster_id].abilities.clone() {
+            match ability {
+                Ability::Regenerate(hp) => {
+                    let hurt =
+                        matches!(self.objects[monster_id].fighter, Some(f) if f.hp < f.max_hp);
+                    if hurt && self.objects[monster_id].alive {
+                        self.heal(monster_id, hp);
+                    }
+                }
+                Ability::Shaman {
+                    heal,
+                    range,
+                    empower_turns,
+                } => {
+                    if awake
+                        && alerted
+                        && self.shamanize(monster_id, heal, range, empower_turns, tcod)
+                    {
+                        return true;
+                    }
+                }
+                // goes off once the monster is dead, not on its turns
+                Ability::DeathBurst { .. } => {}
+                Ability::Cast(spell) => spells.push(spell),
+            }
+        }
+        // of the spells it knows, the monster tries them in a random order and
+        // casts the first that is of use
+        if !awake || !alerted {
+            return false;
+        }
+        thread_rng().shuffle(&mut spells);
+        spells
+            .into_iter()
+            .any(|spell| cast_monster_spell(spell, monster_id, tcod, self))
+    }
+
+    /// mend the wounds of the shaman's most hurt ally in range, or if none is hurt
+    /// spur one on that isn't yet. Returns whether there was anyone to help
+    fn shamanize(
+        &mut self,
+        shaman_id: usize,
+        heal: i32,
+        range: f32,
+        empower_turns: i32,
+        tcod: &Tcod,
+    ) -> bool {
+        let shaman = &self.objects[shaman_id];
+        let allies: Vec<_> = (0..self.objects.len())
+            .filter(|&id| {
+                let ally = &self.objects[id];
+                id != shaman_id
+                    && id != PLAYER
+                    && ally.alive
+                    && ally.ai.is_some()
+                    && ally.allegiance() == shaman.allegiance()
+                    && shaman.distance_to(ally) <= range
+            })
+            .collect();
+        let wounds = |id: usize| self.objects[id].fighter.map_or(0, |f| f.max_hp - f.hp);
+        let shaman_name = self.objects[shaman_id].name.clone();
+        if let Some(&hurt) = allies
+            .iter()
+            .filter(|&&id| wounds(id) > 0)
+            .max_by_key(|&&id| wounds(id))
+        {
+            if sees(&tcod.fov, &self.objects[PLAYER], &self.objects[hurt]) {
+                let msg = format!(
+                    "The {} mends the wounds of the {}.",
+                    shaman_name, self.objects[hurt].name
+                );
+                self.messages.add(msg, LIGHT_GREY);
+            }
+            self.heal(hurt, heal);
+            return true;
+        }
+        let spurred = allies
+            .into_iter()
+            .find(|&id| !self.objects[id].has_effect(EffectKind::Empowered));
+        if let Some(spurred) = spurred {
+            if sees(&tcod.fov, &self.objects[PLAYER], &self.objects[spurred]) {
+                let msg = format!(
+                    "The {} chants, and the {} is spurred on!",
+                    shaman_name, self.objects[spurred].name
+                );
+                self.messages.add(msg, ORANGE);
+            }
+            let turn = self.turn;
+            self.objects[spurred].add_effect(EffectKind::Empowered, empower_turns, turn);
+            return true;
+        }
+        false
+    }
+
+    /// what the player stands in
+    fn player_terrain(&self) -> Terrain {
+        let (x, y) = self.objects[PLAYER].pos();
+        self.map[x as usize][y as usize].terrain
+    }
+
+    /// what the terrain the player stands in does to them: lava burns, deep water
+    /// soaks the scrolls carried and a chasm drops them to the level below
+    fn tread_terrain(&mut self, tcod: &mut Tcod) {
+        match self.player_terrain() {
+            Terrain::Lava if self.objects[PLAYER].has_intrinsic(Intrinsic::FireResistance) => {
+                self.messages
+                    .add("The lava is pleasantly warm underfoot.", ORANGE);
+            }
+            Terrain::Lava => {
+                self.messages.add("The lava burns you!", ORANGE);
+                self.damage(
+                    PLAYER,
+                    LAVA_DAMAGE,
+                    DamageType::Fire,
+                    DamageSource::Environment,
+                );
+            }
+            Terrain::DeepWater if rand::random::<f32>() < SOAK_CHANCE => {
+                let scrolls: Vec<_> = (0..self.inventory.len())
+                    .filter(|&id| {
+                        self.inventory[id].item.map(Item::category) == Some(Category::Scroll)
+                    })
+                    .collect();
+                if let Some(&id) = thread_rng().choose(&scrolls) {
+                    let scroll = self.inventory.remove(id);
+                    self.messages.add(
+                        format!("Your {} gets soaked, its ink running away.", scroll.name),
+                        LIGHT_BLUE,
+                    );
+                }
+            }
+            Terrain::Chasm => {
+                if let Some(below) = self.level.down() {
+                    self.fall(below, tcod);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// drop down a chasm to the level below, landing somewhere on it hard enough
+    /// to hurt but not to kill
+    fn fall(&mut self, below: LevelId, tcod: &mut Tcod) {
+        self.events.emit(GameEvent::FellDown);
+        self.handle_events();
+        self.change_level(below, tcod);
+        let mut rng = thread_rng();
+        let landing = (0..FALL_LANDING_TRIES)
+            .map(|_| (rng.gen_range(0, MAP_WIDTH), rng.gen_range(0, MAP_HEIGHT)))
+            .find(|&(x, y)| {
+                self.map[x as usize][y as usize].terrain == Terrain::Floor
+                    && !is_blocked(x, y, &self.map, &self.objects)
+            });
+        if let Some((x, y)) = landing {
+            self.objects[PLAYER].set_pos(x, y);
+        }
+        let hp = self.objects[PLAYER].fighter.map_or(0, |f| f.hp);
+        self.damage(
+            PLAYER,
+            FALL_DAMAGE.min(hp - 1),
+            DamageType::Physical,
+            DamageSource::Environment,
+        );
+    }
+
+    /// a regenerating object heals a hit point every few turns
+    fn regenerate(&mut self, id: usize) {
+        let object = &self.objects[id];
+        let due = self.turn % REGENERATION_TURNS == 0;
+        if object.alive && due && object.has_intrinsic(Intrinsic::Regeneration) {
+            self.heal(id, 1);
+        }
+    }
+
+    /// poison burns away some of the object's hit points
+    fn suffer_poison(&mut self, id: usize) {
+        let object = &self.objects[id];
+        let poisoned = object.has_effect(EffectKind::Poisoned)
+            && !object.has_intrinsic(Intrinsic::PoisonResistance);
+        if object.alive && poisoned {
+            self.damage(
+                id,
+                POISON_DAMAGE_PER_TURN,
+                DamageType::Poison,
+                DamageSource::Environment,
+            );
+        }
+    }
+
+    fn ai_basic(&mut self, monster_id: usize, tcod: &mut Tcod) -> Ai {
+        // a basic monster takes its turn, going for the player once it has seen them
+        let (monster_x, monster_y) = self.objects[monster_id].pos();
+        if self.spots_player(monster_id) {
+            if self.morale_breaks(monster_id) {
+                self.messages.add(
+                    format!("The {} turns to flee!", self.objects[monster_id].name),
+                    LIGHT_GREY,
+                );
+                return Ai::Basic.frighten(FLEE_TURNS);
+            }
+            let target_id = match self.nearest_enemy(monster_id) {
+                Some(target_id) => target_id,
+                None => return Ai::Basic,
+            };
+            if self.objects[monster_id].distance_to(&self.objects[target_id]) >= 2.0 {
+                // move towards the enemy if far away. A shooter standing in the way
+                // trades places, so the melee fighters end up in front
+                let (target_x, target_y) = self.step_towards(monster_id, target_id);
+                let (dx, dy) = self.direction_towards(monster_id, target_x, target_y);
+                let destination = (monster_x + dx, monster_y + dy);
+                let faction = self.objects[monster_id].allegiance();
+                let ranged_ally = self.objects.iter().position(|object| {
+                    object.pos() == destination
+                        && object.ai == Some(Ai::Ranged)
+                        && object.allegiance() == faction
+                });
+                match ranged_ally {
+                    Some(ally_id) => {
+                        self.objects[ally_id].set_pos(monster_x, monster_y);
+                        self.objects[monster_id].set_pos(destination.0, destination.1);
+                    }
+                    None => self.move_towards(monster_id, target_x, target_y, tcod),
+                }
+            } else {
+                // close enough, attack!
+                self.melee(monster_id, target_id);
+            }
+        } else if self.objects[monster_id].sight.awareness != Awareness::Unaware {
+            // out of sight, follow the player's trail while it is fresh and the
+            // monster still looks out for them
+            let objects = &self.objects;
+            let trail = self.scent.follow(monster_x, monster_y, self.turn, |x, y| {
+                !objects
+                    .iter()
+                    .any(|object| object.blocks && object.pos() == (x, y))
+            });
+            if let Some((dx, dy)) = trail {
+                self.move_towards(monster_id, monster_x + dx, monster_y + dy, tcod);
+            }
+        }
+        Ai::Basic
+    }
+
+    fn ai_ranged(&mut self, monster_id: usize, tcod: &mut Tcod) -> Ai {
+        // a ranged monster closes in until the player is within reach, then holds
+        // back and shoots, leaving the front line to the melee fighters
+        if self.spots_player(monster_id) {
+            if self.objects[monster_id].distance_to(&self.objects[PLAYER]) > RANGED_ATTACK_RANGE {
+                let (step_x, step_y) = self.step_towards(monster_id, PLAYER);
+                self.move_towards(monster_id, step_x, step_y, tcod);
+            } else if self.objects[PLAYER].fighter.is_some_and(|f| f.hp > 0) {
+                self.shoot(monster_id, PLAYER);
+            }
+        }
+        Ai::Ranged
+    }
+
+    /// loose an arrow at the target, the first creature in the way taking it
+    /// instead, as hard as the shooter hits less the defense of whoever it strikes
+    fn shoot(&mut self, shooter_id: usize, target_id: usize) {
+        let objects = &self.objects;
+        let hit_id = Line::new(objects[shooter_id].pos(), objects[target_id].pos())
+            .find_map(|(x, y)| {
+                objects.iter().position(|object| {
+                    object.pos() == (x, y) && object.alive && object.fighter.is_some()
+                })
+            })
+            .unwrap_or(target_id);
+        let blow = self.objects[shooter_id].blow();
+        let power = self.difficulty.monster_damage(blow.power);
+        let damage = power - self.objects[hit_id].fighter.map_or(0, |f| f.defense);
+        let target = if hit_id == PLAYER {
+            "you".to_string()
+        } else {
+            format!("the {}", self.objects[hit_id].name)
+        };
+        if damage > 0 {
+            self.messages.add(
+                format!(
+                    "The {} shoots {} for {} hit points.",
+                    blow.attacker, target, damage
+                ),
+                WHITE,
+            );
+            self.damage(hit_id, damage, blow.kind, DamageSource::Monster);
+        } else {
+            self.messages.add(
+                format!("The {}'s arrow glances off {}.", blow.attacker, target),
+                WHITE,
+            );
+        }
+    }
+
+    fn ai_patrol(
+        &mut self,
+        monster_id: usize,
+        tcod: &mut Tcod,
+        waypoints: Vec<(i32, i32)>,
+        mut next: usize,
+    ) -> Ai {
+        // a patrol breaks off its round to hunt the first enemy it spots, and
+        // stops to look around when it thinks it glimpsed the player
+        let (x, y) = self.objects[monster_id].pos();
+        if self.spots_enemy(monster_id) || waypoints.is_empty() {
+            return self.ai_basic(monster_id, tcod);
+        }
+        if let Awareness::Suspicious(_) = self.objects[monster_id].sight.awareness {
+            return Ai::Patrol { waypoints, next };
+        }
+
+        // walk the round, finding the way around walls
+        if (x, y) == waypoints[next] {
+            next = (next + 1) % waypoints.len();
+        }
+        let map = &self.map;
+        let step = self.paths.first_step((x, y), waypoints[next], |x, y| {
+            map[x as usize][y as usize].monster_passable()
+        });
+        match step {
+            Some((step_x, step_y)) => self.move_towards(monster_id, step_x, step_y, tcod),
+            // no way there, head for the next waypoint
+            None => next = (next + 1) % waypoints.len(),
+        }
+        Ai::Patrol { waypoints, next }
+    }
+
+    fn ai_wander(&mut self, monster_id: usize, tcod: &mut Tcod, home: Rect) -> Ai {
+        // a wanderer ambles about its room, breaking off to hunt the first enemy it
+        // spots and standing still to look around when it thinks it glimpsed the
+        // player
+        if self.spots_enemy(monster_id) {
+            return self.ai_basic(monster_id, tcod);
+        }
+        let suspicious = matches!(
+            self.objects[monster_id].sight.awareness,
+            Awareness::Suspicious(_)
+        );
+        let mut rng = thread_rng();
+        if !suspicious && rng.gen::<f32>() < WANDER_STEP_CHANCE {
+            let (x, y) = self.objects[monster_id].pos();
+            let (dx, dy) = (rng.gen_range(-1, 2), rng.gen_range(-1, 2));
+            if home.contains(x + dx, y + dy) {
+                self.move_by(monster_id, dx, dy);
+            }
+        }
+        Ai::Wander { home }
+    }
+
+    /// whether a patrolling or wandering monster has spotted an enemy to go for:
+    /// the player once it has seen them, its other enemies when they are close
+    /// and in its sight
+    fn spots_enemy(&mut self, monster_id: usize) -> bool {
+        match self.nearest_enemy(monster_id) {
+            Some(PLAYER) => self.spots_player(monster_id),
+            Some(enemy_id) => {
+                let map = &self.map;
+                let monster = &self.objects[monster_id];
+                monster
+                    .sight
+                    .sees(monster.pos(), self.objects[enemy_id].pos(), |x, y| {
+                        map[x as usize][y as usize].block_sight
+                    })
+            }
+            None => false,
+        }
+    }
+
+    /// a charmed monster fights the player's enemies in sight and otherwise keeps
+    /// close to the player, until the charm wears off
+    fn ai_ally(
+        &mut self,
+        monster_id: usize,
+        tcod: &mut Tcod,
+        previous_ai: Box<Ai>,
+        until: i32,
+    ) -> Ai {
+        if self.turn >= until {
+            self.messages.add(
+                format!(
+                    "The {} shakes off the charm!",
+                    self.objects[monster_id].name
+                ),
+                RED,
+            );
+            return *previous_ai;
+        }
+        let ally = Ai::Ally { previous_ai, until };
+
+        let monster = &self.objects[monster_id];
+        let enemy = self
+            .nearest_enemy(monster_id)
+            .filter(|&id| sees(&tcod.fov, &self.objects[PLAYER], &self.objects[id]));
+        let goal = match enemy {
+            Some(enemy_id) if monster.distance_to(&self.objects[enemy_id]) < 2.0 => {
+                self.melee(monster_id, enemy_id);
+                return ally;
+            }
+            Some(enemy_id) => self.objects[enemy_id].pos(),
+            None if monster.distance_to(&self.objects[PLAYER]) > ALLY_FOLLOW_DISTANCE => {
+                self.objects[PLAYER].pos()
+            }
+            None => return ally,
+        };
+
+        // find the way around walls, to the player or to the enemy
+        let map = &self.map;
+        let step = self.paths.first_step(monster.pos(), goal, |x, y| {
+            map[x as usize][y as usize].monster_passable()
+        });
+        if let Some((step_x, step_y)) = step {
+            self.move_towards(monster_id, step_x, step_y, tcod);
+        }
+        ally
+    }
+
+    fn ai_asleep(&mut self, monster_id: usize, tcod: &Tcod, previous_ai: Box<Ai>) -> Ai {
+        // the closer the player sneaks up, the likelier the monster wakes
+        let (monster_x, monster_y) = self.objects[monster_id].pos();
+        let monster = &self.objects[monster_id];
+        if in_sight(&tcod.fov, monster_x, monster_y) && !hidden_from(&self.objects[PLAYER], monster)
+        {
+            let distance = self.objects[monster_id].distance_to(&self.objects[PLAYER]);
+            if rand::random::<f32>() < NOTICE_CHANCE / distance.max(1.0) {
+                self.messages.add(
+                    format!(
+                        "The {} wakes and notices you!",
+                        self.objects[monster_id].name
+                    ),
+                    LIGHT_RED,
+                );
+                let player = self.objects[PLAYER].pos();
+                let sight = &mut self.objects[monster_id].sight;
+                sight.face((monster_x, monster_y), player);
+                sight.alert();
+                return *previous_ai;
+            }
+        }
+        Ai::Asleep { previous_ai }
+    }
+
+    /// grow hungrier by a turn, starving once nothing is left to digest, and let
+    /// the meat carried rot as it would lying on the floor
+    fn digest(&mut self) {
+        if let Some(state) = self.hunger.onset(self.turn) {
+            let color = match state {
+                HungerState::Fed | HungerState::Hungry => YELLOW,
+                HungerState::Weak | HungerState::Starving => RED,
+            };
+            self.messages.add(state.onset_message(), color);
+        }
+        if self.hunger.state(self.turn) == HungerState::Starving && self.turn % STARVE_TURNS == 0 {
+            self.damage(PLAYER, 1, DamageType::Physical, DamageSource::Environment);
+        }
+
+        // meat goes bad as the clock runs, wherever it lies
+        let turn = self.turn;
+        for object in self.objects.iter_mut().chain(self.inventory.iter_mut()) {
+            if let (Some(meat), Some(decays_at)) = (object.meat.as_mut(), object.decays_at) {
+                meat.rotten = is_rotten(decays_at, turn);
+            }
+        }
+        let messages = &mut self.messages;
+        self.inventory.retain(|object| {
+            let rotted_away = matches!(object.decays_at, Some(decays_at) if decays_at <= turn);
+            if rotted_away {
+                messages.add(format!("Your {} rots away.", object.name), LIGHT_GREY);
+            }
+            !rotted_away
+        });
+    }
+
+    /// cut a corpse up into chunks of meat, left lying where it was and as fresh
+    /// as it was
+    fn butcher(&mut self, corpse_id: usize) {
+        let corpse = self.objects.swap_remove(corpse_id);
+        let meat = match corpse.meat {
+            Some(meat) => meat,
+            None => return,
+        };
+        let name = format!("chunk of {} meat", meat.of);
+        for _ in 0..meat.chunks {
+            let mut chunk = item_object(Item::Chunk, corpse.x, corpse.y, 0, &mut thread_rng());
+            chunk.name = name.clone();
+            chunk.decays_at = corpse.decays_at;
+            chunk.meat = Some(meat.clone());
+            self.objects.push(chunk);
+        }
+        let chunks = match meat.chunks {
+            1 => "a chunk".to_string(),
+            chunks => format!("{} chunks", chunks),
+        };
+        self.messages.add(
+            format!(
+                "You butcher the {}, cutting {} of meat.",
+                corpse.name, chunks
+            ),
+            LIGHT_GREY,
+        );
+    }
+
+    /// burn a turn's worth of the light the player carries, warning them when it is
+    /// about to go out. Spent torches crumble, empty lanterns are kept
+    fn burn_fuel(&mut self) {
+        let light_id = match self
+            .inventory
+            .iter()
+            .position(|object| object.equipped && object.fuel.is_some_and(|fuel| fuel > 0))
+        {
+            Some(light_id) => light_id,
+            None => return,
+        };
+        let light = &mut self.inventory[light_id];
+        let fuel = light.fuel.as_mut().unwrap();
+        *fuel -= 1;
+        match *fuel {
+            FUEL_WARNING_TURNS => {
+                let msg = format!("Your {} flickers, it won't last much longer.", light.name);
+                self.messages.add(msg, ORANGE);
+            }
+            0 => {
+                let msg = format!("Your {} goes out, darkness closes in!", light.name);
+                self.messages.add(msg, LIGHT_RED);
+                if light.item == Some(Item::Torch) {
+                    self.inventory.remove(light_id);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// pounds the player can carry without being slowed down, the stronger the more
+    fn carry_capacity(&self) -> i32 {
+        let power = self.objects[PLAYER].fighter.map_or(0, |f| f.power);
+        CARRY_BASE + power * CARRY_PER_POWER
+    }
+
+    fn carried_weight(&self) -> i32 {
+        self.inventory.iter().map(Object::weight).sum()
+    }
+
+    fn overburdened(&self) -> bool {
+        self.carried_weight() > self.carry_capacity()
+    }
+
+    /// the line telling how much the player carries, for the inventory screen
+    fn burden(&self) -> String {
+        let state = if self.overburdened() {
+            ", overburdened"
+        } else {
+            ""
+        };
+        format!(
+            "Carrying {} of {} lb{}.",
+            self.carried_weight(),
+            self.carry_capacity(),
+            state
+        )
+    }
+
+    /// whether the player carries an item of the kind
+    fn carries(&self, item: Item) -> bool {
+        self.inventory
+            .iter()
+            .any(|object| object.item == Some(item))
+    }
+
+    /// the armor the player wears, if any
+    fn worn_armor(&self) -> Option<Armor> {
+        self.inventory
+            .iter()
+            .filter(|object| object.equipped)
+            .filter_map(|object| object.item)
+            .find_map(Item::armor)
+    }
+
+    /// the depth of the deepest level the player has been to
+    pub fn deepest_depth(&self) -> u32 {
+        self.levels
+            .keys()
+            .chain(Some(&self.level))
+            .map(|level| level.absolute_depth())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// how lucky the player is, from the charms they carry and the favour of the
+    /// gods
+    pub fn luck(&self) -> i32 {
+        let charms = self
+            .inventory
+            .iter()
+            .filter(|object| object.item == Some(Item::LuckyCharm))
+            .count() as i32;
+        charms * CHARM_LUCK + self.favour
+    }
+
+    /// the gods grant the player luck, or take it away, within bounds
+    pub fn change_favour(&mut self, change: i32) {
+        self.favour = (self.favour + change).clamp(-MAX_FAVOUR, MAX_FAVOUR);
+    }
+
+    /// hurt the object with damage of the kind, as much of it as it doesn't resist,
+    /// crediting the player with the kill if they dealt the blow and with their
+    /// share of the XP if it dies
+    pub fn damage(&mut self, id: usize, damage: i32, kind: DamageType, source: DamageSource) {
+        let damage = self.objects[id].resist(kind, damage, &mut self.events);
+        if self.objects[id].fighter.is_some() {
+            self.events.emit(GameEvent::Hurt {
+                damage: damage.max(0),
+                by_player: source == DamageSource::Player,
+                to_player: id == PLAYER,
+                at: self.objects[id].pos(),
+            });
+        }
+        if source == DamageSource::Player {
+            self.alert_to_player(id);
+        }
+        let hp = self.objects[id].fighter.map(|f| f.hp);
+        if let Some(xp) = self.objects[id].take_damage(damage, source, self.turn, &mut self.events)
+        {
+            if let Some(fighter) = self.objects[PLAYER].fighter.as_mut() {
+                fighter.xp += xp;
             }
-            _ => DidntTakeTurn,
         }
+        self.float_hp_change(id, hp);
+        self.handle_events();
     }
 
-    fn player_move_or_attack(&mut self, dx: i32, dy: i32) {
-        // the coordinates the player is moving to/attacking
-        let x = self.objects[PLAYER].x + dx;
-        let y = self.objects[PLAYER].y + dy;
+    /// heal the object, the player as well as the difficulty lets them
+    pub fn heal(&mut self, id: usize, amount: i32) {
+        let amount = if id == PLAYER {
+            self.difficulty.healing(amount)
+        } else {
+            amount
+        };
+        let hp = self.objects[id].fighter.map(|f| f.hp);
+        self.objects[id].heal(amount);
+        self.float_hp_change(id, hp);
+    }
 
-        // try to find an attackable object there
-        let target_id = self
-            .objects
-            .iter()
-            .position(|object| object.fighter.is_some() && object.pos() == (x, y));
+    /// heal the object of all its wounds, whatever the difficulty
+    pub fn heal_fully(&mut self, id: usize) {
+        let hp = self.objects[id].fighter.map(|f| f.hp);
+        let max_hp = self.objects[id].fighter.map_or(0, |f| f.max_hp);
+        self.objects[id].heal(max_hp);
+        self.float_hp_change(id, hp);
+    }
 
-        // attack if target found, move otherwise
-        match target_id {
-            Some(target_id) => {
-                let (player, target) = mut_two(PLAYER, target_id, &mut self.objects);
-                player.attack(target, &mut self.messages);
+    /// float the hit points an object lost or gained since it had `hp_before` up
+    /// from it, all of them if the blow killed it and left no fighter behind
+    fn float_hp_change(&mut self, id: usize, hp_before: Option<i32>) {
+        let object = &self.objects[id];
+        let change = match (object.fighter, hp_before) {
+            (Some(fighter), Some(hp_before)) => fighter.hp.max(0) - hp_before,
+            (None, Some(hp_before)) => -hp_before.max(0),
+            _ => 0,
+        };
+        let (text, color) = match change {
+            0 => return,
+            change if change < 0 => (change.to_string(), LIGHT_RED),
+            change => (format!("+{}", change), LIGHT_GREEN),
+        };
+        self.animations.float(object.x, object.y, text, color);
+    }
+
+    /// go through what happened, in order: tell the player, keep count, hand out
+    /// achievements and let the hooks know
+    fn handle_events(&mut self) {
+        for event in self.events.take() {
+            self.messages.log(&event);
+            self.run_log.record(self.turn, self.level, &event);
+            self.stats.record(&event);
+            self.level_stats.record(&event);
+            self.animations.record(&event, self.objects[PLAYER].pos());
+            for achievement in self.achievements.record(&event, &self.stats) {
+                self.messages
+                    .add(format!("Achievement: {}!", achievement.name()), LIGHT_CYAN);
             }
-            None => {
-                self.move_by(PLAYER, dx, dy);
+            for task in self.journal.record(&event) {
+                self.messages.add(
+                    format!("Quest done: {}. Report back to the elder in town.", task),
+                    LIGHT_CYAN,
+                );
             }
+            self.events.run_hooks(&event);
         }
     }
 
-    /// move by the given amount, if the destination is not blocked
-    fn move_by(&mut self, id: usize, dx: i32, dy: i32) {
-        let (x, y) = self.objects[id].pos();
-        if !is_blocked(x + dx, y + dy, &mut self.map, &mut self.objects) {
-            self.objects[id].set_pos(x + dx, y + dy);
+    /// wake every sleeping monster within earshot of a noise, even out of sight
+    pub fn make_noise(&mut self, x: i32, y: i32, radius: f32, tcod: &Tcod) {
+        let seen: Vec<bool> = self
+            .objects
+            .iter()
+            .map(|object| sees(&tcod.fov, &self.objects[PLAYER], object))
+            .collect();
+        for (object, seen) in self.objects.iter_mut().zip(seen) {
+            if object.distance(x, y) > radius {
+                continue;
+            }
+            if object.ai.as_ref().is_some_and(Ai::is_asleep) {
+                if seen {
+                    self.messages
+                        .add(format!("The noise wakes the {}!", object.name), LIGHT_RED);
+                }
+                object.ai = object.ai.take().map(Ai::wake);
+            }
+            // whoever hears it turns to see what it was
+            if object.ai.is_some() {
+                let from = object.pos();
+                object.sight.face(from, (x, y));
+                object.sight.startle();
+            }
         }
     }
 
-    fn move_towards(&mut self, id: usize, target_x: i32, target_y: i32) {
-        // vector from this object to the target, and distance
-        let dx = target_x - self.objects[id].x;
-        let dy = target_y - self.objects[id].y;
-        let distance = ((dx.pow(2) + dy.pow(2)) as f32).sqrt();
-
-        // normalize it to length 1 (preserving direction), then round it and
-        // convert to integer so the movement is restricted to the map grid
-        let dx = (dx as f32 / distance).round() as i32;
-        let dy = (dy as f32 / distance).round() as i32;
-        self.move_by(id, dx, dy);
+    /// whether the monster knows the player is there, taking a look out for them
+    /// with its own eyes and turning towards them if it sees them
+    fn spots_player(&mut self, monster_id: usize) -> bool {
+        let map = &self.map;
+        let monster = &self.objects[monster_id];
+        let player = &self.objects[PLAYER];
+        let seen = player.alive
+            && !hidden_from(player, monster)
+            && monster.sight.sees(monster.pos(), player.pos(), |x, y| {
+                map[x as usize][y as usize].block_sight
+            });
+        let distance = monster.distance_to(player);
+        let (from, to) = (monster.pos(), player.pos());
+        let sight = &mut self.objects[monster_id].sight;
+        if seen {
+            sight.face(from, to);
+        }
+        sight.look(seen, distance)
     }
 
-    /// add to the player's inventory and remove from the map
-    fn pick_item_up(&mut self, object_id: usize) {
-        if self.inventory.len() >= 26 {
-            self.messages.add(
-                format!(
-                    "Your inventory is full, cannot pick up {}.",
-                    self.objects[object_id].name
-                ),
-                RED,
-            );
-        } else {
-            let item = self.objects.swap_remove(object_id);
-            self.messages
-                .add(format!("You picked up a {}!", item.name), GREEN);
-            self.inventory.push(item);
+    /// the monster hit by the player knows where they are, seen or not
+    fn alert_to_player(&mut self, monster_id: usize) {
+        if monster_id != PLAYER {
+            let (from, to) = (self.objects[monster_id].pos(), self.objects[PLAYER].pos());
+            let sight = &mut self.objects[monster_id].sight;
+            sight.face(from, to);
+            sight.alert();
         }
     }
 
-    /// Advance to the next level
-    fn next_level(&mut self, tcod: &mut Tcod) {
-        self.messages.add(
-            "You take a moment to rest, and recover your strength.",
-            VIOLET,
-        );
-        let heal_hp = self.objects[PLAYER].fighter.map_or(0, |f| f.max_hp / 2);
-        self.objects[PLAYER].heal(heal_hp);
+    /// the closest living fighter the monster is hostile to: the player wherever they
+    /// are in sight, other monsters only when close by
+    fn nearest_enemy(&self, monster_id: usize) -> Option<usize> {
+        let monster = &self.objects[monster_id];
+        self.objects
+            .iter()
+            .enumerate()
+            .filter(|&(id, object)| {
+                id != monster_id
+                    && object.alive
+                    && object.fighter.is_some()
+                    && monster.allegiance().is_hostile_to(object.allegiance())
+                    && (id == PLAYER || monster.distance_to(object) <= FACTION_SIGHT)
+            })
+            .min_by(|(_, a), (_, b)| {
+                let distance_a = monster.distance_to(a);
+                let distance_b = monster.distance_to(b);
+                distance_a.partial_cmp(&distance_b).unwrap()
+            })
+            .map(|(id, _)| id)
+    }
 
-        self.messages.add(
-            "After a rare moment of peace, you descend deeper into \
-         the heart of the dungeon...",
-            RED,
-        );
-        self.dungeon_level += 1;
-        self.initialize_map();
-        self.initialise_fov(tcod);
+    /// badly hurt monsters may lose their nerve, bosses never do
+    fn morale_breaks(&self, monster_id: usize) -> bool {
+        let fighter = match self.objects[monster_id].fighter {
+            Some(fighter) if fighter.on_death != DeathCallback::Boss => fighter,
+            _ => return false,
+        };
+        fighter.hp * 100 <= fighter.max_hp * FLEE_HP_PERCENT && rand::random::<f32>() < FLEE_CHANCE
     }
 
-    fn ai_take_turn(&mut self, monster_id: usize, tcod: &Tcod) {
-        if let Some(ai) = self.objects[monster_id].ai.take() {
-            let new_ai = match ai {
-                Ai::Basic => self.ai_basic(monster_id, tcod),
-                Ai::Confused {
-                    previous_ai,
-                    num_turns,
-                } => self.ai_confused(monster_id, tcod, previous_ai, num_turns),
-            };
-            self.objects[monster_id].ai = Some(new_ai);
+    fn ai_flee(&mut self, monster_id: usize, previous_ai: Box<Ai>, num_turns: i32) -> Ai {
+        // keep running until the fear wears off, or the monster rallies
+        let name = self.objects[monster_id].name.clone();
+        if num_turns <= 0 || rand::random::<f32>() < RALLY_CHANCE {
+            self.messages.add(
+                format!("The {} rallies and turns to fight!", name),
+                LIGHT_GREY,
+            );
+            return *previous_ai;
         }
-    }
 
-    fn ai_basic(&mut self, monster_id: usize, tcod: &Tcod) -> Ai {
-        // a basic monster takes its turn. If you can see it, it can see you
-        let (monster_x, monster_y) = self.objects[monster_id].pos();
-        if tcod.fov.is_in_fov(monster_x, monster_y) {
-            if self.objects[monster_id].distance_to(&self.objects[PLAYER]) >= 2.0 {
-                // move towards player if far away
-                let (player_x, player_y) = self.objects[PLAYER].pos();
-                self.move_towards(monster_id, player_x, player_y);
-            } else if self.objects[PLAYER].fighter.map_or(false, |f| f.hp > 0) {
-                // close enough, attack! (if the player is still alive.)
-                let (monster, player) = mut_two(monster_id, PLAYER, &mut self.objects);
-                monster.attack(player, &mut self.messages);
-            }
+        // run downhill on the flee map, which leads around corners and out of
+        // dead ends rather than straight away from the player
+        let player_map = self.player_map.as_ref();
+        if let Some(player_map) = player_map.filter(|_| self.flee_map_stale) {
+            self.flee_map.flee_from(player_map);
+            self.flee_map_stale = false;
+        }
+        let position = self.objects[monster_id].pos();
+        // there is nothing to flee by before the monsters first find their way
+        let step = player_map.and_then(|_| {
+            self.flee_map.downhill(position.0, position.1, |x, y| {
+                !is_blocked(x, y, &self.map, &self.objects)
+            })
+        });
+        if let Some((dx, dy)) = step {
+            self.move_by(monster_id, dx, dy);
+        }
+        if self.objects[monster_id].pos() == position {
+            // nowhere left to run
+            self.messages
+                .add(format!("The cornered {} fights back!", name), LIGHT_GREY);
+            return *previous_ai;
+        }
+        Ai::Flee {
+            previous_ai,
+            num_turns: num_turns - 1,
         }
-        Ai::Basic
     }
 
     fn ai_confused(
@@ -435,18 +3577,28 @@ impl Game {
         _tcod: &Tcod,
         previous_ai: Box<Ai>,
         num_turns: i32,
+        by_player: bool,
     ) -> Ai {
         if num_turns >= 0 {
             // still confused ...
-            // move in a random direction, and decrease the number of turns confused
-            self.move_by(
-                monster_id,
-                thread_rng().gen_range(-1, 2),
-                thread_rng().gen_range(-1, 2),
-            );
+            // move in a random direction, and decrease the number of turns confused.
+            // Whoever is stumbled into gets hit, friend or foe, and the player
+            // shares in the kill if they did the confusing
+            let (dx, dy) = (thread_rng().gen_range(-1, 2), thread_rng().gen_range(-1, 2));
+            let (x, y) = self.objects[monster_id].pos();
+            let bumped = self.objects.iter().position(|object| {
+                object.pos() == (x + dx, y + dy) && object.alive && object.fighter.is_some()
+            });
+            match bumped {
+                Some(target_id) if target_id != monster_id => {
+                    self.confused_melee(monster_id, target_id, by_player)
+                }
+                _ => self.move_by(monster_id, dx, dy),
+            }
             Ai::Confused {
-                previous_ai: previous_ai,
+                previous_ai,
                 num_turns: num_turns - 1,
+                by_player,
             }
         } else {
             // restore the previous AI (this one will be deleted)
@@ -461,139 +3613,403 @@ impl Game {
         }
     }
 
+    /// how far the player can see, by the light they carry
+    fn fov_radius(&self) -> i32 {
+        if self.objects[PLAYER].has_effect(EffectKind::Blind) {
+            return BLIND_RADIUS;
+        }
+        self.inventory
+            .iter()
+            .find(|object| object.equipped && object.fuel.is_some_and(|fuel| fuel > 0))
+            .and_then(|object| object.item)
+            .and_then(Item::light_radius)
+            .unwrap_or(DARK_RADIUS)
+    }
+
     fn render_all(&mut self, tcod: &mut Tcod, fov_recompute: bool) {
+        // between putting the old level away and setting up the new one there is
+        // no map to draw
+        if self.map.is_empty() {
+            return;
+        }
         if fov_recompute {
             // recompute FOV if needed (the player moved or something)
             let player = &self.objects[PLAYER];
-            tcod.fov
-                .compute_fov(player.x, player.y, TORCH_RADIUS, FOV_LIGHT_WALLS, FOV_ALGO);
+            tcod.fov.compute_fov(
+                player.x,
+                player.y,
+                self.fov_radius(),
+                FOV_LIGHT_WALLS,
+                tcod.options.fov_algo.algorithm(),
+            );
         }
 
-        // draw all objects in the list
-        let mut to_draw: Vec<_> = self
-            .objects
-            .iter()
-            .filter(|o| {
-                tcod.fov.is_in_fov(o.x, o.y)
-                    || (o.always_visible && self.map[o.x as usize][o.y as usize].explored)
-            })
-            .collect();
-        to_draw.sort_by(|o1, o2| o1.blocks.cmp(&o2.blocks));
-        for object in to_draw {
-            object.draw(&mut tcod.con);
+        // forget what used to lie on the visible tiles, and remember what lies there now.
+        // monsters and the player are left out, they won't stay put anyway
+        for y in 0..MAP_HEIGHT {
+            for x in 0..MAP_WIDTH {
+                if in_sight(&tcod.fov, x, y) {
+                    self.memory[x as usize][y as usize] = None;
+                }
+            }
+        }
+        let player = &self.objects[PLAYER];
+        for object in &self.objects {
+            if object.fighter.is_none() && sees(&tcod.fov, player, object) {
+                self.memory[object.x as usize][object.y as usize] = Some(object.char);
+            }
         }
 
-        // go through all tiles, and set their background color
+        // draw the doors and the floor glyph of the theme on explored tiles,
+        // objects go on top of them
+        let palette = tcod.options.palette;
+        let colors = &palette.themed(self.theme);
+        let floor_glyph = self.theme.floor_glyph();
         for y in 0..MAP_HEIGHT {
             for x in 0..MAP_WIDTH {
-                let visible = tcod.fov.is_in_fov(x, y);
-                let wall = self.map[x as usize][y as usize].block_sight;
-                let color = match (visible, wall) {
-                    // outside of field of view:
-                    (false, true) => COLOR_DARK_WALL,
-                    (false, false) => COLOR_DARK_GROUND,
-                    // inside fov:
-                    (true, true) => COLOR_LIGHT_WALL,
-                    (true, false) => COLOR_LIGHT_GROUND,
+                let tile = &self.map[x as usize][y as usize];
+                let (char, color) = match (tile.door, floor_glyph) {
+                    (Some(Door::Closed), _) => ('+', colors.door),
+                    (Some(Door::Locked), _) => ('+', colors.locked_door),
+                    (Some(Door::Open), _) => ('\'', colors.door),
+                    (Some(Door::Secret), _) => continue,
+                    (_, Some(char)) if !tile.block_sight && tile.terrain == Terrain::Floor => {
+                        (char, colors.dark_wall)
+                    }
+                    _ => continue,
                 };
-                let explored = &mut self.map[x as usize][y as usize].explored;
-                if visible {
-                    // since it's visible, explore it
-                    *explored = true;
-                }
-                if *explored {
-                    // show explored tiles only (any visible tile is explored already)
+                if tile.explored {
+                    let visible = in_sight(&tcod.fov, x, y);
+                    tcod.con.set_default_foreground(if visible {
+                        color
+                    } else {
+                        colors.remembered
+                    });
                     tcod.con
-                        .set_char_background(x, y, color, BackgroundFlag::Set);
+                        .put_char(x, y, glyph(char, tcod.tiles), BackgroundFlag::None);
+                }
+            }
+        }
+
+        // draw all visible objects, the ones that block on top
+        let mut draw_order = mem::take(&mut tcod.scratch.draw_order);
+        draw_order.clear();
+        draw_order.extend((0..self.objects.len()).filter(|&id| {
+            let o = &self.objects[id];
+            sees(&tcod.fov, &self.objects[PLAYER], o)
+                || (o.always_visible && self.map[o.x as usize][o.y as usize].explored)
+        }));
+        draw_order.sort_by_key(|&id| self.objects[id].blocks);
+        for &id in &draw_order {
+            self.objects[id].draw(&mut tcod.con, palette, tcod.tiles);
+        }
+        // mark the monsters in sight that are on the lookout for the player
+        for &id in &draw_order {
+            let object = &self.objects[id];
+            let awake =
+                matches!(&object.ai, Some(ai) if !ai.is_asleep() && !matches!(ai, Ai::Ally { .. }));
+            let marker = object.sight.awareness.marker().filter(|_| awake);
+            if let (Some(marker), true) = (marker, object.y > 0) {
+                tcod.con.set_default_foreground(match marker {
+                    '!' => colors.alerted,
+                    _ => colors.suspicious,
+                });
+                tcod.con
+                    .put_char(object.x, object.y - 1, marker, BackgroundFlag::None);
+            }
+        }
+        tcod.scratch.draw_order = draw_order;
+
+        self.render_tiles(&mut tcod.con, &tcod.fov, colors, tcod.tiles);
+
+        for effect in self.ground.iter() {
+            if in_sight(&tcod.fov, effect.x, effect.y) {
+                let color = match effect.kind {
+                    GroundKind::Fire => colors.fire,
+                    GroundKind::PoisonGas => colors.gas,
+                };
+                tcod.con
+                    .set_char_background(effect.x, effect.y, color, BackgroundFlag::Set);
+            }
+        }
+
+        if let Some(hazard) = self.hazard {
+            // mark where the announced hazard will strike
+            let reach = hazard.kind.radius() as i32;
+            for x in (hazard.x - reach).max(0)..(hazard.x + reach + 1).min(MAP_WIDTH) {
+                for y in (hazard.y - reach).max(0)..(hazard.y + reach + 1).min(MAP_HEIGHT) {
+                    let in_area = ((x - hazard.x).pow(2) + (y - hazard.y).pow(2)) as f32
+                        <= hazard.kind.radius().powi(2);
+                    if in_area && in_sight(&tcod.fov, x, y) {
+                        tcod.con
+                            .set_char_background(x, y, colors.hazard, BackgroundFlag::Set);
+                    }
                 }
             }
         }
+        if tcod.options.health_bars {
+            self.render_health_bars(tcod, colors);
+        }
+        let fov = &tcod.fov;
+        self.animations
+            .draw(&mut tcod.con, palette, |x, y| in_sight(fov, x, y));
+        let (shake_x, shake_y) = if tcod.options.screen_effects {
+            self.animations
+                .draw_flashes(&mut tcod.con, colors.hit_flash);
+            self.animations.shake_offset()
+        } else {
+            self.animations.clear_effects();
+            (0, 0)
+        };
+        if let (Some(aim), Some(cursor)) = (tcod.aim, tcod.cursor) {
+            self.render_aim(tcod, aim, cursor);
+        }
+        if let Some((x, y)) = tcod.cursor {
+            tcod.con
+                .set_char_background(x, y, colors.cursor, BackgroundFlag::Set);
+        }
 
+        if (shake_x, shake_y) != (0, 0) {
+            // the map moves off the edge it was drawn against, leave nothing there
+            tcod.root.clear();
+        }
         blit(
             &tcod.con,
             (0, 0),
             (MAP_WIDTH, MAP_HEIGHT),
             &mut tcod.root,
+            (shake_x, shake_y),
+            1.0,
+            1.0,
+        );
+
+        // display what is under the cursor, or the names of objects under the mouse
+        let mut under_pointer = mem::take(&mut tcod.scratch.names);
+        match tcod.cursor {
+            Some((x, y)) => {
+                under_pointer.clear();
+                under_pointer.push_str(&self.describe_tile(tcod, x, y));
+            }
+            None => get_names_under_mouse(tcod.mouse, &self.objects, &tcod.fov, &mut under_pointer),
+        }
+        self.render_panel(&mut tcod.panel, &under_pointer, colors, tcod.layout);
+        tcod.scratch.names = under_pointer;
+
+        // blit the contents of `panel` to the root console
+        blit(
+            &tcod.panel,
             (0, 0),
+            (tcod.layout.screen_width, tcod.layout.panel_height),
+            &mut tcod.root,
+            (0, tcod.layout.panel_y),
             1.0,
             1.0,
         );
+    }
+
+    /// set the background of every explored tile, and draw what is remembered of
+    /// the tiles out of sight
+    fn render_tiles(
+        &mut self,
+        con: &mut dyn renderer::Renderer,
+        fov: &FovMap,
+        colors: &Colors,
+        tiles: bool,
+    ) {
+        // go through all tiles, and set their background color
+        for y in 0..MAP_HEIGHT {
+            for x in 0..MAP_WIDTH {
+                let visible = in_sight(fov, x, y);
+                let tile = &self.map[x as usize][y as usize];
+                let color = match (visible, tile.block_sight, tile.terrain) {
+                    // outside of field of view:
+                    (false, true, _) => colors.dark_wall,
+                    (false, false, Terrain::Floor) => colors.dark_ground,
+                    (false, false, Terrain::ShallowWater) => colors.dark_shallow_water,
+                    (false, false, Terrain::DeepWater) => colors.dark_deep_water,
+                    (false, false, Terrain::Lava) => colors.dark_lava,
+                    (false, false, Terrain::Chasm) => colors.dark_chasm,
+                    // inside fov:
+                    (true, true, _) => colors.light_wall,
+                    (true, false, Terrain::Floor) => colors.light_ground,
+                    (true, false, Terrain::ShallowWater) => colors.light_shallow_water,
+                    (true, false, Terrain::DeepWater) => colors.light_deep_water,
+                    (true, false, Terrain::Lava) => colors.light_lava,
+                    (true, false, Terrain::Chasm) => colors.light_chasm,
+                };
+                let tile = &mut self.map[x as usize][y as usize];
+                if visible {
+                    // since it's visible, explore it
+                    tile.explored = true;
+                    tile.last_seen = Some(self.turn);
+                }
+                if tile.explored {
+                    // show explored tiles only (any visible tile is explored already)
+                    con.set_char_background(x, y, color, BackgroundFlag::Set);
+                }
+                if !visible {
+                    if let Some(char) = self.memory[x as usize][y as usize] {
+                        con.set_default_foreground(colors.remembered);
+                        con.put_char(x, y, glyph(char, tiles), BackgroundFlag::None);
+                    }
+                }
+            }
+        }
+    }
 
+    /// the player's bars, the level, the messages and `under_pointer` on the panel
+    fn render_panel(
+        &self,
+        panel: &mut dyn renderer::Renderer,
+        under_pointer: &str,
+        colors: &Colors,
+        layout: Layout,
+    ) {
         // prepare to render the GUI panel
-        tcod.panel.set_default_background(BLACK);
-        tcod.panel.clear();
+        panel.set_default_background(BLACK);
+        panel.clear();
 
         // show the player's stats
         let hp = self.objects[PLAYER].fighter.map_or(0, |f| f.hp);
         let max_hp = self.objects[PLAYER].fighter.map_or(0, |f| f.max_hp);
         render_bar(
-            &mut tcod.panel,
+            panel,
             1,
             1,
             BAR_WIDTH,
             "HP",
             hp,
             max_hp,
-            LIGHT_RED,
-            DARKER_RED,
+            colors.hp_bar,
+            colors.hp_bar_back,
+        );
+
+        let mana = self.objects[PLAYER].fighter.map_or(0, |f| f.mana);
+        let max_mana = self.objects[PLAYER].fighter.map_or(0, |f| f.max_mana);
+        render_bar(
+            panel,
+            1,
+            2,
+            BAR_WIDTH,
+            "MP",
+            mana,
+            max_mana,
+            colors.mana_bar,
+            colors.mana_bar_back,
         );
 
-        tcod.panel.print_ex(
+        panel.print_ex(
             1,
             3,
             BackgroundFlag::None,
             TextAlignment::Left,
-            format!("Dungeon level: {}", self.dungeon_level),
+            &self.level.name(),
+        );
+        panel.print_ex(
+            1,
+            5,
+            BackgroundFlag::None,
+            TextAlignment::Left,
+            &format!("Turn {}", self.turn),
         );
+        if let Some((label, color)) = self.hunger.state(self.turn).label() {
+            panel.set_default_foreground(color);
+            panel.print_ex(1, 4, BackgroundFlag::None, TextAlignment::Left, label);
+        }
 
         // print the game messages, one line at a time
-        let mut y = MSG_HEIGHT as i32;
+        let mut y = layout.msg_height as i32;
         for &(ref msg, color) in self.messages.iter().rev() {
-            let msg_height = tcod.panel.get_height_rect(MSG_X, y, MSG_WIDTH, 0, msg);
+            let msg_height = panel.get_height_rect(layout.msg_x, y, layout.msg_width, 0, msg);
             y -= msg_height;
             if y < 0 {
                 break;
             }
-            tcod.panel.set_default_foreground(color);
-            tcod.panel.print_rect(MSG_X, y, MSG_WIDTH, 0, msg);
+            panel.set_default_foreground(color);
+            panel.print_rect(layout.msg_x, y, layout.msg_width, 0, msg);
         }
 
-        // display names of objects under the mouse
-        tcod.panel.set_default_foreground(LIGHT_GREY);
-        tcod.panel.print_ex(
+        panel.set_default_foreground(LIGHT_GREY);
+        panel.print_ex(
             1,
             0,
             BackgroundFlag::None,
             TextAlignment::Left,
-            get_names_under_mouse(tcod.mouse, &self.objects, &tcod.fov),
+            under_pointer,
         );
+    }
 
-        // blit the contents of `panel` to the root console
-        blit(
-            &tcod.panel,
-            (0, 0),
-            (SCREEN_WIDTH, PANEL_HEIGHT),
-            &mut tcod.root,
-            (0, PANEL_Y),
-            1.0,
-            1.0,
-        );
+    /// Shade the visible tiles out of reach and draw the line a shot would take to
+    /// the cursor, turning red from the first tile where something is in the way
+    fn render_aim(&self, tcod: &mut Tcod, aim: Aim, cursor: (i32, i32)) {
+        let colors = tcod.options.palette.colors();
+        let player = &self.objects[PLAYER];
+        if let Some(range) = aim.max_range {
+            for y in 0..MAP_HEIGHT {
+                for x in 0..MAP_WIDTH {
+                    if in_sight(&tcod.fov, x, y) && player.distance(x, y) > range {
+                        tcod.con.set_char_background(
+                            x,
+                            y,
+                            colors.out_of_range,
+                            BackgroundFlag::Multiply,
+                        );
+                    }
+                }
+            }
+        }
+
+        let mut blocked = false;
+        for (x, y) in Line::new(player.pos(), cursor) {
+            if (x, y) == cursor {
+                break;
+            }
+            blocked =
+                blocked || !in_sight(&tcod.fov, x, y) || is_blocked(x, y, &self.map, &self.objects);
+            let color = if blocked {
+                colors.aim_blocked
+            } else {
+                colors.aim_path
+            };
+            tcod.con
+                .set_char_background(x, y, color, BackgroundFlag::Set);
+        }
+    }
+
+    /// the experience the player needs to reach the next level
+    pub fn level_up_xp(&self) -> i32 {
+        LEVEL_UP_BASE + self.objects[PLAYER].level * LEVEL_UP_FACTOR
+    }
+
+    /// tint the tile over every hurt monster in sight, from the color of good
+    /// health to that of bad by how much of it the monster has left
+    fn render_health_bars(&self, tcod: &mut Tcod, palette: &Colors) {
+        for object in &self.objects[PLAYER + 1..] {
+            let fighter = match object.fighter {
+                Some(fighter) if object.alive && fighter.hp < fighter.max_hp => fighter,
+                _ => continue,
+            };
+            if object.y == 0 || !sees(&tcod.fov, &self.objects[PLAYER], object) {
+                continue;
+            }
+            let health = fighter.hp.max(0) as f32 / fighter.max_hp.max(1) as f32;
+            let color = colors::lerp(palette.health_low, palette.health_high, health);
+            tcod.con
+                .set_char_background(object.x, object.y - 1, color, BackgroundFlag::Set);
+        }
     }
 
     fn level_up(&mut self, tcod: &mut Tcod) {
+        let level_up_xp = self.level_up_xp();
         let player = &mut self.objects[PLAYER];
-        let level_up_xp = LEVEL_UP_BASE + player.level * LEVEL_UP_FACTOR;
+        let new_level = player.level + 1;
         // see if the player's experience is enough to level-up
         if player.fighter.as_ref().map_or(0, |f| f.xp) >= level_up_xp {
             // it is! level up
             player.level += 1;
-            self.messages.add(
-                format!(
-                    "Your battle skills grow stronger! You reached level {}!",
-                    player.level
-                ),
-                YELLOW,
-            );
+            self.events.emit(GameEvent::LevelUp(player.level));
+            self.handle_events();
+            let player = &mut self.objects[PLAYER];
             let fighter = player.fighter.as_mut().unwrap();
             let mut choice = None;
             while choice.is_none() {
@@ -610,6 +4026,8 @@ impl Game {
                 );
             }
             fighter.xp -= level_up_xp;
+            fighter.max_mana += LEVEL_UP_MANA;
+            fighter.mana += LEVEL_UP_MANA;
             match choice.unwrap() {
                 0 => {
                     fighter.max_hp += 20;
@@ -623,6 +4041,19 @@ impl Game {
                 }
                 _ => unreachable!(),
             }
+
+            // a mage sets out knowing a spell others learn later
+            let spell = spell_learned_at(new_level).filter(|spell| !self.spells.contains(spell));
+            if let Some(spell) = spell {
+                self.spells.push(spell);
+                self.messages.add(
+                    format!(
+                        "You have learned the {} spell! Press z to cast it.",
+                        spell.name()
+                    ),
+                    LIGHT_BLUE,
+                );
+            }
         }
     }
 
@@ -634,12 +4065,59 @@ impl Game {
                 Heal => cast_heal,
                 Lightning => cast_lightning,
                 Confuse => cast_confuse,
+                Elixir => cast_elixir,
+                Experience => quaff_experience,
+                Vigor => quaff_vigor,
+                Might => quaff_might,
+                Toughness => quaff_toughness,
+                Resilience => quaff_resilience,
+                Invisibility => quaff_invisibility,
+                SeeInvisible => quaff_see_invisible,
+                WandLightning => zap_lightning,
+                WandConfusion => cast_confuse,
+                WandDigging => zap_digging,
+                Identify => cast_identify,
+                LuckyCharm => rub_charm,
+                Blink => cast_blink,
+                Teleport => cast_teleport,
+                CharmMonster => cast_charm,
+                RemoveCurse => cast_remove_curse,
+                Fire => cast_fire,
+                Key => use_key,
+                Amulet => use_amulet,
+                Arrows => fire_bow,
+                Chunk => eat,
+                LeatherArmor | ChainMail | PlateArmor | Torch | Lantern | Bow | Dagger | Shield => {
+                    equip
+                }
             };
+            if self.inventory[inventory_id].charges == Some(0) {
+                self.messages.add(
+                    format!(
+                        "The {} is out of charges.",
+                        self.inventory[inventory_id].name
+                    ),
+                    WHITE,
+                );
+                return;
+            }
             match on_use(inventory_id, tcod, self) {
                 UseResult::UsedUp => {
-                    // destroy after use, unless it was cancelled for some reason
-                    self.inventory.remove(inventory_id);
+                    self.stats.items_used += 1;
+                    match self.inventory[inventory_id].charges.as_mut() {
+                        // wands are zapped, spending a charge, rather than used up, and
+                        // reveal what they are
+                        Some(charges) => {
+                            *charges -= 1;
+                            identify(&mut self.inventory[inventory_id]);
+                        }
+                        // destroy after use, unless it was cancelled for some reason
+                        None => {
+                            self.inventory.remove(inventory_id);
+                        }
+                    }
                 }
+                UseResult::Kept => self.stats.items_used += 1,
                 UseResult::Cancelled => {
                     self.messages.add("Cancelled", WHITE);
                 }
@@ -652,12 +4130,86 @@ impl Game {
         }
     }
 
+    /// cast one of the known spells, paying its mana cost; returns whether it was cast
+    fn cast_spell(&mut self, spell_index: usize, tcod: &mut Tcod) -> bool {
+        let spell = self.spells[spell_index];
+        let mana = self.objects[PLAYER].fighter.map_or(0, |f| f.mana);
+        if mana < spell.cost() {
+            self.messages.add(
+                format!("You don't have enough mana to cast {}.", spell.name()),
+                RED,
+            );
+            return false;
+        }
+        match cast_spell(spell, tcod, self) {
+            UseResult::UsedUp | UseResult::Kept => {
+                self.objects[PLAYER].fighter.as_mut().unwrap().mana -= spell.cost();
+                true
+            }
+            UseResult::Cancelled => {
+                self.messages.add("Cancelled", WHITE);
+                false
+            }
+        }
+    }
+
+    /// restore mana, without going over the maximum
+    fn regenerate_mana(&mut self, amount: i32) {
+        if let Some(fighter) = self.objects[PLAYER].fighter.as_mut() {
+            fighter.mana = (fighter.mana + amount).min(fighter.max_mana);
+        }
+    }
+
     fn drop_item(&mut self, inventory_id: usize) {
+        if !take_off(self, inventory_id) {
+            return;
+        }
+        let was_overburdened = self.overburdened();
         let mut item = self.inventory.remove(inventory_id);
         item.set_pos(self.objects[PLAYER].x, self.objects[PLAYER].y);
         self.messages
             .add(format!("You dropped a {}.", item.name), YELLOW);
         self.objects.push(item);
+        if was_overburdened && !self.overburdened() {
+            self.messages
+                .add("You are no longer overburdened.", LIGHT_CYAN);
+        }
+    }
+
+    /// how sharp-eyed the player is, growing with experience and luck
+    fn perception(&self) -> i32 {
+        (self.objects[PLAYER].level + self.luck()).max(0)
+    }
+
+    /// find each of the hidden doors next to the player with the chance given,
+    /// returning whether any was found
+    fn find_secret_doors(&mut self, chance: f32, tcod: &mut Tcod) -> bool {
+        let (player_x, player_y) = self.objects[PLAYER].pos();
+        let mut found = false;
+        for dx in -1..2 {
+            for dy in -1..2 {
+                let (x, y) = (player_x + dx, player_y + dy);
+                let in_map = x >= 0 && y >= 0 && x < MAP_WIDTH && y < MAP_HEIGHT;
+                if !in_map
+                    || self.map[x as usize][y as usize].door != Some(Door::Secret)
+                    || rand::random::<f32>() >= chance
+                {
+                    continue;
+                }
+                self.set_door(x, y, Door::Closed, tcod);
+                self.map[x as usize][y as usize].explored = true;
+                self.messages.add("You find a hidden door!", LIGHT_CYAN);
+                found = true;
+            }
+        }
+        found
+    }
+
+    /// change the state of a door, keeping the FOV map in sync
+    fn set_door(&mut self, x: i32, y: i32, door: Door, tcod: &mut Tcod) {
+        let tile = &mut self.map[x as usize][y as usize];
+        tile.set_door(door);
+        tcod.fov.set(x, y, !tile.block_sight, !tile.blocked);
     }
 
     fn initialise_fov(&mut self, tcod: &mut Tcod) {
@@ -678,6 +4230,12 @@ impl Game {
     }
 }
 
+/// whether fire or gas of the kind can lie on the tile
+pub fn ground_open(map: &Map, kind: GroundKind, x: i32, y: i32) -> bool {
+    let in_map = x >= 0 && y >= 0 && x < MAP_WIDTH && y < MAP_HEIGHT;
+    in_map && map[x as usize][y as usize].holds(kind)
+}
+
 pub fn is_blocked(x: i32, y: i32, map: &Map, objects: &[Object]) -> bool {
     // first test the map tile
     if map[x as usize][y as usize].blocked {
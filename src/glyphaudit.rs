@@ -0,0 +1,86 @@
+use crate::{
+    item::{item_object, ITEMS},
+    monsters::{monster_by_name, monster_names},
+    object::Object,
+};
+use tcod::colors::{self, Color};
+use tcod::console::*;
+
+const AUDIT_WIDTH: i32 = 60;
+const AUDIT_BACKGROUND: Color = colors::DARK_GREY;
+// two colors closer than this (summed over the channels) are too easy to confuse
+const SIMILAR_COLOR_DISTANCE: i32 = 96;
+
+/// one of each monster and item the dungeon can spawn
+fn templates() -> Vec<Object> {
+    let monsters = monster_names()
+        .into_iter()
+        .filter_map(|name| monster_by_name(name, 0, 0));
+    let items = ITEMS
+        .iter()
+        .map(|&item| item_object(item, 0, 0, 0, &mut rand::thread_rng()));
+    monsters.chain(items).collect()
+}
+
+fn similar_colors(a: Color, b: Color) -> bool {
+    let distance = (a.r as i32 - b.r as i32).abs()
+        + (a.g as i32 - b.g as i32).abs()
+        + (a.b as i32 - b.b as i32).abs();
+    distance < SIMILAR_COLOR_DISTANCE
+}
+
+/// Debug screen showing every monster and item glyph on a neutral background,
+/// flagging the ones that look alike on the map
+pub fn glyph_audit(root: &mut Root) {
+    let templates = templates();
+    let height = templates.len() as i32 + 2;
+    let mut window = Offscreen::new(AUDIT_WIDTH, height);
+    window.set_default_background(AUDIT_BACKGROUND);
+    window.clear();
+
+    window.set_default_foreground(colors::WHITE);
+    window.print_ex(
+        0,
+        0,
+        BackgroundFlag::None,
+        TextAlignment::Left,
+        "Glyph audit (any key to close)",
+    );
+    for (index, template) in templates.iter().enumerate() {
+        let y = index as i32 + 2;
+        window.set_default_foreground(template.color);
+        window.put_char(1, y, template.char, BackgroundFlag::None);
+
+        let clashes: Vec<_> = templates
+            .iter()
+            .filter(|other| {
+                other.name != template.name
+                    && other.char == template.char
+                    && similar_colors(other.color, template.color)
+            })
+            .map(|other| other.name.as_str())
+            .collect();
+        let (text, color) = if clashes.is_empty() {
+            (template.name.clone(), colors::LIGHT_GREY)
+        } else {
+            let text = format!("{} - looks like {}", template.name, clashes.join(", "));
+            (text, colors::LIGHT_RED)
+        };
+        window.set_default_foreground(color);
+        window.print_ex(3, y, BackgroundFlag::None, TextAlignment::Left, text);
+    }
+
+    let x = root.width() / 2 - AUDIT_WIDTH / 2;
+    let y = root.height() / 2 - height / 2;
+    blit(
+        &window,
+        (0, 0),
+        (AUDIT_WIDTH, height),
+        root,
+        (x, y),
+        1.0,
+        1.0,
+    );
+    root.flush();
+    root.wait_for_keypress(true);
+}
@@ -0,0 +1,144 @@
+//! Fire and poison gas lingering on the ground. A fireball leaves flames where it
+//! bursts, the grave mould of the crypt leaves its spores hanging in the air, and
+//! some monsters go up in one or the other as they die. Either hurts whoever
+//! stands in it for as long as it lasts; fire creeps onto the floor around it,
+//! while gas thins out as it drifts.
+use crate::fighter::DamageType;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+// chance a fire spreads to a tile next to it each turn, and how much sooner the
+// new flames burn out
+const FIRE_SPREAD_CHANCE: f32 = 0.15;
+const FIRE_SPREAD_LOSS: i32 = 2;
+// gas with at least this many turns left drifts onto a tile next to it, taking
+// half of them along
+const GAS_DRIFT_TURNS: i32 = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum GroundKind {
+    Fire,
+    PoisonGas,
+}
+
+impl GroundKind {
+    /// the damage done every turn to whoever stands in it
+    pub fn damage(self) -> i32 {
+        match self {
+            GroundKind::Fire => 3,
+            GroundKind::PoisonGas => 1,
+        }
+    }
+
+    pub fn damage_type(self) -> DamageType {
+        match self {
+            GroundKind::Fire => DamageType::Fire,
+            GroundKind::PoisonGas => DamageType::Poison,
+        }
+    }
+
+    pub fn hurt_message(self, name: &str) -> String {
+        match self {
+            GroundKind::Fire => format!("The {} is burnt by the flames!", name),
+            GroundKind::PoisonGas => format!("The {} chokes on the poison gas!", name),
+        }
+    }
+}
+
+/// Fire or gas on a tile, for some turns more
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GroundEffect {
+    pub kind: GroundKind,
+    pub x: i32,
+    pub y: i32,
+    pub turns: i32,
+}
+
+/// The ground effects on the current level, one at most to a tile
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Ground {
+    effects: Vec<GroundEffect>,
+}
+
+impl Ground {
+    pub fn iter(&self) -> impl Iterator<Item = &GroundEffect> {
+        self.effects.iter()
+    }
+
+    pub fn clear(&mut self) {
+        self.effects.clear();
+    }
+
+    pub fn at(&self, x: i32, y: i32) -> Option<GroundEffect> {
+        self.effects
+            .iter()
+            .find(|effect| (effect.x, effect.y) == (x, y))
+            .cloned()
+    }
+
+    /// put the effect on the tile, unless one lasting longer is there already
+    pub fn place(&mut self, kind: GroundKind, x: i32, y: i32, turns: i32) {
+        let effect = GroundEffect { kind, x, y, turns };
+        match self
+            .effects
+            .iter_mut()
+            .find(|effect| (effect.x, effect.y) == (x, y))
+        {
+            Some(there) if there.turns < turns => *there = effect,
+            Some(_) => {}
+            None if turns > 0 => self.effects.push(effect),
+            None => {}
+        }
+    }
+
+    /// put the effect on every tile within `radius` of the center that `open`
+    /// lets it onto
+    pub fn place_area(
+        &mut self,
+        kind: GroundKind,
+        (x, y): (i32, i32),
+        radius: f32,
+        turns: i32,
+        open: impl Fn(GroundKind, i32, i32) -> bool,
+    ) {
+        let reach = radius as i32;
+        for tile_x in (x - reach)..(x + reach + 1) {
+            for tile_y in (y - reach)..(y + reach + 1) {
+                let in_area = ((tile_x - x).pow(2) + (tile_y - y).pow(2)) as f32 <= radius.powi(2);
+                if in_area && open(kind, tile_x, tile_y) {
+                    self.place(kind, tile_x, tile_y, turns);
+                }
+            }
+        }
+    }
+
+    /// let a turn pass: every effect gets a turn closer to dying out, fires
+    /// maybe spread and gas drifts, onto the tiles `open` lets them onto
+    pub fn tick(&mut self, open: impl Fn(GroundKind, i32, i32) -> bool, rng: &mut impl Rng) {
+        let mut spread = vec![];
+        for effect in &mut self.effects {
+            effect.turns -= 1;
+            let (x, y) = (
+                effect.x + rng.gen_range(-1, 2),
+                effect.y + rng.gen_range(-1, 2),
+            );
+            match effect.kind {
+                GroundKind::Fire if rng.gen::<f32>() < FIRE_SPREAD_CHANCE => {
+                    spread.push((effect.kind, x, y, effect.turns - FIRE_SPREAD_LOSS));
+                }
+                GroundKind::PoisonGas if effect.turns >= GAS_DRIFT_TURNS => {
+                    let drifting = effect.turns / 2;
+                    effect.turns -= drifting;
+                    spread.push((effect.kind, x, y, drifting));
+                }
+                _ => {}
+            }
+        }
+        self.effects.retain(|effect| effect.turns > 0);
+        for (kind, x, y, turns) in spread {
+            if open(kind, x, y) {
+                self.place(kind, x, y, turns);
+            }
+        }
+    }
+}
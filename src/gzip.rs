@@ -0,0 +1,433 @@
+//! Just enough gzip (RFC 1951 and 1952) to keep the saves small without another
+//! dependency. Compressing finds repeats within the last 32 KiB and writes them
+//! with the fixed Huffman codes, which suits JSON well enough; decompressing
+//! reads any gzip file, so a save recompressed by another tool still loads.
+use std::error::Error;
+
+// the first bytes of every gzip file, and the compression method, deflate
+const MAGIC: [u8; 2] = [0x1f, 0x8b];
+const DEFLATE: u8 = 8;
+// header flags for the optional fields that may follow the fixed header
+const FLAG_HEADER_CRC: u8 = 2;
+const FLAG_EXTRA: u8 = 4;
+const FLAG_NAME: u8 = 8;
+const FLAG_COMMENT: u8 = 16;
+
+// how far back a repeat may be found, how long it may be, and how many earlier
+// places with the same three bytes are tried before settling for the best
+const WINDOW: usize = 32 * 1024;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+const MAX_CHAIN: usize = 64;
+const HASH_BITS: u32 = 15;
+
+// the lengths and distances the length and distance symbols start at, and the
+// extra bits read after each to tell the exact one
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+// the order the code lengths of the code length alphabet come in
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+const END_OF_BLOCK: usize = 256;
+
+/// whether the data starts like a gzip file, rather than like plain text
+pub fn is_gzip(data: &[u8]) -> bool {
+    data.starts_with(&MAGIC)
+}
+
+/// the data as a gzip file
+pub fn compress(data: &[u8]) -> Vec<u8> {
+    let mut out = BitWriter::default();
+    out.bytes
+        .extend_from_slice(&[MAGIC[0], MAGIC[1], DEFLATE, 0, 0, 0, 0, 0, 0, 255]);
+    // a single final block in the fixed codes
+    out.write(1, 1);
+    out.write(1, 2);
+    deflate(data, &mut out);
+    out.write_fixed_literal(END_OF_BLOCK);
+    out.flush();
+    out.bytes.extend_from_slice(&crc32(data).to_le_bytes());
+    out.bytes
+        .extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out.bytes
+}
+
+/// the data a gzip file holds, or what is wrong with it
+pub fn decompress(data: &[u8]) -> Result<Vec<u8>, Box<dyn Error>> {
+    if data.len() < 18 || !is_gzip(data) || data[2] != DEFLATE {
+        return Err("it is not a gzip file".into());
+    }
+    let flags = data[3];
+    let mut start = 10;
+    if flags & FLAG_EXTRA != 0 {
+        let extra = data.get(start..start + 2).ok_or(TRUNCATED)?;
+        start += 2 + u16::from_le_bytes([extra[0], extra[1]]) as usize;
+    }
+    for &flag in &[FLAG_NAME, FLAG_COMMENT] {
+        if flags & flag != 0 {
+            let text = data.get(start..).ok_or(TRUNCATED)?;
+            start += text.iter().position(|&byte| byte == 0).ok_or(TRUNCATED)? + 1;
+        }
+    }
+    if flags & FLAG_HEADER_CRC != 0 {
+        start += 2;
+    }
+
+    let mut input = BitReader {
+        data: data.get(start..).ok_or(TRUNCATED)?,
+        pos: 0,
+        bits: 0,
+        count: 0,
+    };
+    let out = inflate(&mut input)?;
+    let trailer = data
+        .get(start + input.pos..start + input.pos + 8)
+        .ok_or(TRUNCATED)?;
+    let crc = u32::from_le_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+    let size = u32::from_le_bytes([trailer[4], trailer[5], trailer[6], trailer[7]]);
+    if crc != crc32(&out) || size != out.len() as u32 {
+        return Err("its checksum doesn't match, so it is damaged".into());
+    }
+    Ok(out)
+}
+
+const TRUNCATED: &str = "it ends too soon";
+
+/// the CRC-32 gzip checks the data with
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// Write the data as literals and repeats of what came before, each repeat the
+/// longest found among the latest places the same three bytes were seen
+fn deflate(data: &[u8], out: &mut BitWriter) {
+    let hash = |pos: usize| {
+        let bytes = (data[pos] as u32) << 16 | (data[pos + 1] as u32) << 8 | data[pos + 2] as u32;
+        (bytes.wrapping_mul(2_654_435_761) >> (32 - HASH_BITS)) as usize
+    };
+    // the latest place each hash was seen, and the place seen before each place
+    let mut head = vec![usize::MAX; 1 << HASH_BITS];
+    let mut prev = vec![usize::MAX; WINDOW];
+    let insert = |pos: usize, head: &mut [usize], prev: &mut [usize]| {
+        if pos + MIN_MATCH <= data.len() {
+            let h = hash(pos);
+            prev[pos % WINDOW] = head[h];
+            head[h] = pos;
+        }
+    };
+
+    let mut pos = 0;
+    while pos < data.len() {
+        let (mut best_len, mut best_distance) = (0, 0);
+        if pos + MIN_MATCH <= data.len() {
+            let max_len = MAX_MATCH.min(data.len() - pos);
+            let mut candidate = head[hash(pos)];
+            let mut tries = 0;
+            while candidate != usize::MAX && pos - candidate <= WINDOW && tries < MAX_CHAIN {
+                let len = (0..max_len)
+                    .take_while(|&i| data[candidate + i] == data[pos + i])
+                    .count();
+                if len > best_len {
+                    best_len = len;
+                    best_distance = pos - candidate;
+                    if len == max_len {
+                        break;
+                    }
+                }
+                candidate = prev[candidate % WINDOW];
+                tries += 1;
+            }
+        }
+
+        if best_len >= MIN_MATCH {
+            out.write_repeat(best_len, best_distance);
+            for pos in pos..pos + best_len {
+                insert(pos, &mut head, &mut prev);
+            }
+            pos += best_len;
+        } else {
+            out.write_fixed_literal(data[pos] as usize);
+            insert(pos, &mut head, &mut prev);
+            pos += 1;
+        }
+    }
+}
+
+/// the symbol of the table whose range holds the value, and how far into it the
+/// value is
+fn symbol_for(bases: &[u16], value: usize) -> (usize, u32) {
+    let symbol = bases
+        .iter()
+        .rposition(|&base| base as usize <= value)
+        .unwrap_or(0);
+    (symbol, (value - bases[symbol] as usize) as u32)
+}
+
+/// Bits packed into bytes from the lowest bit up, the way deflate wants them
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    bits: u32,
+    count: u32,
+}
+
+impl BitWriter {
+    /// write the lowest `len` bits of the value, lowest first
+    fn write(&mut self, value: u32, len: u32) {
+        self.bits |= value << self.count;
+        self.count += len;
+        while self.count >= 8 {
+            self.bytes.push(self.bits as u8);
+            self.bits >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    /// write a Huffman code, which goes highest bit first
+    fn write_code(&mut self, code: u32, len: u32) {
+        self.write(code.reverse_bits() >> (32 - len), len);
+    }
+
+    /// write a literal, length or end of block symbol in the fixed codes
+    fn write_fixed_literal(&mut self, symbol: usize) {
+        let symbol = symbol as u32;
+        match symbol {
+            0..=143 => self.write_code(0x30 + symbol, 8),
+            144..=255 => self.write_code(0x190 + symbol - 144, 9),
+            256..=279 => self.write_code(symbol - 256, 7),
+            _ => self.write_code(0xc0 + symbol - 280, 8),
+        }
+    }
+
+    /// write a repeat of `len` bytes from `distance` bytes back
+    fn write_repeat(&mut self, len: usize, distance: usize) {
+        let (symbol, extra) = symbol_for(&LENGTH_BASE, len);
+        self.write_fixed_literal(257 + symbol);
+        self.write(extra, LENGTH_EXTRA[symbol] as u32);
+        let (symbol, extra) = symbol_for(&DISTANCE_BASE, distance);
+        self.write_code(symbol as u32, 5);
+        self.write(extra, DISTANCE_EXTRA[symbol] as u32);
+    }
+
+    /// write out the last, partly filled byte
+    fn flush(&mut self) {
+        if self.count > 0 {
+            self.bytes.push(self.bits as u8);
+            self.bits = 0;
+            self.count = 0;
+        }
+    }
+}
+
+/// Bits read from bytes lowest bit first. `pos` is past the last byte begun on
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    bits: u32,
+    count: u32,
+}
+
+impl BitReader<'_> {
+    fn read(&mut self, len: u32) -> Result<u32, Box<dyn Error>> {
+        while self.count < len {
+            let byte = *self.data.get(self.pos).ok_or(TRUNCATED)?;
+            self.bits |= (byte as u32) << self.count;
+            self.pos += 1;
+            self.count += 8;
+        }
+        let value = self.bits & ((1u64 << len) - 1) as u32;
+        self.bits >>= len;
+        self.count -= len;
+        Ok(value)
+    }
+
+    /// skip to the start of the next byte
+    fn align(&mut self) {
+        self.bits = 0;
+        self.count = 0;
+    }
+
+    /// read a symbol in the code, a bit at a time
+    fn decode(&mut self, code: &Huffman) -> Result<usize, Box<dyn Error>> {
+        // codes of each length are numbered on from those one bit shorter
+        let (mut bits, mut first, mut index) = (0, 0, 0);
+        for &count in &code.counts[1..] {
+            bits |= self.read(1)? as usize;
+            if bits < first {
+                break;
+            } else if bits < first + count {
+                return Ok(code.symbols[index + bits - first]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            bits <<= 1;
+        }
+        Err("it holds a code that means nothing".into())
+    }
+}
+
+/// A canonical Huffman code, as the number of codes of each length and the
+/// symbols in the order of their codes
+struct Huffman {
+    counts: [usize; 16],
+    symbols: Vec<usize>,
+}
+
+impl Huffman {
+    /// the code whose symbols have the lengths, 0 for the symbols left out
+    fn new(lengths: &[u8]) -> Self {
+        let mut counts = [0; 16];
+        for &len in lengths {
+            counts[len as usize] += 1;
+        }
+        counts[0] = 0;
+        let mut symbols = vec![];
+        for len in 1..16 {
+            symbols.extend((0..lengths.len()).filter(|&symbol| lengths[symbol] == len));
+        }
+        Huffman { counts, symbols }
+    }
+
+    /// the literal and length, and the distance codes of blocks in fixed codes
+    fn fixed() -> (Self, Self) {
+        let mut lengths = [8; 288];
+        lengths[144..256].iter_mut().for_each(|len| *len = 9);
+        lengths[256..280].iter_mut().for_each(|len| *len = 7);
+        (Huffman::new(&lengths), Huffman::new(&[5; 30]))
+    }
+}
+
+/// the data of every block up to the final one
+fn inflate(input: &mut BitReader) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut out = vec![];
+    loop {
+        let last = input.read(1)? == 1;
+        match input.read(2)? {
+            0 => {
+                input.align();
+                let header = input.data.get(input.pos..input.pos + 4).ok_or(TRUNCATED)?;
+                let len = u16::from_le_bytes([header[0], header[1]]);
+                if len != !u16::from_le_bytes([header[2], header[3]]) {
+                    return Err("it has a stored block of a garbled length".into());
+                }
+                let start = input.pos + 4;
+                let stored = input
+                    .data
+                    .get(start..start + len as usize)
+                    .ok_or(TRUNCATED)?;
+                out.extend_from_slice(stored);
+                input.pos = start + len as usize;
+            }
+            1 => {
+                let (literals, distances) = Huffman::fixed();
+                inflate_block(input, &mut out, &literals, &distances)?;
+            }
+            2 => {
+                let (literals, distances) = read_dynamic_codes(input)?;
+                inflate_block(input, &mut out, &literals, &distances)?;
+            }
+            _ => return Err("it has a block of an unknown type".into()),
+        }
+        if last {
+            return Ok(out);
+        }
+    }
+}
+
+/// the literal and length, and the distance codes a block brings along
+fn read_dynamic_codes(input: &mut BitReader) -> Result<(Huffman, Huffman), Box<dyn Error>> {
+    let literal_count = input.read(5)? as usize + 257;
+    let distance_count = input.read(5)? as usize + 1;
+    let code_length_count = input.read(4)? as usize + 4;
+    let mut code_lengths = [0; 19];
+    for &symbol in &CODE_LENGTH_ORDER[..code_length_count] {
+        code_lengths[symbol] = input.read(3)? as u8;
+    }
+    let code_lengths = Huffman::new(&code_lengths);
+
+    let mut lengths = vec![];
+    while lengths.len() < literal_count + distance_count {
+        let (len, repeat) = match input.decode(&code_lengths)? {
+            len @ 0..=15 => (len as u8, 1),
+            16 => {
+                let previous = *lengths
+                    .last()
+                    .ok_or("it repeats a code length before any")?;
+                (previous, 3 + input.read(2)?)
+            }
+            17 => (0, 3 + input.read(3)?),
+            _ => (0, 11 + input.read(7)?),
+        };
+        lengths.extend((0..repeat).map(|_| len));
+    }
+    if lengths.len() > literal_count + distance_count {
+        return Err("it has more code lengths than codes".into());
+    }
+    Ok((
+        Huffman::new(&lengths[..literal_count]),
+        Huffman::new(&lengths[literal_count..]),
+    ))
+}
+
+/// the literals and repeats of a block in the codes, up to its end
+fn inflate_block(
+    input: &mut BitReader,
+    out: &mut Vec<u8>,
+    literals: &Huffman,
+    distances: &Huffman,
+) -> Result<(), Box<dyn Error>> {
+    loop {
+        let symbol = input.decode(literals)?;
+        if symbol < END_OF_BLOCK {
+            out.push(symbol as u8);
+            continue;
+        } else if symbol == END_OF_BLOCK {
+            return Ok(());
+        }
+
+        let symbol = symbol - 257;
+        if symbol >= LENGTH_BASE.len() {
+            return Err("it has a length out of range".into());
+        }
+        let len = LENGTH_BASE[symbol] as usize + input.read(LENGTH_EXTRA[symbol] as u32)? as usize;
+        let symbol = input.decode(distances)?;
+        if symbol >= DISTANCE_BASE.len() {
+            return Err("it has a distance out of range".into());
+        }
+        let distance =
+            DISTANCE_BASE[symbol] as usize + input.read(DISTANCE_EXTRA[symbol] as u32)? as usize;
+        if distance > out.len() {
+            return Err("it repeats from before its start".into());
+        }
+        // the repeat may overlap what it writes, so it goes a byte at a time
+        let start = out.len() - distance;
+        for i in 0..len {
+            out.push(out[start + i]);
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use crate::{dungeon::Branch, fighter::DamageType};
+use serde::{Deserialize, Serialize};
+
+/// A danger of the environment itself, themed after the branch it strikes in.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum HazardKind {
+    /// rocks rain down from the ceiling of the mines
+    CaveIn,
+    /// the mould on the old graves bursts into a toxic cloud
+    SporeRelease,
+}
+
+impl HazardKind {
+    /// the hazard the branch is prone to, if any
+    pub fn of_branch(branch: Branch) -> Option<HazardKind> {
+        match branch {
+            Branch::Mines => Some(HazardKind::CaveIn),
+            Branch::Crypt => Some(HazardKind::SporeRelease),
+            Branch::Town | Branch::Main | Branch::Abyss => None,
+        }
+    }
+
+    pub fn warning_message(self) -> &'static str {
+        match self {
+            HazardKind::CaveIn => "The ceiling creaks and dust trickles down nearby...",
+            HazardKind::SporeRelease => {
+                "A patch of grave mould nearby swells up, ready to burst..."
+            }
+        }
+    }
+
+    pub fn message(self) -> &'static str {
+        match self {
+            HazardKind::CaveIn => "The ceiling caves in with a roar!",
+            HazardKind::SporeRelease => "The mould bursts in a cloud of choking spores!",
+        }
+    }
+
+    /// how far from its center the hazard reaches
+    pub fn radius(self) -> f32 {
+        match self {
+            HazardKind::CaveIn => 1.5,
+            HazardKind::SporeRelease => 2.5,
+        }
+    }
+
+    pub fn damage(self) -> i32 {
+        match self {
+            HazardKind::CaveIn => 8,
+            HazardKind::SporeRelease => 3,
+        }
+    }
+
+    pub fn damage_type(self) -> DamageType {
+        match self {
+            HazardKind::CaveIn => DamageType::Physical,
+            HazardKind::SporeRelease => DamageType::Poison,
+        }
+    }
+}
+
+/// A hazard about to strike an area, counting down the turns until it does.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Hazard {
+    pub kind: HazardKind,
+    pub x: i32,
+    pub y: i32,
+    pub turns: i32,
+}
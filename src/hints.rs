@@ -0,0 +1,109 @@
+//! Tips for new players, each given once in the message log the first time the
+//! situation it helps with comes up: something lying underfoot, a bad wound with
+//! a potion at hand, the stairs, hunger and the first spell learned. Which tips
+//! have been given is kept in the options file, so they aren't given again in
+//! later games, and the options menu turns them off altogether.
+use crate::{
+    food::HungerState,
+    game::{Game, PLAYER},
+    item::Category,
+    keys::{key_for, KeyCommand},
+    tcoder::Tcod,
+};
+use tcod::colors::LIGHT_GREEN;
+
+// the player counts as badly hurt at this percentage of their health or below
+const BADLY_HURT_PERCENT: i32 = 35;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Hint {
+    PickUp,
+    BadlyHurt,
+    Stairs,
+    Hungry,
+    Spell,
+}
+
+/// every hint there is, the more pressing first
+const HINTS: &[Hint] = &[
+    Hint::BadlyHurt,
+    Hint::Hungry,
+    Hint::PickUp,
+    Hint::Stairs,
+    Hint::Spell,
+];
+
+impl Hint {
+    /// the bit of the options' seen hints standing for this one
+    fn bit(self) -> u32 {
+        1 << self as u32
+    }
+
+    /// whether the situation the hint is for has come up
+    fn due(self, game: &Game) -> bool {
+        let player = &game.objects[PLAYER];
+        let underfoot = || {
+            game.objects
+                .iter()
+                .filter(|object| object.pos() == player.pos())
+        };
+        match self {
+            Hint::PickUp => underfoot().any(|object| object.item.is_some()),
+            Hint::BadlyHurt => {
+                let hurt = matches!(player.fighter, Some(f) if f.hp * 100 <= f.max_hp * BADLY_HURT_PERCENT);
+                let potion = game
+                    .inventory
+                    .iter()
+                    .any(|object| matches!(object.item, Some(item) if item.category() == Category::Potion));
+                player.alive && hurt && potion
+            }
+            Hint::Stairs => underfoot().any(|object| object.stairs.is_some()),
+            Hint::Hungry => game.hunger.state(game.turn) != HungerState::Fed,
+            Hint::Spell => !game.spells.is_empty(),
+        }
+    }
+
+    fn text(self) -> String {
+        match self {
+            Hint::PickUp => format!(
+                "Hint: something lies here. Press {} to pick it up.",
+                key_for(KeyCommand::PickUp)
+            ),
+            Hint::BadlyHurt => format!(
+                "Hint: you are badly hurt. Quaff a potion from the inventory with {}.",
+                key_for(KeyCommand::Inventory)
+            ),
+            Hint::Stairs => format!(
+                "Hint: press {} to take the stairs.",
+                key_for(KeyCommand::TakeStairs)
+            ),
+            Hint::Hungry => format!(
+                "Hint: you are hungry. Butcher a corpse with {}, then eat from the inventory with {}.",
+                key_for(KeyCommand::Interact),
+                key_for(KeyCommand::Inventory)
+            ),
+            Hint::Spell => format!(
+                "Hint: you have learned a spell. Cast it with {}.",
+                key_for(KeyCommand::Cast)
+            ),
+        }
+    }
+}
+
+/// give the first hint not yet given whose situation has come up, if hints are
+/// on, and remember it was given. One at a time, so as not to flood the log
+pub fn give_hint(game: &mut Game, tcod: &mut Tcod) {
+    let options = &mut tcod.options;
+    if !options.hints {
+        return;
+    }
+    let hint = HINTS
+        .iter()
+        .find(|&&hint| options.seen_hints & hint.bit() == 0 && hint.due(game));
+    if let Some(&hint) = hint {
+        game.messages.add(hint.text(), LIGHT_GREEN);
+        options.seen_hints |= hint.bit();
+        // not being able to write the file only means the hint comes again
+        let _ = options.save();
+    }
+}
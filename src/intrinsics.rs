@@ -0,0 +1,64 @@
+//! Intrinsics: lasting gifts of the body rather than of what is worn, such as a
+//! stomach for poison. The player gains them from rare potions, and keeps them
+//! for the rest of the run.
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+// turns between the hit points a regenerating fighter heals
+pub const REGENERATION_TURNS: i32 = 10;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Intrinsic {
+    /// shrugs off poison, poison gas and poisonous meat
+    PoisonResistance,
+    /// walks through fire and over lava unharmed
+    FireResistance,
+    /// heals a hit point every few turns
+    Regeneration,
+}
+
+/// every intrinsic there is
+const INTRINSICS: &[Intrinsic] = &[
+    Intrinsic::PoisonResistance,
+    Intrinsic::FireResistance,
+    Intrinsic::Regeneration,
+];
+
+impl Intrinsic {
+    pub fn name(self) -> &'static str {
+        match self {
+            Intrinsic::PoisonResistance => "poison resistance",
+            Intrinsic::FireResistance => "fire resistance",
+            Intrinsic::Regeneration => "regeneration",
+        }
+    }
+
+    pub fn gain_message(self) -> &'static str {
+        match self {
+            Intrinsic::PoisonResistance => "You feel healthy. Poison will trouble you no more.",
+            Intrinsic::FireResistance => "You feel a pleasant chill. Flames will not burn you.",
+            Intrinsic::Regeneration => "You feel your flesh knitting itself together.",
+        }
+    }
+}
+
+/// a random intrinsic from those not yet `had`, None if every one is
+pub fn random_intrinsic(had: &[Intrinsic], rng: &mut impl Rng) -> Option<Intrinsic> {
+    let missing: Vec<_> = INTRINSICS
+        .iter()
+        .filter(|intrinsic| !had.contains(intrinsic))
+        .collect();
+    rng.choose(&missing).map(|&&intrinsic| intrinsic)
+}
+
+/// the intrinsics as the character screen lists them
+pub fn describe_intrinsics(intrinsics: &[Intrinsic]) -> String {
+    if intrinsics.is_empty() {
+        return "none".into();
+    }
+    intrinsics
+        .iter()
+        .map(|intrinsic| intrinsic.name())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
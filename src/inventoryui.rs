@@ -0,0 +1,40 @@
+//! The inventory screen: the items grouped under their category, a page at a
+//! time, next to a description of the highlighted one.
+use crate::{menuui::Menu, msgbox, object::Object};
+use tcod::console::Root;
+
+const LIST_WIDTH: i32 = 40;
+const DESCRIPTION_WIDTH: i32 = 30;
+// items shown on a page, each picked with a letter
+const PAGE_SIZE: usize = 20;
+
+/// Show the inventory and return the index of the item picked, either with its
+/// letter or by highlighting it with the arrow keys and pressing Enter.
+pub fn inventory_menu(inventory: &[Object], header: &str, root: &mut Root) -> Option<usize> {
+    if inventory.is_empty() {
+        msgbox("Inventory is empty.", LIST_WIDTH, root);
+        return None;
+    }
+
+    // items of a category are listed together, in the order they were picked up
+    let mut order: Vec<usize> = (0..inventory.len()).collect();
+    order.sort_by_key(|&id| inventory[id].item.map(|item| item.category()));
+
+    let menu = order.iter().fold(
+        Menu::new(header, LIST_WIDTH)
+            .details_width(DESCRIPTION_WIDTH)
+            .page_size(PAGE_SIZE),
+        |menu, &id| {
+            let object = &inventory[id];
+            let heading = object.item.map_or("Other", |item| item.category().name());
+            menu.entry(object.inventory_name())
+                .group(heading)
+                .details(format!(
+                    "{}\n\nWeight: {} lb",
+                    object.describe(),
+                    object.weight()
+                ))
+        },
+    );
+    menu.show(root).map(|index| order[index])
+}
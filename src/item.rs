@@ -1,21 +1,84 @@
 use crate::{
     ai::Ai,
-    game::{Game, PLAYER},
+    artifact::{apply_affixes, make_artifact, ARTIFACT_CHANCE},
+    blessings::{random_beatitude, remove_curses, Beatitude},
+    dungeon::LevelId,
+    effects::EffectKind,
+    fighter::{DamageSource, DamageType, DeathCallback},
+    food::{is_rotten, CHUNK_NOURISHMENT, NAUSEA_TURNS, POISON_DAMAGE},
+    game::{ground_open, is_blocked, Game, PLAYER},
+    groundeffects::GroundKind,
+    intrinsics::{random_intrinsic, Intrinsic},
+    luck::{pick_with_luck, roll_with_luck, split_stream},
+    map::Tile,
+    monsters::Faction,
     object::Object,
-    tcoder::Tcod,
+    skills::WeaponClass,
+    spawntables::item_table,
+    tcoder::{Tcod, MAP_HEIGHT, MAP_WIDTH},
+    visibility::sees,
 };
-use rand::distributions::{IndependentSample, Weighted, WeightedChoice};
+use rand::Rng;
 use serde::{Deserialize, Serialize};
-use tcod::colors::{LIGHT_BLUE, LIGHT_CYAN, LIGHT_GREEN, LIGHT_VIOLET, LIGHT_YELLOW, RED, VIOLET};
+use tcod::colors::{
+    CRIMSON, GOLD, LIGHTEST_GREY, LIGHT_BLUE, LIGHT_CYAN, LIGHT_GREEN, LIGHT_GREY, LIGHT_RED,
+    LIGHT_VIOLET, LIGHT_YELLOW, ORANGE, RED, SEPIA, SILVER, VIOLET, WHITE, YELLOW,
+};
+use tcod::line::Line;
 
 const HEAL_AMOUNT: i32 = 4;
 const LIGHTNING_DAMAGE: i32 = 40;
 const LIGHTNING_RANGE: i32 = 5;
+// a scroll of fire burns the nearest enemy, and the ground under it for a while
+const FIRE_DAMAGE: i32 = 15;
+const FIRE_RANGE: i32 = 6;
+const FIRE_TURNS: i32 = 4;
 const CONFUSE_RANGE: i32 = 8;
 const CONFUSE_NUM_TURNS: i32 = 10;
+const CHARM_RANGE: i32 = 6;
+const CHARM_NUM_TURNS: i32 = 30;
+const ELIXIR_MAX_HP: i32 = 10;
+// what a potion of vigor adds to the drinker's maximum health for good
+const VIGOR_MAX_HP: i32 = 5;
+// turns a potion of invisibility hides the drinker, or one of see invisible opens
+// their eyes
+const INVISIBILITY_TURNS: i32 = 30;
+const SEE_INVISIBLE_TURNS: i32 = 60;
+const WAND_MIN_CHARGES: i32 = 3;
+const WAND_MAX_CHARGES: i32 = 6;
+const WAND_LIGHTNING_DAMAGE: i32 = 20;
+const WAND_RANGE: i32 = 8;
+const DIG_LENGTH: i32 = 8;
+// how far a blink may take the player, and how close it always does
+const BLINK_RANGE: i32 = 6;
+const BLINK_MIN_DISTANCE: f32 = 3.0;
+// how far each light shines, and the turns it burns for
+const TORCH_RADIUS: i32 = 8;
+const TORCH_FUEL: i32 = 600;
+const LANTERN_RADIUS: i32 = 10;
+const LANTERN_FUEL: i32 = 1500;
+// how far the din of lightning and of digging carries
+const THUNDER_NOISE: f32 = 12.0;
+const DIGGING_NOISE: f32 = 10.0;
+// how far a bow shoots and how hard, the chance to hit point blank and how much
+// of it is lost with every tile of distance, down to the least chance there is
+const BOW_RANGE: f32 = 10.0;
+const BOW_DAMAGE: i32 = 6;
+const BOW_ACCURACY: f32 = 0.95;
+const BOW_ACCURACY_FALLOFF: f32 = 0.06;
+const BOW_MIN_ACCURACY: f32 = 0.2;
+// arrows in a bundle found lying around
+const ARROWS_MIN: i32 = 8;
+const ARROWS_MAX: i32 = 15;
+// what a dagger adds to the power of a blow struck with it, and the chance of a
+// shield in the off hand stopping a blow
+const DAGGER_POWER: i32 = 2;
+const SHIELD_BLOCK_CHANCE: f32 = 0.2;
 
 pub enum UseResult {
     UsedUp,
+    /// used, but not something that gets used up
+    Kept,
     Cancelled,
 }
 
@@ -24,67 +87,890 @@ pub enum Item {
     Heal,
     Lightning,
     Confuse,
+    Elixir,
+    Experience,
+    Vigor,
+    Might,
+    Toughness,
+    /// grants an intrinsic the drinker doesn't have yet
+    Resilience,
+    Invisibility,
+    SeeInvisible,
+    WandLightning,
+    WandConfusion,
+    WandDigging,
+    Identify,
+    LuckyCharm,
+    LeatherArmor,
+    ChainMail,
+    PlateArmor,
+    Blink,
+    Teleport,
+    Torch,
+    Lantern,
+    CharmMonster,
+    RemoveCurse,
+    Fire,
+    Key,
+    /// the artifact guarded by the Ancient King, to be carried back to the surface
+    Amulet,
+    Bow,
+    Arrows,
+    /// light enough to fight with one in each hand
+    Dagger,
+    /// carried in the off hand, it stops some of the blows aimed at the player
+    Shield,
+    /// meat cut from a corpse, named after the monster it came from
+    Chunk,
+}
+
+/// Where on the body an item is worn, one item to a slot.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Slot {
+    Body,
+    Light,
+    Weapon,
+    /// the other hand, for a shield or a second dagger
+    OffHand,
+}
+
+/// What a suit of armor does for, and to, whoever wears it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Armor {
+    pub defense: i32,
+    /// how far the clatter of every step carries
+    pub noise: f32,
+    /// heavy enough that monsters get an extra move now and then
+    pub slows: bool,
+}
+
+impl Item {
+    /// short name used to refer to the item in the debug console
+    pub fn id(self) -> &'static str {
+        match self {
+            Item::Heal => "potion_heal",
+            Item::Lightning => "scroll_lightning",
+            Item::Confuse => "scroll_confusion",
+            Item::Elixir => "elixir",
+            Item::Experience => "potion_experience",
+            Item::Vigor => "potion_vigor",
+            Item::Might => "potion_might",
+            Item::Toughness => "potion_toughness",
+            Item::Resilience => "potion_resilience",
+            Item::Invisibility => "potion_invisibility",
+            Item::SeeInvisible => "potion_see_invisible",
+            Item::WandLightning => "wand_lightning",
+            Item::WandConfusion => "wand_confusion",
+            Item::WandDigging => "wand_digging",
+            Item::Identify => "scroll_identify",
+            Item::LuckyCharm => "lucky_charm",
+            Item::LeatherArmor => "armor_leather",
+            Item::ChainMail => "armor_chain",
+            Item::PlateArmor => "armor_plate",
+            Item::Blink => "scroll_blink",
+            Item::Teleport => "scroll_teleport",
+            Item::Torch => "torch",
+            Item::Lantern => "lantern",
+            Item::CharmMonster => "scroll_charm",
+            Item::RemoveCurse => "scroll_remove_curse",
+            Item::Fire => "scroll_fire",
+            Item::Key => "key",
+            Item::Amulet => "amulet",
+            Item::Bow => "bow",
+            Item::Arrows => "arrows",
+            Item::Dagger => "dagger",
+            Item::Shield => "shield",
+            Item::Chunk => "meat_chunk",
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Item::Heal => "healing potion",
+            Item::Lightning => "scroll of lightning bolt",
+            Item::Confuse => "scroll of confusion",
+            Item::Elixir => "abyssal elixir",
+            Item::Experience => "potion of experience",
+            Item::Vigor => "potion of vigor",
+            Item::Might => "potion of might",
+            Item::Toughness => "potion of toughness",
+            Item::Resilience => "potion of resilience",
+            Item::Invisibility => "potion of invisibility",
+            Item::SeeInvisible => "potion of see invisible",
+            Item::WandLightning => "wand of lightning",
+            Item::WandConfusion => "wand of confusion",
+            Item::WandDigging => "wand of digging",
+            Item::Identify => "scroll of identify",
+            Item::LuckyCharm => "lucky charm",
+            Item::LeatherArmor => "leather armor",
+            Item::ChainMail => "chain mail",
+            Item::PlateArmor => "plate armor",
+            Item::Blink => "scroll of blinking",
+            Item::Teleport => "scroll of teleportation",
+            Item::Torch => "torch",
+            Item::Lantern => "lantern",
+            Item::CharmMonster => "scroll of charm monster",
+            Item::RemoveCurse => "scroll of remove curse",
+            Item::Fire => "scroll of fire",
+            Item::Key => "vault key",
+            Item::Amulet => "Amulet of the Ancient Kings",
+            Item::Bow => "shortbow",
+            Item::Arrows => "bundle of arrows",
+            Item::Dagger => "dagger",
+            Item::Shield => "round shield",
+            Item::Chunk => "chunk of meat",
+        }
+    }
+
+    /// what the item is called until it has been identified, None if it is
+    /// recognised at a glance
+    pub fn unidentified_name(self) -> Option<&'static str> {
+        match self {
+            Item::Heal
+            | Item::LeatherArmor
+            | Item::ChainMail
+            | Item::PlateArmor
+            | Item::Torch
+            | Item::Lantern
+            | Item::Key
+            | Item::Amulet
+            | Item::Bow
+            | Item::Arrows
+            | Item::Dagger
+            | Item::Shield
+            | Item::Chunk => None,
+            Item::Lightning => Some("scroll labeled ZELGO MER"),
+            Item::Confuse => Some("scroll labeled FOOBIE BLETCH"),
+            Item::Elixir => Some("smoking crimson potion"),
+            Item::Experience => Some("bubbling golden potion"),
+            Item::Vigor => Some("murky green potion"),
+            Item::Might => Some("fizzing orange potion"),
+            Item::Toughness => Some("cloudy blue potion"),
+            Item::Resilience => Some("milky white potion"),
+            Item::Invisibility => Some("clear potion"),
+            Item::SeeInvisible => Some("glittering silver potion"),
+            Item::WandLightning => Some("copper wand"),
+            Item::WandConfusion => Some("glass wand"),
+            Item::WandDigging => Some("iron wand"),
+            Item::Identify => Some("scroll labeled READ ME"),
+            Item::LuckyCharm => Some("small trinket"),
+            Item::Blink => Some("scroll labeled XIXAXA"),
+            Item::Teleport => Some("scroll labeled VELOX NEB"),
+            Item::CharmMonster => Some("scroll labeled ELBIB YLOH"),
+            Item::RemoveCurse => Some("scroll labeled PRATYAVAYAH"),
+            Item::Fire => Some("scroll labeled IGNIS ARDENS"),
+        }
+    }
+
+    /// what a sage charges to identify the item, the rarer the dearer
+    pub fn appraisal_fee(self) -> i32 {
+        match self {
+            Item::Heal
+            | Item::LeatherArmor
+            | Item::ChainMail
+            | Item::PlateArmor
+            | Item::Torch
+            | Item::Lantern
+            | Item::Key
+            | Item::Amulet
+            | Item::Bow
+            | Item::Arrows
+            | Item::Dagger
+            | Item::Shield
+            | Item::Chunk => 0,
+            Item::Lightning | Item::Confuse | Item::Identify | Item::Blink => 10,
+            Item::Invisibility | Item::SeeInvisible => 25,
+            Item::Teleport | Item::CharmMonster | Item::RemoveCurse | Item::Fire => 25,
+            Item::WandLightning | Item::WandConfusion | Item::WandDigging => 25,
+            Item::Elixir | Item::LuckyCharm => 50,
+            Item::Experience | Item::Vigor | Item::Might | Item::Toughness | Item::Resilience => 60,
+        }
+    }
+
+    pub fn category(self) -> Category {
+        match self {
+            Item::Heal
+            | Item::Elixir
+            | Item::Experience
+            | Item::Vigor
+            | Item::Might
+            | Item::Toughness
+            | Item::Resilience
+            | Item::Invisibility
+            | Item::SeeInvisible => Category::Potion,
+            Item::Lightning
+            | Item::Confuse
+            | Item::Identify
+            | Item::Blink
+            | Item::Teleport
+            | Item::CharmMonster
+            | Item::RemoveCurse
+            | Item::Fire => Category::Scroll,
+            Item::WandLightning | Item::WandConfusion | Item::WandDigging => Category::Wand,
+            Item::LuckyCharm | Item::Amulet => Category::Charm,
+            Item::LeatherArmor | Item::ChainMail | Item::PlateArmor | Item::Shield => {
+                Category::Armor
+            }
+            Item::Torch | Item::Lantern => Category::Light,
+            Item::Key => Category::Key,
+            Item::Bow | Item::Arrows | Item::Dagger => Category::Weapon,
+            Item::Chunk => Category::Food,
+        }
+    }
+
+    /// how heavy the item is to carry, in pounds
+    pub fn weight(self) -> i32 {
+        match self {
+            Item::Key => 0,
+            Item::Heal
+            | Item::Elixir
+            | Item::Experience
+            | Item::Vigor
+            | Item::Might
+            | Item::Toughness
+            | Item::Resilience
+            | Item::Invisibility
+            | Item::SeeInvisible
+            | Item::Lightning
+            | Item::Confuse
+            | Item::Identify
+            | Item::Blink
+            | Item::Teleport
+            | Item::CharmMonster
+            | Item::RemoveCurse
+            | Item::Fire
+            | Item::LuckyCharm
+            | Item::Arrows
+            | Item::Chunk => 1,
+            Item::WandLightning
+            | Item::WandConfusion
+            | Item::WandDigging
+            | Item::Amulet
+            | Item::Dagger => 2,
+            Item::Torch | Item::Bow => 3,
+            Item::Lantern => 5,
+            Item::Shield => 8,
+            Item::LeatherArmor => 15,
+            Item::ChainMail => 40,
+            Item::PlateArmor => 60,
+        }
+    }
+
+    /// where the item is worn, None if it can't be
+    pub fn slot(self) -> Option<Slot> {
+        match self {
+            Item::LeatherArmor | Item::ChainMail | Item::PlateArmor => Some(Slot::Body),
+            Item::Torch | Item::Lantern => Some(Slot::Light),
+            Item::Bow | Item::Dagger => Some(Slot::Weapon),
+            Item::Shield => Some(Slot::OffHand),
+            _ => None,
+        }
+    }
+
+    /// what a blow struck with the item adds to its power, None if it isn't
+    /// fought with
+    pub fn melee_power(self) -> Option<i32> {
+        match self {
+            Item::Dagger => Some(DAGGER_POWER),
+            _ => None,
+        }
+    }
+
+    /// the chance of the item stopping a blow, None if it doesn't block
+    pub fn block_chance(self) -> Option<f32> {
+        match self {
+            Item::Shield => Some(SHIELD_BLOCK_CHANCE),
+            _ => None,
+        }
+    }
+
+    /// how far the light lets the player see while it burns, None if it isn't a light
+    pub fn light_radius(self) -> Option<i32> {
+        match self {
+            Item::Torch => Some(TORCH_RADIUS),
+            Item::Lantern => Some(LANTERN_RADIUS),
+            _ => None,
+        }
+    }
+
+    /// what wearing the item does, None if it isn't armor
+    pub fn armor(self) -> Option<Armor> {
+        let (defense, noise, slows) = match self {
+            Item::LeatherArmor => (1, 0.0, false),
+            Item::ChainMail => (2, 4.0, false),
+            Item::PlateArmor => (4, 6.0, true),
+            _ => return None,
+        };
+        Some(Armor {
+            defense,
+            noise,
+            slows,
+        })
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Item::Heal => "mends a few wounds when quaffed",
+            Item::Lightning => "strikes the closest enemy with lightning",
+            Item::Confuse => "makes a chosen enemy stumble around for a while",
+            Item::Elixir => "heals fully and hardens the body, found only in the Abyss",
+            Item::Experience => "fills the drinker's head with the lessons of a lifetime",
+            Item::Vigor => "raises the drinker's hit points for good",
+            Item::Might => "makes the drinker hit harder for good",
+            Item::Toughness => "hardens the drinker's skin against blows for good",
+            Item::Resilience => "grants the drinker a gift of the body, such as a resistance",
+            Item::Invisibility => "hides the drinker from monsters that aren't right next to them",
+            Item::SeeInvisible => "lets the drinker see the invisible for a while",
+            Item::WandLightning => "zaps a chosen enemy with lightning, once per charge",
+            Item::WandConfusion => "confuses a chosen enemy, once per charge",
+            Item::WandDigging => "bores a tunnel through the walls, once per charge",
+            Item::Identify => "reveals the nature of everything carried",
+            Item::LuckyCharm => {
+                "brings good fortune to whoever carries it, rubbing it tells how much"
+            }
+            Item::LeatherArmor => "light and quiet, it turns the odd blow",
+            Item::ChainMail => "solid protection, but its rattle carries",
+            Item::Blink => "whisks the reader away to a spot close by",
+            Item::Torch => "lights the way for a while, then crumbles to ash",
+            Item::Lantern => "burns brighter and longer than a torch",
+            Item::Teleport => "takes the reader to any place they have seen",
+            Item::CharmMonster => "makes a chosen monster fight at your side for a while",
+            Item::RemoveCurse => "lifts the curse off everything carried",
+            Item::Fire => "engulfs the nearest enemy in flames, which the cold-blooded dread",
+            Item::Key => "opens a locked door, and stays in the lock",
+            Item::Amulet => "the treasure of the tombs, carry it up to the town to win",
+            Item::Bow => "once readied, shoots arrows at enemies farther away the less surely",
+            Item::Arrows => "shot from a bow, each one lost once fired",
+            Item::Dagger => "a keen blade, and with a second in the off hand two blows a turn",
+            Item::Shield => "blocks some of the blows aimed at you, carried in the off hand",
+            Item::Chunk => "staves off hunger, if eaten before it goes bad",
+            Item::PlateArmor => {
+                "the best protection there is, loud and so heavy that the monsters get \
+                 extra moves"
+            }
+        }
+    }
+}
+
+/// The kinds of items, each listed under a heading of its own in the inventory.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Category {
+    Weapon,
+    Armor,
+    Light,
+    Potion,
+    Scroll,
+    Wand,
+    Charm,
+    Food,
+    Key,
+}
+
+impl Category {
+    pub fn name(self) -> &'static str {
+        match self {
+            Category::Weapon => "Weapons",
+            Category::Armor => "Armor",
+            Category::Light => "Lights",
+            Category::Potion => "Potions",
+            Category::Scroll => "Scrolls",
+            Category::Wand => "Wands",
+            Category::Charm => "Charms",
+            Category::Food => "Food",
+            Category::Key => "Keys",
+        }
+    }
+}
+
+/// every category, in the order they are listed
+pub const CATEGORIES: &[Category] = &[
+    Category::Weapon,
+    Category::Armor,
+    Category::Light,
+    Category::Potion,
+    Category::Scroll,
+    Category::Wand,
+    Category::Charm,
+    Category::Food,
+    Category::Key,
+];
+
+/// a random item for a monster to carry, the luckier the player the rarer it may
+/// be, rolled on a stream split off `rng` like `create_item`
+pub fn random_loot(level: LevelId, luck: i32, rng: &mut impl Rng) -> Item {
+    pick_with_luck(&item_table(level), luck, &mut split_stream(rng))
+}
+
+/// Create a random item, the luckier the player the rarer it may be. The item is
+/// rolled on a stream split off `rng`, so what luck makes of it doesn't change the
+/// rolls made after it
+pub fn create_item(x: i32, y: i32, level: LevelId, luck: i32, rng: &mut impl Rng) -> Object {
+    let rng = &mut split_stream(rng);
+    let item = pick_with_luck(&item_table(level), luck, rng);
+    let mut object = item_object(item, x, y, luck, rng);
+    object.beatitude = random_beatitude(item, rng);
+    let equipment =
+        item.armor().is_some() || matches!(item.slot(), Some(Slot::Weapon) | Some(Slot::OffHand));
+    if equipment && rng.gen::<f32>() < ARTIFACT_CHANCE {
+        make_artifact(&mut object, rng);
+    }
+    object
+}
+
+/// every kind of item there is
+pub const ITEMS: &[Item] = &[
+    Item::Heal,
+    Item::Lightning,
+    Item::Confuse,
+    Item::Elixir,
+    Item::Experience,
+    Item::Vigor,
+    Item::Might,
+    Item::Toughness,
+    Item::Resilience,
+    Item::Invisibility,
+    Item::SeeInvisible,
+    Item::WandLightning,
+    Item::WandConfusion,
+    Item::WandDigging,
+    Item::Identify,
+    Item::LuckyCharm,
+    Item::LeatherArmor,
+    Item::ChainMail,
+    Item::PlateArmor,
+    Item::Blink,
+    Item::Teleport,
+    Item::Torch,
+    Item::Lantern,
+    Item::CharmMonster,
+    Item::RemoveCurse,
+    Item::Fire,
+    Item::Key,
+    Item::Amulet,
+    Item::Bow,
+    Item::Arrows,
+    Item::Dagger,
+    Item::Shield,
+    Item::Chunk,
+];
+
+/// create the object for an item of the given kind, unidentified unless it is
+/// recognised at a glance. Luck favours wands with more charges.
+pub fn item_object(item: Item, x: i32, y: i32, luck: i32, rng: &mut impl Rng) -> Object {
+    let (char, color) = match item {
+        Item::Heal => ('!', VIOLET),
+        Item::Lightning
+        | Item::Confuse
+        | Item::Identify
+        | Item::Blink
+        | Item::Teleport
+        | Item::CharmMonster
+        | Item::RemoveCurse
+        | Item::Fire => ('#', LIGHT_YELLOW),
+        Item::Elixir => ('!', CRIMSON),
+        Item::Experience => ('!', GOLD),
+        Item::Vigor => ('!', LIGHT_GREEN),
+        Item::Might => ('!', ORANGE),
+        Item::Toughness => ('!', LIGHT_BLUE),
+        Item::Resilience => ('!', WHITE),
+        Item::Invisibility => ('!', LIGHTEST_GREY),
+        Item::SeeInvisible => ('!', SILVER),
+        Item::WandLightning => ('/', LIGHT_BLUE),
+        Item::WandConfusion => ('/', LIGHT_GREEN),
+        Item::WandDigging => ('/', SEPIA),
+        Item::LuckyCharm => ('"', LIGHT_GREEN),
+        Item::LeatherArmor => ('[', SEPIA),
+        Item::ChainMail => ('[', LIGHT_GREY),
+        Item::PlateArmor => ('[', WHITE),
+        Item::Torch => ('(', ORANGE),
+        Item::Lantern => ('(', YELLOW),
+        Item::Key => ('-', GOLD),
+        Item::Amulet => ('"', GOLD),
+        Item::Bow => (')', SEPIA),
+        Item::Arrows => ('{', LIGHT_GREY),
+        Item::Dagger => (')', LIGHT_GREY),
+        Item::Shield => (']', SEPIA),
+        Item::Chunk => ('%', LIGHT_RED),
+    };
+    let mut object = Object::new(x, y, char, item.name(), color, false);
+    object.item = Some(item);
+    if matches!(
+        item,
+        Item::WandLightning | Item::WandConfusion | Item::WandDigging
+    ) {
+        let extra = (WAND_MAX_CHARGES - WAND_MIN_CHARGES + 1) as f32 * roll_with_luck(luck, rng);
+        object.charges =
+            Some(WAND_MIN_CHARGES + (extra as i32).min(WAND_MAX_CHARGES - WAND_MIN_CHARGES));
+    }
+    if item == Item::Arrows {
+        object.count = Some(rng.gen_range(ARROWS_MIN, ARROWS_MAX + 1));
+    }
+    object.fuel = match item {
+        Item::Torch => Some(TORCH_FUEL),
+        Item::Lantern => Some(LANTERN_FUEL),
+        _ => None,
+    };
+    if let Some(name) = item.unidentified_name() {
+        object.name = name.into();
+        object.unidentified = true;
+    }
+    object
+}
+
+/// reveal what an item is, and whether it is blessed or cursed
+pub fn identify(object: &mut Object) {
+    if object.item.is_some() {
+        reveal_kind(object);
+        object.beatitude_known = true;
+    }
+}
+
+/// reveal what kind of item it is, but not whether it is blessed or cursed
+pub fn reveal_kind(object: &mut Object) {
+    if let Some(item) = object.item {
+        object.name = item.name().into();
+        object.unidentified = false;
+    }
+}
+
+/// identify everything the player carries, telling them what was revealed in one
+/// message; returns how many items were identified
+pub fn identify_inventory(game: &mut Game) -> usize {
+    let mut revealed = vec![];
+    let unknown = game
+        .inventory
+        .iter_mut()
+        .filter(|o| o.unidentified || !o.beatitude_known);
+    for object in unknown {
+        let unknown_name = object.name.clone();
+        let kind_known = !object.unidentified;
+        identify(object);
+        revealed.push(if kind_known {
+            format!("the {} is {}", unknown_name, object.beatitude.name())
+        } else {
+            format!("the {} is a {}", unknown_name, object.inventory_name())
+        });
+    }
+    if !revealed.is_empty() {
+        game.messages.add(
+            format!("You learn that {}.", revealed.join(", ")),
+            LIGHT_CYAN,
+        );
+    }
+    revealed.len()
 }
 
-pub fn create_item(x: i32, y: i32) -> Object {
-    // item random table
-    let item_chances = &mut [
-        Weighted {
-            weight: 70,
-            item: Item::Heal,
-        },
-        Weighted {
-            weight: 10,
-            item: Item::Lightning,
-        },
-        Weighted {
-            weight: 10,
-            item: Item::Confuse,
-        },
-    ];
-    let item_choice = WeightedChoice::new(item_chances);
-
-    let mut item = match item_choice.ind_sample(&mut rand::thread_rng()) {
-        Item::Heal => {
-            // create a healing potion
-            let mut object = Object::new(x, y, '!', "healing potion", VIOLET, false);
-            object.item = Some(Item::Heal);
-            object
-        }
-        Item::Lightning => {
-            // create a lightning bolt scroll
-            let mut object =
-                Object::new(x, y, '#', "scroll of lightning bolt", LIGHT_YELLOW, false);
-            object.item = Some(Item::Lightning);
-            object
-        }
-        Item::Confuse => {
-            // create a confuse scroll
-            let mut object = Object::new(x, y, '#', "scroll of confusion", LIGHT_YELLOW, false);
-            object.item = Some(Item::Confuse);
-            object
-        }
-    };
-
-    item.always_visible = true;
-    item
-}
-
-pub fn cast_heal(_inventory_id: usize, _tcod: &mut Tcod, game: &mut Game) -> UseResult {
-    // heal the player
+pub fn cast_identify(inventory_id: usize, _tcod: &mut Tcod, game: &mut Game) -> UseResult {
+    // the scroll itself is known once read
+    identify(&mut game.inventory[inventory_id]);
+    if identify_inventory(game) == 0 {
+        game.messages.add(
+            "You feel knowledgeable, but carry nothing unknown.",
+            LIGHT_CYAN,
+        );
+    }
+    UseResult::UsedUp
+}
+
+pub fn cast_heal(inventory_id: usize, _tcod: &mut Tcod, game: &mut Game) -> UseResult {
+    // heal the player, twice as much with a blessed potion and half with a cursed one
     if let Some(fighter) = game.objects[PLAYER].fighter {
         if fighter.hp == fighter.max_hp {
             game.messages.add("You are already at full health.", RED);
             return UseResult::Cancelled;
         }
+        let amount = match game.inventory[inventory_id].beatitude {
+            Beatitude::Blessed => HEAL_AMOUNT * 2,
+            Beatitude::Uncursed => HEAL_AMOUNT,
+            Beatitude::Cursed => HEAL_AMOUNT / 2,
+        };
         game.messages
             .add("Your wounds start to feel better!", LIGHT_VIOLET);
-        game.objects[PLAYER].heal(HEAL_AMOUNT);
+        game.heal(PLAYER, amount);
+        return UseResult::UsedUp;
+    }
+    UseResult::Cancelled
+}
+
+pub fn cast_remove_curse(inventory_id: usize, _tcod: &mut Tcod, game: &mut Game) -> UseResult {
+    // the scroll itself is known once read
+    identify(&mut game.inventory[inventory_id]);
+    if remove_curses(game) > 0 {
+        game.messages
+            .add("You feel as if someone is helping you.", LIGHT_CYAN);
+    } else {
+        game.messages
+            .add("You feel a gentle warmth, but nothing changes.", LIGHT_CYAN);
+    }
+    UseResult::UsedUp
+}
+
+/// the charm works by being carried, rubbing it tells how lucky the player is
+pub fn rub_charm(_inventory_id: usize, _tcod: &mut Tcod, game: &mut Game) -> UseResult {
+    let (message, color) = match game.luck() {
+        luck if luck < 0 => (
+            "You rub the charm, but feel a chill. Luck is against you.",
+            RED,
+        ),
+        0 => ("You rub the charm, but feel no luckier than anyone.", WHITE),
+        1 | 2 => ("You rub the charm and feel lucky.", LIGHT_GREEN),
+        _ => ("You rub the charm and feel very lucky indeed.", LIGHT_GREEN),
+    };
+    game.messages.add(message, color);
+    UseResult::Kept
+}
+
+/// put on the item, taking off whatever was worn in its slot before, or take it
+/// off if it already is worn
+pub fn equip(inventory_id: usize, _tcod: &mut Tcod, game: &mut Game) -> UseResult {
+    if game.inventory[inventory_id].equipped {
+        return if take_off(game, inventory_id) {
+            UseResult::Kept
+        } else {
+            UseResult::Cancelled
+        };
+    }
+    let item = match game.inventory[inventory_id].item {
+        Some(item) if item.slot().is_some() => item,
+        _ => return UseResult::Cancelled,
+    };
+    let worn_in = |game: &Game, slot| {
+        game.inventory
+            .iter()
+            .position(|object| object.equipped && object.worn_slot() == Some(slot))
+    };
+    // a second dagger is taken up in the off hand, to fight with both
+    let dagger_in_hand = matches!(worn_in(game, Slot::Weapon), Some(id) if game.inventory[id].item == Some(Item::Dagger));
+    let slot = if item == Item::Dagger && dagger_in_hand && worn_in(game, Slot::OffHand).is_none() {
+        Slot::OffHand
+    } else {
+        item.slot().unwrap()
+    };
+    if let Some(worn_id) = worn_in(game, slot) {
+        if !take_off(game, worn_id) {
+            return UseResult::Cancelled;
+        }
+    }
+    game.inventory[inventory_id].equipped = true;
+    game.inventory[inventory_id].off_hand = item.slot() != Some(slot);
+    let beatitude = game.inventory[inventory_id].beatitude;
+    if let Some(fighter) = game.objects[PLAYER].fighter.as_mut() {
+        if let Some(armor) = item.armor() {
+            fighter.defense += armor.defense + beatitude.bonus();
+        }
+        apply_affixes(&game.inventory[inventory_id].affixes, fighter, 1);
+    }
+    let verb = match slot {
+        Slot::Light => "light",
+        Slot::Weapon | Slot::OffHand => "ready",
+        Slot::Body => "put on",
+    };
+    game.messages.add(
+        format!("You {} the {}.", verb, game.inventory[inventory_id].name),
+        LIGHT_GREEN,
+    );
+    if beatitude == Beatitude::Cursed {
+        game.inventory[inventory_id].beatitude_known = true;
+        game.messages.add(
+            format!(
+                "The {} clings to you with a will of its own. It is cursed!",
+                game.inventory[inventory_id].name
+            ),
+            RED,
+        );
+    }
+    UseResult::Kept
+}
+
+/// take off the item, if it is worn and not cursed; returns whether it is off
+pub fn take_off(game: &mut Game, inventory_id: usize) -> bool {
+    let object = &mut game.inventory[inventory_id];
+    if !object.equipped {
+        return true;
+    }
+    if object.beatitude == Beatitude::Cursed {
+        object.beatitude_known = true;
+        game.messages.add(
+            format!("You can't let go of the {}, it is cursed!", object.name),
+            RED,
+        );
+        return false;
+    }
+    object.equipped = false;
+    object.off_hand = false;
+    let defense = object
+        .item
+        .and_then(Item::armor)
+        .map_or(0, |armor| armor.defense + object.beatitude.bonus());
+    let verb = match object.item.and_then(Item::slot) {
+        Some(Slot::Light) => "put out",
+        Some(Slot::Weapon) | Some(Slot::OffHand) => "put away",
+        _ => "take off",
+    };
+    game.messages
+        .add(format!("You {} the {}.", verb, object.name), LIGHT_GREEN);
+    if let Some(fighter) = game.objects[PLAYER].fighter.as_mut() {
+        fighter.defense -= defense;
+        apply_affixes(&object.affixes, fighter, -1);
+    }
+    true
+}
+
+pub fn cast_blink(_inventory_id: usize, _tcod: &mut Tcod, game: &mut Game) -> UseResult {
+    // a random free spot a few steps away outside of any vault, out of the reach
+    // of monsters if possible
+    let (player_x, player_y) = game.objects[PLAYER].pos();
+    let mut spots = vec![];
+    for x in (player_x - BLINK_RANGE)..(player_x + BLINK_RANGE + 1) {
+        for y in (player_y - BLINK_RANGE)..(player_y + BLINK_RANGE + 1) {
+            let in_map = x >= 0 && y >= 0 && x < MAP_WIDTH && y < MAP_HEIGHT;
+            let distance = game.objects[PLAYER].distance(x, y);
+            if in_map
+                && distance >= BLINK_MIN_DISTANCE
+                && distance <= BLINK_RANGE as f32
+                && !game.map[x as usize][y as usize].terrain.is_perilous()
+                && !game.map[x as usize][y as usize].vault
+                && !is_blocked(x, y, &game.map, &game.objects)
+            {
+                spots.push((x, y));
+            }
+        }
+    }
+    let (safe, unsafe_spots): (Vec<_>, Vec<_>) = spots
+        .into_iter()
+        .partition(|&(x, y)| !next_to_monster(game, x, y));
+    let spots = if safe.is_empty() { unsafe_spots } else { safe };
+    let (x, y) = match rand::thread_rng().choose(&spots) {
+        Some(&spot) => spot,
+        None => {
+            game.messages
+                .add("The air shimmers, but you stay put.", LIGHT_CYAN);
+            return UseResult::Cancelled;
+        }
+    };
+    game.objects[PLAYER].set_pos(x, y);
+    game.messages
+        .add("The world blurs, and you are elsewhere!", LIGHT_CYAN);
+    UseResult::UsedUp
+}
+
+pub fn cast_teleport(_inventory_id: usize, tcod: &mut Tcod, game: &mut Game) -> UseResult {
+    // ask the player where to go, anywhere they have been that is free to stand on
+    game.messages.add(
+        "Left-click a place you have seen to teleport there, or move the cursor there and \
+         press Enter. Escape cancels.",
+        LIGHT_CYAN,
+    );
+    let (x, y) = match game.target_explored_tile(tcod) {
+        Some(tile) => tile,
+        None => return UseResult::Cancelled,
+    };
+    game.objects[PLAYER].set_pos(x, y);
+    game.messages
+        .add("You vanish, and appear where you willed!", LIGHT_CYAN);
+    UseResult::UsedUp
+}
+
+/// whether a living monster stands right next to a tile
+fn next_to_monster(game: &Game, x: i32, y: i32) -> bool {
+    game.objects.iter().any(|object| {
+        object.ai.is_some()
+            && object.alive
+            && (object.x - x).abs() <= 1
+            && (object.y - y).abs() <= 1
+    })
+}
+
+pub fn cast_elixir(_inventory_id: usize, _tcod: &mut Tcod, game: &mut Game) -> UseResult {
+    // heal the player completely and raise their maximum health
+    if let Some(fighter) = game.objects[PLAYER].fighter.as_mut() {
+        fighter.max_hp += ELIXIR_MAX_HP;
+        fighter.hp = fighter.max_hp;
+        game.messages.add(
+            "The elixir burns like liquid fire, then you feel reborn!",
+            CRIMSON,
+        );
+        return UseResult::UsedUp;
+    }
+    UseResult::Cancelled
+}
+
+pub fn quaff_experience(_inventory_id: usize, _tcod: &mut Tcod, game: &mut Game) -> UseResult {
+    // bring the player up to the experience they need for the next level
+    let level_up_xp = game.level_up_xp();
+    if let Some(fighter) = game.objects[PLAYER].fighter.as_mut() {
+        fighter.xp = fighter.xp.max(level_up_xp);
+        game.messages
+            .add("Memories of battles never fought flood your mind!", GOLD);
+        return UseResult::UsedUp;
+    }
+    UseResult::Cancelled
+}
+
+pub fn quaff_vigor(_inventory_id: usize, _tcod: &mut Tcod, game: &mut Game) -> UseResult {
+    if let Some(fighter) = game.objects[PLAYER].fighter.as_mut() {
+        fighter.max_hp += VIGOR_MAX_HP;
+        fighter.hp += VIGOR_MAX_HP;
+        game.messages.add("You feel hale and hearty.", LIGHT_GREEN);
         return UseResult::UsedUp;
     }
     UseResult::Cancelled
 }
 
+pub fn quaff_might(_inventory_id: usize, _tcod: &mut Tcod, game: &mut Game) -> UseResult {
+    if let Some(fighter) = game.objects[PLAYER].fighter.as_mut() {
+        fighter.power += 1;
+        game.messages
+            .add("Your muscles swell with strength.", ORANGE);
+        return UseResult::UsedUp;
+    }
+    UseResult::Cancelled
+}
+
+pub fn quaff_toughness(_inventory_id: usize, _tcod: &mut Tcod, game: &mut Game) -> UseResult {
+    if let Some(fighter) = game.objects[PLAYER].fighter.as_mut() {
+        fighter.defense += 1;
+        game.messages
+            .add("Your skin grows tough as leather.", LIGHT_BLUE);
+        return UseResult::UsedUp;
+    }
+    UseResult::Cancelled
+}
+
+pub fn quaff_resilience(_inventory_id: usize, _tcod: &mut Tcod, game: &mut Game) -> UseResult {
+    // grant an intrinsic the player lacks; once they have them all it is wasted
+    let player = &mut game.objects[PLAYER];
+    match random_intrinsic(&player.intrinsics, &mut rand::thread_rng()) {
+        Some(intrinsic) => {
+            player.intrinsics.push(intrinsic);
+            game.messages.add(intrinsic.gain_message(), WHITE);
+        }
+        None => game.messages.add(
+            "You feel as you did before. Nothing more can be gained.",
+            LIGHT_GREY,
+        ),
+    }
+    UseResult::UsedUp
+}
+
+pub fn quaff_invisibility(_inventory_id: usize, _tcod: &mut Tcod, game: &mut Game) -> UseResult {
+    game.objects[PLAYER].add_effect(EffectKind::Invisible, INVISIBILITY_TURNS, game.turn);
+    game.messages.add(
+        "Your body fades until you can't see it yourself.",
+        LIGHTEST_GREY,
+    );
+    UseResult::UsedUp
+}
+
+pub fn quaff_see_invisible(_inventory_id: usize, _tcod: &mut Tcod, game: &mut Game) -> UseResult {
+    game.objects[PLAYER].add_effect(EffectKind::SeeInvisible, SEE_INVISIBLE_TURNS, game.turn);
+    game.messages.add(
+        "Your eyes tingle, and the shadows lose their secrets.",
+        SILVER,
+    );
+    UseResult::UsedUp
+}
+
 pub fn cast_lightning(_inventory_id: usize, tcod: &mut Tcod, game: &mut Game) -> UseResult {
     // find closest enemy (inside a maximum range and damage it)
     let monster_id = closest_monster(tcod, &game.objects, LIGHTNING_RANGE);
@@ -98,10 +984,14 @@ pub fn cast_lightning(_inventory_id: usize, tcod: &mut Tcod, game: &mut Game) ->
             ),
             LIGHT_BLUE,
         );
-        if let Some(xp) = game.objects[monster_id].take_damage(LIGHTNING_DAMAGE, &mut game.messages)
-        {
-            game.objects[PLAYER].fighter.as_mut().unwrap().xp += xp;
-        }
+        let (x, y) = game.objects[monster_id].pos();
+        game.make_noise(x, y, THUNDER_NOISE, tcod);
+        game.damage(
+            monster_id,
+            LIGHTNING_DAMAGE,
+            DamageType::Lightning,
+            DamageSource::Player,
+        );
         UseResult::UsedUp
     } else {
         // no enemy found within maximum range
@@ -111,6 +1001,225 @@ pub fn cast_lightning(_inventory_id: usize, tcod: &mut Tcod, game: &mut Game) ->
     }
 }
 
+pub fn cast_fire(_inventory_id: usize, tcod: &mut Tcod, game: &mut Game) -> UseResult {
+    // set the closest enemy in range alight
+    let monster_id = match closest_monster(tcod, &game.objects, FIRE_RANGE) {
+        Some(monster_id) => monster_id,
+        None => {
+            game.messages.add("No enemy is close enough to burn.", RED);
+            return UseResult::Cancelled;
+        }
+    };
+    let (x, y) = game.objects[monster_id].pos();
+    game.messages.add(
+        format!(
+            "A pillar of flame engulfs the {} for {} hit points!",
+            game.objects[monster_id].name, FIRE_DAMAGE
+        ),
+        ORANGE,
+    );
+    game.damage(
+        monster_id,
+        FIRE_DAMAGE,
+        DamageType::Fire,
+        DamageSource::Player,
+    );
+    if ground_open(&game.map, GroundKind::Fire, x, y) {
+        game.ground.place(GroundKind::Fire, x, y, FIRE_TURNS);
+    }
+    UseResult::UsedUp
+}
+
+pub fn zap_lightning(_inventory_id: usize, tcod: &mut Tcod, game: &mut Game) -> UseResult {
+    // ask the player for a target to strike
+    game.messages.add(
+        "Left-click an enemy to zap it, or pick one with Tab and Enter. Escape cancels.",
+        LIGHT_CYAN,
+    );
+    let monster_id = target_monster(tcod, game, Some(WAND_RANGE as f32));
+    if let Some(monster_id) = monster_id {
+        game.messages.add(
+            format!(
+                "A crackling bolt leaps from the wand and strikes the {} for {} hit points.",
+                game.objects[monster_id].name, WAND_LIGHTNING_DAMAGE
+            ),
+            LIGHT_BLUE,
+        );
+        game.damage(
+            monster_id,
+            WAND_LIGHTNING_DAMAGE,
+            DamageType::Lightning,
+            DamageSource::Player,
+        );
+        UseResult::UsedUp
+    } else {
+        UseResult::Cancelled
+    }
+}
+
+/// shoot an arrow from the readied bow at a chosen monster, the first creature
+/// in the way taking it instead. The farther the shot the likelier it misses.
+pub fn fire_bow(_inventory_id: usize, tcod: &mut Tcod, game: &mut Game) -> UseResult {
+    let readied = game
+        .inventory
+        .iter()
+        .find(|object| object.equipped && object.item == Some(Item::Bow));
+    let damage = match readied {
+        Some(bow) => {
+            BOW_DAMAGE + bow.beatitude.bonus() + game.weapon_skills.damage_bonus(WeaponClass::Bow)
+        }
+        None => {
+            game.messages.add("You need to ready a bow first.", RED);
+            return UseResult::Cancelled;
+        }
+    };
+    let bundle = match game
+        .inventory
+        .iter()
+        .position(|object| object.item == Some(Item::Arrows))
+    {
+        Some(bundle) => bundle,
+        None => {
+            game.messages.add("You have no arrows to shoot.", RED);
+            return UseResult::Cancelled;
+        }
+    };
+
+    game.messages.add(
+        "Left-click an enemy to shoot at it, or pick one with Tab and Enter. Escape cancels.",
+        LIGHT_CYAN,
+    );
+    let aimed_at = match target_monster(tcod, game, Some(BOW_RANGE)) {
+        Some(monster_id) => monster_id,
+        None => return UseResult::Cancelled,
+    };
+    let player_pos = game.objects[PLAYER].pos();
+    let monster_id = Line::new(player_pos, game.objects[aimed_at].pos())
+        .find_map(|(x, y)| {
+            game.objects.iter().position(|object| {
+                object.pos() == (x, y) && object.alive && object.fighter.is_some()
+            })
+        })
+        .unwrap_or(aimed_at);
+
+    if let Some(count) = game.inventory[bundle].count.as_mut() {
+        *count -= 1;
+    }
+    if !matches!(game.inventory[bundle].count, Some(count) if count > 0) {
+        game.inventory.remove(bundle);
+        game.messages.add("That was your last arrow.", LIGHT_GREY);
+    }
+
+    let distance = game.objects[PLAYER].distance_to(&game.objects[monster_id]);
+    let chance = (BOW_ACCURACY - distance * BOW_ACCURACY_FALLOFF).max(BOW_MIN_ACCURACY)
+        + game.weapon_skills.accuracy_bonus(WeaponClass::Bow);
+    let name = game.objects[monster_id].name.clone();
+    if rand::random::<f32>() < chance {
+        game.messages.add(
+            format!("Your arrow strikes the {} for {} hit points.", name, damage),
+            LIGHT_BLUE,
+        );
+        game.damage(
+            monster_id,
+            damage,
+            DamageType::Physical,
+            DamageSource::Player,
+        );
+        game.strike_with_affixes(monster_id);
+        game.train(WeaponClass::Bow);
+    } else {
+        game.messages
+            .add(format!("Your arrow misses the {}.", name), LIGHT_GREY);
+    }
+    UseResult::Kept
+}
+
+/// eat a chunk of meat, leaving the player retching if it had gone rotten and
+/// hurting them if it was poisonous
+pub fn eat(inventory_id: usize, _tcod: &mut Tcod, game: &mut Game) -> UseResult {
+    if !game.hunger.can_eat(CHUNK_NOURISHMENT, game.turn) {
+        game.messages.add("You are too full to eat any more.", RED);
+        return UseResult::Cancelled;
+    }
+    let chunk = &game.inventory[inventory_id];
+    let rotten = chunk
+        .decays_at
+        .is_some_and(|decays_at| is_rotten(decays_at, game.turn));
+    let poisonous = matches!(&chunk.meat, Some(meat) if meat.poisonous);
+    game.messages
+        .add(format!("You eat the {}.", chunk.name), LIGHT_GREEN);
+    game.hunger.eat(CHUNK_NOURISHMENT, game.turn);
+
+    if rotten {
+        game.messages
+            .add("Ugh, it had gone rotten! You retch and reel.", LIGHT_CYAN);
+        game.objects[PLAYER].add_effect(EffectKind::Confused, NAUSEA_TURNS, game.turn);
+    }
+    if poisonous && game.objects[PLAYER].has_intrinsic(Intrinsic::PoisonResistance) {
+        game.messages
+            .add("The meat tastes bitter, but you keep it down.", LIGHT_GREEN);
+    } else if poisonous {
+        // the poison leaves the player at death's door, but no farther
+        let hp = game.objects[PLAYER].fighter.map_or(0, |f| f.hp);
+        game.messages
+            .add("Your stomach burns, the meat was poisonous!", RED);
+        game.damage(
+            PLAYER,
+            POISON_DAMAGE.min(hp - 1),
+            DamageType::Poison,
+            DamageSource::Environment,
+        );
+    }
+    UseResult::UsedUp
+}
+
+pub fn zap_digging(_inventory_id: usize, tcod: &mut Tcod, game: &mut Game) -> UseResult {
+    // bore a straight tunnel from the player towards a chosen tile
+    game.messages.add(
+        "Left-click where to dig, or move the cursor there and press Enter. Escape cancels.",
+        LIGHT_CYAN,
+    );
+    let (target_x, target_y) = match game.target_tile(tcod, None) {
+        Some(target) => target,
+        None => return UseResult::Cancelled,
+    };
+    let (player_x, player_y) = game.objects[PLAYER].pos();
+    if (target_x, target_y) == (player_x, player_y) {
+        return UseResult::Cancelled;
+    }
+    // aim far past the target so the tunnel always has its full length
+    let end = (
+        player_x + (target_x - player_x) * DIG_LENGTH,
+        player_y + (target_y - player_y) * DIG_LENGTH,
+    );
+    let mut dug = 0;
+    for (x, y) in Line::new((player_x, player_y), end).take(DIG_LENGTH as usize) {
+        // the outer edge of the map stays solid
+        if x <= 0 || y <= 0 || x >= MAP_WIDTH - 1 || y >= MAP_HEIGHT - 1 {
+            break;
+        }
+        let tile = &mut game.map[x as usize][y as usize];
+        if tile.blocked && tile.door.is_none() {
+            *tile = Tile {
+                explored: tile.explored,
+                last_seen: tile.last_seen,
+                ..Tile::empty()
+            };
+            tcod.fov.set(x, y, true, true);
+            dug += 1;
+        }
+    }
+    if dug > 0 {
+        game.messages
+            .add("The rock crumbles away before the wand.", SEPIA);
+        game.make_noise(player_x, player_y, DIGGING_NOISE, tcod);
+    } else {
+        game.messages
+            .add("The wand hums, but there is nothing to dig.", SEPIA);
+    }
+    UseResult::UsedUp
+}
+
 /// find closest enemy, up to a maximum range, and in the player's FOV
 pub fn closest_monster(tcod: &Tcod, objects: &[Object], max_range: i32) -> Option<usize> {
     let mut closest_enemy = None;
@@ -120,7 +1229,8 @@ pub fn closest_monster(tcod: &Tcod, objects: &[Object], max_range: i32) -> Optio
         if (id != PLAYER)
             && object.fighter.is_some()
             && object.ai.is_some()
-            && tcod.fov.is_in_fov(object.x, object.y)
+            && object.allegiance() != Faction::Player
+            && sees(&tcod.fov, &objects[PLAYER], object)
         {
             // calculate distance between this object and the player
             let dist = objects[PLAYER].distance_to(object);
@@ -137,7 +1247,7 @@ pub fn closest_monster(tcod: &Tcod, objects: &[Object], max_range: i32) -> Optio
 pub fn cast_confuse(_inventory_id: usize, tcod: &mut Tcod, game: &mut Game) -> UseResult {
     // ask the player for a target to confuse
     game.messages.add(
-        "Left-click an enemy to confuse it, or right-click to cancel.",
+        "Left-click an enemy to confuse it, or pick one with Tab and Enter. Escape cancels.",
         LIGHT_CYAN,
     );
     let monster_id = target_monster(tcod, game, Some(CONFUSE_RANGE as f32));
@@ -145,10 +1255,7 @@ pub fn cast_confuse(_inventory_id: usize, tcod: &mut Tcod, game: &mut Game) -> U
         let old_ai = game.objects[monster_id].ai.take().unwrap_or(Ai::Basic);
         // replace the monster's AI with a "confused" one; after
         // some turns it will restore the old AI
-        game.objects[monster_id].ai = Some(Ai::Confused {
-            previous_ai: Box::new(old_ai),
-            num_turns: CONFUSE_NUM_TURNS,
-        });
+        game.objects[monster_id].ai = Some(old_ai.confuse(CONFUSE_NUM_TURNS, true));
         game.messages.add(
             format!(
                 "The eyes of {} look vacant, as he starts to stumble around!",
@@ -165,6 +1272,44 @@ pub fn cast_confuse(_inventory_id: usize, tcod: &mut Tcod, game: &mut Game) -> U
     }
 }
 
+pub fn use_key(_inventory_id: usize, _tcod: &mut Tcod, game: &mut Game) -> UseResult {
+    game.messages
+        .add("Walk into a locked door to unlock it.", WHITE);
+    UseResult::Cancelled
+}
+
+pub fn use_amulet(_inventory_id: usize, _tcod: &mut Tcod, game: &mut Game) -> UseResult {
+    game.messages.add(
+        "The amulet hums in your hand. Carry it up to the town!",
+        GOLD,
+    );
+    UseResult::Cancelled
+}
+
+pub fn cast_charm(_inventory_id: usize, tcod: &mut Tcod, game: &mut Game) -> UseResult {
+    // ask the player for a monster to win over
+    game.messages.add(
+        "Left-click a monster to charm it, or pick one with Tab and Enter. Escape cancels.",
+        LIGHT_CYAN,
+    );
+    let monster_id = match target_monster(tcod, game, Some(CHARM_RANGE as f32)) {
+        Some(monster_id) => monster_id,
+        None => return UseResult::Cancelled,
+    };
+    let monster = &mut game.objects[monster_id];
+    // the guardians of the branches don't bend to anyone
+    if matches!(monster.fighter, Some(f) if f.on_death == DeathCallback::Boss) {
+        let msg = format!("The {} shrugs off the charm!", monster.name);
+        game.messages.add(msg, RED);
+        return UseResult::UsedUp;
+    }
+    let until = game.turn + CHARM_NUM_TURNS;
+    monster.ai = monster.ai.take().map(|ai| ai.charm(until));
+    let msg = format!("The {} looks at you adoringly!", monster.name);
+    game.messages.add(msg, LIGHT_GREEN);
+    UseResult::UsedUp
+}
+
 /// returns a clicked monster inside FOV up to a range, or None if right-clicked
 pub fn target_monster(tcod: &mut Tcod, game: &mut Game, max_range: Option<f32>) -> Option<usize> {
     loop {
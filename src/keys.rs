@@ -0,0 +1,369 @@
+//! The keys the player presses while playing, and what each of them does. The
+//! game looks up the command for a key press here, and the help screen lists the
+//! same table, so the two can't disagree. Macros are kept here too, being
+//! nothing but commands played back.
+use tcod::input::{Key, KeyCode};
+
+// the width of the help screen, and of the primer
+pub const HELP_WIDTH: i32 = 60;
+
+/// what a key does
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum KeyCommand {
+    Fullscreen,
+    Menu,
+    Pause,
+    Help,
+    DebugConsole,
+    GlyphAudit,
+    Move(i32, i32),
+    /// walk in a direction until something is in the way or worth a look
+    Run(i32, i32),
+    /// wait until healed, or for as long as resting does any good
+    Rest,
+    /// do the last run or rest again
+    Repeat,
+    /// start recording a macro, or stop and keep it
+    RecordMacro,
+    PlayMacro,
+    PickUp,
+    Inventory,
+    Drop,
+    TakeStairs,
+    Interact,
+    Examine,
+    /// walk to one of the landmarks known on the level
+    Travel,
+    Fire,
+    Cast,
+    /// look for hidden doors around the player
+    Search,
+    Statistics,
+    Journal,
+    Character,
+}
+
+/// The key press a command is bound to
+#[derive(Clone, Copy, Debug)]
+pub enum KeyPress {
+    Code(KeyCode),
+    Alt(KeyCode),
+    Shift(KeyCode),
+    /// a letter typed with Ctrl held
+    Ctrl(char),
+    /// a key typing this text
+    Text(&'static str),
+}
+
+impl KeyPress {
+    fn matches(self, key: Key) -> bool {
+        match self {
+            KeyPress::Code(code) => {
+                key.code == code && !key.alt && !(key.shift && has_shift_binding(code))
+            }
+            KeyPress::Alt(code) => key.code == code && key.alt,
+            KeyPress::Shift(code) => key.code == code && key.shift,
+            KeyPress::Ctrl(letter) => key.ctrl && key.printable == letter,
+            KeyPress::Text(text) => key.code == KeyCode::Text && key.text() == text,
+        }
+    }
+
+    /// the key as the help screen shows it
+    fn label(self) -> String {
+        let name = |code| match code {
+            KeyCode::Enter => "Enter".into(),
+            KeyCode::Escape => "Escape".into(),
+            KeyCode::Up => "Up".into(),
+            KeyCode::Down => "Down".into(),
+            KeyCode::Left => "Left".into(),
+            KeyCode::Right => "Right".into(),
+            code => format!("{:?}", code),
+        };
+        match self {
+            KeyPress::Code(code) => name(code),
+            KeyPress::Alt(code) => format!("Alt+{}", name(code)),
+            KeyPress::Shift(code) => format!("Shift+{}", name(code)),
+            KeyPress::Ctrl(letter) => format!("Ctrl+{}", letter.to_ascii_uppercase()),
+            KeyPress::Text(text) => text.into(),
+        }
+    }
+}
+
+pub struct Binding {
+    pub key: KeyPress,
+    pub command: KeyCommand,
+    /// what the help screen says it does
+    pub description: &'static str,
+    /// whether it does anything once the player has died
+    pub while_dead: bool,
+    /// whether it is there in debug builds only
+    pub debug_only: bool,
+}
+
+const fn bind(key: KeyPress, command: KeyCommand, description: &'static str) -> Binding {
+    Binding {
+        key,
+        command,
+        description,
+        while_dead: false,
+        debug_only: false,
+    }
+}
+
+/// a binding that still works when the player has died
+const fn always(key: KeyPress, command: KeyCommand, description: &'static str) -> Binding {
+    Binding {
+        while_dead: true,
+        ..bind(key, command, description)
+    }
+}
+
+const fn debug(key: KeyPress, command: KeyCommand, description: &'static str) -> Binding {
+    Binding {
+        debug_only: true,
+        ..always(key, command, description)
+    }
+}
+
+/// whether the key is bound with Shift held too, its plain binding then standing aside
+fn has_shift_binding(code: KeyCode) -> bool {
+    BINDINGS
+        .iter()
+        .any(|binding| matches!(binding.key, KeyPress::Shift(shifted) if shifted == code))
+}
+
+/// every key the game responds to while playing, in the order the help lists them
+pub const BINDINGS: &[Binding] = &[
+    bind(
+        KeyPress::Code(KeyCode::Up),
+        KeyCommand::Move(0, -1),
+        "move or attack north",
+    ),
+    bind(
+        KeyPress::Code(KeyCode::Down),
+        KeyCommand::Move(0, 1),
+        "move or attack south",
+    ),
+    bind(
+        KeyPress::Code(KeyCode::Left),
+        KeyCommand::Move(-1, 0),
+        "move or attack west",
+    ),
+    bind(
+        KeyPress::Code(KeyCode::Right),
+        KeyCommand::Move(1, 0),
+        "move or attack east",
+    ),
+    bind(
+        KeyPress::Shift(KeyCode::Up),
+        KeyCommand::Run(0, -1),
+        "run north",
+    ),
+    bind(
+        KeyPress::Shift(KeyCode::Down),
+        KeyCommand::Run(0, 1),
+        "run south",
+    ),
+    bind(
+        KeyPress::Shift(KeyCode::Left),
+        KeyCommand::Run(-1, 0),
+        "run west",
+    ),
+    bind(
+        KeyPress::Shift(KeyCode::Right),
+        KeyCommand::Run(1, 0),
+        "run east",
+    ),
+    bind(KeyPress::Text("R"), KeyCommand::Rest, "rest until healed"),
+    bind(KeyPress::Ctrl('a'), KeyCommand::Repeat, "run or rest again"),
+    bind(
+        KeyPress::Text("Q"),
+        KeyCommand::RecordMacro,
+        "start or stop recording a macro",
+    ),
+    bind(KeyPress::Text("q"), KeyCommand::PlayMacro, "play the macro"),
+    bind(
+        KeyPress::Text("g"),
+        KeyCommand::PickUp,
+        "pick up what lies here",
+    ),
+    bind(KeyPress::Text("i"), KeyCommand::Inventory, "use an item"),
+    bind(KeyPress::Text("d"), KeyCommand::Drop, "drop an item"),
+    bind(
+        KeyPress::Text("v"),
+        KeyCommand::TakeStairs,
+        "take the stairs",
+    ),
+    bind(
+        KeyPress::Text("e"),
+        KeyCommand::Interact,
+        "interact with what is here or next to you",
+    ),
+    bind(
+        KeyPress::Text("x"),
+        KeyCommand::Examine,
+        "examine the surroundings",
+    ),
+    bind(
+        KeyPress::Text("G"),
+        KeyCommand::Travel,
+        "travel to a known landmark",
+    ),
+    bind(
+        KeyPress::Text("f"),
+        KeyCommand::Fire,
+        "shoot the readied bow",
+    ),
+    bind(KeyPress::Text("z"), KeyCommand::Cast, "cast a spell"),
+    bind(
+        KeyPress::Text("s"),
+        KeyCommand::Search,
+        "search for hidden doors",
+    ),
+    bind(
+        KeyPress::Text("c"),
+        KeyCommand::Character,
+        "show the character",
+    ),
+    always(
+        KeyPress::Text("S"),
+        KeyCommand::Statistics,
+        "show the run statistics",
+    ),
+    always(
+        KeyPress::Text("j"),
+        KeyCommand::Journal,
+        "show the quest journal",
+    ),
+    always(KeyPress::Text("p"), KeyCommand::Pause, "pause the game"),
+    always(KeyPress::Text("?"), KeyCommand::Help, "show this help"),
+    always(
+        KeyPress::Code(KeyCode::Escape),
+        KeyCommand::Menu,
+        "save, change options or quit",
+    ),
+    always(
+        KeyPress::Alt(KeyCode::Enter),
+        KeyCommand::Fullscreen,
+        "toggle fullscreen",
+    ),
+    debug(
+        KeyPress::Text("`"),
+        KeyCommand::DebugConsole,
+        "open the command console",
+    ),
+    debug(
+        KeyPress::Code(KeyCode::F2),
+        KeyCommand::GlyphAudit,
+        "check the glyphs apart",
+    ),
+];
+
+impl KeyCommand {
+    /// whether the command can go into a macro, needing no more input than the
+    /// key itself
+    pub fn recordable(self) -> bool {
+        matches!(
+            self,
+            KeyCommand::Move(..)
+                | KeyCommand::Run(..)
+                | KeyCommand::Rest
+                | KeyCommand::PickUp
+                | KeyCommand::TakeStairs
+                | KeyCommand::Search
+        )
+    }
+}
+
+/// A run of commands the player recorded, played back a command a turn
+#[derive(Debug, Default)]
+pub struct Macro {
+    /// the commands recorded so far, while recording
+    recording: Option<Vec<KeyCommand>>,
+    /// the last macro recorded
+    commands: Vec<KeyCommand>,
+    /// the commands still to play back, the next one last
+    playing: Vec<KeyCommand>,
+}
+
+impl Macro {
+    /// start recording, or stop and keep what was recorded. Returns whether it is
+    /// recording now
+    pub fn toggle_recording(&mut self) -> bool {
+        match self.recording.take() {
+            Some(commands) => {
+                self.commands = commands;
+                false
+            }
+            None => {
+                self.recording = Some(vec![]);
+                true
+            }
+        }
+    }
+
+    /// add the command to the macro being recorded, if it is one that can go in
+    pub fn record(&mut self, command: KeyCommand) {
+        if let Some(commands) = self.recording.as_mut() {
+            if command.recordable() {
+                commands.push(command);
+            }
+        }
+    }
+
+    /// start playing the macro back, returning false if there is none
+    pub fn play(&mut self) -> bool {
+        self.playing = self.commands.iter().rev().copied().collect();
+        !self.playing.is_empty()
+    }
+
+    /// the next command to play back, if the macro is being played
+    pub fn next_command(&mut self) -> Option<KeyCommand> {
+        self.playing.pop()
+    }
+
+    pub fn stop(&mut self) {
+        self.playing.clear();
+    }
+}
+
+/// the binding for the key press, if the game has any right now
+pub fn binding_for(key: Key) -> Option<&'static Binding> {
+    BINDINGS
+        .iter()
+        .filter(|binding| !binding.debug_only || cfg!(debug_assertions))
+        .find(|binding| binding.key.matches(key))
+}
+
+/// the key the command is bound to, as the help screen shows it
+pub fn key_for(command: KeyCommand) -> String {
+    BINDINGS
+        .iter()
+        .find(|binding| binding.command == command)
+        .map_or_else(String::new, |binding| binding.key.label())
+}
+
+/// the text of the help screen: every key there is, and the mouse
+pub fn help_text() -> String {
+    let mut text = String::from("Commands\n");
+    let bindings = BINDINGS
+        .iter()
+        .filter(|binding| !binding.debug_only || cfg!(debug_assertions));
+    for binding in bindings {
+        text += &format!("\n{:>10}  {}", binding.key.label(), binding.description);
+    }
+    text + "\n\nLeft-click  walk to a tile, or attack the monster next to you"
+}
+
+/// what the player is told before their very first game
+pub const PRIMER: &str = "Welcome to the Tombs of the Ancient Kings!
+
+Somewhere below lies the Amulet of the Ancient Kings. Find it and carry it \
+back up to the town to win.
+
+You are the @. Walk into a monster to attack it, and take the stairs down \
+with 'v' when you stand on them. Pick items up with 'g' and use them from the \
+inventory with 'i'. Keep an eye on your health at the bottom of the screen, \
+and on your hunger: butcher what you kill with 'e' and eat it before it rots.
+
+Press '?' at any time for the list of commands.";
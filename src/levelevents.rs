@@ -0,0 +1,165 @@
+use crate::{
+    game::{is_blocked, Game, PLAYER},
+    inventoryui::inventory_menu,
+    item::{create_item, identify_inventory, take_off},
+    menu,
+    menuui::Menu,
+    monsters::create_monster,
+    tcoder::{Tcod, INVENTORY_WIDTH},
+};
+use rand::distributions::{IndependentSample, Weighted, WeightedChoice};
+use rand::Rng;
+use tcod::colors::{LIGHT_BLUE, RED};
+
+const AMBUSH_MONSTERS: usize = 4;
+const TRADER_WARES: usize = 3;
+// the width of the description of the highlighted ware
+const WARE_DETAILS_WIDTH: i32 = 30;
+
+/// Something out of the ordinary that can happen on the way down the stairs.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LevelEvent {
+    Nothing,
+    Ambush,
+    Trader,
+    CollapsedShaft,
+    Sage,
+}
+
+pub fn roll_level_event() -> LevelEvent {
+    // level event random table
+    let event_chances = &mut [
+        Weighted {
+            weight: 65,
+            item: LevelEvent::Nothing,
+        },
+        Weighted {
+            weight: 10,
+            item: LevelEvent::Ambush,
+        },
+        Weighted {
+            weight: 10,
+            item: LevelEvent::Trader,
+        },
+        Weighted {
+            weight: 10,
+            item: LevelEvent::CollapsedShaft,
+        },
+        Weighted {
+            weight: 5,
+            item: LevelEvent::Sage,
+        },
+    ];
+    let event_choice = WeightedChoice::new(event_chances);
+    event_choice.ind_sample(&mut rand::thread_rng())
+}
+
+/// surround the freshly arrived player with monsters
+pub fn spring_ambush(game: &mut Game) {
+    let (player_x, player_y) = game.objects[PLAYER].pos();
+    let mut spots = vec![];
+    for dx in -1..2 {
+        for dy in -1..2 {
+            let (x, y) = (player_x + dx, player_y + dy);
+            let perilous = game.map[x as usize][y as usize].terrain.is_perilous();
+            if (dx, dy) != (0, 0) && !perilous && !is_blocked(x, y, &game.map, &game.objects) {
+                spots.push((x, y));
+            }
+        }
+    }
+    rand::thread_rng().shuffle(&mut spots);
+
+    for &(x, y) in spots.iter().take(AMBUSH_MONSTERS) {
+        let mut monster = create_monster(x, y, game.level, &mut rand::thread_rng());
+        game.difficulty.toughen(&mut monster);
+        game.objects.push(monster);
+    }
+    game.messages
+        .add("It's an ambush! Monsters close in from all sides!", RED);
+}
+
+/// offer a few random wares in exchange for one of the player's items
+pub fn meet_trader(game: &mut Game, tcod: &mut Tcod) {
+    game.messages.add(
+        "A wandering trader waits at the bottom of the stairs.",
+        LIGHT_BLUE,
+    );
+    if game.inventory.is_empty() {
+        game.messages.add(
+            "Seeing your empty pockets, the trader wanders off.",
+            LIGHT_BLUE,
+        );
+        return;
+    }
+
+    let mut wares: Vec<_> = (0..TRADER_WARES)
+        .map(|_| create_item(0, 0, game.level, game.luck(), &mut rand::thread_rng()))
+        .collect();
+    let header = "The trader offers one of these in exchange for an item of yours:\n";
+    let menu = wares.iter().fold(
+        Menu::new(header, INVENTORY_WIDTH).details_width(WARE_DETAILS_WIDTH),
+        |menu, ware| menu.entry(ware.inventory_name()).details(ware.describe()),
+    );
+    let ware_index = menu.show(&mut tcod.root);
+    if let Some(ware_index) = ware_index {
+        let inventory_index = inventory_menu(
+            &game.inventory,
+            "Which item do you give in return?\n",
+            &mut tcod.root,
+        );
+        if let Some(inventory_index) = inventory_index.filter(|&id| take_off(game, id)) {
+            let given = game.inventory.remove(inventory_index);
+            let received = wares.swap_remove(ware_index);
+            game.messages.add(
+                format!("You trade your {} for a {}.", given.name, received.name),
+                LIGHT_BLUE,
+            );
+            game.inventory.push(received);
+            return;
+        }
+    }
+    game.messages
+        .add("The trader shrugs and wanders off.", LIGHT_BLUE);
+}
+
+/// offer to identify everything the player carries, for a fee
+pub fn meet_sage(game: &mut Game, tcod: &mut Tcod) {
+    game.messages.add(
+        "A hooded sage beckons you over, eyeing your pack.",
+        LIGHT_BLUE,
+    );
+    let unknown: Vec<_> = game
+        .inventory
+        .iter()
+        .filter(|object| object.unidentified)
+        .filter_map(|object| object.item)
+        .collect();
+    if unknown.is_empty() {
+        game.messages.add(
+            "\"You know all that you carry,\" the sage says, and wanders off.",
+            LIGHT_BLUE,
+        );
+        return;
+    }
+
+    let fee: i32 = unknown.iter().map(|item| item.appraisal_fee()).sum();
+    let header = format!(
+        "\"For {} gold I will tell you what your {} unknown items are.\" You have {} gold.\n",
+        fee,
+        unknown.len(),
+        game.gold
+    );
+    let choices = &["Pay the sage", "Decline"];
+    if menu(&header, choices, INVENTORY_WIDTH, &mut tcod.root) != Some(0) {
+        game.messages
+            .add("The sage shrugs and wanders off.", LIGHT_BLUE);
+    } else if game.gold < fee {
+        game.messages.add(
+            "\"Come back when your purse is heavier,\" the sage scoffs.",
+            RED,
+        );
+    } else {
+        game.gold -= fee;
+        identify_inventory(game);
+    }
+}
@@ -0,0 +1,55 @@
+//! Luck tilts random rolls in the player's favour, or against them. It comes from
+//! the lucky charms carried and the favour of the gods won or lost at altars, and
+//! biases the items found and carried by monsters, wand charges, prayers and
+//! dodging hazards. Every roll it should affect goes through `roll_with_luck`, so
+//! its influence stays the same everywhere.
+use rand::{Rng, SeedableRng, XorShiftRng};
+use std::cmp::Reverse;
+
+/// luck beyond this makes no further difference
+const MAX_LUCK: i32 = 3;
+// the rest of the seed of the streams split off for rolls luck may change
+const STREAM_SEED: [u32; 3] = [0x2545_f491, 0x9e37_79b9, 0x6c07_8965];
+
+/// A stream of rolls of its own, seeded with a single draw from `rng`. However
+/// many rolls are made from it, the rolls made from `rng` after it stay the same.
+pub fn split_stream(rng: &mut impl Rng) -> XorShiftRng {
+    XorShiftRng::from_seed([rng.gen(), STREAM_SEED[0], STREAM_SEED[1], STREAM_SEED[2]])
+}
+
+/// A random roll between 0 and 1 where higher is better for the player. Each point
+/// of luck rolls once more and keeps the best roll, each point of bad luck keeps
+/// the worst. The rolls come from a stream split off `rng`, so that however lucky
+/// the player is, the rolls laying out a seeded level stay the same.
+pub fn roll_with_luck(luck: i32, rng: &mut impl Rng) -> f32 {
+    let luck = luck.clamp(-MAX_LUCK, MAX_LUCK);
+    let mut luck_rng = split_stream(rng);
+    let rolls = (0..=luck.abs()).map(|_| luck_rng.gen::<f32>());
+    if luck >= 0 {
+        rolls.fold(0.0, f32::max)
+    } else {
+        rolls.fold(1.0, f32::min)
+    }
+}
+
+/// whether something good with the given chance happens to the player
+pub fn lucky(chance: f32, luck: i32) -> bool {
+    roll_with_luck(luck, &mut rand::thread_rng()) >= 1.0 - chance
+}
+
+/// Pick from a table of entries and weights. Without luck each entry comes up as
+/// often as its weight says, good luck favours the rarer ones.
+pub fn pick_with_luck<T: Copy>(table: &[(T, u32)], luck: i32, rng: &mut impl Rng) -> T {
+    let mut entries = table.to_vec();
+    // most common first, so that high rolls land on the rare entries
+    entries.sort_by_key(|&(_, weight)| Reverse(weight));
+    let total: u32 = entries.iter().map(|&(_, weight)| weight).sum();
+    let mut roll = (roll_with_luck(luck, rng) * total as f32) as u32;
+    for &(entry, weight) in &entries {
+        if roll < weight {
+            return entry;
+        }
+        roll -= weight;
+    }
+    entries[entries.len() - 1].0
+}
@@ -2,53 +2,102 @@ use tcod::colors::*;
 use tcod::console::*;
 use tcod::{input::Mouse, map::Map as FovMap};
 
+mod achievements;
 mod ai;
+mod animations;
+mod artifact;
+mod audio;
+mod bestiary;
+mod blessings;
+mod bones;
+mod debugconsole;
+mod difficulty;
+mod dungeon;
+mod effects;
+mod events;
 mod fighter;
+mod food;
 mod game;
+mod glyphaudit;
+mod groundeffects;
+mod gzip;
+mod hazards;
+mod hints;
+mod intrinsics;
+mod inventoryui;
 mod item;
+mod keys;
+mod levelevents;
+mod luck;
 mod map;
 mod mapbuilder;
+mod menuui;
 mod messages;
 mod monsters;
+mod morgue;
 mod object;
+mod options;
+mod palette;
+mod pathfinding;
+mod pickupui;
+mod prefabs;
+mod profile;
+mod quests;
 mod rect;
+mod renderer;
+mod runlog;
+mod savefile;
+mod sight;
+mod skills;
+mod spawntables;
+mod spells;
+mod stats;
 mod tcoder;
+mod theme;
+mod tiles;
+mod transition;
+mod visibility;
+mod world;
 
-use game::Game;
+use difficulty::DIFFICULTIES;
+use game::{Game, GameMode, PLAYER};
+use keys::{HELP_WIDTH, PRIMER};
+use menuui::Menu;
 use object::Object;
+use options::{options_menu, Options};
+use profile::Profile;
 use std::{
-    cmp,
+    env,
     error::Error,
-    fs::File,
-    io::{Read, Write},
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Write},
+    time::SystemTime,
 };
-use tcoder::{Tcod, INVENTORY_WIDTH, SCREEN_HEIGHT, SCREEN_WIDTH};
-
-const LIMIT_FPS: i32 = 20; // 20 frames-per-second maximum
+use tcoder::Tcod;
+use visibility::sees;
 
 fn main() {
-    tcod::system::set_fps(LIMIT_FPS);
+    let options = Options::load();
+    tcod::system::set_fps(options.limit_fps);
+    audio::configure(&options);
 
-    let mut tcod = Tcod::new();
+    let mut tcod = Tcod::new(options);
 
-    main_menu(&mut tcod);
+    main_menu(&mut tcod, seed_from_args());
 }
 
-/// Mutably borrow two *separate* elements from the given slice.
-/// Panics when the indexes are equal or out of bounds.
-pub fn mut_two<T>(first_index: usize, second_index: usize, items: &mut [T]) -> (&mut T, &mut T) {
-    assert!(first_index != second_index);
-    let split_at_index = cmp::max(first_index, second_index);
-    let (first_slice, second_slice) = items.split_at_mut(split_at_index);
-    if first_index < second_index {
-        (&mut first_slice[first_index], &mut second_slice[0])
-    } else {
-        (&mut second_slice[0], &mut first_slice[second_index])
-    }
+/// the seed given with `--seed <number>`, for every new game to be generated from
+fn seed_from_args() -> Option<u64> {
+    let args: Vec<String> = env::args().collect();
+    args.iter()
+        .position(|arg| arg == "--seed")
+        .and_then(|i| args.get(i + 1))
+        .and_then(|seed| seed.parse().ok())
 }
 
+#[allow(clippy::too_many_arguments)]
 fn render_bar(
-    panel: &mut Offscreen,
+    panel: &mut dyn renderer::Renderer,
     x: i32,
     y: i32,
     total_width: i32,
@@ -82,148 +131,137 @@ fn render_bar(
     );
 }
 
-/// return a string with the names of all objects under the mouse
-fn get_names_under_mouse(mouse: Mouse, objects: &[Object], fov_map: &FovMap) -> String {
+/// write the names of all objects under the mouse into `names`
+fn get_names_under_mouse(mouse: Mouse, objects: &[Object], fov_map: &FovMap, names: &mut String) {
     let (x, y) = (mouse.cx as i32, mouse.cy as i32);
 
-    // create a list with the names of all objects at the mouse's coordinates and in FOV
-    let names = objects
+    // list the names of all objects at the mouse's coordinates and in FOV,
+    // separated by commas
+    names.clear();
+    let under_mouse = objects
         .iter()
-        .filter(|obj| obj.pos() == (x, y) && fov_map.is_in_fov(obj.x, obj.y))
-        .map(|obj| obj.name.clone())
-        .collect::<Vec<_>>();
-
-    names.join(", ") // join the names, separated by commas
-}
-
-fn menu<T: AsRef<str>>(header: &str, options: &[T], width: i32, root: &mut Root) -> Option<usize> {
-    assert!(
-        options.len() <= 26,
-        "Cannot have a menu with more than 26 options."
-    );
-    // calculate total height for the header (after auto-wrap) and one line per option
-    // calculate total height for the header (after auto-wrap) and one line per option
-    let header_height = if header.is_empty() {
-        0
-    } else {
-        root.get_height_rect(0, 0, width, SCREEN_HEIGHT, header)
-    };
-    let height = options.len() as i32 + header_height;
-
-    // create an off-screen console that represents the menu's window
-    let mut window = Offscreen::new(width, height);
-
-    // print the header, with auto-wrap
-    window.set_default_foreground(WHITE);
-    window.print_rect_ex(
-        0,
-        0,
-        width,
-        height,
-        BackgroundFlag::None,
-        TextAlignment::Left,
-        header,
-    );
-
-    // print all the options
-    for (index, option_text) in options.iter().enumerate() {
-        let menu_letter = (b'a' + index as u8) as char;
-        let text = format!("({}) {}", menu_letter, option_text.as_ref());
-        window.print_ex(
-            0,
-            header_height + index as i32,
-            BackgroundFlag::None,
-            TextAlignment::Left,
-            text,
-        );
-    }
-
-    // blit the contents of "window" to the root console
-    let x = SCREEN_WIDTH / 2 - width / 2;
-    let y = SCREEN_HEIGHT / 2 - height / 2;
-    blit(&window, (0, 0), (width, height), root, (x, y), 1.0, 0.7);
-
-    root.flush();
-    let key = root.wait_for_keypress(true);
-    // convert the ASCII code to an index; if it corresponds to an option, return it
-    if key.printable.is_alphabetic() {
-        let index = key.printable.to_ascii_lowercase() as usize - 'a' as usize;
-        if index < options.len() {
-            Some(index)
-        } else {
-            None
+        .filter(|obj| obj.pos() == (x, y) && sees(fov_map, &objects[PLAYER], obj));
+    for obj in under_mouse {
+        if !names.is_empty() {
+            names.push_str(", ");
         }
-    } else {
-        None
+        names.push_str(&obj.name);
     }
 }
 
-fn inventory_menu(inventory: &[Object], header: &str, root: &mut Root) -> Option<usize> {
-    // how a menu with each item of the inventory as an option
-    let options = if inventory.len() == 0 {
-        vec!["Inventory is empty.".into()]
-    } else {
-        inventory.iter().map(|item| item.name.clone()).collect()
-    };
-
-    let inventory_index = menu(header, &options, INVENTORY_WIDTH, root);
-
-    // if an item was chosen, return it
-    if inventory.len() > 0 {
-        inventory_index
-    } else {
-        None
-    }
+fn menu<T: AsRef<str>>(header: &str, options: &[T], width: i32, root: &mut Root) -> Option<usize> {
+    Menu::new(header, width).entries(options).show(root)
 }
 
-fn main_menu(tcod: &mut Tcod) {
-    let img = tcod::image::Image::from_file("menu_background.png")
-        .ok()
-        .expect("Background image not found");
+fn main_menu(tcod: &mut Tcod, seed: Option<u64>) {
+    let img =
+        tcod::image::Image::from_file("menu_background.png").expect("Background image not found");
 
+    let (width, height) = (tcod.layout.screen_width, tcod.layout.screen_height);
     while !tcod.root.window_closed() {
         // show the background image, at twice the regular console resolution
         tcod::image::blit_2x(&img, (0, 0), (-1, -1), &mut tcod.root, (0, 0));
 
         tcod.root.set_default_foreground(LIGHT_YELLOW);
         tcod.root.print_ex(
-            SCREEN_WIDTH / 2,
-            SCREEN_HEIGHT / 2 - 4,
+            width / 2,
+            height / 2 - 4,
             BackgroundFlag::None,
             TextAlignment::Center,
             "TOMBS OF THE ANCIENT KINGS",
         );
+        let profile = Profile::load();
+        tcod.root.set_default_foreground(LIGHT_GREY);
+        tcod.root.print_ex(
+            width / 2,
+            height / 2 - 2,
+            BackgroundFlag::None,
+            TextAlignment::Center,
+            profile.summary(),
+        );
         tcod.root.print_ex(
-            SCREEN_WIDTH / 2,
-            SCREEN_HEIGHT - 2,
+            width / 2,
+            height - 2,
             BackgroundFlag::None,
             TextAlignment::Center,
             "By Yours Truly",
         );
 
         // show options and wait for the player's choice
-        let choices = &["Play a new game", "Continue last game", "Quit"];
+        let choices = &["Play a new game", "Continue last game", "Options", "Quit"];
         let choice = menu("", choices, 24, &mut tcod.root);
 
         match choice {
             Some(0) => {
-                // new game
-                let mut game = Game::new(tcod);
+                // new game, after choosing how death is handled
+                let modes = &[
+                    "Permadeath: death is the end",
+                    "Explorer: respawn at the last checkpoint",
+                ];
+                let mode = match menu("Choose your fate:\n", modes, 40, &mut tcod.root) {
+                    Some(0) => GameMode::Permadeath,
+                    Some(1) => GameMode::Explorer,
+                    _ => continue,
+                };
+                let choices: Vec<_> = DIFFICULTIES.iter().map(|d| d.description()).collect();
+                let difficulty =
+                    match menu("Choose your difficulty:\n", &choices, 50, &mut tcod.root) {
+                        Some(choice) => DIFFICULTIES[choice],
+                        None => continue,
+                    };
+                let classes = profile.classes();
+                let choices: Vec<_> = classes.iter().map(|class| class.description()).collect();
+                let mut header = String::from("Choose your class:\n");
+                if !profile.locked().is_empty() {
+                    header = format!("Locked: {}\n\n{}", profile.locked().join(", "), header);
+                }
+                let class = match menu(&header, &choices, 50, &mut tcod.root) {
+                    Some(choice) => classes[choice],
+                    None => continue,
+                };
+                // the autosave left by an earlier run would be taken for this one's
+                let _ = fs::remove_file(AUTOSAVE_FILE);
+                let seed = seed.unwrap_or_else(rand::random);
+                let mut game = Game::new(tcod, mode, difficulty, class, seed);
+                if !tcod.options.seen_primer {
+                    msgbox(PRIMER, HELP_WIDTH, &mut tcod.root);
+                    tcod.options.seen_primer = true;
+                    let _ = tcod.options.save();
+                }
                 game.play(tcod);
             }
             Some(1) => {
                 // load game
                 match load_game() {
+                    Ok(game) if game.mode == GameMode::Permadeath && is_buried(game.run_id) => {
+                        // a save put back after its character died
+                        msgbox(
+                            "\nThis character has died, and the dead stay dead.\n",
+                            24,
+                            &mut tcod.root,
+                        );
+                        delete_save().ok();
+                        continue;
+                    }
                     Ok(mut game) => {
                         game.play(tcod);
                     }
-                    Err(_e) => {
-                        msgbox("\nNo saved game to load.\n", 24, &mut tcod.root);
+                    Err(e) => {
+                        let not_found = e
+                            .downcast_ref::<io::Error>()
+                            .is_some_and(|e| e.kind() == io::ErrorKind::NotFound);
+                        if not_found {
+                            msgbox("\nNo saved game to load.\n", 24, &mut tcod.root);
+                        } else {
+                            let msg = format!("\nThe saved game could not be loaded: {}.\n", e);
+                            msgbox(&msg, 40, &mut tcod.root);
+                        }
                         continue;
                     }
                 }
             }
-            Some(2) => {
+            Some(2) => options_menu(tcod),
+            Some(3) => {
                 // quit
                 break;
             }
@@ -237,17 +275,122 @@ fn msgbox(text: &str, width: i32, root: &mut Root) {
     menu(text, options, width, root);
 }
 
+// the save, and the save before it
+const SAVE_FILE: &str = "savegame";
+const BACKUP_FILE: &str = "savegame.bak";
+// the slot saved to while playing, in case the game never gets to save on exit
+const AUTOSAVE_FILE: &str = "autosave";
+
+/// the file written in place of the one at `path`, before it is put there
+fn temp_path(path: &str) -> String {
+    format!("{}.tmp", path)
+}
+
+/// Write the contents next to the file first, synced to the disk, and only then
+/// put them in its place, so a crash while writing leaves the file as it was
+pub fn write_atomically(path: &str, contents: &[u8]) -> Result<(), Box<dyn Error>> {
+    let temp = temp_path(path);
+    let mut file = File::create(&temp)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    fs::rename(temp, path)?;
+    Ok(())
+}
+
+/// write the game in place of the file at `path`, as gzipped JSON
+fn write_save(game: &Game, path: &str) -> Result<(), Box<dyn Error>> {
+    let json = savefile::to_json(game)?;
+    write_atomically(path, &gzip::compress(json.as_bytes()))
+}
+
+/// Save the game, keeping the old save as the backup. Should the new one fail
+/// to be written, the backup is what gets loaded.
 fn save_game(game: &Game) -> Result<(), Box<dyn Error>> {
-    let save_data = serde_json::to_string(game)?;
-    let mut file = File::create("savegame")?;
-    file.write_all(save_data.as_bytes())?;
+    match fs::rename(SAVE_FILE, BACKUP_FILE) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+        _ => {}
+    }
+    write_save(game, SAVE_FILE)
+}
+
+/// save the game to the autosave slot, leaving the regular save alone
+fn autosave(game: &Game) -> Result<(), Box<dyn Error>> {
+    write_save(game, AUTOSAVE_FILE)
+}
+
+/// remove the saved game, its backup and the autosave once the run they belong
+/// to is over, along with any left half written
+fn delete_save() -> io::Result<()> {
+    for path in &[SAVE_FILE, BACKUP_FILE, AUTOSAVE_FILE] {
+        for path in &[path.to_string(), temp_path(path)] {
+            match fs::remove_file(path) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                _ => {}
+            }
+        }
+    }
     Ok(())
 }
 
-fn load_game() -> Result<Game, Box<dyn Error>> {
-    let mut json_save_state = String::new();
-    let mut file = File::open("savegame")?;
-    file.read_to_string(&mut json_save_state)?;
-    let result = serde_json::from_str::<Game>(&json_save_state)?;
+/// append a line describing a death to the score file
+fn record_score(line: &str) -> Result<(), Box<dyn Error>> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open("scores")?;
+    writeln!(file, "{}", line)?;
+    Ok(())
+}
+
+// the runs whose permadeath character has died
+const GRAVEYARD_FILE: &str = "graveyard";
+
+/// remember that the character of a run died, so its saves can't be played on
+fn bury(run_id: u64) -> io::Result<()> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(GRAVEYARD_FILE)?;
+    writeln!(file, "{}", run_id)
+}
+
+/// whether the character of a run has died, never for saves without a run id
+fn is_buried(run_id: u64) -> bool {
+    run_id != 0
+        && fs::read_to_string(GRAVEYARD_FILE)
+            .map(|graveyard| graveyard.lines().any(|line| line == run_id.to_string()))
+            .unwrap_or(false)
+}
+
+/// read the game from the file, gzipped or, as older releases saved it, plain
+fn read_save(path: &str) -> Result<Game, Box<dyn Error>> {
+    let mut save_state = vec![];
+    let mut file = File::open(path)?;
+    file.read_to_end(&mut save_state)?;
+    if gzip::is_gzip(&save_state) {
+        save_state = gzip::decompress(&save_state)?;
+    }
+    let result = savefile::from_json(&String::from_utf8(save_state)?)?;
     Ok(result)
 }
+
+/// when the file was last written, None if there is no such file
+fn modified(path: &str) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+/// The saved game, or the one saved before it if the last save can't be read.
+/// An autosave written after the last save means the game ended without saving,
+/// and is loaded instead.
+fn load_game() -> Result<Game, Box<dyn Error>> {
+    if modified(AUTOSAVE_FILE) > modified(SAVE_FILE) {
+        if let Ok(game) = read_save(AUTOSAVE_FILE) {
+            return Ok(game);
+        }
+    }
+    read_save(SAVE_FILE)
+        .or_else(|e| read_save(BACKUP_FILE).map_err(|_| e))
+        .or_else(|e| read_save(AUTOSAVE_FILE).map_err(|_| e))
+}
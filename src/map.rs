@@ -1,4 +1,4 @@
-use crate::rect::Rect;
+use crate::{groundeffects::GroundKind, rect::Rect};
 use serde::{Deserialize, Serialize};
 use std::cmp;
 /// A tile of the map and its properties
@@ -7,6 +7,72 @@ pub struct Tile {
     pub blocked: bool,
     pub block_sight: bool,
     pub explored: bool,
+    /// the turn the tile was last in view, None if it never was
+    #[serde(default)]
+    pub last_seen: Option<i32>,
+    #[serde(default)]
+    pub door: Option<Door>,
+    #[serde(default)]
+    pub terrain: Terrain,
+    /// inside a vault, which no blink lands in
+    #[serde(default)]
+    pub vault: bool,
+}
+
+/// What covers the ground of a tile that isn't wall
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Terrain {
+    #[default]
+    Floor,
+    /// wading through it slows the player down
+    ShallowWater,
+    /// swum through as slowly, soaking the scrolls carried
+    DeepWater,
+    /// burns whoever stands in it
+    Lava,
+    /// whoever steps in falls to the level below
+    Chasm,
+}
+
+impl Terrain {
+    pub fn description(self) -> &'static str {
+        match self {
+            Terrain::Floor => "The dungeon floor.",
+            Terrain::ShallowWater => "Shallow water, slow to wade through.",
+            Terrain::DeepWater => "Deep water, to be swum through.",
+            Terrain::Lava => "Bubbling lava.",
+            Terrain::Chasm => "A chasm, falling away to the level below.",
+        }
+    }
+
+    /// whether it is dangerous to be in; monsters keep out of it
+    pub fn is_perilous(self) -> bool {
+        matches!(self, Terrain::DeepWater | Terrain::Lava | Terrain::Chasm)
+    }
+
+    /// whether the player moves through it at half speed
+    pub fn slows(self) -> bool {
+        matches!(self, Terrain::ShallowWater | Terrain::DeepWater)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Door {
+    Closed,
+    Open,
+    /// smashed to splinters, it won't close anymore
+    Broken,
+    /// shut until the player unlocks it with a key, nobody else gets through
+    Locked,
+    /// passing for a stretch of wall until somebody finds it, a closed door after
+    Secret,
+}
+
+impl Door {
+    /// whether the door is in the way
+    pub fn is_shut(self) -> bool {
+        matches!(self, Door::Closed | Door::Locked | Door::Secret)
+    }
 }
 
 impl Tile {
@@ -15,6 +81,10 @@ impl Tile {
             blocked: false,
             block_sight: false,
             explored: false,
+            last_seen: None,
+            door: None,
+            terrain: Terrain::Floor,
+            vault: false,
         }
     }
 
@@ -23,8 +93,59 @@ impl Tile {
             blocked: true,
             block_sight: true,
             explored: false,
+            last_seen: None,
+            door: None,
+            terrain: Terrain::Floor,
+            vault: false,
+        }
+    }
+
+    pub fn door() -> Self {
+        Tile {
+            blocked: true,
+            block_sight: true,
+            explored: false,
+            last_seen: None,
+            door: Some(Door::Closed),
+            terrain: Terrain::Floor,
+            vault: false,
+        }
+    }
+
+    pub fn locked_door() -> Self {
+        Tile {
+            door: Some(Door::Locked),
+            ..Tile::door()
+        }
+    }
+
+    pub fn secret_door() -> Self {
+        Tile {
+            door: Some(Door::Secret),
+            ..Tile::door()
         }
     }
+
+    /// whether monsters find their way across the tile: closed doors they open or
+    /// smash, but locked and secret ones keep them out, as does perilous terrain
+    pub fn monster_passable(&self) -> bool {
+        let door = matches!(self.door, Some(Door::Closed));
+        (!self.blocked || door) && !self.terrain.is_perilous()
+    }
+
+    /// whether fire or gas can lie on the tile: anywhere open, though fire doesn't
+    /// take on water
+    pub fn holds(&self, kind: GroundKind) -> bool {
+        let water = matches!(self.terrain, Terrain::ShallowWater | Terrain::DeepWater);
+        !self.blocked && (kind != GroundKind::Fire || !water)
+    }
+
+    /// change the state of the door on this tile, only shut doors are in the way
+    pub fn set_door(&mut self, door: Door) {
+        self.door = Some(door);
+        self.blocked = door.is_shut();
+        self.block_sight = door.is_shut();
+    }
 }
 
 pub type Map = Vec<Vec<Tile>>;
@@ -1,27 +1,173 @@
 use crate::{
+    ai::Ai,
+    blessings::create_altar,
+    dungeon::{Branch, LevelId},
     game::{is_blocked, PLAYER},
-    item::create_item,
-    map::{create_h_tunnel, create_room, create_v_tunnel, Map, Tile},
-    monsters::create_monster,
+    item::{create_item, item_object, random_loot, Item},
+    map::{create_h_tunnel, create_room, create_v_tunnel, Map, Terrain, Tile},
+    monsters::{create_monster, monster_by_name, put_to_sleep},
     object::Object,
+    prefabs::prefabs,
     rect::Rect,
     tcoder::{MAP_HEIGHT, MAP_WIDTH},
+    theme::Theme,
 };
-use rand::Rng;
-use serde::{Deserialize, Serialize};
-use tcod::colors::WHITE;
+use rand::{Rng, SeedableRng, XorShiftRng};
+use tcod::colors::{GOLD, WHITE};
+
+//parameters for dungeon generator
+const ROOM_MAX_SIZE: i32 = 10;
+const ROOM_MIN_SIZE: i32 = 6;
+const MAX_ROOMS: i32 = 30;
+
+const MAX_ROOM_MONSTERS: i32 = 3;
+const MAX_ROOM_ITEMS: i32 = 2;
+const DOOR_CHANCE: f32 = 0.5;
+// chance for a door to be hidden in the wall, to be searched for
+const SECRET_DOOR_CHANCE: f32 = 0.1;
+// chance for a room to hold a pile of gold, and the coins in it per dungeon level
+const GOLD_CHANCE: f32 = 0.3;
+const GOLD_PER_LEVEL: i32 = 10;
+// chance for a monster to patrol between rooms, or to wander about its own, instead
+// of sleeping, and the rooms a patrol walks through before turning back
+const PATROL_CHANCE: f32 = 0.15;
+const WANDER_CHANCE: f32 = 0.25;
+const PATROL_ROOMS: usize = 3;
+// chance for a monster to carry an item, dropped when it dies
+const LOOT_CHANCE: f32 = 0.1;
+// chance for a level to hide a vault behind locked doors, its size walls included,
+// the items in it and how much luckier they are than what lies about
+const VAULT_CHANCE: f32 = 0.25;
+const VAULT_SIZE: i32 = 6;
+const VAULT_TRIES: i32 = 50;
+const VAULT_ITEMS: i32 = 3;
+const VAULT_LUCK: i32 = 2;
+const VAULT_GOLD_FACTOR: i32 = 3;
+// chance for a monster to carry the key to the vault, rather than it lying about
+const KEY_CARRIED_CHANCE: f32 = 0.5;
+// chance for a room to hold a lake, and for a stream to run across the level
+const LAKE_CHANCE: f32 = 0.1;
+const RIVER_CHANCE: f32 = 0.3;
+// chance for a level to have an altar
+const ALTAR_CHANCE: f32 = 0.2;
+// tries at stamping a prefab room into a level, and the chance of each
+const PREFAB_TRIES: i32 = 2;
+const PREFAB_CHANCE: f32 = 0.3;
+// the depth of the main dungeon from which on it is drawn in the colors of the
+// depths below
+const HELL_DEPTH: u32 = 8;
 
-#[derive(Serialize, Deserialize)]
 pub struct MapBuilder {
+    pub level: LevelId,
     pub max_rooms: i32,
     pub room_min_size: i32,
     pub room_max_size: i32,
     pub max_room_monsters: i32,
     pub max_room_items: i32,
+    /// chance for each doorway into a room to get a door
+    pub door_chance: f32,
+    /// chance for each door to be a secret one
+    pub secret_door_chance: f32,
+    /// branches whose boss has already been slain, the Ancient King's death opens
+    /// the postgame branches
+    pub bosses_slain: Vec<Branch>,
+    /// the player's luck, biasing the items found
+    pub luck: i32,
+    /// chance for each room to hold a lake, and what the lakes are of
+    pub lake_chance: f32,
+    pub lake: Terrain,
+    /// chance for a stream of shallow water to run across the level
+    pub river_chance: f32,
+    /// chance for each try at a prefab room to stamp one
+    pub prefab_chance: f32,
+    /// what the layout is generated from, the same seed always giving the same
+    /// level
+    pub seed: [u32; 4],
+    /// the colors the level is drawn in
+    pub theme: Theme,
 }
 
 impl MapBuilder {
-    pub fn build(&self, objects: &mut Vec<Object>) -> Map {
+    /// a builder for the given level, each branch has a layout of its own
+    pub fn for_level(level: LevelId) -> Self {
+        let builder = MapBuilder {
+            level,
+            max_rooms: MAX_ROOMS,
+            room_min_size: ROOM_MIN_SIZE,
+            room_max_size: ROOM_MAX_SIZE,
+            max_room_monsters: MAX_ROOM_MONSTERS,
+            max_room_items: MAX_ROOM_ITEMS,
+            door_chance: DOOR_CHANCE,
+            secret_door_chance: SECRET_DOOR_CHANCE,
+            bosses_slain: vec![],
+            luck: 0,
+            lake_chance: LAKE_CHANCE,
+            lake: Terrain::DeepWater,
+            river_chance: RIVER_CHANCE,
+            prefab_chance: PREFAB_CHANCE,
+            seed: level.seed(0, 0),
+            theme: Theme::Stone,
+        };
+        match level.branch {
+            // the town is laid out by hand, see `world::build_town`
+            Branch::Town => builder,
+            Branch::Main if level.depth >= HELL_DEPTH => MapBuilder {
+                theme: Theme::Hell,
+                ..builder
+            },
+            Branch::Main => builder,
+            // a warren of small, doorless burrows, and shafts sunk deeper
+            Branch::Mines => MapBuilder {
+                max_rooms: 50,
+                room_min_size: 4,
+                room_max_size: 7,
+                max_room_monsters: 2,
+                door_chance: 0.0,
+                lake: Terrain::Chasm,
+                river_chance: 0.1,
+                theme: Theme::Ice,
+                ..builder
+            },
+            // few, large halls shut behind heavy doors, dry as bone
+            Branch::Crypt => MapBuilder {
+                max_rooms: 20,
+                room_min_size: 8,
+                room_max_size: 12,
+                max_room_monsters: 4,
+                door_chance: 0.9,
+                lake_chance: 0.0,
+                river_chance: 0.0,
+                theme: Theme::Bone,
+                ..builder
+            },
+            // sprawling caverns teeming with horrors, around pools of lava
+            Branch::Abyss => MapBuilder {
+                max_rooms: 40,
+                room_min_size: 7,
+                room_max_size: 12,
+                max_room_monsters: 5,
+                max_room_items: 3,
+                door_chance: 0.0,
+                lake_chance: 0.2,
+                lake: Terrain::Lava,
+                river_chance: 0.0,
+                theme: Theme::Hell,
+                ..builder
+            },
+        }
+    }
+
+    fn postgame(&self) -> bool {
+        self.bosses_slain.contains(&Branch::Main)
+    }
+
+    /// Generate the level, returning its map and the feelings the player gets
+    /// on arriving, hinting at what is unusual about it
+    pub fn build(&self, objects: &mut Vec<Object>) -> (Map, Vec<&'static str>) {
+        // the level's own random number stream, untouched by anything the player
+        // does
+        let mut rng = XorShiftRng::from_seed(self.seed);
+
         // fill map with "unblocked" tiles
         let mut map = vec![vec![Tile::wall(); MAP_HEIGHT as usize]; MAP_WIDTH as usize];
 
@@ -29,16 +175,19 @@ impl MapBuilder {
         // NOTE: works only when the player is the first object!
         assert_eq!(&objects[PLAYER] as *const _, &objects[0] as *const _);
         objects.truncate(1);
+        // park the player in the rock until the first room is dug, so where they
+        // stood on the last level doesn't change how this one is filled
+        objects[PLAYER].set_pos(0, 0);
 
         let mut rooms = vec![];
 
         for _ in 0..self.max_rooms {
             // random width and height
-            let w = rand::thread_rng().gen_range(self.room_min_size, self.room_max_size + 1);
-            let h = rand::thread_rng().gen_range(self.room_min_size, self.room_max_size + 1);
+            let w = rng.gen_range(self.room_min_size, self.room_max_size + 1);
+            let h = rng.gen_range(self.room_min_size, self.room_max_size + 1);
             // random position without going out of the boundaries of the map
-            let x = rand::thread_rng().gen_range(0, MAP_WIDTH - w);
-            let y = rand::thread_rng().gen_range(0, MAP_HEIGHT - h);
+            let x = rng.gen_range(0, MAP_WIDTH - w);
+            let y = rng.gen_range(0, MAP_HEIGHT - h);
 
             let new_room = Rect::new(x, y, w, h);
 
@@ -52,7 +201,7 @@ impl MapBuilder {
 
                 // "paint" it to the map's tiles
                 create_room(new_room, &mut map);
-                self.place_objects(new_room, &mut map, objects);
+                self.place_objects(new_room, &mut map, objects, &mut rng);
 
                 // center coordinates of the new room, will be useful later
                 let (new_x, new_y) = new_room.center();
@@ -69,7 +218,7 @@ impl MapBuilder {
                     let (prev_x, prev_y) = rooms[rooms.len() - 1].center();
 
                     // toss a coin (random bool value -- either true or false)
-                    if rand::random() {
+                    if rng.gen() {
                         // first move horizontally, then vertically
                         create_h_tunnel(prev_x, new_x, prev_y, &mut map);
                         create_v_tunnel(prev_y, new_y, new_x, &mut map);
@@ -85,41 +234,427 @@ impl MapBuilder {
             }
         }
 
-        // create stairs at the center of the last room
+        let mut feelings = vec![];
+        let prefab_rooms = self.place_prefabs(&rooms, &mut map, objects, &mut feelings, &mut rng);
+        self.place_doors(&rooms, &mut map, &mut rng);
+        if rooms.len() > 1 && rng.gen::<f32>() < VAULT_CHANCE {
+            self.place_vault(&rooms, &mut map, objects, &mut rng);
+            feelings.push("You hear the clink of coins behind a locked door.");
+        }
+        if rooms.len() > 2 && rng.gen::<f32>() < ALTAR_CHANCE {
+            // in a corner of one of the rooms between the stairs, out of the way
+            let room = rooms[rng.gen_range(1, rooms.len() - 1)];
+            let (x, y) = (room.x1 + 1, room.y1 + 1);
+            let floor = map[x as usize][y as usize].terrain == Terrain::Floor;
+            if floor && !is_blocked(x, y, &map, objects) {
+                objects.push(create_altar(x, y));
+                feelings.push("You sense a holy presence on this level.");
+            }
+        }
+
+        // patrols walk the corridors from the room they were placed in through the
+        // rooms dug next to it, each joined to the one before, and back the same way
+        for object in objects.iter_mut() {
+            let (x, y) = object.pos();
+            let start = rooms.iter().position(|room| room.contains(x, y));
+            if let (Some(Ai::Patrol { waypoints, .. }), Some(start)) = (object.ai.as_mut(), start) {
+                let route: Vec<usize> = if start + PATROL_ROOMS < rooms.len() {
+                    (start + 1..=start + PATROL_ROOMS).collect()
+                } else {
+                    (start.saturating_sub(PATROL_ROOMS)..start).rev().collect()
+                };
+                waypoints.extend(route.iter().map(|&room| rooms[room].center()));
+                let back = route.iter().rev().skip(1);
+                waypoints.extend(back.map(|&room| rooms[room].center()));
+            }
+        }
+
+        // up-stairs where the player starts, down-stairs at the center of the last
+        // room and the stairs into side branches in the rooms between
+        if let Some(up) = self.level.up() {
+            let (x, y) = rooms[0].center();
+            objects.push(create_stairs(x, y, up, '<', "stairs up"));
+        }
         let (last_room_x, last_room_y) = rooms[rooms.len() - 1].center();
-        let mut stairs = Object::new(last_room_x, last_room_y, '<', "stairs", WHITE, false);
-        stairs.always_visible = true;
-        objects.push(stairs);
+        match (self.level.down(), self.level.branch.boss()) {
+            (Some(down), _) => objects.push(create_stairs(
+                last_room_x,
+                last_room_y,
+                down,
+                '>',
+                "stairs down",
+            )),
+            // the bottom of some branches is guarded
+            (None, Some(boss)) if !self.bosses_slain.contains(&self.level.branch) => {
+                let (x, y) = if rooms.len() > 1 {
+                    // clear the throne room's center of whatever stands there
+                    objects.retain(|object| {
+                        object.pos() != (last_room_x, last_room_y) || !object.blocks
+                    });
+                    (last_room_x, last_room_y)
+                } else {
+                    (last_room_x + 1, last_room_y)
+                };
+                let mut boss = monster_by_name(boss, x, y).unwrap();
+                // the Ancient King guards the amulet the player came for
+                if self.level.branch == Branch::Main {
+                    boss.loot = Some(Item::Amulet);
+                }
+                objects.push(boss)
+            }
+            (None, _) => {}
+        }
+        let branches = self
+            .level
+            .branches()
+            .into_iter()
+            .filter(|branch| self.postgame() || !branch.branch.is_postgame());
+        for (i, branch) in branches.enumerate() {
+            let (x, y) = match rooms.len() {
+                n if n > 2 => rooms[1 + i % (n - 2)].center(),
+                // too few rooms to spare one, squeeze it next to the up-stairs
+                _ => {
+                    let (x, y) = rooms[0].center();
+                    (x + 1 + i as i32, y)
+                }
+            };
+            let name = format!("stairs to the {}", branch.branch.name());
+            objects.push(create_stairs(x, y, branch, '>', &name));
+        }
+
+        // the prefabs are laid out as they are, no lake drowns them
+        let open_rooms: Vec<Rect> = rooms
+            .iter()
+            .filter(|room| !prefab_rooms.contains(room))
+            .cloned()
+            .collect();
+        self.place_terrain(&open_rooms, &mut map, objects, &mut rng);
+        (map, feelings)
+    }
+
+    /// Stamp a prefab into a room or two that fits it, each prefab once at most,
+    /// adding its feeling. The first room, the last and those the stairs into
+    /// the branches go in are left alone. Returns the rooms stamped
+    fn place_prefabs(
+        &self,
+        rooms: &[Rect],
+        map: &mut Map,
+        objects: &mut Vec<Object>,
+        feelings: &mut Vec<&'static str>,
+        rng: &mut XorShiftRng,
+    ) -> Vec<Rect> {
+        let mut stamped = vec![];
+        let first_free = 1 + self.level.branches().len();
+        if rooms.len() <= first_free + 1 {
+            return stamped;
+        }
+        let mut prefabs: Vec<_> = prefabs()
+            .into_iter()
+            .filter(|prefab| prefab.found_in(self.level))
+            .collect();
+        for _ in 0..PREFAB_TRIES {
+            if prefabs.is_empty() || rng.gen::<f32>() >= self.prefab_chance {
+                continue;
+            }
+            let prefab = prefabs.remove(rng.gen_range(0, prefabs.len()));
+            let fitting: Vec<Rect> = rooms[first_free..rooms.len() - 1]
+                .iter()
+                .filter(|&room| prefab.fits(*room) && !stamped.contains(room))
+                .cloned()
+                .collect();
+            if let Some(&room) = rng.choose(&fitting) {
+                prefab.stamp(room, self.level, self.luck, map, objects, rng);
+                feelings.push(prefab.feeling);
+                stamped.push(room);
+            }
+        }
+        stamped
+    }
+
+    /// Flood some of the rooms with lakes and run a stream across the level
+    fn place_terrain(
+        &self,
+        rooms: &[Rect],
+        map: &mut Map,
+        objects: &[Object],
+        rng: &mut XorShiftRng,
+    ) {
+        // there is nowhere to fall to from the bottom of a branch
+        let lake = match self.lake {
+            Terrain::Chasm if self.level.down().is_none() => None,
+            lake => Some(lake),
+        };
+        for &room in rooms {
+            if let Some(lake) = lake.filter(|_| rng.gen::<f32>() < self.lake_chance) {
+                place_lake(room, lake, map, objects, rng);
+            }
+        }
+        if rng.gen::<f32>() < self.river_chance {
+            place_river(map, rng);
+        }
+    }
+
+    fn place_doors(&self, rooms: &[Rect], map: &mut Map, rng: &mut XorShiftRng) {
+        // put doors in some of the gaps tunnels made in the room walls
+        for room in rooms {
+            for x in room.x1..(room.x2 + 1) {
+                for y in room.y1..(room.y2 + 1) {
+                    let on_wall = x == room.x1 || x == room.x2 || y == room.y1 || y == room.y2;
+                    if on_wall && is_doorway(x, y, map) && rng.gen::<f32>() < self.door_chance {
+                        map[x as usize][y as usize] = if rng.gen::<f32>() < self.secret_door_chance
+                        {
+                            Tile::secret_door()
+                        } else {
+                            Tile::door()
+                        };
+                    }
+                }
+            }
+        }
+    }
+
+    /// Dig a small treasure room out of solid rock next to the others, seal it with
+    /// locked doors and leave a key to each with a monster or lying in one of the
+    /// rooms
+    fn place_vault(
+        &self,
+        rooms: &[Rect],
+        map: &mut Map,
+        objects: &mut Vec<Object>,
+        rng: &mut XorShiftRng,
+    ) {
+        let vault = (0..VAULT_TRIES)
+            .map(|_| {
+                let x = rng.gen_range(0, MAP_WIDTH - VAULT_SIZE);
+                let y = rng.gen_range(0, MAP_HEIGHT - VAULT_SIZE);
+                Rect::new(x, y, VAULT_SIZE, VAULT_SIZE)
+            })
+            .find(|&vault| is_solid_rock(vault, map));
+        let vault = match vault {
+            Some(vault) => vault,
+            None => return,
+        };
+        create_room(vault, map);
+        for x in (vault.x1 + 1)..vault.x2 {
+            for y in (vault.y1 + 1)..vault.y2 {
+                map[x as usize][y as usize].vault = true;
+            }
+        }
 
-        map
+        // a tunnel to the closest room, and a locked door where it leaves the vault
+        let (x, y) = vault.center();
+        let (room_x, room_y) = rooms
+            .iter()
+            .map(|room| room.center())
+            .min_by_key(|&(room_x, room_y)| (room_x - x).pow(2) + (room_y - y).pow(2))
+            .unwrap();
+        create_h_tunnel(x, room_x, y, map);
+        create_v_tunnel(y, room_y, room_x, map);
+        // the tunnels may leave the vault more than once, every way out is locked
+        let mut locks = 0;
+        for wall_x in vault.x1..(vault.x2 + 1) {
+            for wall_y in vault.y1..(vault.y2 + 1) {
+                let on_wall = wall_x == vault.x1
+                    || wall_x == vault.x2
+                    || wall_y == vault.y1
+                    || wall_y == vault.y2;
+                if on_wall && !map[wall_x as usize][wall_y as usize].blocked {
+                    map[wall_x as usize][wall_y as usize] = Tile::locked_door();
+                    locks += 1;
+                }
+            }
+        }
+
+        // the treasure: a heap of gold and items better than the usual
+        self.place_gold(vault, VAULT_GOLD_FACTOR, map, objects, rng);
+        for _ in 0..VAULT_ITEMS {
+            let x = rng.gen_range(vault.x1 + 1, vault.x2);
+            let y = rng.gen_range(vault.y1 + 1, vault.y2);
+            if !is_blocked(x, y, map, objects) {
+                objects.push(create_item(x, y, self.level, self.luck + VAULT_LUCK, rng));
+            }
+        }
+
+        // the keys, each carried by a monster that has nothing else to drop or
+        // lying in a room
+        for _ in 0..locks {
+            let carriers: Vec<usize> = (0..objects.len())
+                .filter(|&id| objects[id].ai.is_some() && objects[id].loot.is_none())
+                .collect();
+            match rng.choose(&carriers) {
+                Some(&id) if rng.gen::<f32>() < KEY_CARRIED_CHANCE => {
+                    objects[id].loot = Some(Item::Key);
+                }
+                _ => {
+                    let room = rooms[rng.gen_range(0, rooms.len())];
+                    let (x, y) = (0..VAULT_TRIES)
+                        .map(|_| {
+                            let x = rng.gen_range(room.x1 + 1, room.x2);
+                            let y = rng.gen_range(room.y1 + 1, room.y2);
+                            (x, y)
+                        })
+                        .find(|&(x, y)| !is_blocked(x, y, map, objects))
+                        .unwrap_or_else(|| room.center());
+                    objects.push(item_object(Item::Key, x, y, self.luck, rng));
+                }
+            }
+        }
     }
 
-    fn place_objects(&self, room: Rect, map: &mut Map, objects: &mut Vec<Object>) {
+    /// a pile of gold somewhere in the room, the deeper the richer
+    fn place_gold(
+        &self,
+        room: Rect,
+        factor: i32,
+        map: &Map,
+        objects: &mut Vec<Object>,
+        rng: &mut XorShiftRng,
+    ) {
+        let x = rng.gen_range(room.x1 + 1, room.x2);
+        let y = rng.gen_range(room.y1 + 1, room.y2);
+        if !is_blocked(x, y, map, objects) {
+            let most = factor * GOLD_PER_LEVEL * self.level.absolute_depth() as i32;
+            let mut gold = Object::new(x, y, '$', "pile of gold", GOLD, false);
+            gold.gold = Some(rng.gen_range(most / 2, most + 1));
+            objects.push(gold);
+        }
+    }
+
+    fn place_objects(
+        &self,
+        room: Rect,
+        map: &mut Map,
+        objects: &mut Vec<Object>,
+        rng: &mut XorShiftRng,
+    ) {
+        if rng.gen::<f32>() < GOLD_CHANCE {
+            self.place_gold(room, 1, map, objects, rng);
+        }
+
         // choose random number of monsters
-        let num_monsters = rand::thread_rng().gen_range(0, self.max_room_monsters + 1);
+        let num_monsters = rng.gen_range(0, self.max_room_monsters + 1);
 
         for _ in 0..num_monsters {
             // choose random spot for this monster
-            let x = rand::thread_rng().gen_range(room.x1 + 1, room.x2);
-            let y = rand::thread_rng().gen_range(room.y1 + 1, room.y2);
+            let x = rng.gen_range(room.x1 + 1, room.x2);
+            let y = rng.gen_range(room.y1 + 1, room.y2);
 
+            // monsters sleep until disturbed, unless they are on patrol or wander
             if !is_blocked(x, y, map, objects) {
-                objects.push(create_monster(x, y));
+                let mut monster = create_monster(x, y, self.level, rng);
+                let roll = rng.gen::<f32>();
+                if monster.ai == Some(Ai::Basic) && roll < PATROL_CHANCE {
+                    monster.ai = Some(Ai::Patrol {
+                        waypoints: vec![(x, y)],
+                        next: 0,
+                    });
+                } else if monster.ai == Some(Ai::Basic) && roll < PATROL_CHANCE + WANDER_CHANCE {
+                    monster.ai = Some(Ai::Wander { home: room });
+                } else {
+                    put_to_sleep(&mut monster);
+                }
+                if monster.loot.is_none() && rng.gen::<f32>() < LOOT_CHANCE {
+                    monster.loot = Some(random_loot(self.level, self.luck, rng));
+                }
+                objects.push(monster);
             }
 
             // choose random number of items
-            let num_items = rand::thread_rng().gen_range(0, self.max_room_items + 1);
+            let num_items = rng.gen_range(0, self.max_room_items + 1);
 
             for _ in 0..num_items {
                 // choose random spot for this item
-                let x = rand::thread_rng().gen_range(room.x1 + 1, room.x2);
-                let y = rand::thread_rng().gen_range(room.y1 + 1, room.y2);
+                let x = rng.gen_range(room.x1 + 1, room.x2);
+                let y = rng.gen_range(room.y1 + 1, room.y2);
 
                 // only place it if the tile is not blocked
                 if !is_blocked(x, y, map, objects) {
-                    objects.push(create_item(x, y));
+                    objects.push(create_item(x, y, self.level, self.luck, rng));
                 }
             }
         }
     }
 }
+
+/// Fill the middle of the room with a lake, shallow around the edges if it is
+/// of deep water. It keeps a ring of floor around it so nothing gets cut off,
+/// leaves the tiles where something stands dry and stays out of rooms with stairs
+fn place_lake(room: Rect, lake: Terrain, map: &mut Map, objects: &[Object], rng: &mut XorShiftRng) {
+    let inside = |x: i32, y: i32| x > room.x1 && x < room.x2 && y > room.y1 && y < room.y2;
+    let has_stairs = objects
+        .iter()
+        .any(|object| object.stairs.is_some() && inside(object.x, object.y));
+    let radius = ((room.x2 - room.x1).min(room.y2 - room.y1) - 1) / 2 - 1;
+    if has_stairs || radius < 1 {
+        return;
+    }
+    let (center_x, center_y) = room.center();
+    for x in (room.x1 + 1)..room.x2 {
+        for y in (room.y1 + 1)..room.y2 {
+            // a ragged shore
+            let distance = (((x - center_x).pow(2) + (y - center_y).pow(2)) as f32).sqrt()
+                - rng.gen::<f32>() * 0.5;
+            let terrain = match lake {
+                _ if distance > radius as f32 => continue,
+                Terrain::DeepWater if distance > (radius - 1) as f32 => Terrain::ShallowWater,
+                lake => lake,
+            };
+            if !objects.iter().any(|object| object.pos() == (x, y)) {
+                map[x as usize][y as usize].terrain = terrain;
+            }
+        }
+    }
+}
+
+/// Run a stream of shallow water across the map, wandering as it goes. It only
+/// floods the floor, it doesn't dig
+fn place_river(map: &mut Map, rng: &mut XorShiftRng) {
+    let vertical = rng.gen();
+    let (length, breadth) = if vertical {
+        (MAP_HEIGHT, MAP_WIDTH)
+    } else {
+        (MAP_WIDTH, MAP_HEIGHT)
+    };
+    let mut across = rng.gen_range(breadth / 4, breadth * 3 / 4);
+    for along in 0..length {
+        across = (across + rng.gen_range(-1, 2)).clamp(1, breadth - 3);
+        for width in 0..2 {
+            let (x, y) = if vertical {
+                (across + width, along)
+            } else {
+                (along, across + width)
+            };
+            let tile = &mut map[x as usize][y as usize];
+            if !tile.blocked && tile.terrain == Terrain::Floor {
+                tile.terrain = Terrain::ShallowWater;
+            }
+        }
+    }
+}
+
+pub fn create_stairs(x: i32, y: i32, destination: LevelId, char: char, name: &str) -> Object {
+    let mut stairs = Object::new(x, y, char, name, WHITE, false);
+    stairs.stairs = Some(destination);
+    stairs
+}
+
+/// whether the rectangle, walls included, is all rock for a room to be dug out of
+fn is_solid_rock(rect: Rect, map: &Map) -> bool {
+    (rect.x1..(rect.x2 + 1)).all(|x| {
+        (rect.y1..(rect.y2 + 1)).all(|y| {
+            let tile = &map[x as usize][y as usize];
+            tile.blocked && tile.door.is_none()
+        })
+    })
+}
+
+/// a floor tile squeezed between two walls, connecting the floor on either side
+fn is_doorway(x: i32, y: i32, map: &Map) -> bool {
+    if x <= 0 || y <= 0 || x >= MAP_WIDTH - 1 || y >= MAP_HEIGHT - 1 {
+        return false;
+    }
+    let floor = |x: i32, y: i32| !map[x as usize][y as usize].blocked;
+    let horizontal = !floor(x, y - 1) && !floor(x, y + 1) && floor(x - 1, y) && floor(x + 1, y);
+    let vertical = !floor(x - 1, y) && !floor(x + 1, y) && floor(x, y - 1) && floor(x, y + 1);
+    floor(x, y) && (horizontal || vertical)
+}
@@ -0,0 +1,251 @@
+//! The menu every choice in the game is made from: a header over lettered
+//! entries, a page of them at a time. An entry is picked with its letter, or by
+//! moving the highlight onto it with the arrow keys and pressing Enter. Entries
+//! can be shown disabled, can be grouped under headings, and can come with
+//! details shown next to the list while they are highlighted.
+use tcod::colors::{DARKER_BLUE, DARK_GREY, LIGHT_GREY, LIGHT_YELLOW, WHITE};
+use tcod::console::{blit, Console, Offscreen, Root};
+use tcod::input::KeyCode;
+use tcod::{BackgroundFlag, TextAlignment};
+
+// entries on a page, one for every letter
+const PAGE_SIZE: usize = 26;
+
+struct Entry {
+    text: String,
+    /// whether it can be picked
+    enabled: bool,
+    /// the heading it is listed under
+    group: Option<String>,
+    /// shown next to the list while the entry is highlighted
+    details: Option<String>,
+}
+
+/// A menu, built up entry by entry and then shown
+pub struct Menu<'a> {
+    header: &'a str,
+    width: i32,
+    entries: Vec<Entry>,
+    page_size: usize,
+    /// the width of the details next to the list, 0 for none
+    details_width: i32,
+}
+
+impl<'a> Menu<'a> {
+    pub fn new(header: &'a str, width: i32) -> Self {
+        Menu {
+            header,
+            width,
+            entries: vec![],
+            page_size: PAGE_SIZE,
+            details_width: 0,
+        }
+    }
+
+    pub fn entry(self, text: impl Into<String>) -> Self {
+        self.entry_if(text, true)
+    }
+
+    /// an entry that is shown, but can only be picked if `enabled`
+    pub fn entry_if(mut self, text: impl Into<String>, enabled: bool) -> Self {
+        self.entries.push(Entry {
+            text: text.into(),
+            enabled,
+            group: None,
+            details: None,
+        });
+        self
+    }
+
+    pub fn entries<T: AsRef<str>>(self, texts: &[T]) -> Self {
+        texts
+            .iter()
+            .fold(self, |menu, text| menu.entry(text.as_ref()))
+    }
+
+    /// put the last entry under a heading, shown whenever the heading changes
+    /// and at the top of every page
+    pub fn group(mut self, heading: impl Into<String>) -> Self {
+        if let Some(entry) = self.entries.last_mut() {
+            entry.group = Some(heading.into());
+        }
+        self
+    }
+
+    /// show the details next to the list while the last entry is highlighted
+    pub fn details(mut self, details: impl Into<String>) -> Self {
+        if let Some(entry) = self.entries.last_mut() {
+            entry.details = Some(details.into());
+        }
+        self
+    }
+
+    pub fn details_width(mut self, width: i32) -> Self {
+        self.details_width = width;
+        self
+    }
+
+    pub fn page_size(mut self, page_size: usize) -> Self {
+        self.page_size = page_size.clamp(1, PAGE_SIZE);
+        self
+    }
+
+    /// the entry after `from` that can be picked, going back if `step` is -1,
+    /// `from` itself if there is none
+    fn step(&self, from: usize, step: isize) -> usize {
+        let count = self.entries.len() as isize;
+        (1..count)
+            .map(|offset| (from as isize + step * offset).rem_euclid(count) as usize)
+            .find(|&index| self.entries[index].enabled)
+            .unwrap_or(from)
+    }
+
+    /// the header and the entries on the page as lines, each with the entry it
+    /// shows if it isn't a heading
+    fn lines(&self, first: usize) -> Vec<(String, Option<usize>)> {
+        let last = (first + self.page_size).min(self.entries.len());
+        let mut lines = vec![];
+        let mut group = None;
+        for (index, entry) in self.entries[first..last].iter().enumerate() {
+            if entry.group.is_some() && (entry.group != group || index == 0) {
+                group = entry.group.clone();
+                lines.push((entry.group.clone().unwrap_or_default(), None));
+            }
+            let letter = (b'a' + index as u8) as char;
+            lines.push((format!("({}) {}", letter, entry.text), Some(first + index)));
+        }
+        lines
+    }
+
+    /// Show the menu and return the index of the entry picked, or None if the
+    /// player pressed any other key. A menu without entries is a message box,
+    /// closed by any key.
+    pub fn show(&self, root: &mut Root) -> Option<usize> {
+        let count = self.entries.len();
+        let pages = count.div_ceil(self.page_size).max(1);
+        let mut selected = self
+            .entries
+            .iter()
+            .position(|entry| entry.enabled)
+            .unwrap_or(0);
+
+        // what lies under the menu, redrawn before every change of highlight
+        let mut background = Offscreen::new(root.width(), root.height());
+        let whole_screen = (root.width(), root.height());
+        blit(
+            root,
+            (0, 0),
+            whole_screen,
+            &mut background,
+            (0, 0),
+            1.0,
+            1.0,
+        );
+
+        loop {
+            let page = selected / self.page_size;
+            let first = page * self.page_size;
+            let lines = self.lines(first);
+
+            let header_height = if self.header.is_empty() {
+                0
+            } else {
+                root.get_height_rect(0, 0, self.width, root.height(), self.header)
+            };
+            let details = self
+                .entries
+                .get(selected)
+                .and_then(|entry| entry.details.as_deref())
+                .filter(|_| self.details_width > 0);
+            let details_height = details.map_or(0, |details| {
+                root.get_height_rect(0, 0, self.details_width - 1, root.height(), details)
+            });
+            let footer_height = if pages > 1 { 1 } else { 0 };
+            let width = self.width + self.details_width;
+            let height = header_height + (lines.len() as i32 + footer_height).max(details_height);
+            let mut window = Offscreen::new(width, height.max(1));
+            window.set_default_foreground(WHITE);
+            window.print_rect_ex(
+                0,
+                0,
+                self.width,
+                header_height,
+                BackgroundFlag::None,
+                TextAlignment::Left,
+                self.header,
+            );
+            for (row, (text, index)) in lines.iter().enumerate() {
+                let y = header_height + row as i32;
+                let color = match *index {
+                    Some(index) if index == selected && self.entries[index].enabled => {
+                        window.set_default_background(DARKER_BLUE);
+                        window.rect(0, y, self.width, 1, false, BackgroundFlag::Set);
+                        WHITE
+                    }
+                    Some(index) if self.entries[index].enabled => WHITE,
+                    Some(_) => DARK_GREY,
+                    None => LIGHT_YELLOW,
+                };
+                window.set_default_foreground(color);
+                window.print_ex(0, y, BackgroundFlag::None, TextAlignment::Left, text);
+            }
+            if pages > 1 {
+                window.set_default_foreground(LIGHT_GREY);
+                window.print_ex(
+                    0,
+                    height - 1,
+                    BackgroundFlag::None,
+                    TextAlignment::Left,
+                    format!("Page {}/{}, arrows to browse", page + 1, pages),
+                );
+            }
+            if let Some(details) = details {
+                window.set_default_foreground(WHITE);
+                window.print_rect(
+                    self.width + 1,
+                    header_height,
+                    self.details_width - 1,
+                    height - header_height,
+                    details,
+                );
+            }
+
+            blit(&background, (0, 0), whole_screen, root, (0, 0), 1.0, 1.0);
+            let x = root.width() / 2 - width / 2;
+            let y = root.height() / 2 - height / 2;
+            blit(&window, (0, 0), (width, height), root, (x, y), 1.0, 0.7);
+            root.flush();
+
+            let key = root.wait_for_keypress(true);
+            if count == 0 {
+                return None;
+            }
+            match key.code {
+                KeyCode::Up => selected = self.step(selected, -1),
+                KeyCode::Down => selected = self.step(selected, 1),
+                KeyCode::Left | KeyCode::PageUp if pages > 1 => {
+                    selected = (page + pages - 1) % pages * self.page_size
+                }
+                KeyCode::Right | KeyCode::PageDown if pages > 1 => {
+                    selected = (page + 1) % pages * self.page_size
+                }
+                KeyCode::Enter | KeyCode::NumPadEnter if self.entries[selected].enabled => {
+                    return Some(selected)
+                }
+                KeyCode::Enter | KeyCode::NumPadEnter => {}
+                _ if key.printable.is_ascii_alphabetic() => {
+                    // a letter picks the entry next to it on this page
+                    let letter = key.printable.to_ascii_lowercase() as usize - 'a' as usize;
+                    let index = first + letter;
+                    match self.entries.get(index).filter(|_| letter < self.page_size) {
+                        Some(entry) if entry.enabled => return Some(index),
+                        // a disabled entry can't be picked, but doesn't close the menu
+                        Some(_) => {}
+                        None => return None,
+                    }
+                }
+                _ => return None,
+            }
+        }
+    }
+}
@@ -1,22 +1,132 @@
+use crate::events::GameEvent;
 use serde::{Deserialize, Serialize};
+use tcod::colors::{GREEN, LIGHT_GREY, LIGHT_RED, ORANGE, RED, WHITE, YELLOW};
 use tcod::Color;
 
 #[derive(Serialize, Deserialize)]
 
 pub struct Messages {
     messages: Vec<(String, Color)>,
+    /// the messages added straight rather than told of an event, not yet taken
+    /// for the run log
+    #[serde(skip)]
+    unlogged: Vec<String>,
 }
 
 impl Messages {
     pub fn new() -> Self {
-        Self { messages: vec![] }
+        Self {
+            messages: vec![],
+            unlogged: vec![],
+        }
     }
 
     /// add the new message as a tuple, with the text and the color
     pub fn add<T: Into<String>>(&mut self, message: T, color: Color) {
+        let message = message.into();
+        self.unlogged.push(message.clone());
+        self.messages.push((message, color));
+    }
+
+    /// add a message telling of an event, which the run log keeps on its own
+    fn tell<T: Into<String>>(&mut self, message: T, color: Color) {
         self.messages.push((message.into(), color));
     }
 
+    /// take the messages added straight since the last time
+    pub fn take_unlogged(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.unlogged)
+    }
+
+    /// tell the player what happened
+    pub fn log(&mut self, event: &GameEvent) {
+        match event {
+            GameEvent::Attack {
+                attacker,
+                target,
+                damage,
+                ..
+            } => {
+                if *damage > 0 {
+                    self.tell(
+                        format!("{} attacks {} for {} hit points.", attacker, target, damage),
+                        WHITE,
+                    );
+                } else {
+                    self.tell(
+                        format!("{} attacks {} but it has no effect!", attacker, target),
+                        WHITE,
+                    );
+                }
+            }
+            GameEvent::Hurt { .. } => {}
+            GameEvent::Resisted {
+                target,
+                kind,
+                damage,
+                weak,
+            } => {
+                let kind = format!("{} damage", kind.name());
+                if *weak {
+                    self.tell(
+                        format!(
+                            "The {} is wracked by the {}, taking {}!",
+                            target, kind, damage
+                        ),
+                        ORANGE,
+                    );
+                } else if *damage > 0 {
+                    self.tell(
+                        format!(
+                            "The {} resists the {}, taking only {}.",
+                            target, kind, damage
+                        ),
+                        LIGHT_GREY,
+                    );
+                } else {
+                    self.tell(
+                        format!("The {} is unharmed by the {}.", target, kind),
+                        LIGHT_GREY,
+                    );
+                }
+            }
+            GameEvent::Afflicted { target, kind } => {
+                self.tell(kind.onset_message(target), LIGHT_RED)
+            }
+            GameEvent::PlayerDied => self.tell("You died!", RED),
+            GameEvent::FellDown => {
+                self.tell("You fall into the chasm, tumbling down into the dark!", RED)
+            }
+            GameEvent::MonsterDied { name, xp, boss, .. } => {
+                if *boss {
+                    self.tell(
+                        format!("With a final howl, the {} crumbles to dust!", name),
+                        ORANGE,
+                    );
+                }
+                if *xp > 0 {
+                    self.tell(
+                        format!("{} is dead! You gain {} experience points.", name, xp),
+                        ORANGE,
+                    );
+                } else {
+                    self.tell(format!("{} is dead!", name), ORANGE);
+                }
+            }
+            GameEvent::PickedUp(name) => self.tell(format!("You picked up a {}!", name), GREEN),
+            GameEvent::PickedUpGold(gold) => {
+                self.tell(format!("You pick up {} gold pieces.", gold), YELLOW)
+            }
+            GameEvent::LevelUp(level) => self.tell(
+                format!(
+                    "Your battle skills grow stronger! You reached level {}!",
+                    level
+                ),
+                YELLOW,
+            ),
+        }
+    }
+
     /// Create a `DoubleEndedIterator` over the messages
     pub fn iter(&self) -> impl DoubleEndedIterator<Item = &(String, Color)> {
         self.messages.iter()
@@ -1,26 +1,227 @@
 use crate::{
     ai::Ai,
-    fighter::{DeathCallback, Fighter},
+    dungeon::{Branch, LevelId, SIDE_BRANCHES},
+    effects::{Effect, EffectKind},
+    fighter::{DamageType, DeathCallback, Fighter},
+    groundeffects::GroundKind,
+    item::Item,
     object::Object,
+    spawntables::{branch_monsters, monster_table},
+    spells::MonsterSpell,
+    tiles::TILE_ORC_CHIEF,
 };
 use rand::distributions::{IndependentSample, Weighted, WeightedChoice};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
 use tcod::colors;
 
-pub fn create_monster(x: i32, y: i32) -> Object {
+// chance for a randomly spawned monster to be an elite of its kind
+const ELITE_CHANCE: f32 = 0.05;
+// the prefixes of elites to their kind's name, one for each extra ability
+const ELITE_PREFIXES: &[&str] = &["frenzied", "armored", "venomous", "hulking"];
+
+// the abilities of every kind of monster that has any
+const ABILITIES: &[(&str, &[Ability])] = &[
+    ("troll", &[Ability::Regenerate(1)]),
+    (
+        "orc shaman",
+        &[Ability::Shaman {
+            heal: 3,
+            range: 6.0,
+            empower_turns: 10,
+        }],
+    ),
+    (
+        "ghoul",
+        &[Ability::DeathBurst {
+            kind: GroundKind::PoisonGas,
+            radius: 1.5,
+            turns: 6,
+        }],
+    ),
+    (
+        "abyssal horror",
+        &[Ability::DeathBurst {
+            kind: GroundKind::Fire,
+            radius: 1.5,
+            turns: 5,
+        }],
+    ),
+    (
+        "lich",
+        &[Ability::Cast(MonsterSpell::Curse {
+            drain: 3,
+            range: 6.0,
+        })],
+    ),
+    ("rat summoner", &[Ability::Cast(MonsterSpell::Summon(2))]),
+    ("imp", &[Ability::Cast(MonsterSpell::Blink)]),
+];
+
+// how far the kinds of monster with eyes better or worse than most see
+const SIGHT_RADII: &[(&str, i32)] = &[
+    ("orc archer", 10),
+    ("troll", 6),
+    ("skeleton", 6),
+    ("cave bat", 4),
+    ("giant rat", 5),
+    ("shade", 10),
+    ("lich", 8),
+    ("Eater of Kings", 12),
+];
+
+// the kinds of monster that can't be seen, unless from right next to them
+const INVISIBLE: &[&str] = &["shade"];
+
+// the kinds of monster that shrug off some types of damage, in percent, or
+// suffer worse from them: trolls have thick hides, the dead don't choke, and
+// the frost wight melts in the heat
+const RESISTANCES: &[(&str, &[(DamageType, i32)])] = &[
+    ("troll", &[(DamageType::Physical, 30)]),
+    ("skeleton", &[(DamageType::Poison, 100)]),
+    (
+        "lich",
+        &[(DamageType::Poison, 100), (DamageType::Fire, -50)],
+    ),
+    ("ghoul", &[(DamageType::Poison, 100)]),
+    (
+        "frost wight",
+        &[(DamageType::Fire, -100), (DamageType::Poison, 100)],
+    ),
+    (
+        "shade",
+        &[(DamageType::Physical, 50), (DamageType::Lightning, -50)],
+    ),
+    ("abyssal horror", &[(DamageType::Fire, 100)]),
+    ("imp", &[(DamageType::Fire, 100)]),
+    (
+        "ghost",
+        &[(DamageType::Physical, 50), (DamageType::Poison, 100)],
+    ),
+];
+
+/// Something a kind of monster does on its turn besides moving and fighting
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Ability {
+    /// heals this many hit points every turn
+    Regenerate(i32),
+    /// instead of acting, mends the wounds of the most hurt ally within range or
+    /// else spurs one on for a while, whenever it is alerted to the player
+    Shaman {
+        heal: i32,
+        range: f32,
+        empower_turns: i32,
+    },
+    /// goes up in fire or gas over the tiles around it when it dies
+    DeathBurst {
+        kind: GroundKind,
+        radius: f32,
+        turns: i32,
+    },
+    /// casts a spell on its turn, when the spell is of use
+    Cast(MonsterSpell),
+}
+
+/// What a monster is able to do to the terrain around it.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Capabilities {
+    /// opens closed doors in its way
+    pub opens_doors: bool,
+    /// smashes doors in its way to splinters
+    pub smashes_doors: bool,
+}
+
+/// Which side a creature is on. Monsters of the horde stick together against the
+/// player, while vermin attack anyone but their own.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Faction {
+    Player,
+    #[default]
+    Horde,
+    Vermin,
+}
+
+impl Faction {
+    pub fn is_hostile_to(self, other: Faction) -> bool {
+        self != other
+    }
+}
+
+/// the names of every kind of monster, from all branches and including the bosses
+pub fn monster_names() -> Vec<&'static str> {
+    let mut names = vec![];
+    for &branch in [Branch::Main].iter().chain(SIDE_BRANCHES) {
+        let spawned = branch_monsters(branch);
+        for name in spawned.chain(branch.boss()) {
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+    }
+    names
+}
+
+/// a random monster of those found on the level
+pub fn create_monster(x: i32, y: i32, level: LevelId, rng: &mut impl Rng) -> Object {
     // monster random table
-    let monster_chances = &mut [
-        Weighted {
-            weight: 80,
-            item: "orc",
-        },
-        Weighted {
-            weight: 20,
-            item: "troll",
-        },
-    ];
+    let monster_chances = &mut monster_table(level)
+        .iter()
+        .map(|&(item, weight)| Weighted { weight, item })
+        .collect::<Vec<_>>();
     let monster_choice = WeightedChoice::new(monster_chances);
+    let name = monster_choice.ind_sample(rng);
+    let mut monster = monster_by_name(name, x, y).unwrap();
+    if rng.gen::<f32>() < ELITE_CHANCE {
+        make_elite(&mut monster, rng);
+    }
+    monster
+}
+
+/// send a monster to sleep, it wakes to behave as before
+pub fn put_to_sleep(monster: &mut Object) {
+    monster.ai = monster.ai.take().map(Ai::fall_asleep);
+}
+
+/// Turn a monster into a tougher elite of its kind: more health and one extra
+/// ability, announced by a prefix to its name
+fn make_elite(monster: &mut Object, rng: &mut impl Rng) {
+    let fighter = match monster.fighter.as_mut() {
+        Some(fighter) => fighter,
+        None => return,
+    };
+    fighter.max_hp += fighter.max_hp / 2;
+    fighter.hp = fighter.max_hp;
+    fighter.xp *= 2;
+    let ability = rng.gen_range(0, ELITE_PREFIXES.len());
+    match ability {
+        0 => fighter.power += 2,
+        1 => fighter.defense += 2,
+        2 => {
+            fighter.on_hit = Some(Effect {
+                kind: EffectKind::Paralyzed,
+                turns: 1,
+            })
+        }
+        _ => monster.capabilities.smashes_doors = true,
+    }
+    monster.name = format!("{} {}", ELITE_PREFIXES[ability], monster.name);
+}
+
+/// the kind of monster going by the name, which for an elite is its name without
+/// the prefix
+pub fn kind_of(name: &str) -> &str {
+    ELITE_PREFIXES
+        .iter()
+        .find_map(|prefix| {
+            name.strip_prefix(prefix)
+                .and_then(|rest| rest.strip_prefix(' '))
+        })
+        .unwrap_or(name)
+}
 
-    let mut monster = match monster_choice.ind_sample(&mut rand::thread_rng()) {
+/// create the monster of the given kind, if there is such a monster
+pub fn monster_by_name(name: &str, x: i32, y: i32) -> Option<Object> {
+    let mut monster = match name {
         "orc" => {
             // create an orc
             let mut orc = Object::new(x, y, 'o', "orc", colors::DESATURATED_GREEN, true);
@@ -30,11 +231,80 @@ pub fn create_monster(x: i32, y: i32) -> Object {
                 defense: 0,
                 power: 3,
                 xp: 35,
+                mana: 0,
+                max_mana: 0,
+                on_hit: None,
                 on_death: DeathCallback::Monster,
+                damage_taken: Default::default(),
+                resistances: Default::default(),
             });
+            orc.capabilities = Capabilities {
+                opens_doors: true,
+                ..Default::default()
+            };
             orc.ai = Some(Ai::Basic);
             orc
         }
+        "orc chief" => {
+            // an orc, only bigger, that never goes to battle without a potion
+            let mut chief = monster_by_name("orc", x, y)?;
+            chief.name = "orc chief".into();
+            chief.color = colors::DARKER_GREEN;
+            chief.tile_index = Some(TILE_ORC_CHIEF);
+            if let Some(fighter) = chief.fighter.as_mut() {
+                fighter.max_hp = 20;
+                fighter.hp = 20;
+                fighter.power = 5;
+                fighter.xp = 100;
+            }
+            chief.loot = Some(Item::Heal);
+            chief
+        }
+        "orc archer" => {
+            let mut archer = Object::new(x, y, 'a', "orc archer", colors::DESATURATED_GREEN, true);
+            archer.fighter = Some(Fighter {
+                max_hp: 6,
+                hp: 6,
+                defense: 0,
+                power: 3,
+                xp: 40,
+                mana: 0,
+                max_mana: 0,
+                on_hit: None,
+                on_death: DeathCallback::Monster,
+                damage_taken: Default::default(),
+                resistances: Default::default(),
+            });
+            archer.capabilities = Capabilities {
+                opens_doors: true,
+                ..Default::default()
+            };
+            archer.ai = Some(Ai::Ranged);
+            archer
+        }
+        "orc shaman" => {
+            // frail itself, it keeps the orcs around it fighting
+            let mut shaman = Object::new(x, y, 'o', "orc shaman", colors::LIGHT_VIOLET, true);
+            shaman.fighter = Some(Fighter {
+                max_hp: 8,
+                hp: 8,
+                defense: 0,
+                power: 2,
+                xp: 60,
+                mana: 0,
+                max_mana: 0,
+                on_hit: None,
+                on_death: DeathCallback::Monster,
+                damage_taken: Default::default(),
+                resistances: Default::default(),
+            });
+            shaman.capabilities = Capabilities {
+                opens_doors: true,
+                ..Default::default()
+            };
+            shaman.ai = Some(Ai::Basic);
+            shaman
+        }
         "troll" => {
             let mut troll = Object::new(x, y, 'T', "troll", colors::DARKER_GREEN, true);
             troll.fighter = Some(Fighter {
@@ -43,14 +313,372 @@ pub fn create_monster(x: i32, y: i32) -> Object {
                 defense: 1,
                 power: 4,
                 xp: 100,
+                mana: 0,
+                max_mana: 0,
+                on_hit: None,
                 on_death: DeathCallback::Monster,
+                damage_taken: Default::default(),
+                resistances: Default::default(),
             });
+            troll.capabilities = Capabilities {
+                smashes_doors: true,
+                ..Default::default()
+            };
             troll.ai = Some(Ai::Basic);
             troll
         }
-        _ => unreachable!(),
+        "skeleton" => {
+            let mut skeleton = Object::new(x, y, 'z', "skeleton", colors::LIGHTER_GREY, true);
+            skeleton.fighter = Some(Fighter {
+                max_hp: 10,
+                hp: 10,
+                defense: 2,
+                power: 3,
+                xp: 50,
+                mana: 0,
+                max_mana: 0,
+                on_hit: None,
+                on_death: DeathCallback::Monster,
+                damage_taken: Default::default(),
+                resistances: Default::default(),
+            });
+            skeleton.capabilities = Capabilities {
+                opens_doors: true,
+                ..Default::default()
+            };
+            skeleton.ai = Some(Ai::Basic);
+            skeleton
+        }
+        "ghoul" => {
+            // its claws leave the victim paralyzed for a moment
+            let mut ghoul = Object::new(x, y, 'g', "ghoul", colors::LIGHT_GREY, true);
+            ghoul.fighter = Some(Fighter {
+                max_hp: 12,
+                hp: 12,
+                defense: 1,
+                power: 3,
+                xp: 60,
+                mana: 0,
+                max_mana: 0,
+                on_hit: Some(Effect {
+                    kind: EffectKind::Paralyzed,
+                    turns: 2,
+                }),
+                on_death: DeathCallback::Monster,
+                damage_taken: Default::default(),
+                resistances: Default::default(),
+            });
+            ghoul.capabilities = Capabilities {
+                opens_doors: true,
+                ..Default::default()
+            };
+            ghoul.ai = Some(Ai::Basic);
+            ghoul
+        }
+        "frost wight" => {
+            // a corpse of the old kings' court, kept by the cold of the crypt
+            let mut wight = Object::new(x, y, 'W', "frost wight", colors::LIGHT_CYAN, true);
+            wight.fighter = Some(Fighter {
+                max_hp: 18,
+                hp: 18,
+                defense: 2,
+                power: 5,
+                xp: 120,
+                mana: 0,
+                max_mana: 0,
+                on_hit: None,
+                on_death: DeathCallback::Monster,
+                damage_taken: Default::default(),
+                resistances: Default::default(),
+            });
+            wight.capabilities = Capabilities {
+                opens_doors: true,
+                ..Default::default()
+            };
+            wight.ai = Some(Ai::Basic);
+            wight
+        }
+        "cave bat" => {
+            // its shrieks leave the victim confused
+            let mut bat = Object::new(x, y, 'b', "cave bat", colors::DARK_SEPIA, true);
+            bat.fighter = Some(Fighter {
+                max_hp: 4,
+                hp: 4,
+                defense: 0,
+                power: 2,
+                xp: 20,
+                mana: 0,
+                max_mana: 0,
+                on_hit: Some(Effect {
+                    kind: EffectKind::Confused,
+                    turns: 5,
+                }),
+                on_death: DeathCallback::Monster,
+                damage_taken: Default::default(),
+                resistances: Default::default(),
+            });
+            bat.ai = Some(Ai::Basic);
+            bat
+        }
+        "giant rat" => {
+            // vermin that bite whatever comes near, orcs included
+            let mut rat = Object::new(x, y, 'r', "giant rat", colors::LIGHT_SEPIA, true);
+            rat.fighter = Some(Fighter {
+                max_hp: 6,
+                hp: 6,
+                defense: 0,
+                power: 3,
+                xp: 15,
+                mana: 0,
+                max_mana: 0,
+                on_hit: None,
+                on_death: DeathCallback::Monster,
+                damage_taken: Default::default(),
+                resistances: Default::default(),
+            });
+            rat.faction = Faction::Vermin;
+            rat.ai = Some(Ai::Basic);
+            rat
+        }
+        "giant spider" => {
+            // its bite is venomous
+            let mut spider = Object::new(x, y, 'x', "giant spider", colors::DARKER_GREY, true);
+            spider.fighter = Some(Fighter {
+                max_hp: 8,
+                hp: 8,
+                defense: 1,
+                power: 3,
+                xp: 40,
+                mana: 0,
+                max_mana: 0,
+                on_hit: Some(Effect {
+                    kind: EffectKind::Poisoned,
+                    turns: 5,
+                }),
+                on_death: DeathCallback::Monster,
+                damage_taken: Default::default(),
+                resistances: Default::default(),
+            });
+            spider.ai = Some(Ai::Basic);
+            spider
+        }
+        "spitting cobra" => {
+            // its venom blinds the victim
+            let mut cobra = Object::new(x, y, 's', "spitting cobra", colors::DARK_YELLOW, true);
+            cobra.fighter = Some(Fighter {
+                max_hp: 6,
+                hp: 6,
+                defense: 0,
+                power: 3,
+                xp: 35,
+                mana: 0,
+                max_mana: 0,
+                on_hit: Some(Effect {
+                    kind: EffectKind::Blind,
+                    turns: 8,
+                }),
+                on_death: DeathCallback::Monster,
+                damage_taken: Default::default(),
+                resistances: Default::default(),
+            });
+            cobra.ai = Some(Ai::Basic);
+            cobra
+        }
+        "shade" => {
+            // a wisp of darkness that drains the light from its victims' eyes
+            let mut shade = Object::new(x, y, 'S', "shade", colors::DARKEST_VIOLET, true);
+            shade.fighter = Some(Fighter {
+                max_hp: 20,
+                hp: 20,
+                defense: 3,
+                power: 8,
+                xp: 250,
+                mana: 0,
+                max_mana: 0,
+                on_hit: Some(Effect {
+                    kind: EffectKind::Blind,
+                    turns: 4,
+                }),
+                on_death: DeathCallback::Monster,
+                damage_taken: Default::default(),
+                resistances: Default::default(),
+            });
+            shade.ai = Some(Ai::Basic);
+            shade
+        }
+        "lich" => {
+            // a sorcerer who cheated death, and withers the living from afar
+            let mut lich = Object::new(x, y, 'L', "lich", colors::LIGHT_PURPLE, true);
+            lich.fighter = Some(Fighter {
+                max_hp: 24,
+                hp: 24,
+                defense: 3,
+                power: 6,
+                xp: 300,
+                mana: 0,
+                max_mana: 0,
+                on_hit: None,
+                on_death: DeathCallback::Monster,
+                damage_taken: Default::default(),
+                resistances: Default::default(),
+            });
+            lich.capabilities = Capabilities {
+                opens_doors: true,
+                ..Default::default()
+            };
+            lich.ai = Some(Ai::Basic);
+            lich
+        }
+        "rat summoner" => {
+            // a ragged hermit the rats of the dungeon come to at a whistle
+            let mut summoner = Object::new(x, y, 'h', "rat summoner", colors::DARK_SEPIA, true);
+            summoner.fighter = Some(Fighter {
+                max_hp: 12,
+                hp: 12,
+                defense: 1,
+                power: 3,
+                xp: 90,
+                mana: 0,
+                max_mana: 0,
+                on_hit: None,
+                on_death: DeathCallback::Monster,
+                damage_taken: Default::default(),
+                resistances: Default::default(),
+            });
+            summoner.capabilities = Capabilities {
+                opens_doors: true,
+                ..Default::default()
+            };
+            summoner.faction = Faction::Vermin;
+            summoner.ai = Some(Ai::Basic);
+            summoner
+        }
+        "imp" => {
+            // a spiteful little fiend that pops away once it gets hurt
+            let mut imp = Object::new(x, y, 'i', "imp", colors::FLAME, true);
+            imp.fighter = Some(Fighter {
+                max_hp: 14,
+                hp: 14,
+                defense: 2,
+                power: 6,
+                xp: 150,
+                mana: 0,
+                max_mana: 0,
+                on_hit: None,
+                on_death: DeathCallback::Monster,
+                damage_taken: Default::default(),
+                resistances: Default::default(),
+            });
+            imp.ai = Some(Ai::Basic);
+            imp
+        }
+        "abyssal horror" => {
+            let mut horror = Object::new(x, y, 'H', "abyssal horror", colors::DARK_CRIMSON, true);
+            horror.fighter = Some(Fighter {
+                max_hp: 45,
+                hp: 45,
+                defense: 4,
+                power: 10,
+                xp: 400,
+                mana: 0,
+                max_mana: 0,
+                on_hit: None,
+                on_death: DeathCallback::Monster,
+                damage_taken: Default::default(),
+                resistances: Default::default(),
+            });
+            horror.capabilities = Capabilities {
+                smashes_doors: true,
+                ..Default::default()
+            };
+            horror.ai = Some(Ai::Basic);
+            horror
+        }
+        "Ancient King" => {
+            // the undead ruler waiting at the bottom of the tombs
+            let mut king = Object::new(x, y, 'K', "Ancient King", colors::GOLD, true);
+            king.fighter = Some(Fighter {
+                max_hp: 60,
+                hp: 60,
+                defense: 4,
+                power: 9,
+                xp: 1000,
+                mana: 0,
+                max_mana: 0,
+                on_hit: None,
+                on_death: DeathCallback::Boss,
+                damage_taken: Default::default(),
+                resistances: Default::default(),
+            });
+            king.capabilities = Capabilities {
+                opens_doors: true,
+                ..Default::default()
+            };
+            king.ai = Some(Ai::Basic);
+            king
+        }
+        "Eater of Kings" => {
+            // what the Ancient King was guarding the world from
+            let mut eater = Object::new(x, y, 'E', "Eater of Kings", colors::LIGHT_CRIMSON, true);
+            eater.fighter = Some(Fighter {
+                max_hp: 120,
+                hp: 120,
+                defense: 6,
+                power: 14,
+                xp: 3000,
+                mana: 0,
+                max_mana: 0,
+                on_hit: Some(Effect {
+                    kind: EffectKind::Paralyzed,
+                    turns: 1,
+                }),
+                on_death: DeathCallback::Boss,
+                damage_taken: Default::default(),
+                resistances: Default::default(),
+            });
+            eater.capabilities = Capabilities {
+                smashes_doors: true,
+                ..Default::default()
+            };
+            eater.ai = Some(Ai::Basic);
+            eater
+        }
+        "ghost" => {
+            // what is left of a character who died down here in another run, see
+            // `bones::haunt`
+            let mut ghost = Object::new(x, y, 'G', "ghost", colors::LIGHTEST_GREY, true);
+            ghost.fighter = Some(Fighter {
+                max_hp: 10,
+                hp: 10,
+                defense: 1,
+                power: 3,
+                xp: 50,
+                mana: 0,
+                max_mana: 0,
+                on_hit: None,
+                on_death: DeathCallback::Monster,
+                damage_taken: Default::default(),
+                resistances: Default::default(),
+            });
+            ghost.ai = Some(Ai::Basic);
+            ghost
+        }
+        _ => return None,
     };
 
     monster.alive = true;
-    monster
+    if let Some(&(_, abilities)) = ABILITIES.iter().find(|&&(kind, _)| kind == name) {
+        monster.abilities = abilities.to_vec();
+    }
+    if let Some(&(_, radius)) = SIGHT_RADII.iter().find(|&&(kind, _)| kind == name) {
+        monster.sight.radius = radius;
+    }
+    monster.invisible = INVISIBLE.contains(&name);
+    if let Some(&(_, resistances)) = RESISTANCES.iter().find(|&&(kind, _)| kind == name) {
+        let fighter = monster.fighter.as_mut().unwrap();
+        for &(kind, resistance) in resistances {
+            *fighter.resistances.get_mut(kind) = resistance;
+        }
+    }
+    Some(monster)
 }
@@ -0,0 +1,76 @@
+//! The post-mortem of a finished run: a plain-text morgue file written on death,
+//! for the player to look back on.
+use crate::game::{Game, PLAYER};
+use std::{
+    fs::File,
+    io::{self, Write},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+// how many of the last messages make it into the morgue file
+const MORGUE_MESSAGES: usize = 30;
+
+/// the whole morgue file for the run that just ended
+pub fn morgue_text(game: &Game) -> String {
+    let player = &game.objects[PLAYER];
+    let mut text = String::from("Tombs of the Ancient Kings -- morgue file\n\n");
+    text += &format!(
+        "Died on {} (depth {}) as a level {} {}, in {} mode on {} after {} deaths.\n",
+        game.level.name(),
+        game.level.absolute_depth(),
+        player.level,
+        game.class.name(),
+        game.mode.name(),
+        game.difficulty.name(),
+        game.deaths
+    );
+
+    if let Some(fighter) = player.fighter {
+        text += &format!(
+            "\nExperience: {}\nMaximum HP: {}\nMaximum mana: {}\nAttack: {}\nDefense: {}\n",
+            fighter.xp, fighter.max_hp, fighter.max_mana, fighter.power, fighter.defense
+        );
+    }
+    text += &format!("Gold: {}\nLuck: {}\n", game.gold, game.luck());
+
+    text += "\nInventory:\n";
+    if game.inventory.is_empty() {
+        text += "  nothing\n";
+    }
+    for item in &game.inventory {
+        text += &format!("  {}\n", item.inventory_name());
+    }
+
+    text += &format!("\n{}\n", game.stats.summary(game.turn));
+    text += "\nKills:\n";
+    for (name, count) in game.stats.kill_list() {
+        text += &format!("  {:4} {}\n", count, name);
+    }
+
+    text += "\nAchievements:\n";
+    if game.achievements.iter().next().is_none() {
+        text += "  none\n";
+    }
+    for achievement in game.achievements.iter() {
+        text += &format!("  {}\n", achievement.name());
+    }
+
+    text += "\nLast messages:\n";
+    let mut messages: Vec<_> = game.messages.iter().rev().take(MORGUE_MESSAGES).collect();
+    messages.reverse();
+    for (message, _) in messages {
+        text += &format!("  {}\n", message);
+    }
+    text
+}
+
+/// write a morgue file named after the time of death, returning its name
+pub fn write_morgue(text: &str) -> io::Result<String> {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs());
+    let name = format!("morgue-{}.txt", seconds);
+    let mut file = File::create(&name)?;
+    file.write_all(text.as_bytes())?;
+    Ok(name)
+}
@@ -1,6 +1,34 @@
-use crate::{ai::Ai, fighter::Fighter, item::Item, messages::Messages};
+use crate::{
+    ai::Ai,
+    artifact::{describe_affixes, Affix},
+    blessings::Beatitude,
+    dungeon::LevelId,
+    effects::{ActiveEffect, Effect, EffectKind, EMPOWERED_POWER},
+    events::{Events, GameEvent},
+    fighter::{DamageSource, DamageType, DeathCallback, Fighter},
+    food::Meat,
+    intrinsics::Intrinsic,
+    item::{Item, Slot},
+    monsters::{Ability, Capabilities, Faction},
+    palette::Palette,
+    renderer::Renderer,
+    sight::Sight,
+    tiles::{glyph, tile_char},
+    world::Service,
+};
 use serde::{Deserialize, Serialize};
-use tcod::{colors::WHITE, BackgroundFlag, Color, Console};
+use tcod::{BackgroundFlag, Color};
+
+/// What an attack brings to bear on its target, taken from the attacker before
+/// the target suffers it so the two are never borrowed at once
+#[derive(Clone, Debug)]
+pub struct Blow {
+    pub attacker: String,
+    pub power: i32,
+    pub kind: DamageType,
+    /// the lasting effect the blow inflicts, if it hurts
+    pub on_hit: Option<Effect>,
+}
 
 /// This is a generic object: the player, a monster, an item, the stairs...
 /// It's always represented by a character on screen.
@@ -9,6 +37,10 @@ pub struct Object {
     pub x: i32,
     pub y: i32,
     pub char: char,
+    /// the tile drawn for this object while playing with graphical tiles, None
+    /// for the tile of its character
+    #[serde(default)]
+    pub tile_index: Option<u32>,
     pub color: Color,
     pub name: String,
     pub blocks: bool,
@@ -18,6 +50,67 @@ pub struct Object {
     pub item: Option<Item>,
     pub always_visible: bool,
     pub level: i32,
+    /// the turn this object rots away on, for worthless clutter like corpses
+    pub decays_at: Option<i32>,
+    #[serde(default)]
+    pub effects: Vec<ActiveEffect>,
+    #[serde(default)]
+    pub capabilities: Capabilities,
+    /// where a staircase leads to
+    #[serde(default)]
+    pub stairs: Option<LevelId>,
+    /// zaps left in a wand
+    #[serde(default)]
+    pub charges: Option<i32>,
+    /// arrows left in a bundle
+    #[serde(default)]
+    pub count: Option<i32>,
+    /// item a monster is sure to leave behind when it dies
+    #[serde(default)]
+    pub loot: Option<Item>,
+    /// whether this item is still only known by its looks
+    #[serde(default)]
+    pub unidentified: bool,
+    /// coins in a pile of gold
+    #[serde(default)]
+    pub gold: Option<i32>,
+    #[serde(default)]
+    pub faction: Faction,
+    /// whether the player wears this item
+    #[serde(default)]
+    pub equipped: bool,
+    /// whether the worn item is a second weapon, held in the off hand
+    #[serde(default)]
+    pub off_hand: bool,
+    /// turns a light has left to burn
+    #[serde(default)]
+    pub fuel: Option<i32>,
+    /// what the townsperson, or the chest, does for the player bumping into it
+    #[serde(default)]
+    pub service: Option<Service>,
+    /// what a corpse, or a chunk of meat, can be eaten as
+    #[serde(default)]
+    pub meat: Option<Meat>,
+    /// what the monster does on its turn besides moving and fighting
+    #[serde(default)]
+    pub abilities: Vec<Ability>,
+    /// what an artifact does besides what its kind does
+    #[serde(default)]
+    pub affixes: Vec<Affix>,
+    /// whether the item is blessed or cursed, and whether the player knows it
+    #[serde(default)]
+    pub beatitude: Beatitude,
+    #[serde(default)]
+    pub beatitude_known: bool,
+    /// how far and which way a monster looks, and what it knows of the player
+    #[serde(default)]
+    pub sight: Sight,
+    /// lasting gifts of the body, such as a resistance to poison
+    #[serde(default)]
+    pub intrinsics: Vec<Intrinsic>,
+    /// unseen by nature, rather than for a while
+    #[serde(default)]
+    pub invisible: bool,
 }
 
 impl Object {
@@ -26,6 +119,7 @@ impl Object {
             x,
             y,
             char,
+            tile_index: None,
             color,
             name: name.into(),
             blocks,
@@ -35,13 +129,114 @@ impl Object {
             item: None,
             always_visible: false,
             level: 1,
+            decays_at: None,
+            effects: vec![],
+            charges: None,
+            count: None,
+            loot: None,
+            unidentified: false,
+            gold: None,
+            faction: Faction::default(),
+            equipped: false,
+            off_hand: false,
+            fuel: None,
+            capabilities: Default::default(),
+            stairs: None,
+            service: None,
+            meat: None,
+            abilities: vec![],
+            affixes: vec![],
+            beatitude: Beatitude::Uncursed,
+            beatitude_known: false,
+            sight: Sight::default(),
+            intrinsics: vec![],
+            invisible: false,
+        }
+    }
+
+    /// set the color, as the palette has it, and then draw the character that
+    /// represents this object at its position, or its tile if `tiles` are on
+    pub fn draw(&self, con: &mut dyn Renderer, palette: Palette, tiles: bool) {
+        con.set_default_foreground(palette.object_color(self.color));
+        let glyph = match self.tile_index.and_then(tile_char) {
+            Some(tile) if tiles => tile,
+            _ => glyph(self.char, tiles),
+        };
+        con.put_char(self.x, self.y, glyph, BackgroundFlag::None);
+    }
+
+    /// a short description for the examine cursor
+    pub fn describe(&self) -> String {
+        if let Some(fighter) = self.fighter {
+            let health = match fighter.hp * 100 / fighter.max_hp.max(1) {
+                100 => "unhurt",
+                50..=99 => "wounded",
+                1..=49 => "badly wounded",
+                _ => "dead",
+            };
+            if self.ai.as_ref().is_some_and(Ai::is_asleep) {
+                format!("{} ({}, asleep)", self.name, health)
+            } else {
+                format!("{} ({})", self.name, health)
+            }
+        } else if self.unidentified {
+            format!("{}: unknown", self.name)
+        } else if let Some(item) = self.item.filter(|_| !self.affixes.is_empty()) {
+            format!(
+                "{}: {}; {}",
+                self.inventory_name(),
+                item.description(),
+                describe_affixes(&self.affixes)
+            )
+        } else if let Some(item) = self.item {
+            format!("{}: {}", self.inventory_name(), item.description())
+        } else if self.is_rotten() {
+            format!("{} (rotten)", self.name)
+        } else {
+            self.name.clone()
+        }
+    }
+
+    /// how heavy the object is to carry, nothing if it isn't an item
+    pub fn weight(&self) -> i32 {
+        self.item.map_or(0, Item::weight)
+    }
+
+    /// the name as shown when carried, with the charges left in a wand, the fuel
+    /// left in a light, the arrows left in a bundle and whether it is worn
+    pub fn inventory_name(&self) -> String {
+        let charges = self.charges.filter(|_| !self.unidentified);
+        let name = match (charges, self.fuel, self.count) {
+            (Some(1), _, _) => format!("{} (1 charge)", self.name),
+            (Some(charges), _, _) => format!("{} ({} charges)", self.name, charges),
+            (None, Some(0), _) => format!("{} (spent)", self.name),
+            (None, Some(fuel), _) => format!("{} ({} turns)", self.name, fuel),
+            (None, None, Some(count)) => format!("{} ({} left)", self.name, count),
+            (None, None, None) if self.is_rotten() => {
+                format!("{} (rotten)", self.name)
+            }
+            (None, None, None) => self.name.clone(),
+        };
+        let name = if self.beatitude_known && self.item.is_some() {
+            format!("{} {}", self.beatitude.name(), name)
+        } else {
+            name
+        };
+        if self.equipped && self.item.and_then(Item::slot) == Some(Slot::Weapon) {
+            format!("{} (readied)", name)
+        } else if self.equipped {
+            format!("{} (worn)", name)
+        } else {
+            name
         }
     }
 
-    /// set the color and then draw the character that represents this object at its position
-    pub fn draw(&self, con: &mut dyn Console) {
-        con.set_default_foreground(self.color);
-        con.put_char(self.x, self.y, self.char, BackgroundFlag::None);
+    /// where the item is worn, if it can be, the off hand for a second weapon
+    pub fn worn_slot(&self) -> Option<Slot> {
+        if self.off_hand {
+            return Some(Slot::OffHand);
+        }
+        self.item.and_then(Item::slot)
     }
 
     pub fn pos(&self) -> (i32, i32) {
@@ -65,52 +260,173 @@ impl Object {
         (((x - self.x).pow(2) + (y - self.y).pow(2)) as f32).sqrt()
     }
 
-    pub fn take_damage(&mut self, damage: i32, messages: &mut Messages) -> Option<i32> {
-        // apply damage if possible
+    /// hurt the object on turn `now`, returning the player's share of the XP it is
+    /// worth if this killed it
+    pub fn take_damage(
+        &mut self,
+        damage: i32,
+        source: DamageSource,
+        now: i32,
+        events: &mut Events,
+    ) -> Option<i32> {
+        // apply damage if possible, counting only what the fighter had left
         if let Some(fighter) = self.fighter.as_mut() {
             if damage > 0 {
+                fighter
+                    .damage_taken
+                    .add(source, damage.min(fighter.hp.max(0)));
                 fighter.hp -= damage;
             }
         }
+        // nobody sleeps through being hurt
+        self.ai = self.ai.take().map(Ai::wake);
 
         // check for death, call the death function
         if let Some(fighter) = self.fighter {
             if fighter.hp <= 0 {
                 self.alive = false;
-                fighter.on_death.callback(self, messages);
-                return Some(fighter.xp);
+                fighter.on_death.callback(self, source, now, events);
+                let xp = fighter.damage_taken.player_share(fighter.xp);
+                if xp > 0 {
+                    return Some(xp);
+                }
             }
         }
         None
     }
 
-    pub fn attack(&mut self, target: &mut Object, messages: &mut Messages) {
+    /// the blow this object deals when it attacks
+    pub fn blow(&self) -> Blow {
         // a simple formula for attack damage
-        let damage = self.fighter.map_or(0, |f| f.power) - target.fighter.map_or(0, |f| f.defense);
+        let power = self.fighter.map_or(0, |f| f.power)
+            + if self.has_effect(EffectKind::Empowered) {
+                EMPOWERED_POWER
+            } else {
+                0
+            };
+        Blow {
+            attacker: self.name.clone(),
+            power,
+            kind: DamageType::Physical,
+            on_hit: self.fighter.and_then(|f| f.on_hit),
+        }
+    }
+
+    /// take a blow on turn `now`, returning the damage dealt and, if the blow was
+    /// the death of this object, the XP the player earns for their part in it
+    pub fn suffer_blow(
+        &mut self,
+        blow: &Blow,
+        source: DamageSource,
+        now: i32,
+        events: &mut Events,
+    ) -> (i32, Option<i32>) {
+        let damage = blow.power - self.fighter.map_or(0, |f| f.defense);
+        let damage = self.resist(blow.kind, damage, events);
+        events.emit(GameEvent::Attack {
+            attacker: blow.attacker.clone(),
+            target: self.name.clone(),
+            damage: damage.max(0),
+            by_player: source == DamageSource::Player,
+            to_player: self.is_player(),
+            at: self.pos(),
+        });
         if damage > 0 {
-            // make the target take some damage
-            messages.add(
-                format!(
-                    "{} attacks {} for {} hit points.",
-                    self.name, target.name, damage
-                ),
-                WHITE,
-            );
-            if let Some(xp) = target.take_damage(damage, messages) {
-                // yield experience to the player
-                self.fighter.as_mut().unwrap().xp += xp;
+            // take some damage
+            let xp = self.take_damage(damage, source, now, events);
+            let resisted = |effect: &Effect| {
+                effect.kind == EffectKind::Poisoned
+                    && self.has_intrinsic(Intrinsic::PoisonResistance)
+            };
+            let on_hit = blow.on_hit.filter(|effect| self.alive && !resisted(effect));
+            if let Some(effect) = on_hit {
+                // some attackers inflict a lasting effect on their target
+                if !self.has_effect(effect.kind) {
+                    events.emit(GameEvent::Afflicted {
+                        target: self.name.clone(),
+                        kind: effect.kind,
+                    });
+                }
+                self.add_effect(effect.kind, effect.turns, now);
             }
+            (damage, xp)
         } else {
-            messages.add(
-                format!(
-                    "{} attacks {} but it has no effect!",
-                    self.name, target.name
-                ),
-                WHITE,
-            );
+            (0, None)
         }
     }
 
+    /// what is left of damage of the kind once the object's resistance to it is
+    /// taken off, or its weakness added on, telling whenever it makes a difference
+    pub fn resist(&self, kind: DamageType, damage: i32, events: &mut Events) -> i32 {
+        let resistances = match self.fighter {
+            Some(fighter) if damage > 0 => fighter.resistances,
+            _ => return damage,
+        };
+        let left = resistances.reduce(kind, damage);
+        if left != damage {
+            events.emit(GameEvent::Resisted {
+                target: self.name.clone(),
+                kind,
+                damage: left,
+                weak: left > damage,
+            });
+        }
+        left
+    }
+
+    /// the faction the object fights for, the player's while it is charmed
+    pub fn allegiance(&self) -> Faction {
+        match self.ai {
+            Some(Ai::Ally { .. }) => Faction::Player,
+            _ => self.faction,
+        }
+    }
+
+    /// whether this is the player, the one fighter whose death ends the game
+    pub fn is_player(&self) -> bool {
+        matches!(self.fighter, Some(f) if f.on_death == DeathCallback::Player)
+    }
+
+    /// invisible by nature or for a while
+    pub fn is_invisible(&self) -> bool {
+        self.invisible || self.has_effect(EffectKind::Invisible)
+    }
+
+    pub fn has_intrinsic(&self, intrinsic: Intrinsic) -> bool {
+        self.intrinsics.contains(&intrinsic)
+    }
+
+    pub fn has_effect(&self, kind: EffectKind) -> bool {
+        self.effects.iter().any(|effect| effect.kind == kind)
+    }
+
+    /// whether this is meat that has gone bad
+    pub fn is_rotten(&self) -> bool {
+        matches!(&self.meat, Some(meat) if meat.rotten)
+    }
+
+    /// put the object under an effect for some turns from turn `now`, or prolong
+    /// it if it already is
+    pub fn add_effect(&mut self, kind: EffectKind, turns: i32, now: i32) {
+        let until = now + turns;
+        match self.effects.iter_mut().find(|effect| effect.kind == kind) {
+            Some(effect) => effect.until = effect.until.max(until),
+            None => self.effects.push(ActiveEffect { kind, until }),
+        }
+    }
+
+    /// lift the effects that wear off by turn `now`, returning them
+    pub fn expire_effects(&mut self, now: i32) -> Vec<EffectKind> {
+        let expired = self
+            .effects
+            .iter()
+            .filter(|effect| effect.until <= now)
+            .map(|effect| effect.kind)
+            .collect();
+        self.effects.retain(|effect| effect.until > now);
+        expired
+    }
+
     /// heal by the given amount, without going over the maximum
     pub fn heal(&mut self, amount: i32) {
         if let Some(ref mut fighter) = self.fighter {
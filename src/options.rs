@@ -0,0 +1,318 @@
+//! Settings the player can change from the options menu. They are kept in the
+//! `options` file next to the save, read on startup and rewritten whenever the
+//! menu is closed.
+use crate::{
+    audio,
+    item::{Category, CATEGORIES},
+    menu,
+    palette::Palette,
+    tcoder::{Tcod, DEFAULT_SCREEN_HEIGHT, DEFAULT_SCREEN_WIDTH},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    error::Error,
+    fs::{self, File},
+    io::Write,
+    path::Path,
+};
+use tcod::map::FovAlgorithm;
+
+const OPTIONS_FILE: &str = "options";
+const OPTIONS_WIDTH: i32 = 44;
+
+// the values each option cycles through
+const FPS_CHOICES: &[i32] = &[10, 20, 30, 60];
+const PALETTE_CHOICES: &[Palette] = &[
+    Palette::Default,
+    Palette::Deuteranopia,
+    Palette::HighContrast,
+];
+// window sizes in characters, (0, 0) to fill the display
+const SCREEN_SIZE_CHOICES: &[(i32, i32)] = &[(80, 50), (100, 60), (120, 70), (0, 0)];
+// the sound volume in percent
+const VOLUME_CHOICES: &[i32] = &[25, 50, 75, 100];
+// turns between autosaves, 0 for none
+const AUTOSAVE_CHOICES: &[i32] = &[0, 50, 100, 250, 500];
+const FOV_CHOICES: &[FovAlgo] = &[
+    FovAlgo::Basic,
+    FovAlgo::Diamond,
+    FovAlgo::Shadow,
+    FovAlgo::Permissive,
+    FovAlgo::Restrictive,
+];
+const FONT_CHOICES: &[Font] = &[Font::Arial10, Font::Arial12, Font::DejaVu12];
+
+/// The field of view algorithms on offer, a stand-in for tcod's own enum so it
+/// can be saved.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum FovAlgo {
+    Basic,
+    Diamond,
+    Shadow,
+    Permissive,
+    Restrictive,
+}
+
+impl FovAlgo {
+    pub fn algorithm(self) -> FovAlgorithm {
+        match self {
+            FovAlgo::Basic => FovAlgorithm::Basic,
+            FovAlgo::Diamond => FovAlgorithm::Diamond,
+            FovAlgo::Shadow => FovAlgorithm::Shadow,
+            FovAlgo::Permissive => FovAlgorithm::Permissive4,
+            FovAlgo::Restrictive => FovAlgorithm::Restrictive,
+        }
+    }
+}
+
+/// The fonts on offer, all of them libtcod fonts laid out the way it lays out its
+/// own. Only the first comes with the game, the others have to be put next to it.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Font {
+    Arial10,
+    Arial12,
+    DejaVu12,
+}
+
+impl Font {
+    pub fn name(self) -> &'static str {
+        match self {
+            Font::Arial10 => "Arial 10",
+            Font::Arial12 => "Arial 12",
+            Font::DejaVu12 => "DejaVu 12",
+        }
+    }
+
+    pub fn file(self) -> &'static str {
+        match self {
+            Font::Arial10 => "arial10x10.png",
+            Font::Arial12 => "arial12x12.png",
+            Font::DejaVu12 => "dejavu12x12_gs_tc.png",
+        }
+    }
+
+    /// pixels a character takes each way
+    pub fn cell_size(self) -> i32 {
+        match self {
+            Font::Arial10 => 10,
+            Font::Arial12 | Font::DejaVu12 => 12,
+        }
+    }
+
+    /// the font, or the one that comes with the game if its file isn't there
+    pub fn available(self) -> Font {
+        if Path::new(self.file()).exists() {
+            self
+        } else {
+            Font::Arial10
+        }
+    }
+}
+
+/// What gets picked up on walking onto it, without having to ask
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutoPickup {
+    pub gold: bool,
+    /// the categories of items picked up, one bit each
+    pub categories: u32,
+}
+
+impl Default for AutoPickup {
+    fn default() -> Self {
+        AutoPickup {
+            gold: true,
+            categories: 0,
+        }
+    }
+}
+
+impl AutoPickup {
+    pub fn picks_up(self, category: Category) -> bool {
+        self.categories & (1 << category as u32) != 0
+    }
+
+    fn toggle(&mut self, category: Category) {
+        self.categories ^= 1 << category as u32;
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Options {
+    /// frames-per-second maximum
+    pub limit_fps: i32,
+    pub fullscreen: bool,
+    pub fov_algo: FovAlgo,
+    /// flash the player when hit, and shake the screen on heavy blows
+    pub screen_effects: bool,
+    /// turns between autosaves, 0 to save only when taking the stairs down
+    pub autosave_turns: i32,
+    pub palette: Palette,
+    /// draw the objects as graphical tiles, if the tileset is there. The font is
+    /// picked on startup, so this takes effect on the next one
+    pub tiles: bool,
+    /// the size of the window in characters, (0, 0) to fill the display. Also
+    /// taking effect on the next startup
+    pub screen_size: (i32, i32),
+    /// the font the text is drawn in, taking effect on the next startup as well
+    pub font: Font,
+    /// the volume of the sounds and the music, in percent
+    pub volume: i32,
+    pub mute: bool,
+    /// tint the tile over every hurt monster in sight by the health it has left
+    pub health_bars: bool,
+    /// whether the primer for first-time players has been shown, not an option
+    /// the menu offers
+    pub seen_primer: bool,
+    /// give tips in the message log as situations first come up
+    pub hints: bool,
+    /// show the summary of a level on leaving it for the first time, besides
+    /// telling it in the message log
+    pub level_summary: bool,
+    /// which of the hints have been given, one bit each, not an option either
+    pub seen_hints: u32,
+    pub auto_pickup: AutoPickup,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            limit_fps: 20,
+            fullscreen: false,
+            fov_algo: FovAlgo::Basic,
+            screen_effects: true,
+            autosave_turns: 100,
+            palette: Palette::Default,
+            tiles: false,
+            screen_size: (DEFAULT_SCREEN_WIDTH, DEFAULT_SCREEN_HEIGHT),
+            font: Font::Arial10,
+            volume: 75,
+            mute: false,
+            health_bars: true,
+            seen_primer: false,
+            hints: true,
+            level_summary: true,
+            seen_hints: 0,
+            auto_pickup: Default::default(),
+        }
+    }
+}
+
+impl Options {
+    /// the saved options, or the defaults if there are none or they can't be read
+    pub fn load() -> Self {
+        fs::read_to_string(OPTIONS_FILE)
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        let mut file = File::create(OPTIONS_FILE)?;
+        file.write_all(serde_json::to_string(self)?.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// the value following `current` in `choices`, wrapping around
+fn next<T: Copy + PartialEq>(choices: &[T], current: T) -> T {
+    let index = choices.iter().position(|&c| c == current).unwrap_or(0);
+    choices[(index + 1) % choices.len()]
+}
+
+/// let the player change the options until they are done, applying and saving them
+pub fn options_menu(tcod: &mut Tcod) {
+    loop {
+        let options = tcod.options;
+        let choices = &[
+            format!("Frame rate cap: {}", options.limit_fps),
+            format!(
+                "Fullscreen: {}",
+                if options.fullscreen { "on" } else { "off" }
+            ),
+            format!("Field of view: {:?}", options.fov_algo),
+            format!(
+                "Screen effects: {}",
+                if options.screen_effects { "on" } else { "off" }
+            ),
+            match options.autosave_turns {
+                0 => "Autosave: on descent only".into(),
+                turns => format!("Autosave: every {} turns", turns),
+            },
+            format!("Colors: {}", options.palette.name()),
+            format!(
+                "Graphical tiles: {} (on restart)",
+                if options.tiles { "on" } else { "off" }
+            ),
+            match options.screen_size {
+                (0, 0) => "Screen size: fill the display (on restart)".into(),
+                (width, height) => format!("Screen size: {}x{} (on restart)", width, height),
+            },
+            format!("Sound: {}", if options.mute { "off" } else { "on" }),
+            format!("Volume: {}%", options.volume),
+            format!(
+                "Monster health bars: {}",
+                if options.health_bars { "on" } else { "off" }
+            ),
+            format!("Hints: {}", if options.hints { "on" } else { "off" }),
+            format!(
+                "Level summary screen: {}",
+                if options.level_summary { "on" } else { "off" }
+            ),
+            format!("Font: {} (on restart)", options.font.name()),
+            "Auto-pickup...".into(),
+            "Done".into(),
+        ];
+        let choice = menu("Options\n", choices, OPTIONS_WIDTH, &mut tcod.root);
+        let options = &mut tcod.options;
+        match choice {
+            Some(0) => options.limit_fps = next(FPS_CHOICES, options.limit_fps),
+            Some(1) => options.fullscreen = !options.fullscreen,
+            Some(2) => options.fov_algo = next(FOV_CHOICES, options.fov_algo),
+            Some(3) => options.screen_effects = !options.screen_effects,
+            Some(4) => options.autosave_turns = next(AUTOSAVE_CHOICES, options.autosave_turns),
+            Some(5) => options.palette = next(PALETTE_CHOICES, options.palette),
+            Some(6) => options.tiles = !options.tiles,
+            Some(7) => options.screen_size = next(SCREEN_SIZE_CHOICES, options.screen_size),
+            Some(8) => options.mute = !options.mute,
+            Some(9) => options.volume = next(VOLUME_CHOICES, options.volume),
+            Some(10) => options.health_bars = !options.health_bars,
+            Some(11) => options.hints = !options.hints,
+            Some(12) => options.level_summary = !options.level_summary,
+            Some(13) => options.font = next(FONT_CHOICES, options.font),
+            Some(14) => auto_pickup_menu(tcod),
+            _ => break,
+        }
+        let options = &tcod.options;
+        tcod::system::set_fps(options.limit_fps);
+        tcod.root.set_fullscreen(options.fullscreen);
+        audio::configure(options);
+    }
+    // not being able to write the file only costs the options on the next start
+    let _ = tcod.options.save();
+}
+
+/// let the player pick what is picked up on walking onto it
+fn auto_pickup_menu(tcod: &mut Tcod) {
+    let on_off = |on: bool| if on { "on" } else { "off" };
+    loop {
+        let auto_pickup = tcod.options.auto_pickup;
+        let mut choices = vec![format!("Gold: {}", on_off(auto_pickup.gold))];
+        choices.extend(CATEGORIES.iter().map(|&category| {
+            format!(
+                "{}: {}",
+                category.name(),
+                on_off(auto_pickup.picks_up(category))
+            )
+        }));
+        choices.push("Done".into());
+        let choice = menu("Auto-pickup\n", &choices, OPTIONS_WIDTH, &mut tcod.root);
+        let auto_pickup = &mut tcod.options.auto_pickup;
+        match choice {
+            Some(0) => auto_pickup.gold = !auto_pickup.gold,
+            Some(index) if index <= CATEGORIES.len() => auto_pickup.toggle(CATEGORIES[index - 1]),
+            _ => break,
+        }
+    }
+}
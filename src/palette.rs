@@ -0,0 +1,256 @@
+//! The colors the map and the panel are drawn in, looked up in the palette the
+//! player picked in the options rather than hardcoded where they are drawn. The
+//! monsters and items keep their own colors, which the palette adjusts as they
+//! are drawn. The walls and floors take the colors of the level's theme, save in
+//! the high-contrast palette.
+use crate::theme::Theme;
+use serde::{Deserialize, Serialize};
+use tcod::colors::{self, Color};
+
+/// Every color a palette gives, by what it is used for
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Colors {
+    pub dark_wall: Color,
+    pub light_wall: Color,
+    pub dark_ground: Color,
+    pub light_ground: Color,
+    pub dark_shallow_water: Color,
+    pub light_shallow_water: Color,
+    pub dark_deep_water: Color,
+    pub light_deep_water: Color,
+    pub dark_lava: Color,
+    pub light_lava: Color,
+    pub dark_chasm: Color,
+    pub light_chasm: Color,
+    /// objects remembered on tiles out of sight
+    pub remembered: Color,
+    pub cursor: Color,
+    /// while aiming: tiles out of range, the shot's path and where it is blocked
+    pub out_of_range: Color,
+    pub aim_path: Color,
+    pub aim_blocked: Color,
+    /// where an announced hazard will strike
+    pub hazard: Color,
+    /// fire and poison gas lingering on the ground
+    pub fire: Color,
+    pub gas: Color,
+    /// the marks over monsters that are suspicious of the player or alerted to them
+    pub suspicious: Color,
+    pub alerted: Color,
+    /// the tint over a hurt monster, fading from the first to the second as it
+    /// loses its health
+    pub health_high: Color,
+    pub health_low: Color,
+    pub door: Color,
+    pub locked_door: Color,
+    /// the player's tile when they are hit
+    pub hit_flash: Color,
+    pub hp_bar: Color,
+    pub hp_bar_back: Color,
+    pub mana_bar: Color,
+    pub mana_bar_back: Color,
+}
+
+const DEFAULT_COLORS: Colors = Colors {
+    dark_wall: Color { r: 0, g: 0, b: 100 },
+    light_wall: Color {
+        r: 130,
+        g: 110,
+        b: 50,
+    },
+    dark_ground: Color {
+        r: 50,
+        g: 50,
+        b: 150,
+    },
+    light_ground: Color {
+        r: 200,
+        g: 180,
+        b: 50,
+    },
+    dark_shallow_water: Color {
+        r: 30,
+        g: 60,
+        b: 140,
+    },
+    light_shallow_water: Color {
+        r: 70,
+        g: 130,
+        b: 200,
+    },
+    dark_deep_water: Color {
+        r: 10,
+        g: 20,
+        b: 90,
+    },
+    light_deep_water: Color {
+        r: 20,
+        g: 50,
+        b: 160,
+    },
+    dark_lava: Color { r: 90, g: 20, b: 0 },
+    light_lava: Color {
+        r: 230,
+        g: 80,
+        b: 10,
+    },
+    dark_chasm: colors::BLACK,
+    light_chasm: Color {
+        r: 20,
+        g: 15,
+        b: 10,
+    },
+    remembered: colors::GREY,
+    cursor: colors::WHITE,
+    out_of_range: colors::GREY,
+    aim_path: colors::DARK_YELLOW,
+    aim_blocked: colors::DARK_RED,
+    hazard: colors::DARKER_ORANGE,
+    fire: colors::DARK_FLAME,
+    gas: colors::DARK_CHARTREUSE,
+    suspicious: colors::YELLOW,
+    alerted: colors::LIGHT_RED,
+    health_high: colors::DARK_GREEN,
+    health_low: colors::DARK_RED,
+    door: colors::LIGHT_SEPIA,
+    locked_door: colors::GOLD,
+    hit_flash: colors::RED,
+    hp_bar: colors::LIGHT_RED,
+    hp_bar_back: colors::DARKER_RED,
+    mana_bar: colors::LIGHT_BLUE,
+    mana_bar_back: colors::DARKER_BLUE,
+};
+
+// the map is blue and yellow already, what is left to fix are the yellow-red
+// pairs and the reds on dark backgrounds
+const DEUTERANOPIA_COLORS: Colors = Colors {
+    aim_path: colors::DARK_AZURE,
+    aim_blocked: colors::DARK_ORANGE,
+    hazard: colors::DARK_MAGENTA,
+    gas: colors::DARK_VIOLET,
+    alerted: colors::ORANGE,
+    health_high: colors::DARK_AZURE,
+    health_low: colors::DARK_ORANGE,
+    hit_flash: colors::ORANGE,
+    hp_bar: colors::ORANGE,
+    hp_bar_back: colors::DARKER_ORANGE,
+    ..DEFAULT_COLORS
+};
+
+const HIGH_CONTRAST_COLORS: Colors = Colors {
+    dark_wall: Color {
+        r: 90,
+        g: 90,
+        b: 90,
+    },
+    light_wall: Color {
+        r: 230,
+        g: 230,
+        b: 230,
+    },
+    dark_ground: Color {
+        r: 20,
+        g: 20,
+        b: 20,
+    },
+    light_ground: Color {
+        r: 50,
+        g: 50,
+        b: 50,
+    },
+    dark_shallow_water: colors::DARKER_AZURE,
+    light_shallow_water: colors::AZURE,
+    dark_deep_water: colors::DARKEST_BLUE,
+    light_deep_water: colors::DARK_BLUE,
+    dark_lava: colors::DARKER_RED,
+    light_lava: colors::RED,
+    dark_chasm: colors::BLACK,
+    light_chasm: colors::BLACK,
+    remembered: colors::LIGHT_GREY,
+    cursor: colors::YELLOW,
+    out_of_range: colors::DARK_GREY,
+    aim_path: colors::BLUE,
+    aim_blocked: colors::RED,
+    hazard: colors::DARK_ORANGE,
+    fire: colors::RED,
+    gas: colors::GREEN,
+    suspicious: colors::YELLOW,
+    alerted: colors::RED,
+    health_high: colors::GREEN,
+    health_low: colors::RED,
+    door: colors::ORANGE,
+    locked_door: colors::YELLOW,
+    hit_flash: colors::RED,
+    hp_bar: colors::RED,
+    hp_bar_back: colors::DARKEST_RED,
+    mana_bar: colors::BLUE,
+    mana_bar_back: colors::DARKEST_BLUE,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Palette {
+    Default,
+    /// safe for red-green colorblindness
+    Deuteranopia,
+    HighContrast,
+}
+
+impl Palette {
+    pub fn name(self) -> &'static str {
+        match self {
+            Palette::Default => "default",
+            Palette::Deuteranopia => "deuteranopia",
+            Palette::HighContrast => "high contrast",
+        }
+    }
+
+    pub fn colors(self) -> &'static Colors {
+        match self {
+            Palette::Default => &DEFAULT_COLORS,
+            Palette::Deuteranopia => &DEUTERANOPIA_COLORS,
+            Palette::HighContrast => &HIGH_CONTRAST_COLORS,
+        }
+    }
+
+    /// the colors to draw a level of the given theme in, the high-contrast palette
+    /// keeping its own walls and floors
+    pub fn themed(self, theme: Theme) -> Colors {
+        let colors = *self.colors();
+        if self == Palette::HighContrast {
+            return colors;
+        }
+        let themed = theme.colors();
+        Colors {
+            dark_wall: themed.dark_wall,
+            light_wall: themed.light_wall,
+            dark_ground: themed.dark_ground,
+            light_ground: themed.light_ground,
+            ..colors
+        }
+    }
+
+    /// The color to draw a monster or an item of the given color in. Greens are
+    /// given blue so they no longer pass for reds, and the high-contrast palette
+    /// brightens every color as far as it goes.
+    pub fn object_color(self, color: Color) -> Color {
+        match self {
+            Palette::Default => color,
+            Palette::Deuteranopia => {
+                let blue = color.b as i32 + (color.g as i32 - color.r as i32).max(0);
+                Color {
+                    b: blue.min(255) as u8,
+                    ..color
+                }
+            }
+            Palette::HighContrast => {
+                let brightest = color.r.max(color.g).max(color.b).max(1) as i32;
+                let scale = |channel: u8| (channel as i32 * 255 / brightest) as u8;
+                Color {
+                    r: scale(color.r),
+                    g: scale(color.g),
+                    b: scale(color.b),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,284 @@
+//! Finding the way around the dungeon, for anything that needs more than a
+//! straight line towards its goal: paths to a tile, Dijkstra maps leading
+//! towards or away from the player, and the scent the player leaves behind.
+use crate::tcoder::{MAP_HEIGHT, MAP_WIDTH};
+use std::collections::VecDeque;
+
+/// Finds the shortest ways between tiles, keeping its buffers from one search to
+/// the next so the monsters looking for their way every turn don't allocate
+#[derive(Debug, Default)]
+pub struct PathFinder {
+    /// the tile each tile was reached from in the last search
+    came_from: Vec<Vec<Option<(i32, i32)>>>,
+    frontier: VecDeque<(i32, i32)>,
+}
+
+impl PathFinder {
+    /// Search for the shortest way from `from` to `to` over the tiles `passable`
+    /// lets through, moving in all eight directions. Returns whether there is one.
+    fn search<F>(&mut self, from: (i32, i32), to: (i32, i32), passable: F) -> bool
+    where
+        F: Fn(i32, i32) -> bool,
+    {
+        let in_map = |x: i32, y: i32| x >= 0 && y >= 0 && x < MAP_WIDTH && y < MAP_HEIGHT;
+        if !in_map(to.0, to.1) {
+            return false;
+        }
+
+        // breadth-first from the start, remembering where each tile was reached from
+        let came_from = &mut self.came_from;
+        came_from.resize_with(MAP_WIDTH as usize, Vec::new);
+        for column in came_from.iter_mut() {
+            column.clear();
+            column.resize(MAP_HEIGHT as usize, None);
+        }
+        came_from[from.0 as usize][from.1 as usize] = Some(from);
+        self.frontier.clear();
+        self.frontier.push_back(from);
+        while let Some((x, y)) = self.frontier.pop_front() {
+            if (x, y) == to {
+                break;
+            }
+            for dx in -1..2 {
+                for dy in -1..2 {
+                    let (next_x, next_y) = (x + dx, y + dy);
+                    if in_map(next_x, next_y)
+                        && came_from[next_x as usize][next_y as usize].is_none()
+                        && passable(next_x, next_y)
+                    {
+                        came_from[next_x as usize][next_y as usize] = Some((x, y));
+                        self.frontier.push_back((next_x, next_y));
+                    }
+                }
+            }
+        }
+        came_from[to.0 as usize][to.1 as usize].is_some()
+    }
+
+    /// The shortest way from `from` to `to` over the tiles `passable` lets through,
+    /// moving in all eight directions. The steps are listed from the last to the
+    /// first, ready to be popped, and leave out the start. None if there is no way.
+    pub fn find_path<F>(
+        &mut self,
+        from: (i32, i32),
+        to: (i32, i32),
+        passable: F,
+    ) -> Option<Vec<(i32, i32)>>
+    where
+        F: Fn(i32, i32) -> bool,
+    {
+        if !self.search(from, to, passable) {
+            return None;
+        }
+        // walk back from the goal
+        let mut path = vec![];
+        let mut step = to;
+        while step != from {
+            path.push(step);
+            step = self.came_from[step.0 as usize][step.1 as usize].unwrap();
+        }
+        Some(path)
+    }
+
+    /// the first step of the shortest way from `from` to `to`, without listing
+    /// the rest of the way. None if there is no way, or nowhere to step
+    pub fn first_step<F>(
+        &mut self,
+        from: (i32, i32),
+        to: (i32, i32),
+        passable: F,
+    ) -> Option<(i32, i32)>
+    where
+        F: Fn(i32, i32) -> bool,
+    {
+        if from == to || !self.search(from, to, passable) {
+            return None;
+        }
+        let mut step = to;
+        loop {
+            let previous = self.came_from[step.0 as usize][step.1 as usize].unwrap();
+            if previous == from {
+                return Some(step);
+            }
+            step = previous;
+        }
+    }
+}
+
+// fleeing monsters value getting away this much more than the steps it takes, so
+// they run around corners rather than into the nearest dead end
+const FLEE_FACTOR: f32 = 1.2;
+// turns the player's scent lingers on a tile after they left it
+const SCENT_TURNS: i32 = 30;
+
+/// the tiles around (x, y) that are on the map
+fn neighbours(x: i32, y: i32) -> impl Iterator<Item = (i32, i32)> {
+    (-1..2)
+        .flat_map(move |dx| (-1..2).map(move |dy| (x + dx, y + dy)))
+        .filter(move |&(next_x, next_y)| {
+            (next_x, next_y) != (x, y)
+                && next_x >= 0
+                && next_y >= 0
+                && next_x < MAP_WIDTH
+                && next_y < MAP_HEIGHT
+        })
+}
+
+/// A Dijkstra map: the steps from every tile to the nearest of some goals. A
+/// monster walking downhill on it heads for the closest goal by the shortest way.
+#[derive(Debug, Default)]
+pub struct DijkstraMap {
+    /// None on the tiles the goals can't be reached from
+    distances: Vec<Vec<Option<i32>>>,
+    passable: Vec<Vec<bool>>,
+    /// the tiles left to reach the neighbours of, kept for the next filling
+    frontier: VecDeque<(i32, i32)>,
+}
+
+impl DijkstraMap {
+    /// how far every tile is from the nearest goal, over the tiles `passable` lets
+    /// through
+    pub fn new<F>(goals: &[(i32, i32)], passable: F) -> Self
+    where
+        F: Fn(i32, i32) -> bool,
+    {
+        let mut map = DijkstraMap::default();
+        map.fill(goals, passable);
+        map
+    }
+
+    /// work the distances out afresh for new goals, in the buffers of the last
+    /// time rather than new ones
+    pub fn fill<F>(&mut self, goals: &[(i32, i32)], passable: F)
+    where
+        F: Fn(i32, i32) -> bool,
+    {
+        self.passable.resize_with(MAP_WIDTH as usize, Vec::new);
+        for (x, column) in self.passable.iter_mut().enumerate() {
+            column.clear();
+            column.extend((0..MAP_HEIGHT).map(|y| passable(x as i32, y)));
+        }
+        self.distances.resize_with(MAP_WIDTH as usize, Vec::new);
+        for column in &mut self.distances {
+            column.clear();
+            column.resize(MAP_HEIGHT as usize, None);
+        }
+        let (distances, passable) = (&mut self.distances, &self.passable);
+        self.frontier.clear();
+        for &(x, y) in goals {
+            distances[x as usize][y as usize] = Some(0);
+            self.frontier.push_back((x, y));
+        }
+        while let Some((x, y)) = self.frontier.pop_front() {
+            let next_distance = distances[x as usize][y as usize].map(|d| d + 1);
+            for (next_x, next_y) in neighbours(x, y) {
+                let (next_x, next_y) = (next_x as usize, next_y as usize);
+                if passable[next_x][next_y] && distances[next_x][next_y].is_none() {
+                    distances[next_x][next_y] = next_distance;
+                    self.frontier.push_back((next_x as i32, next_y as i32));
+                }
+            }
+        }
+    }
+
+    /// Make this the map to walk downhill on to get away from the goals of
+    /// `towards`, reusing its buffers. The distances are flipped and then left to
+    /// settle, so that the way out of a dead end leads back past the goal rather
+    /// than deeper into it.
+    pub fn flee_from(&mut self, towards: &DijkstraMap) {
+        self.distances.clone_from(&towards.distances);
+        self.passable.clone_from(&towards.passable);
+        let (distances, passable) = (&mut self.distances, &self.passable);
+        for distance in distances.iter_mut().flatten().flatten() {
+            *distance = -(*distance as f32 * FLEE_FACTOR) as i32;
+        }
+        let mut settled = false;
+        while !settled {
+            settled = true;
+            for x in 0..MAP_WIDTH {
+                for y in 0..MAP_HEIGHT {
+                    let here = match distances[x as usize][y as usize] {
+                        Some(here) => here,
+                        None => continue,
+                    };
+                    let lowest = neighbours(x, y)
+                        .filter(|&(x, y)| passable[x as usize][y as usize])
+                        .filter_map(|(x, y)| distances[x as usize][y as usize])
+                        .min();
+                    if let Some(lowest) = lowest.filter(|&lowest| lowest + 1 < here) {
+                        distances[x as usize][y as usize] = Some(lowest + 1);
+                        settled = false;
+                    }
+                }
+            }
+        }
+    }
+
+    /// The step from (x, y) to the lowest tile around it that `free` lets
+    /// through, None if none is lower than (x, y)
+    pub fn downhill<F>(&self, x: i32, y: i32, free: F) -> Option<(i32, i32)>
+    where
+        F: Fn(i32, i32) -> bool,
+    {
+        let here = self.distances[x as usize][y as usize]?;
+        neighbours(x, y)
+            .filter(|&(next_x, next_y)| free(next_x, next_y))
+            .filter_map(|(next_x, next_y)| {
+                let distance = self.distances[next_x as usize][next_y as usize]?;
+                Some(((next_x - x, next_y - y), distance))
+            })
+            .filter(|&(_, distance)| distance < here)
+            .min_by_key(|&(_, distance)| distance)
+            .map(|(step, _)| step)
+    }
+}
+
+/// Where the player has walked lately, for monsters that lost sight of them to
+/// follow their trail. The scent fades away over a few turns.
+#[derive(Debug)]
+pub struct ScentMap {
+    /// the turn the player last stood on each tile
+    last_visit: Vec<Vec<Option<i32>>>,
+}
+
+impl Default for ScentMap {
+    fn default() -> Self {
+        ScentMap {
+            last_visit: vec![vec![None; MAP_HEIGHT as usize]; MAP_WIDTH as usize],
+        }
+    }
+}
+
+impl ScentMap {
+    pub fn mark(&mut self, x: i32, y: i32, turn: i32) {
+        self.last_visit[x as usize][y as usize] = Some(turn);
+    }
+
+    /// how strong the scent on (x, y) still is, 0 once it has faded
+    fn strength(&self, x: i32, y: i32, turn: i32) -> i32 {
+        match self.last_visit[x as usize][y as usize] {
+            Some(visit) => (SCENT_TURNS - (turn - visit)).max(0),
+            None => 0,
+        }
+    }
+
+    /// The step from (x, y) to the strongest scent around it that `free` lets
+    /// through, None if none is stronger than on (x, y)
+    pub fn follow<F>(&self, x: i32, y: i32, turn: i32, free: F) -> Option<(i32, i32)>
+    where
+        F: Fn(i32, i32) -> bool,
+    {
+        let here = self.strength(x, y, turn);
+        neighbours(x, y)
+            .filter(|&(next_x, next_y)| free(next_x, next_y))
+            .map(|(next_x, next_y)| {
+                (
+                    (next_x - x, next_y - y),
+                    self.strength(next_x, next_y, turn),
+                )
+            })
+            .filter(|&(_, strength)| strength > here)
+            .max_by_key(|&(_, strength)| strength)
+            .map(|(step, _)| step)
+    }
+}
@@ -0,0 +1,206 @@
+//! The menu for picking things up from a pile. Items that look the same are
+//! stacked on one line, any number of lines can be checked before taking them,
+//! and typing after `/` narrows the list down to the names that match.
+use crate::object::Object;
+use tcod::colors::{DARKER_BLUE, LIGHT_GREY, LIGHT_YELLOW, WHITE};
+use tcod::console::{blit, Console, Offscreen, Root};
+use tcod::input::KeyCode;
+use tcod::{BackgroundFlag, TextAlignment};
+
+const PICKUP_WIDTH: i32 = 50;
+// lines shown at once, each checked with a letter
+const MAX_LINES: usize = 26;
+
+/// Items on a pile that look alike and are picked up together
+struct Stack {
+    name: String,
+    /// indices into the pile
+    items: Vec<usize>,
+    checked: bool,
+}
+
+impl Stack {
+    fn label(&self) -> String {
+        match self.items.len() {
+            1 => self.name.clone(),
+            count => format!("{} x {}", count, self.name),
+        }
+    }
+}
+
+/// Let the player choose what to take from `pile`, returning the indices of the
+/// items chosen. Nothing is chosen if the menu is cancelled.
+pub fn pickup_menu(pile: &[&Object], root: &mut Root) -> Vec<usize> {
+    let mut stacks: Vec<Stack> = vec![];
+    for (index, object) in pile.iter().enumerate() {
+        let name = object.inventory_name();
+        match stacks.iter_mut().find(|stack| stack.name == name) {
+            Some(stack) => stack.items.push(index),
+            None => stacks.push(Stack {
+                name,
+                items: vec![index],
+                checked: false,
+            }),
+        }
+    }
+
+    let mut filter = String::new();
+    let mut typing_filter = false;
+    let mut selected = 0;
+
+    // what lies under the menu, redrawn before every change
+    let mut background = Offscreen::new(root.width(), root.height());
+    let whole_screen = (root.width(), root.height());
+    blit(
+        root,
+        (0, 0),
+        whole_screen,
+        &mut background,
+        (0, 0),
+        1.0,
+        1.0,
+    );
+
+    loop {
+        // the stacks whose name matches the filter, as many as there are letters
+        let needle = filter.to_lowercase();
+        let matching: Vec<usize> = (0..stacks.len())
+            .filter(|&id| stacks[id].name.to_lowercase().contains(&needle))
+            .collect();
+        let shown = &matching[..matching.len().min(MAX_LINES)];
+        selected = selected.min(shown.len().saturating_sub(1));
+
+        let header = if typing_filter || !filter.is_empty() {
+            format!("Pick up what? Filter: {}", filter)
+        } else {
+            "Pick up what?".to_string()
+        };
+        let hidden = matching.len() - shown.len();
+        // the header, a line per stack (or to say there are none), a line for
+        // those left out and the two lines of help
+        let rows = shown.len().max(1) as i32;
+        let height = 1 + rows + if hidden > 0 { 1 } else { 0 } + 2;
+        let mut window = Offscreen::new(PICKUP_WIDTH, height);
+        window.set_default_foreground(LIGHT_YELLOW);
+        window.print_ex(0, 0, BackgroundFlag::None, TextAlignment::Left, header);
+        for (row, &id) in shown.iter().enumerate() {
+            let y = row as i32 + 1;
+            if row == selected {
+                window.set_default_background(DARKER_BLUE);
+                window.rect(0, y, PICKUP_WIDTH, 1, false, BackgroundFlag::Set);
+            }
+            let letter = (b'a' + row as u8) as char;
+            let check = if stacks[id].checked { 'x' } else { ' ' };
+            let text = format!("({}) [{}] {}", letter, check, stacks[id].label());
+            window.set_default_foreground(WHITE);
+            window.print_ex(1, y, BackgroundFlag::None, TextAlignment::Left, text);
+        }
+        window.set_default_foreground(LIGHT_GREY);
+        if shown.is_empty() {
+            window.print_ex(
+                1,
+                1,
+                BackgroundFlag::None,
+                TextAlignment::Left,
+                "Nothing matches.",
+            );
+        }
+        if hidden > 0 {
+            let more = format!("...and {} more, filter with /", hidden);
+            window.print_ex(1, rows + 1, BackgroundFlag::None, TextAlignment::Left, more);
+        }
+        let help = if typing_filter {
+            "Type to filter, Enter when done"
+        } else {
+            "Letters check, ',' checks all, / filters"
+        };
+        window.print_ex(
+            0,
+            height - 2,
+            BackgroundFlag::None,
+            TextAlignment::Left,
+            help,
+        );
+        window.print_ex(
+            0,
+            height - 1,
+            BackgroundFlag::None,
+            TextAlignment::Left,
+            "Enter takes the checked or highlighted items",
+        );
+
+        blit(&background, (0, 0), whole_screen, root, (0, 0), 1.0, 1.0);
+        let x = root.width() / 2 - PICKUP_WIDTH / 2;
+        let y = root.height() / 2 - height / 2;
+        blit(
+            &window,
+            (0, 0),
+            (PICKUP_WIDTH, height),
+            root,
+            (x, y),
+            1.0,
+            0.7,
+        );
+        root.flush();
+
+        let key = root.wait_for_keypress(true);
+        if typing_filter {
+            match key.code {
+                KeyCode::Enter => typing_filter = false,
+                KeyCode::Escape => {
+                    typing_filter = false;
+                    filter.clear();
+                }
+                KeyCode::Backspace => {
+                    filter.pop();
+                }
+                _ if key.printable != '\0' && !key.printable.is_control() => {
+                    filter.push(key.printable);
+                    selected = 0;
+                }
+                _ => {}
+            }
+            continue;
+        }
+        match key.code {
+            KeyCode::Up if !shown.is_empty() => {
+                selected = (selected + shown.len() - 1) % shown.len()
+            }
+            KeyCode::Down if !shown.is_empty() => selected = (selected + 1) % shown.len(),
+            KeyCode::Spacebar if !shown.is_empty() => {
+                let stack = &mut stacks[shown[selected]];
+                stack.checked = !stack.checked;
+            }
+            KeyCode::Enter => {
+                let mut chosen: Vec<usize> = stacks
+                    .iter()
+                    .filter(|stack| stack.checked)
+                    .flat_map(|stack| stack.items.iter().cloned())
+                    .collect();
+                if chosen.is_empty() {
+                    if let Some(&id) = shown.get(selected) {
+                        chosen = stacks[id].items.clone();
+                    }
+                }
+                return chosen;
+            }
+            _ if key.printable == '/' => typing_filter = true,
+            _ if key.printable == ',' => {
+                // check everything shown, or clear it all if it already was
+                let all_checked = shown.iter().all(|&id| stacks[id].checked);
+                for &id in shown {
+                    stacks[id].checked = !all_checked;
+                }
+            }
+            _ if key.printable.is_ascii_lowercase() => {
+                let row = key.printable as usize - 'a' as usize;
+                if let Some(&id) = shown.get(row) {
+                    stacks[id].checked = !stacks[id].checked;
+                    selected = row;
+                }
+            }
+            KeyCode::Escape => return vec![],
+            _ => {}
+        }
+    }
+}
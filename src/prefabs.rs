@@ -0,0 +1,156 @@
+//! Rooms laid out by hand: fountain rooms, barracks, libraries and flooded
+//! rooms, read from the text templates in `src/prefabs` and stamped into rooms
+//! of a generated level. A template starts with its name, the feeling the player
+//! gets on arriving at a level with it and the branches it turns up in, followed
+//! by its rows:
+//!
+//! - `.` floor, `#` wall, `~` shallow water, `=` deep water
+//! - `{` a fountain, `|` a bookshelf and `b` a bunk
+//! - `o` a bunk with a monster asleep on it
+//! - `?`, `!` and `[` a random scroll, potion or piece of armor
+//!
+//! It is stamped in the middle of the room, maybe mirrored, keeping a ring of
+//! floor around it so no doorway is cut off.
+use crate::{
+    blessings::random_beatitude,
+    dungeon::LevelId,
+    item::{item_object, Category, ITEMS},
+    map::{Map, Terrain, Tile},
+    monsters::{create_monster, put_to_sleep},
+    object::Object,
+    rect::Rect,
+};
+use rand::Rng;
+use tcod::colors::{LIGHT_BLUE, SEPIA};
+use tcod::Color;
+
+const TEMPLATES: &[&str] = &[
+    include_str!("prefabs/fountain.txt"),
+    include_str!("prefabs/barracks.txt"),
+    include_str!("prefabs/library.txt"),
+    include_str!("prefabs/flooded.txt"),
+];
+
+pub struct Prefab {
+    pub name: &'static str,
+    /// told to the player arriving on a level with it
+    pub feeling: &'static str,
+    /// the names of the branches it turns up in
+    branches: Vec<&'static str>,
+    rows: Vec<Vec<char>>,
+}
+
+/// every prefab there is
+pub fn prefabs() -> Vec<Prefab> {
+    TEMPLATES
+        .iter()
+        .map(|template| Prefab::parse(template))
+        .collect()
+}
+
+impl Prefab {
+    fn parse(template: &'static str) -> Self {
+        let mut prefab = Prefab {
+            name: "",
+            feeling: "",
+            branches: vec![],
+            rows: vec![],
+        };
+        for line in template.lines().filter(|line| !line.is_empty()) {
+            match line.split_once(": ") {
+                Some(("name", name)) => prefab.name = name,
+                Some(("feeling", feeling)) => prefab.feeling = feeling,
+                Some(("branches", branches)) => {
+                    prefab.branches = branches.split_whitespace().collect()
+                }
+                _ => prefab.rows.push(line.chars().collect()),
+            }
+        }
+        prefab
+    }
+
+    fn width(&self) -> i32 {
+        self.rows.iter().map(|row| row.len()).max().unwrap_or(0) as i32
+    }
+
+    fn height(&self) -> i32 {
+        self.rows.len() as i32
+    }
+
+    pub fn found_in(&self, level: LevelId) -> bool {
+        self.branches.contains(&level.branch.name())
+    }
+
+    /// whether it fits inside the room with a ring of floor around it
+    pub fn fits(&self, room: Rect) -> bool {
+        self.width() <= room.x2 - room.x1 - 3 && self.height() <= room.y2 - room.y1 - 3
+    }
+
+    /// Lay it out in the middle of the room, clearing away whatever was placed
+    /// there before. The monsters and items it holds are those of the level
+    pub fn stamp(
+        &self,
+        room: Rect,
+        level: LevelId,
+        luck: i32,
+        map: &mut Map,
+        objects: &mut Vec<Object>,
+        rng: &mut impl Rng,
+    ) {
+        let (width, height) = (self.width(), self.height());
+        let left = room.x1 + 1 + (room.x2 - room.x1 - 1 - width) / 2;
+        let top = room.y1 + 1 + (room.y2 - room.y1 - 1 - height) / 2;
+        let inside = |x: i32, y: i32| x >= left && x < left + width && y >= top && y < top + height;
+        objects.retain(|object| !inside(object.x, object.y));
+
+        let (mirror_x, mirror_y): (bool, bool) = (rng.gen(), rng.gen());
+        for (row, glyphs) in self.rows.iter().enumerate() {
+            for (column, &glyph) in glyphs.iter().enumerate() {
+                let (column, row) = (column as i32, row as i32);
+                let x = left + if mirror_x { width - 1 - column } else { column };
+                let y = top + if mirror_y { height - 1 - row } else { row };
+                let tile = &mut map[x as usize][y as usize];
+                *tile = if glyph == '#' {
+                    Tile::wall()
+                } else {
+                    Tile::empty()
+                };
+                match glyph {
+                    '~' => tile.terrain = Terrain::ShallowWater,
+                    '=' => tile.terrain = Terrain::DeepWater,
+                    '{' => objects.push(furniture(x, y, '{', "fountain", LIGHT_BLUE, true)),
+                    '|' => objects.push(furniture(x, y, '|', "bookshelf", SEPIA, true)),
+                    'b' => objects.push(furniture(x, y, '=', "bunk", SEPIA, false)),
+                    'o' => {
+                        objects.push(furniture(x, y, '=', "bunk", SEPIA, false));
+                        let mut monster = create_monster(x, y, level, rng);
+                        put_to_sleep(&mut monster);
+                        objects.push(monster);
+                    }
+                    '?' => objects.push(random_item(Category::Scroll, x, y, luck, rng)),
+                    '!' => objects.push(random_item(Category::Potion, x, y, luck, rng)),
+                    '[' => objects.push(random_item(Category::Armor, x, y, luck, rng)),
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+fn furniture(x: i32, y: i32, char: char, name: &str, color: Color, blocks: bool) -> Object {
+    let mut object = Object::new(x, y, char, name, color, blocks);
+    object.always_visible = true;
+    object
+}
+
+/// an item of any kind in the category, whatever the level
+fn random_item(category: Category, x: i32, y: i32, luck: i32, rng: &mut impl Rng) -> Object {
+    let kinds: Vec<_> = ITEMS
+        .iter()
+        .filter(|item| item.category() == category)
+        .collect();
+    let item = **rng.choose(&kinds).unwrap();
+    let mut object = item_object(item, x, y, luck, rng);
+    object.beatitude = random_beatitude(item, rng);
+    object
+}
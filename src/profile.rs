@@ -0,0 +1,216 @@
+//! The player's profile, kept across games in the `profile` file apart from the
+//! saves: the runs finished and won, the deepest any character got, and the
+//! starting classes unlocked along the way. The main menu shows it, and the new
+//! game menu offers the classes unlocked.
+use crate::{
+    game::{Game, PLAYER},
+    item::{item_object, Item, Slot},
+    spells::Spell,
+};
+use rand::thread_rng;
+use serde::{Deserialize, Serialize};
+use std::{
+    error::Error,
+    fs::{self, File},
+    io::Write,
+};
+use tcod::colors::{LIGHT_CYAN, RED};
+
+const PROFILE_FILE: &str = "profile";
+// runs it takes to unlock the fighter, and the depth to reach for the rogue
+const FIGHTER_RUNS: u32 = 3;
+const ROGUE_DEPTH: u32 = 5;
+// what the fighter and the mage set out with over a wanderer
+const FIGHTER_HP: i32 = 10;
+const MAGE_MANA: i32 = 10;
+
+/// What a new character sets out as
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Class {
+    #[default]
+    Wanderer,
+    Fighter,
+    Rogue,
+    Mage,
+}
+
+/// every class there is, in the order the new game menu lists them
+pub const CLASSES: &[Class] = &[Class::Wanderer, Class::Fighter, Class::Rogue, Class::Mage];
+
+impl Class {
+    pub fn name(self) -> &'static str {
+        match self {
+            Class::Wanderer => "wanderer",
+            Class::Fighter => "fighter",
+            Class::Rogue => "rogue",
+            Class::Mage => "mage",
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Class::Wanderer => "Wanderer: a torch and a will to survive",
+            Class::Fighter => "Fighter: tougher, in chain mail and with a shield",
+            Class::Rogue => "Rogue: a dagger in each hand, and a potion to vanish",
+            Class::Mage => "Mage: more mana, and the magic missile spell",
+        }
+    }
+
+    /// what it takes to unlock the class
+    fn requirement(self) -> String {
+        match self {
+            Class::Wanderer => "always open".into(),
+            Class::Fighter => format!("finish {} runs", FIGHTER_RUNS),
+            Class::Rogue => format!("reach depth {}", ROGUE_DEPTH),
+            Class::Mage => "win a run".into(),
+        }
+    }
+
+    fn unlocked_by(self, profile: &Profile) -> bool {
+        match self {
+            Class::Wanderer => true,
+            Class::Fighter => profile.runs >= FIGHTER_RUNS,
+            Class::Rogue => profile.deepest >= ROGUE_DEPTH,
+            Class::Mage => profile.wins > 0,
+        }
+    }
+
+    /// fit out a new character of the class, on top of the torch everyone gets
+    pub fn outfit(self, game: &mut Game) {
+        match self {
+            Class::Wanderer => {}
+            Class::Fighter => {
+                if let Some(fighter) = game.objects[PLAYER].fighter.as_mut() {
+                    fighter.max_hp += FIGHTER_HP;
+                    fighter.hp = fighter.max_hp;
+                }
+                wear(game, Item::ChainMail);
+                wear(game, Item::Shield);
+            }
+            Class::Rogue => {
+                wear(game, Item::Dagger);
+                wear(game, Item::Dagger);
+                carry(game, Item::Invisibility);
+            }
+            Class::Mage => {
+                if let Some(fighter) = game.objects[PLAYER].fighter.as_mut() {
+                    fighter.max_mana += MAGE_MANA;
+                    fighter.mana = fighter.max_mana;
+                }
+                game.spells.push(Spell::MagicMissile);
+            }
+        }
+    }
+}
+
+/// put the item in the new character's pack, known for what it is
+fn carry(game: &mut Game, item: Item) {
+    let mut object = item_object(item, 0, 0, 0, &mut thread_rng());
+    object.name = item.name().into();
+    object.unidentified = false;
+    game.inventory.push(object);
+}
+
+/// put the item on the new character, a second weapon going in the off hand
+fn wear(game: &mut Game, item: Item) {
+    let off_hand = item.slot() == Some(Slot::Weapon)
+        && game
+            .inventory
+            .iter()
+            .any(|object| object.equipped && object.worn_slot() == Some(Slot::Weapon));
+    carry(game, item);
+    let object = game.inventory.last_mut().unwrap();
+    object.equipped = true;
+    object.off_hand = off_hand;
+    let defense = item.armor().map_or(0, |armor| armor.defense);
+    if let Some(fighter) = game.objects[PLAYER].fighter.as_mut() {
+        fighter.defense += defense;
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    /// runs that came to an end, by death, victory or being abandoned
+    runs: u32,
+    wins: u32,
+    /// the deepest level any character reached
+    deepest: u32,
+    unlocked: Vec<Class>,
+}
+
+impl Profile {
+    /// the saved profile, or a fresh one if there is none or it can't be read
+    pub fn load() -> Self {
+        fs::read_to_string(PROFILE_FILE)
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn Error>> {
+        let mut file = File::create(PROFILE_FILE)?;
+        file.write_all(serde_json::to_string(self)?.as_bytes())?;
+        Ok(())
+    }
+
+    /// the classes a new character may be, in menu order
+    pub fn classes(&self) -> Vec<Class> {
+        CLASSES
+            .iter()
+            .copied()
+            .filter(|&class| class == Class::Wanderer || self.unlocked.contains(&class))
+            .collect()
+    }
+
+    /// the line the main menu shows
+    pub fn summary(&self) -> String {
+        format!(
+            "Runs: {}   Wins: {}   Deepest: {}   Classes: {}/{}",
+            self.runs,
+            self.wins,
+            self.deepest,
+            self.classes().len(),
+            CLASSES.len()
+        )
+    }
+
+    /// what is left to unlock, and how, for the new game menu
+    pub fn locked(&self) -> Vec<String> {
+        CLASSES
+            .iter()
+            .filter(|class| !self.classes().contains(class))
+            .map(|class| format!("{} ({})", class.name(), class.requirement()))
+            .collect()
+    }
+
+    /// count the run that just ended, returning the classes it unlocks
+    fn record_run(&mut self, game: &Game, won: bool) -> Vec<Class> {
+        self.runs += 1;
+        self.wins += won as u32;
+        self.deepest = self.deepest.max(game.deepest_depth());
+        let unlocked: Vec<_> = CLASSES
+            .iter()
+            .copied()
+            .filter(|&class| class.unlocked_by(self) && !self.classes().contains(&class))
+            .collect();
+        self.unlocked.extend(&unlocked);
+        unlocked
+    }
+}
+
+/// count the finished run in the profile, telling the player what it unlocked,
+/// or why the profile couldn't be kept
+pub fn record_run_end(game: &mut Game, won: bool) {
+    let mut profile = Profile::load();
+    for class in profile.record_run(game, won) {
+        game.messages.add(
+            format!("You have unlocked the {} class!", class.name()),
+            LIGHT_CYAN,
+        );
+    }
+    if let Err(e) = profile.save() {
+        game.messages
+            .add(format!("Could not save the profile: {}", e), RED);
+    }
+}
@@ -0,0 +1,223 @@
+//! Quests given by the elder in town: slay so many of a kind of monster, or
+//! bring back an item from the dungeon. The quests taken on are kept in the
+//! journal, the kills counted as the events come in, and the reward is paid in
+//! experience and gold once the player reports back to the elder.
+use crate::{
+    events::GameEvent,
+    game::{Game, PLAYER},
+    item::{take_off, Item},
+    menu,
+    monsters::kind_of,
+    tcoder::{Tcod, INVENTORY_WIDTH},
+};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use tcod::colors::LIGHT_BLUE;
+
+// quests the player can have taken on at once
+const MAX_QUESTS: usize = 3;
+
+// the monsters the elder wants slain: the monster, how many, and the reward in
+// experience and gold
+const KILL_QUESTS: &[(&str, i32, i32, i32)] = &[
+    ("orc", 6, 100, 40),
+    ("orc archer", 3, 120, 50),
+    ("giant rat", 4, 60, 30),
+    ("troll", 2, 200, 80),
+    ("ghoul", 2, 150, 60),
+    ("orc chief", 1, 250, 100),
+];
+
+// the items the elder wants brought back, and the reward
+const RETRIEVE_QUESTS: &[(Item, i32, i32)] = &[
+    (Item::LuckyCharm, 150, 120),
+    (Item::WandDigging, 100, 80),
+    (Item::PlateArmor, 200, 150),
+];
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Objective {
+    /// slay this many of a kind of monster
+    Kill { monster: String, count: i32 },
+    /// bring an item back to the elder
+    Retrieve(Item),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Quest {
+    pub objective: Objective,
+    /// monsters slain towards the objective so far
+    pub progress: i32,
+    pub reward_xp: i32,
+    pub reward_gold: i32,
+}
+
+impl Quest {
+    /// what the player is asked to do
+    pub fn task(&self) -> String {
+        match &self.objective {
+            Objective::Kill { monster, count: 1 } => format!("Slay one {}", monster),
+            Objective::Kill { monster, count } => format!("Slay {} {}s", count, monster),
+            Objective::Retrieve(item) => format!("Bring back a {}", item.name()),
+        }
+    }
+
+    /// whether all the monsters asked for are slain, items are only checked for
+    /// when the player reports back
+    fn slain_all(&self) -> bool {
+        match self.objective {
+            Objective::Kill { count, .. } => self.progress >= count,
+            Objective::Retrieve(_) => false,
+        }
+    }
+}
+
+/// every quest the elder can give
+fn all_quests() -> Vec<Quest> {
+    let kills = KILL_QUESTS.iter().map(|&(monster, count, xp, gold)| Quest {
+        objective: Objective::Kill {
+            monster: monster.into(),
+            count,
+        },
+        progress: 0,
+        reward_xp: xp,
+        reward_gold: gold,
+    });
+    let items = RETRIEVE_QUESTS.iter().map(|&(item, xp, gold)| Quest {
+        objective: Objective::Retrieve(item),
+        progress: 0,
+        reward_xp: xp,
+        reward_gold: gold,
+    });
+    kills.chain(items).collect()
+}
+
+/// The quests the player has taken on and not yet been rewarded for
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Journal {
+    pub quests: Vec<Quest>,
+    /// how many quests have been rewarded
+    pub completed: i32,
+}
+
+impl Journal {
+    /// count the event towards the quests, returning the tasks it completes
+    pub fn record(&mut self, event: &GameEvent) -> Vec<String> {
+        let mut done = vec![];
+        if let GameEvent::MonsterDied {
+            name,
+            by_player: true,
+            ..
+        } = event
+        {
+            for quest in &mut self.quests {
+                let wanted = match &quest.objective {
+                    Objective::Kill { monster, .. } => monster == kind_of(name),
+                    Objective::Retrieve(_) => false,
+                };
+                if wanted && !quest.slain_all() {
+                    quest.progress += 1;
+                    if quest.slain_all() {
+                        done.push(quest.task());
+                    }
+                }
+            }
+        }
+        done
+    }
+
+    /// the text of the journal screen
+    pub fn text(&self) -> String {
+        let mut text = String::from("Quest journal\n");
+        if self.quests.is_empty() {
+            text += "\nYou have no quests. The elder in town may have work for you.";
+        }
+        for quest in &self.quests {
+            let status = match quest.objective {
+                _ if quest.slain_all() => " (done, report to the elder)".to_string(),
+                Objective::Kill { count, .. } => format!(" ({} of {})", quest.progress, count),
+                Objective::Retrieve(_) => String::new(),
+            };
+            text += &format!("\n{}{}", quest.task(), status);
+        }
+        text + &format!("\n\nQuests completed: {}", self.completed)
+    }
+}
+
+/// reward the player for the quests they have done, then offer them a new one
+pub fn visit_elder(game: &mut Game, tcod: &mut Tcod) {
+    let mut index = 0;
+    while index < game.journal.quests.len() {
+        let quest = &game.journal.quests[index];
+        let carried = match quest.objective {
+            Objective::Retrieve(item) => game
+                .inventory
+                .iter()
+                .position(|object| object.item == Some(item)),
+            Objective::Kill { .. } => None,
+        };
+        if !quest.slain_all() && carried.is_none() {
+            index += 1;
+            continue;
+        }
+        if let Some(carried) = carried {
+            if !take_off(game, carried) {
+                index += 1;
+                continue;
+            }
+            game.inventory.remove(carried);
+        }
+        let quest = game.journal.quests.remove(index);
+        game.journal.completed += 1;
+        game.gold += quest.reward_gold;
+        if let Some(fighter) = game.objects[PLAYER].fighter.as_mut() {
+            fighter.xp += quest.reward_xp;
+        }
+        game.messages.add(
+            format!(
+                "\"Well done!\" For \"{}\", the elder rewards you with {} gold and {} experience.",
+                quest.task(),
+                quest.reward_gold,
+                quest.reward_xp
+            ),
+            LIGHT_BLUE,
+        );
+    }
+
+    if game.journal.quests.len() >= MAX_QUESTS {
+        game.messages.add(
+            "\"You have enough on your hands already,\" the elder says.",
+            LIGHT_BLUE,
+        );
+        return;
+    }
+    let offers: Vec<_> = all_quests()
+        .into_iter()
+        .filter(|offer| {
+            game.journal
+                .quests
+                .iter()
+                .all(|quest| quest.objective != offer.objective)
+        })
+        .collect();
+    let quest = match rand::thread_rng().choose(&offers) {
+        Some(quest) => quest.clone(),
+        None => return,
+    };
+    let header = format!(
+        "\"{}, and I will give you {} gold for your trouble.\"\n",
+        quest.task(),
+        quest.reward_gold
+    );
+    let choices = &["Accept the quest", "Decline"];
+    if menu(&header, choices, INVENTORY_WIDTH, &mut tcod.root) == Some(0) {
+        game.messages
+            .add("You write the quest down in your journal.", LIGHT_BLUE);
+        game.journal.quests.push(quest);
+    } else {
+        game.messages.add(
+            "\"Come back if you change your mind,\" the elder says.",
+            LIGHT_BLUE,
+        );
+    }
+}
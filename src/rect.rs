@@ -1,5 +1,7 @@
+use serde::{Deserialize, Serialize};
+
 /// A rectangle on the map, used to characterise a room.
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Rect {
     pub x1: i32,
     pub y1: i32,
@@ -23,6 +25,11 @@ impl Rect {
         (center_x, center_y)
     }
 
+    /// whether the tile is inside the room, rather than in its walls
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        x > self.x1 && x < self.x2 && y > self.y1 && y < self.y2
+    }
+
     pub fn intersects_with(&self, other: &Rect) -> bool {
         // returns true if this rectangle intersects with another one
         (self.x1 <= other.x2)
@@ -0,0 +1,74 @@
+//! The drawing calls the game makes, gathered in one trait so the screen can be
+//! drawn by something other than libtcod, such as a plain terminal. For now
+//! every tcod console is a renderer, and drawing code that takes a
+//! `&mut dyn Renderer` no longer cares which console it's given.
+use tcod::colors::Color;
+use tcod::console::Console;
+use tcod::{BackgroundFlag, TextAlignment};
+
+pub trait Renderer {
+    fn set_default_foreground(&mut self, color: Color);
+    fn set_default_background(&mut self, color: Color);
+    fn clear(&mut self);
+    fn put_char(&mut self, x: i32, y: i32, glyph: char, flag: BackgroundFlag);
+    fn set_char_background(&mut self, x: i32, y: i32, color: Color, flag: BackgroundFlag);
+    /// fill a rectangle with the default background, clearing the characters if asked
+    fn rect(&mut self, x: i32, y: i32, width: i32, height: i32, clear: bool, flag: BackgroundFlag);
+    fn print_ex(
+        &mut self,
+        x: i32,
+        y: i32,
+        flag: BackgroundFlag,
+        alignment: TextAlignment,
+        text: &str,
+    );
+    /// print wrapped to a rectangle
+    fn print_rect(&mut self, x: i32, y: i32, width: i32, height: i32, text: &str);
+    /// the number of lines `text` takes once wrapped to the rectangle
+    fn get_height_rect(&self, x: i32, y: i32, width: i32, height: i32, text: &str) -> i32;
+}
+
+impl<C: Console> Renderer for C {
+    fn set_default_foreground(&mut self, color: Color) {
+        Console::set_default_foreground(self, color);
+    }
+
+    fn set_default_background(&mut self, color: Color) {
+        Console::set_default_background(self, color);
+    }
+
+    fn clear(&mut self) {
+        Console::clear(self);
+    }
+
+    fn put_char(&mut self, x: i32, y: i32, glyph: char, flag: BackgroundFlag) {
+        Console::put_char(self, x, y, glyph, flag);
+    }
+
+    fn set_char_background(&mut self, x: i32, y: i32, color: Color, flag: BackgroundFlag) {
+        Console::set_char_background(self, x, y, color, flag);
+    }
+
+    fn rect(&mut self, x: i32, y: i32, width: i32, height: i32, clear: bool, flag: BackgroundFlag) {
+        Console::rect(self, x, y, width, height, clear, flag);
+    }
+
+    fn print_ex(
+        &mut self,
+        x: i32,
+        y: i32,
+        flag: BackgroundFlag,
+        alignment: TextAlignment,
+        text: &str,
+    ) {
+        Console::print_ex(self, x, y, flag, alignment, text);
+    }
+
+    fn print_rect(&mut self, x: i32, y: i32, width: i32, height: i32, text: &str) {
+        Console::print_rect(self, x, y, width, height, text);
+    }
+
+    fn get_height_rect(&self, x: i32, y: i32, width: i32, height: i32, text: &str) -> i32 {
+        Console::get_height_rect(self, x, y, width, height, text)
+    }
+}
@@ -0,0 +1,169 @@
+//! The record of a run, kept from the events as they happen and from the
+//! messages the player is told straight, and the run summary made from it. Only
+//! the latest entries are kept, so a long run doesn't grow the save for ever.
+//! The player exports the summary from the pause menu, as markdown to read and
+//! share and as JSON for tools, with the seed to play the same dungeon again.
+use crate::{
+    dungeon::LevelId,
+    events::GameEvent,
+    game::{Game, PLAYER},
+    messages::Messages,
+    stats::Stats,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::VecDeque,
+    fs, io,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+// the most entries kept, the oldest going first
+const MAX_ENTRIES: usize = 2000;
+
+/// An event, or a message the player was told straight, with when and where it
+/// happened
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LogEntry {
+    pub turn: i32,
+    pub level: LevelId,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub event: Option<GameEvent>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// The latest events of the run worth telling, oldest first
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct RunLog {
+    entries: VecDeque<LogEntry>,
+}
+
+impl RunLog {
+    /// keep the event, unless it is damage some other entry tells of already
+    pub fn record(&mut self, turn: i32, level: LevelId, event: &GameEvent) {
+        if let GameEvent::Hurt { .. } = event {
+            return;
+        }
+        self.push(LogEntry {
+            turn,
+            level,
+            event: Some(event.clone()),
+            message: None,
+        });
+    }
+
+    /// keep the messages the player was told straight rather than of an event
+    pub fn record_messages(&mut self, turn: i32, level: LevelId, messages: Vec<String>) {
+        for message in messages {
+            self.push(LogEntry {
+                turn,
+                level,
+                event: None,
+                message: Some(message),
+            });
+        }
+    }
+
+    fn push(&mut self, entry: LogEntry) {
+        if self.entries.len() == MAX_ENTRIES {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+}
+
+/// What the JSON summary holds
+#[derive(Serialize)]
+struct RunSummary<'a> {
+    seed: u64,
+    mode: &'static str,
+    difficulty: &'static str,
+    level: String,
+    depth: u32,
+    character_level: i32,
+    turns: i32,
+    deaths: u32,
+    gold: i32,
+    stats: &'a Stats,
+    log: &'a VecDeque<LogEntry>,
+}
+
+/// the run so far as markdown: who the character is, how they fared and all
+/// that happened to them, level by level
+pub fn summary_markdown(game: &Game) -> String {
+    let player = &game.objects[PLAYER];
+    let mut text = String::from("# Tombs of the Ancient Kings: run summary\n\n");
+    text += &format!("- Seed: {}\n", game.seed);
+    text += &format!(
+        "- Mode: {}, {} difficulty\n",
+        game.mode.name(),
+        game.difficulty.name()
+    );
+    text += &format!(
+        "- Reached: {} (depth {})\n",
+        game.level.name(),
+        game.level.absolute_depth()
+    );
+    text += &format!("- Character level: {}\n", player.level);
+    text += &format!("- Turns: {}\n", game.turn);
+    text += &format!("- Deaths: {}\n", game.deaths);
+    text += &format!("- Gold: {}\n", game.gold);
+
+    text += &format!("\n## Statistics\n\n{}\n", game.stats.summary(game.turn));
+    text += "\n## Kills\n\n";
+    for (name, count) in game.stats.kill_list() {
+        text += &format!("- {} {}\n", count, name);
+    }
+
+    text += "\n## Log\n";
+    let mut level = None;
+    for entry in &game.run_log.entries {
+        if level != Some(entry.level) {
+            level = Some(entry.level);
+            text += &format!("\n### {}\n\n", entry.level.name());
+        }
+        // told in the very words the message log uses
+        let mut messages = Messages::new();
+        if let Some(event) = &entry.event {
+            messages.log(event);
+        }
+        for message in messages
+            .iter()
+            .map(|(message, _)| message)
+            .chain(&entry.message)
+        {
+            text += &format!("- Turn {}: {}\n", entry.turn, message);
+        }
+    }
+    text
+}
+
+pub fn summary_json(game: &Game) -> String {
+    let player = &game.objects[PLAYER];
+    let summary = RunSummary {
+        seed: game.seed,
+        mode: game.mode.name(),
+        difficulty: game.difficulty.name(),
+        level: game.level.name(),
+        depth: game.level.absolute_depth(),
+        character_level: player.level,
+        turns: game.turn,
+        deaths: game.deaths,
+        gold: game.gold,
+        stats: &game.stats,
+        log: &game.run_log.entries,
+    };
+    serde_json::to_string_pretty(&summary).unwrap_or_default()
+}
+
+/// write the summary as markdown and as JSON, named after the time of export,
+/// returning the name the two files share
+pub fn export_summary(game: &Game) -> io::Result<String> {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs());
+    let name = format!("run-{}", seconds);
+    fs::write(format!("{}.md", name), summary_markdown(game))?;
+    fs::write(format!("{}.json", name), summary_json(game))?;
+    Ok(name)
+}
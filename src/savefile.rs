@@ -0,0 +1,288 @@
+//! The on-disk save format. The game is wrapped in a `SaveFile` carrying the
+//! version of the format it was written with, so saves from older releases can
+//! be upgraded step by step before being loaded.
+//!
+//! Fields that merely get added with a sensible default use `#[serde(default)]`
+//! and need no new version; bump `SAVE_VERSION` and add a migration whenever
+//! existing data changes shape.
+use crate::{
+    food::Hunger,
+    game::Game,
+    item::{item_object, Item},
+    mapbuilder::create_stairs,
+    tcoder::{MAP_HEIGHT, MAP_WIDTH},
+    world::TOWN,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::error::Error;
+
+pub const SAVE_VERSION: u32 = 9;
+
+/// `MIGRATIONS[n]` upgrades a save from version `n` to version `n + 1`
+const MIGRATIONS: &[fn(&mut Value)] = &[
+    migrate_v0_to_v1,
+    migrate_v1_to_v2,
+    migrate_v2_to_v3,
+    migrate_v3_to_v4,
+    migrate_v4_to_v5,
+    migrate_v5_to_v6,
+    migrate_v6_to_v7,
+    migrate_v7_to_v8,
+    migrate_v8_to_v9,
+];
+
+#[derive(Serialize, Deserialize)]
+struct SaveFile {
+    version: u32,
+    game: Value,
+}
+
+pub fn to_json(game: &Game) -> Result<String, Box<dyn Error>> {
+    let save_file = SaveFile {
+        version: SAVE_VERSION,
+        game: serde_json::to_value(game)?,
+    };
+    Ok(serde_json::to_string(&save_file)?)
+}
+
+pub fn from_json(json: &str) -> Result<Game, Box<dyn Error>> {
+    let value: Value = serde_json::from_str(json)?;
+    // the first releases saved the bare game, without a version
+    let SaveFile { version, mut game } = if value.get("version").is_some() {
+        serde_json::from_value(value)?
+    } else {
+        SaveFile {
+            version: 0,
+            game: value,
+        }
+    };
+
+    if version > SAVE_VERSION {
+        return Err(format!(
+            "it was saved by a newer version of the game (save format {}, this game reads up to {})",
+            version, SAVE_VERSION
+        )
+        .into());
+    }
+    for migration in &MIGRATIONS[version as usize..] {
+        migration(&mut game);
+    }
+
+    serde_json::from_value(game)
+        .map_err(|e| format!("it is damaged or incompatible ({})", e).into())
+}
+
+/// version 1 added spells and mana, casual mode, level grading, tile memory and decay
+fn migrate_v0_to_v1(game: &mut Value) {
+    game["memory"] = json!(vec![
+        vec![Value::Null; MAP_HEIGHT as usize];
+        MAP_WIDTH as usize
+    ]);
+    game["level_stats"] = json!({"kills": 0, "items_found": 0, "turns": 0});
+    game["spells"] = json!([]);
+    game["mode"] = json!("Permadeath");
+    game["checkpoint_level"] = json!(1);
+    game["deaths"] = json!(0);
+
+    for list in &["objects", "inventory"] {
+        if let Some(objects) = game[*list].as_array_mut() {
+            for object in objects {
+                object["decay"] = Value::Null;
+                if object["fighter"].is_object() {
+                    object["fighter"]["mana"] = json!(0);
+                    object["fighter"]["max_mana"] = json!(0);
+                }
+            }
+        }
+    }
+
+    // the player is always the first object
+    if game["objects"][0]["fighter"].is_object() {
+        game["objects"][0]["fighter"]["mana"] = json!(10);
+        game["objects"][0]["fighter"]["max_mana"] = json!(10);
+    }
+}
+
+/// version 2 replaced the dungeon level counter by a place in the level graph
+fn migrate_v1_to_v2(game: &mut Value) {
+    let depth = game["dungeon_level"].as_u64().unwrap_or(1);
+    let checkpoint = game["checkpoint_level"].as_u64().unwrap_or(1);
+    game["level"] = json!({"branch": "Main", "depth": depth});
+    game["checkpoint"] = json!({"branch": "Main", "depth": checkpoint});
+
+    if let Some(objects) = game["objects"].as_array_mut() {
+        for object in objects {
+            if object["name"] == "stairs" {
+                object["name"] = json!("stairs down");
+                object["char"] = json!(">");
+                object["stairs"] = json!({"branch": "Main", "depth": depth + 1});
+            }
+        }
+    }
+}
+
+/// version 3 gave every object a faction, the player's own not being the default
+fn migrate_v2_to_v3(game: &mut Value) {
+    // the player is always the first object
+    if game["objects"][0].is_object() {
+        game["objects"][0]["faction"] = json!("Player");
+    }
+}
+
+/// version 4 made the player's sight depend on a light, hand them a lit torch
+fn migrate_v3_to_v4(game: &mut Value) {
+    let mut torch = item_object(Item::Torch, 0, 0, 0, &mut rand::thread_rng());
+    torch.equipped = true;
+    if let (Some(inventory), Ok(torch)) = (
+        game["inventory"].as_array_mut(),
+        serde_json::to_value(torch),
+    ) {
+        inventory.push(torch);
+    }
+}
+
+/// version 5 put a town above the dungeon, the first level gets stairs up to it
+/// on a free floor tile
+fn migrate_v4_to_v5(game: &mut Value) {
+    let first_level = json!({"branch": "Main", "depth": 1});
+    let level = if game["level"] == first_level {
+        Some(game)
+    } else {
+        game["levels"].as_array_mut().and_then(|levels| {
+            levels
+                .iter_mut()
+                .find(|pair| pair[0] == first_level)
+                .map(|pair| &mut pair[1])
+        })
+    };
+    let level = match level {
+        Some(level) => level,
+        None => return,
+    };
+
+    let occupied = |x: usize, y: usize, objects: &Value| {
+        objects
+            .as_array()
+            .into_iter()
+            .flatten()
+            .any(|o| o["x"] == json!(x) && o["y"] == json!(y))
+    };
+    let mut spot = None;
+    if let Some(columns) = level["map"].as_array() {
+        'search: for (x, column) in columns.iter().enumerate() {
+            for (y, tile) in column.as_array().into_iter().flatten().enumerate() {
+                if tile["blocked"] == json!(false) && !occupied(x, y, &level["objects"]) {
+                    spot = Some((x as i32, y as i32));
+                    break 'search;
+                }
+            }
+        }
+    }
+    if let Some((x, y)) = spot {
+        let stairs = create_stairs(x, y, TOWN, '<', "stairs up");
+        if let (Some(objects), Ok(stairs)) = (
+            level["objects"].as_array_mut(),
+            serde_json::to_value(stairs),
+        ) {
+            objects.push(stairs);
+        }
+    }
+}
+
+/// version 6 had the Ancient King guard the amulet, hand it to a king still alive
+fn migrate_v5_to_v6(game: &mut Value) {
+    let give_amulet = |objects: &mut Value| {
+        for object in objects.as_array_mut().into_iter().flatten() {
+            if object["name"] == "Ancient King" && object["alive"] == json!(true) {
+                object["loot"] = json!("Amulet");
+            }
+        }
+    };
+    give_amulet(&mut game["objects"]);
+    if let Some(levels) = game["levels"].as_array_mut() {
+        for pair in levels {
+            give_amulet(&mut pair[1]["objects"]);
+        }
+    }
+}
+
+/// version 7 moved the count of turns taken out of the statistics into the clock
+fn migrate_v6_to_v7(game: &mut Value) {
+    let turns = game["stats"]["turns"].take().as_i64().unwrap_or(0);
+    game["turn"] = json!(turns);
+}
+
+/// version 8 made the clock the timebase of rot, effects and hunger, which keep
+/// the turn they run out on instead of the turns they have left
+fn migrate_v7_to_v8(game: &mut Value) {
+    let turn = game["turn"].as_i64().unwrap_or(0);
+    let restamp = |objects: &mut Value| {
+        for object in objects.as_array_mut().into_iter().flatten() {
+            let decay = object
+                .as_object_mut()
+                .and_then(|object| object.remove("decay"))
+                .and_then(|decay| decay.as_i64());
+            object["decays_at"] = json!(decay.map(|decay| turn + decay));
+            for effect in object["effects"].as_array_mut().into_iter().flatten() {
+                let turns = effect["turns"].take().as_i64().unwrap_or(0);
+                effect["until"] = json!(turn + turns);
+            }
+        }
+    };
+    restamp(&mut game["objects"]);
+    restamp(&mut game["inventory"]);
+    if let Some(levels) = game["levels"].as_array_mut() {
+        for pair in levels {
+            restamp(&mut pair[1]["objects"]);
+        }
+    }
+
+    let hunger = match game["hunger"]["nourishment"].as_i64() {
+        Some(nourishment) => json!({ "starves_at": turn + nourishment }),
+        None => json!(Hunger::starting_at(turn as i32)),
+    };
+    game["hunger"] = hunger;
+}
+
+/// version 9 did the same for charmed allies, which keep the turn the charm
+/// wears off on
+fn migrate_v8_to_v9(game: &mut Value) {
+    let turn = game["turn"].as_i64().unwrap_or(0);
+    let restamp = |objects: &mut Value| {
+        for object in objects.as_array_mut().into_iter().flatten() {
+            if let Some(ally) = object.pointer_mut("/ai/Ally") {
+                let num_turns = ally["num_turns"].take().as_i64().unwrap_or(0);
+                ally["until"] = json!(turn + num_turns);
+            }
+        }
+    };
+    restamp(&mut game["objects"]);
+    if let Some(levels) = game["levels"].as_array_mut() {
+        for pair in levels {
+            restamp(&mut pair[1]["objects"]);
+        }
+    }
+}
+
+/// Serialize the levels the player has left as a list of pairs, since JSON only
+/// allows strings as map keys. Use with `#[serde(with = "crate::savefile::level_map")]`.
+pub mod level_map {
+    use crate::dungeon::{LevelId, LevelState};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::collections::HashMap;
+
+    pub fn serialize<S: Serializer>(
+        levels: &HashMap<LevelId, LevelState>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        levels.iter().collect::<Vec<_>>().serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<HashMap<LevelId, LevelState>, D::Error> {
+        let levels = Vec::<(LevelId, LevelState)>::deserialize(deserializer)?;
+        Ok(levels.into_iter().collect())
+    }
+}
@@ -0,0 +1,121 @@
+//! What monsters see. Every monster looks out for itself, as far as its eyes
+//! reach and only the way it faces, so the player can sneak past a monster that
+//! looks elsewhere, or up behind it. A monster catching sight of the player at a
+//! distance only grows suspicious and turns to look; on a second look it is
+//! alerted and gives chase.
+use serde::{Deserialize, Serialize};
+use tcod::line::Line;
+
+// how far a monster sees, unless its kind sees farther or less far
+const SIGHT_RADIUS: i32 = 8;
+// how far to either side of the way it faces a monster looks, as the cosine of
+// the angle: a little more than half the way round
+const VIEW_COS: f32 = -0.2;
+// whoever is right next to a monster is seen whichever way it faces, and whoever
+// is this close is seen for what they are at once
+const NOTICE_DISTANCE: f32 = 1.5;
+const ALERT_DISTANCE: f32 = 3.0;
+// turns a suspicious monster keeps looking out before it loses interest
+const SUSPICION_TURNS: i32 = 6;
+
+/// What a monster knows of the player
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Awareness {
+    #[default]
+    Unaware,
+    /// caught a glimpse, or heard something, and looks out for some turns more
+    Suspicious(i32),
+    Alerted,
+}
+
+impl Awareness {
+    /// what is drawn over the monster's head
+    pub fn marker(self) -> Option<char> {
+        match self {
+            Awareness::Unaware => None,
+            Awareness::Suspicious(_) => Some('?'),
+            Awareness::Alerted => Some('!'),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Sight {
+    pub radius: i32,
+    /// the way the monster looks, the way it last stepped or turned
+    pub facing: (i32, i32),
+    pub awareness: Awareness,
+}
+
+impl Default for Sight {
+    fn default() -> Self {
+        Sight {
+            radius: SIGHT_RADIUS,
+            facing: (0, 1),
+            awareness: Awareness::Unaware,
+        }
+    }
+}
+
+impl Sight {
+    /// whether the monster at `from` sees what is at `to`, with `blocks_sight`
+    /// telling the tiles it can't see through
+    pub fn sees(
+        &self,
+        from: (i32, i32),
+        to: (i32, i32),
+        blocks_sight: impl Fn(i32, i32) -> bool,
+    ) -> bool {
+        let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+        let distance = ((dx.pow(2) + dy.pow(2)) as f32).sqrt();
+        if distance > self.radius as f32 {
+            return false;
+        }
+        if distance > NOTICE_DISTANCE {
+            let (facing_x, facing_y) = self.facing;
+            let facing = ((facing_x.pow(2) + facing_y.pow(2)) as f32).sqrt().max(1.0);
+            let cos = (dx * facing_x + dy * facing_y) as f32 / (distance * facing);
+            if cos < VIEW_COS {
+                return false;
+            }
+        }
+        Line::new(from, to)
+            .take_while(|&tile| tile != to)
+            .all(|(x, y)| !blocks_sight(x, y))
+    }
+
+    /// turn to look at a tile
+    pub fn face(&mut self, from: (i32, i32), to: (i32, i32)) {
+        let facing = ((to.0 - from.0).signum(), (to.1 - from.1).signum());
+        if facing != (0, 0) {
+            self.facing = facing;
+        }
+    }
+
+    /// take a look out for the player, who is `seen` or not this far away.
+    /// Seeing them makes the monster suspicious, or alerted if it was already or
+    /// they are close; not seeing them it calms down again. Returns whether it is
+    /// alerted
+    pub fn look(&mut self, seen: bool, distance: f32) -> bool {
+        self.awareness = match (self.awareness, seen) {
+            (_, true) if distance <= ALERT_DISTANCE => Awareness::Alerted,
+            (Awareness::Unaware, true) => Awareness::Suspicious(SUSPICION_TURNS),
+            (_, true) => Awareness::Alerted,
+            (Awareness::Alerted, false) => Awareness::Suspicious(SUSPICION_TURNS),
+            (Awareness::Suspicious(turns), false) if turns > 1 => Awareness::Suspicious(turns - 1),
+            (_, false) => Awareness::Unaware,
+        };
+        self.awareness == Awareness::Alerted
+    }
+
+    /// something heard makes an unaware monster suspicious
+    pub fn startle(&mut self) {
+        if self.awareness == Awareness::Unaware {
+            self.awareness = Awareness::Suspicious(SUSPICION_TURNS);
+        }
+    }
+
+    pub fn alert(&mut self) {
+        self.awareness = Awareness::Alerted;
+    }
+}
@@ -0,0 +1,107 @@
+//! Weapon skills: the player grows more proficient with each class of weapon by
+//! landing blows with it that hurt. Every rank the blows that may miss land more
+//! surely, and every few they hit harder. The character screen shows how far
+//! along each class is.
+use crate::item::Item;
+use serde::{Deserialize, Serialize};
+
+// the blows it takes to reach each rank past the first, and what the ranks are
+// called
+const RANK_HITS: &[i32] = &[15, 40, 80, 150];
+const RANK_NAMES: &[&str] = &["unskilled", "basic", "skilled", "expert", "master"];
+// what every rank adds to the chance of landing a blow that may miss, and the
+// ranks it takes for every point of damage added
+const ACCURACY_PER_RANK: f32 = 0.05;
+const RANKS_PER_DAMAGE: usize = 2;
+
+/// What the player fights with, each trained apart
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum WeaponClass {
+    /// fists, and whatever is held that is no melee weapon
+    Unarmed,
+    Dagger,
+    Bow,
+}
+
+/// every weapon class there is, in the order the character screen lists them
+pub const WEAPON_CLASSES: &[WeaponClass] =
+    &[WeaponClass::Unarmed, WeaponClass::Dagger, WeaponClass::Bow];
+
+impl WeaponClass {
+    pub fn name(self) -> &'static str {
+        match self {
+            WeaponClass::Unarmed => "unarmed",
+            WeaponClass::Dagger => "dagger",
+            WeaponClass::Bow => "bow",
+        }
+    }
+
+    /// the class a blow struck with the item in hand, if any, is trained in
+    pub fn melee(item: Option<Item>) -> Self {
+        match item {
+            Some(Item::Dagger) => WeaponClass::Dagger,
+            _ => WeaponClass::Unarmed,
+        }
+    }
+}
+
+/// The blows the player has landed with every class of weapon
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct WeaponSkills {
+    pub unarmed: i32,
+    pub dagger: i32,
+    pub bow: i32,
+}
+
+impl WeaponSkills {
+    fn get_mut(&mut self, class: WeaponClass) -> &mut i32 {
+        match class {
+            WeaponClass::Unarmed => &mut self.unarmed,
+            WeaponClass::Dagger => &mut self.dagger,
+            WeaponClass::Bow => &mut self.bow,
+        }
+    }
+
+    fn get(mut self, class: WeaponClass) -> i32 {
+        *self.get_mut(class)
+    }
+
+    /// the rank reached with the class, starting at 0
+    fn rank(self, class: WeaponClass) -> usize {
+        let hits = self.get(class);
+        RANK_HITS.iter().filter(|&&needed| hits >= needed).count()
+    }
+
+    /// count a blow landed with the class, returning the name of the rank it
+    /// brought the player to, if it did
+    pub fn train(&mut self, class: WeaponClass) -> Option<&'static str> {
+        let rank = self.rank(class);
+        *self.get_mut(class) += 1;
+        let new_rank = self.rank(class);
+        Some(RANK_NAMES[new_rank]).filter(|_| new_rank > rank)
+    }
+
+    /// what the rank with the class adds to the chance of landing a blow
+    pub fn accuracy_bonus(self, class: WeaponClass) -> f32 {
+        self.rank(class) as f32 * ACCURACY_PER_RANK
+    }
+
+    /// what the rank with the class adds to the damage of every blow
+    pub fn damage_bonus(self, class: WeaponClass) -> i32 {
+        (self.rank(class) / RANKS_PER_DAMAGE) as i32
+    }
+
+    /// the rank with every class trained at all, "none" if there are none
+    pub fn describe(self) -> String {
+        let described: Vec<_> = WEAPON_CLASSES
+            .iter()
+            .filter(|&&class| self.get(class) > 0)
+            .map(|&class| format!("{} {}", class.name(), RANK_NAMES[self.rank(class)]))
+            .collect();
+        if described.is_empty() {
+            "none".into()
+        } else {
+            described.join(", ")
+        }
+    }
+}
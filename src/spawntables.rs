@@ -0,0 +1,170 @@
+//! What turns up where: the monsters and items of every branch, with chances
+//! that change with depth. Deeper floors bring out the trolls and the better
+//! scrolls and wands.
+use crate::{
+    dungeon::{Branch, LevelId},
+    item::Item,
+};
+
+/// From an absolute depth on, the weight an entry has until the next transition
+/// takes over: `(level, value)`.
+pub type Transition = (u32, u32);
+
+type SpawnTable<T> = &'static [(T, &'static [Transition])];
+
+/// the weight given by a list of transitions at the given absolute depth, 0 above
+/// the first of them
+pub fn from_dungeon_level(table: &[Transition], level: u32) -> u32 {
+    table
+        .iter()
+        .rev()
+        .find(|&&(from, _)| level >= from)
+        .map_or(0, |&(_, value)| value)
+}
+
+/// the weights of a table at a level, leaving out what can't turn up there
+fn weights_at<T: Copy>(table: SpawnTable<T>, level: LevelId) -> Vec<(T, u32)> {
+    let depth = level.absolute_depth();
+    table
+        .iter()
+        .map(|&(entry, transitions)| (entry, from_dungeon_level(transitions, depth)))
+        .filter(|&(_, weight)| weight > 0)
+        .collect()
+}
+
+const MAIN_MONSTERS: SpawnTable<&str> = &[
+    ("orc", &[(1, 53)]),
+    ("orc chief", &[(4, 2)]),
+    ("orc archer", &[(2, 15)]),
+    ("orc shaman", &[(3, 5)]),
+    ("troll", &[(3, 15), (5, 30), (7, 60)]),
+    ("ghoul", &[(4, 4)]),
+    ("cave bat", &[(1, 3)]),
+    ("spitting cobra", &[(3, 3)]),
+    ("giant spider", &[(2, 4)]),
+    ("giant rat", &[(1, 8), (5, 3)]),
+    ("rat summoner", &[(3, 3)]),
+];
+
+const MINES_MONSTERS: SpawnTable<&str> = &[
+    ("orc", &[(1, 40)]),
+    ("orc archer", &[(1, 20)]),
+    ("orc shaman", &[(2, 6)]),
+    ("troll", &[(5, 15)]),
+    ("giant spider", &[(1, 10)]),
+    ("cave bat", &[(1, 15)]),
+    ("giant rat", &[(1, 10)]),
+];
+
+const CRYPT_MONSTERS: SpawnTable<&str> = &[
+    ("skeleton", &[(1, 45)]),
+    ("ghoul", &[(1, 45)]),
+    ("frost wight", &[(1, 15)]),
+    ("lich", &[(1, 5)]),
+    ("cave bat", &[(1, 10)]),
+];
+
+const ABYSS_MONSTERS: SpawnTable<&str> = &[
+    ("shade", &[(1, 40)]),
+    ("abyssal horror", &[(1, 30), (13, 45)]),
+    ("imp", &[(1, 20)]),
+    ("troll", &[(1, 30)]),
+];
+
+const MAIN_ITEMS: SpawnTable<Item> = &[
+    (Item::Heal, &[(1, 70)]),
+    (Item::Lightning, &[(1, 5), (4, 15)]),
+    (Item::Confuse, &[(2, 10)]),
+    (Item::WandLightning, &[(5, 3)]),
+    (Item::WandConfusion, &[(4, 3)]),
+    (Item::WandDigging, &[(3, 2)]),
+    (Item::Identify, &[(1, 2)]),
+    (Item::LuckyCharm, &[(3, 1)]),
+    (Item::LeatherArmor, &[(2, 4)]),
+    (Item::ChainMail, &[(4, 3)]),
+    (Item::PlateArmor, &[(7, 2)]),
+    (Item::Blink, &[(2, 5)]),
+    (Item::Teleport, &[(5, 2)]),
+    (Item::Torch, &[(1, 6)]),
+    (Item::Lantern, &[(4, 2)]),
+    (Item::CharmMonster, &[(3, 3)]),
+    (Item::RemoveCurse, &[(2, 2)]),
+    (Item::Fire, &[(3, 5)]),
+    (Item::Bow, &[(2, 2)]),
+    (Item::Arrows, &[(2, 5)]),
+    (Item::Dagger, &[(1, 3)]),
+    (Item::Shield, &[(2, 2)]),
+    (Item::Experience, &[(3, 1)]),
+    (Item::Vigor, &[(3, 1)]),
+    (Item::Might, &[(4, 1)]),
+    (Item::Toughness, &[(4, 1)]),
+    (Item::Resilience, &[(5, 1)]),
+    (Item::Invisibility, &[(3, 2)]),
+    (Item::SeeInvisible, &[(3, 2)]),
+];
+
+// wands of digging are common among the miners' leftovers
+const MINES_ITEMS: SpawnTable<Item> = &[
+    (Item::Heal, &[(1, 70)]),
+    (Item::Lightning, &[(1, 10)]),
+    (Item::Confuse, &[(1, 10)]),
+    (Item::WandDigging, &[(1, 8)]),
+    (Item::Identify, &[(1, 2)]),
+    (Item::ChainMail, &[(1, 3)]),
+    (Item::Blink, &[(1, 4)]),
+    (Item::Torch, &[(1, 8)]),
+    (Item::Arrows, &[(1, 4)]),
+];
+
+const ABYSS_ITEMS: SpawnTable<Item> = &[
+    (Item::Heal, &[(1, 50)]),
+    (Item::Lightning, &[(1, 15)]),
+    (Item::Confuse, &[(1, 15)]),
+    (Item::Elixir, &[(1, 20)]),
+    (Item::WandLightning, &[(1, 5)]),
+    (Item::Identify, &[(1, 2)]),
+    (Item::LuckyCharm, &[(1, 2)]),
+    (Item::PlateArmor, &[(1, 3)]),
+    (Item::Teleport, &[(1, 3)]),
+    (Item::Lantern, &[(1, 3)]),
+    (Item::CharmMonster, &[(1, 3)]),
+    (Item::RemoveCurse, &[(1, 2)]),
+    (Item::Experience, &[(1, 2)]),
+    (Item::Vigor, &[(1, 2)]),
+    (Item::Might, &[(1, 2)]),
+    (Item::Toughness, &[(1, 2)]),
+    (Item::Resilience, &[(1, 2)]),
+];
+
+fn monster_spawns(branch: Branch) -> SpawnTable<&'static str> {
+    match branch {
+        Branch::Town => &[],
+        Branch::Main => MAIN_MONSTERS,
+        Branch::Mines => MINES_MONSTERS,
+        Branch::Crypt => CRYPT_MONSTERS,
+        Branch::Abyss => ABYSS_MONSTERS,
+    }
+}
+
+fn item_spawns(branch: Branch) -> SpawnTable<Item> {
+    match branch {
+        Branch::Town | Branch::Main | Branch::Crypt => MAIN_ITEMS,
+        Branch::Mines => MINES_ITEMS,
+        Branch::Abyss => ABYSS_ITEMS,
+    }
+}
+
+/// the random monster table of a level: name and weight of each monster
+pub fn monster_table(level: LevelId) -> Vec<(&'static str, u32)> {
+    weights_at(monster_spawns(level.branch), level)
+}
+
+/// the random item table of a level
+pub fn item_table(level: LevelId) -> Vec<(Item, u32)> {
+    weights_at(item_spawns(level.branch), level)
+}
+
+/// every monster that may turn up in a branch, at any depth
+pub fn branch_monsters(branch: Branch) -> impl Iterator<Item = &'static str> {
+    monster_spawns(branch).iter().map(|&(name, _)| name)
+}
@@ -0,0 +1,315 @@
+use crate::{
+    fighter::{DamageSource, DamageType},
+    game::{ground_open, is_blocked, Game, PLAYER},
+    groundeffects::GroundKind,
+    item::{closest_monster, UseResult},
+    monsters::monster_by_name,
+    sight::Awareness,
+    tcoder::{Tcod, MAP_HEIGHT, MAP_WIDTH},
+    visibility::{hidden_from, in_sight, sees},
+};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use tcod::colors::{DARK_VIOLET, LIGHT_BLUE, LIGHT_CYAN, LIGHT_GREY, LIGHT_VIOLET, ORANGE, RED};
+
+const MAGIC_MISSILE_DAMAGE: i32 = 12;
+const MAGIC_MISSILE_RANGE: i32 = 6;
+const SPELL_HEAL_AMOUNT: i32 = 10;
+const BLINK_RANGE: i32 = 6;
+const BLINK_TRIES: i32 = 50;
+// a fireball burns everyone close to where it bursts, and leaves the ground
+// there burning for a few turns
+const FIREBALL_RANGE: f32 = 8.0;
+const FIREBALL_RADIUS: f32 = 1.5;
+const FIREBALL_DAMAGE: i32 = 10;
+const FIREBALL_FIRE_TURNS: i32 = 6;
+// a curse never leaves the player with less maximum health than this
+const CURSE_MIN_MAX_HP: i32 = 10;
+const CURSE_CHANCE: f32 = 0.3;
+// a summoner calls no more while this many of its summons are still close by
+const SUMMONED_MONSTER: &str = "giant rat";
+const SUMMON_LIMIT: usize = 4;
+const SUMMON_RANGE: f32 = 8.0;
+const SUMMON_CHANCE: f32 = 0.25;
+// under this percentage of its health a blinking monster flees the player's blows
+const BLINK_HURT_PERCENT: i32 = 50;
+
+/// A spell the player can cast from their mana pool, as often as it lasts.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Spell {
+    MagicMissile,
+    Heal,
+    Blink,
+    Fireball,
+}
+
+impl Spell {
+    pub fn name(self) -> &'static str {
+        match self {
+            Spell::MagicMissile => "magic missile",
+            Spell::Heal => "heal",
+            Spell::Blink => "blink",
+            Spell::Fireball => "fireball",
+        }
+    }
+
+    /// mana needed to cast the spell
+    pub fn cost(self) -> i32 {
+        match self {
+            Spell::MagicMissile => 4,
+            Spell::Heal => 6,
+            Spell::Blink => 5,
+            Spell::Fireball => 8,
+        }
+    }
+}
+
+/// A spell a monster casts on its turn instead of acting otherwise, whenever it
+/// is alerted to the player and the spell is of use.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum MonsterSpell {
+    /// drains this much of the player's maximum health from within range
+    Curse { drain: i32, range: f32 },
+    /// calls this many giant rats to its side
+    Summon(i32),
+    /// jumps away from the player once badly hurt
+    Blink,
+}
+
+/// the spell learned upon reaching the given character level, if any
+pub fn spell_learned_at(level: i32) -> Option<Spell> {
+    match level {
+        2 => Some(Spell::MagicMissile),
+        3 => Some(Spell::Heal),
+        4 => Some(Spell::Blink),
+        5 => Some(Spell::Fireball),
+        _ => None,
+    }
+}
+
+pub fn cast_spell(spell: Spell, tcod: &mut Tcod, game: &mut Game) -> UseResult {
+    let on_cast = match spell {
+        Spell::MagicMissile => cast_magic_missile,
+        Spell::Heal => cast_mending,
+        Spell::Blink => cast_blink,
+        Spell::Fireball => cast_fireball,
+    };
+    on_cast(tcod, game)
+}
+
+fn cast_magic_missile(tcod: &mut Tcod, game: &mut Game) -> UseResult {
+    // strike the closest enemy in range
+    let monster_id = closest_monster(tcod, &game.objects, MAGIC_MISSILE_RANGE);
+    if let Some(monster_id) = monster_id {
+        game.messages.add(
+            format!(
+                "A glowing missile hits the {} for {} hit points.",
+                game.objects[monster_id].name, MAGIC_MISSILE_DAMAGE
+            ),
+            LIGHT_BLUE,
+        );
+        game.damage(
+            monster_id,
+            MAGIC_MISSILE_DAMAGE,
+            DamageType::Physical,
+            DamageSource::Player,
+        );
+        UseResult::UsedUp
+    } else {
+        game.messages
+            .add("No enemy is close enough to strike.", RED);
+        UseResult::Cancelled
+    }
+}
+
+fn cast_mending(_tcod: &mut Tcod, game: &mut Game) -> UseResult {
+    if let Some(fighter) = game.objects[PLAYER].fighter {
+        if fighter.hp == fighter.max_hp {
+            game.messages.add("You are already at full health.", RED);
+            return UseResult::Cancelled;
+        }
+        game.messages
+            .add("A warm light knits your wounds together.", LIGHT_VIOLET);
+        game.heal(PLAYER, SPELL_HEAL_AMOUNT);
+        return UseResult::UsedUp;
+    }
+    UseResult::Cancelled
+}
+
+fn cast_blink(_tcod: &mut Tcod, game: &mut Game) -> UseResult {
+    if blink(game, PLAYER) {
+        game.messages.add(
+            "The world lurches and you find yourself elsewhere.",
+            LIGHT_BLUE,
+        );
+        return UseResult::UsedUp;
+    }
+    game.messages.add("The spell fizzles.", RED);
+    UseResult::Cancelled
+}
+
+/// make the caster jump to a random free tile nearby, outside of any vault,
+/// returning whether there was one to jump to
+fn blink(game: &mut Game, caster: usize) -> bool {
+    let (caster_x, caster_y) = game.objects[caster].pos();
+    for _ in 0..BLINK_TRIES {
+        let x = caster_x + rand::thread_rng().gen_range(-BLINK_RANGE, BLINK_RANGE + 1);
+        let y = caster_y + rand::thread_rng().gen_range(-BLINK_RANGE, BLINK_RANGE + 1);
+        let in_map = x >= 0 && y >= 0 && x < MAP_WIDTH && y < MAP_HEIGHT;
+        if in_map
+            && (x, y) != (caster_x, caster_y)
+            && game.objects[caster].distance(x, y) <= BLINK_RANGE as f32
+            && !game.map[x as usize][y as usize].vault
+            && !is_blocked(x, y, &game.map, &game.objects)
+        {
+            game.objects[caster].set_pos(x, y);
+            return true;
+        }
+    }
+    false
+}
+
+fn cast_fireball(tcod: &mut Tcod, game: &mut Game) -> UseResult {
+    game.messages.add(
+        "Left-click where to throw the fireball, or move the cursor there and press \
+         Enter. Escape cancels.",
+        LIGHT_CYAN,
+    );
+    let (x, y) = match game.target_tile(tcod, Some(FIREBALL_RANGE)) {
+        Some(tile) => tile,
+        None => return UseResult::Cancelled,
+    };
+    game.messages
+        .add("The fireball bursts, burning everything around it!", ORANGE);
+    for id in 0..game.objects.len() {
+        let object = &game.objects[id];
+        if object.alive && object.fighter.is_some() && object.distance(x, y) <= FIREBALL_RADIUS {
+            game.messages.add(
+                format!(
+                    "The {} gets burned for {} hit points.",
+                    object.name, FIREBALL_DAMAGE
+                ),
+                ORANGE,
+            );
+            game.damage(id, FIREBALL_DAMAGE, DamageType::Fire, DamageSource::Player);
+        }
+    }
+    let map = &game.map;
+    game.ground.place_area(
+        GroundKind::Fire,
+        (x, y),
+        FIREBALL_RADIUS,
+        FIREBALL_FIRE_TURNS,
+        |kind, x, y| ground_open(map, kind, x, y),
+    );
+    UseResult::UsedUp
+}
+
+/// Let the monster cast the spell if it is of use now, returning whether it did
+/// and so took its turn. Spells cast by a monster go unannounced unless the
+/// player sees it.
+pub fn cast_monster_spell(
+    spell: MonsterSpell,
+    caster: usize,
+    tcod: &Tcod,
+    game: &mut Game,
+) -> bool {
+    let seen = sees(&tcod.fov, &game.objects[PLAYER], &game.objects[caster]);
+    let name = game.objects[caster].name.clone();
+    match spell {
+        MonsterSpell::Curse { drain, range } => {
+            let (player, monster) = (&game.objects[PLAYER], &game.objects[caster]);
+            // the curse needs a clear line to the player, the same both ways
+            let in_reach = in_sight(&tcod.fov, monster.x, monster.y)
+                && player.distance_to(monster) <= range
+                && !hidden_from(player, monster);
+            let drains = matches!(player.fighter, Some(f) if f.max_hp > CURSE_MIN_MAX_HP);
+            if !in_reach || !drains || rand::random::<f32>() >= CURSE_CHANCE {
+                return false;
+            }
+            let fighter = game.objects[PLAYER].fighter.as_mut().unwrap();
+            let drained = drain.min(fighter.max_hp - CURSE_MIN_MAX_HP);
+            fighter.max_hp -= drained;
+            fighter.hp = fighter.hp.min(fighter.max_hp);
+            let subject = if seen {
+                format!("The {}", name)
+            } else {
+                "Something".into()
+            };
+            game.messages.add(
+                format!(
+                    "{} curses you, and your life withers! You lose {} maximum hit points.",
+                    subject, drained
+                ),
+                DARK_VIOLET,
+            );
+            true
+        }
+        MonsterSpell::Summon(count) => {
+            let monster = &game.objects[caster];
+            let summoned = game
+                .objects
+                .iter()
+                .filter(|object| {
+                    object.alive
+                        && object.name == SUMMONED_MONSTER
+                        && object.distance_to(monster) <= SUMMON_RANGE
+                })
+                .count();
+            if summoned >= SUMMON_LIMIT || rand::random::<f32>() >= SUMMON_CHANCE {
+                return false;
+            }
+            let arrived = summon(game, caster, count);
+            if arrived > 0 && seen {
+                game.messages.add(
+                    format!("The {} calls, and giant rats come swarming!", name),
+                    LIGHT_GREY,
+                );
+            }
+            arrived > 0
+        }
+        MonsterSpell::Blink => {
+            let (player, monster) = (&game.objects[PLAYER], &game.objects[caster]);
+            let hurt =
+                matches!(monster.fighter, Some(f) if f.hp * 100 < f.max_hp * BLINK_HURT_PERCENT);
+            if !hurt || player.distance_to(monster) >= 2.0 || !blink(game, caster) {
+                return false;
+            }
+            if seen {
+                game.messages
+                    .add(format!("The {} vanishes with a pop!", name), LIGHT_BLUE);
+            }
+            true
+        }
+    }
+}
+
+/// call up to `count` of the summoned monsters to the free tiles around the
+/// caster, already hunting the player, returning how many came. They are worth
+/// no experience, lest a summoner be farmed
+fn summon(game: &mut Game, caster: usize, count: i32) -> i32 {
+    let (caster_x, caster_y) = game.objects[caster].pos();
+    let mut arrived = 0;
+    for dx in -1..2 {
+        for dy in -1..2 {
+            let (x, y) = (caster_x + dx, caster_y + dy);
+            let in_map = x >= 0 && y >= 0 && x < MAP_WIDTH && y < MAP_HEIGHT;
+            if arrived == count
+                || !in_map
+                || game.map[x as usize][y as usize].terrain.is_perilous()
+                || is_blocked(x, y, &game.map, &game.objects)
+            {
+                continue;
+            }
+            let mut monster = monster_by_name(SUMMONED_MONSTER, x, y).unwrap();
+            game.difficulty.toughen(&mut monster);
+            if let Some(fighter) = monster.fighter.as_mut() {
+                fighter.xp = 0;
+            }
+            monster.sight.awareness = Awareness::Alerted;
+            game.objects.push(monster);
+            arrived += 1;
+        }
+    }
+    arrived
+}
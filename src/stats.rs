@@ -0,0 +1,106 @@
+use crate::{events::GameEvent, monsters::kind_of};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Bookkeeping for the level the player is currently on, graded on descent.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct LevelStats {
+    pub kills: i32,
+    pub items_found: i32,
+    pub turns: i32,
+    /// whether the level was already graded, it only is the first time it is left
+    /// downwards
+    #[serde(default)]
+    pub graded: bool,
+}
+
+impl LevelStats {
+    pub fn record(&mut self, event: &GameEvent) {
+        match event {
+            GameEvent::MonsterDied {
+                by_player: true, ..
+            } => self.kills += 1,
+            GameEvent::PickedUp(_) => self.items_found += 1,
+            _ => {}
+        }
+    }
+}
+
+/// Bookkeeping for the whole run, shown on the statistics screen and in the
+/// morgue file.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Stats {
+    /// how many of each kind of monster the player has killed, elites counting
+    /// towards their kind
+    pub kills: HashMap<String, i32>,
+    pub damage_dealt: i32,
+    pub damage_taken: i32,
+    pub items_used: i32,
+    pub steps: i32,
+    /// chasms fallen down, which aren't counted as taking the stairs
+    pub falls: i32,
+}
+
+impl Stats {
+    pub fn record(&mut self, event: &GameEvent) {
+        match *event {
+            GameEvent::Attack {
+                damage,
+                by_player,
+                to_player,
+                ..
+            }
+            | GameEvent::Hurt {
+                damage,
+                by_player,
+                to_player,
+                ..
+            } => {
+                if by_player {
+                    self.damage_dealt += damage;
+                }
+                if to_player {
+                    self.damage_taken += damage;
+                }
+            }
+            GameEvent::MonsterDied {
+                ref name,
+                by_player: true,
+                ..
+            } => *self.kills.entry(kind_of(name).into()).or_insert(0) += 1,
+            GameEvent::FellDown => self.falls += 1,
+            _ => {}
+        }
+    }
+
+    pub fn total_kills(&self) -> i32 {
+        self.kills.values().sum()
+    }
+
+    /// the monsters killed, the most often killed first
+    pub fn kill_list(&self) -> Vec<(&str, i32)> {
+        let mut kills: Vec<_> = self
+            .kills
+            .iter()
+            .map(|(name, &count)| (name.as_str(), count))
+            .collect();
+        kills.sort_by(|(name1, count1), (name2, count2)| count2.cmp(count1).then(name1.cmp(name2)));
+        kills
+    }
+
+    /// the run statistics, one per line, `turns` being how long the run has lasted
+    pub fn summary(&self, turns: i32) -> String {
+        format!(
+            "Turns taken: {}\nSteps walked: {}\nDamage dealt: {}\nDamage received: {}\n\
+             Items used: {}\nMonsters killed: {}\nChasms fallen down: {}",
+            turns,
+            self.steps,
+            self.damage_dealt,
+            self.damage_taken,
+            self.items_used,
+            self.total_kills(),
+            self.falls
+        )
+    }
+}
@@ -1,3 +1,8 @@
+use crate::{
+    options::Options,
+    tiles::{map_tiles, TILESET_COLUMNS, TILESET_FILE, TILESET_ROWS},
+};
+use std::path::Path;
 use tcod::console::{Offscreen, Root};
 use tcod::{
     input::{Key, Mouse},
@@ -5,25 +10,66 @@ use tcod::{
     FontLayout, FontType,
 };
 
-// actual size of the window
-pub const SCREEN_WIDTH: i32 = 80;
-pub const SCREEN_HEIGHT: i32 = 50;
+// size of the window unless the options ask for another
+pub const DEFAULT_SCREEN_WIDTH: i32 = 80;
+pub const DEFAULT_SCREEN_HEIGHT: i32 = 50;
+// the panel needs this many rows, the health bars and a few messages
+const MIN_PANEL_HEIGHT: i32 = 7;
 
 pub const BAR_WIDTH: i32 = 20;
-pub const PANEL_HEIGHT: i32 = 7;
-pub const PANEL_Y: i32 = SCREEN_HEIGHT - PANEL_HEIGHT;
 pub const INVENTORY_WIDTH: i32 = 50;
 pub const LEVEL_SCREEN_WIDTH: i32 = 40;
 pub const CHARACTER_SCREEN_WIDTH: i32 = 30;
 
-pub const MSG_X: i32 = BAR_WIDTH + 2;
-pub const MSG_WIDTH: i32 = SCREEN_WIDTH - BAR_WIDTH - 2;
-pub const MSG_HEIGHT: usize = PANEL_HEIGHT as usize - 1;
-
 // size of the map
 pub const MAP_WIDTH: i32 = 80;
 pub const MAP_HEIGHT: i32 = 43;
 
+/// Where the panel and the messages go in a window of a given size. The map
+/// keeps its size, a bigger window gives the panel more rows for messages and
+/// more room across for each of them.
+#[derive(Clone, Copy, Debug)]
+pub struct Layout {
+    pub screen_width: i32,
+    pub screen_height: i32,
+    pub panel_y: i32,
+    pub panel_height: i32,
+    pub msg_x: i32,
+    pub msg_width: i32,
+    pub msg_height: usize,
+}
+
+impl Layout {
+    /// the layout of a window of the given size, grown to fit the map and the
+    /// panel if it is too small for them
+    pub fn new(width: i32, height: i32) -> Layout {
+        let screen_width = width.max(MAP_WIDTH);
+        let screen_height = height.max(MAP_HEIGHT + MIN_PANEL_HEIGHT);
+        let panel_height = screen_height - MAP_HEIGHT;
+        Layout {
+            screen_width,
+            screen_height,
+            panel_y: MAP_HEIGHT,
+            panel_height,
+            msg_x: BAR_WIDTH + 2,
+            msg_width: screen_width - BAR_WIDTH - 2,
+            msg_height: panel_height as usize - 1,
+        }
+    }
+
+    /// the layout for the screen size in the options, (0, 0) filling the display
+    pub fn from_options(options: &Options) -> Layout {
+        match options.screen_size {
+            (0, 0) => {
+                let (width, height) = tcod::system::get_current_resolution();
+                let cell_size = options.font.available().cell_size();
+                Layout::new(width / cell_size, height / cell_size)
+            }
+            (width, height) => Layout::new(width, height),
+        }
+    }
+}
+
 pub struct Tcod {
     pub root: Root,
     pub con: Offscreen,
@@ -31,24 +77,70 @@ pub struct Tcod {
     pub fov: FovMap,
     pub key: Key,
     pub mouse: Mouse,
+    /// map position of the keyboard cursor while looking or targeting
+    pub cursor: Option<(i32, i32)>,
+    /// set while the player aims something at the cursor
+    pub aim: Option<Aim>,
+    pub scratch: Scratch,
+    pub options: Options,
+    /// whether the graphical tileset was loaded and objects are drawn as tiles
+    pub tiles: bool,
+    pub layout: Layout,
+}
+
+/// Buffers reused from frame to frame and turn to turn, sparing the allocator the
+/// short-lived temporaries of the hot paths.
+#[derive(Default)]
+pub struct Scratch {
+    /// indices of the objects to draw, in drawing order
+    pub draw_order: Vec<usize>,
+    /// the turns every decaying object rots away on
+    pub decay_turns: Vec<i32>,
+    /// names of the objects under the mouse
+    pub names: String,
+}
+
+/// What is being aimed, for drawing the path and reach of a shot.
+#[derive(Clone, Copy, Debug)]
+pub struct Aim {
+    pub max_range: Option<f32>,
 }
 
 impl Tcod {
-    pub fn new() -> Tcod {
-        let root = Root::initializer()
-            .font("arial10x10.png", FontLayout::Tcod)
+    pub fn new(options: Options) -> Tcod {
+        // tiles are only drawn if they are wanted and can be found
+        let tiles = options.tiles && Path::new(TILESET_FILE).exists();
+        let layout = Layout::from_options(&options);
+        let mut root = Root::initializer();
+        if tiles {
+            root.font(TILESET_FILE, FontLayout::Tcod)
+                .font_dimensions(TILESET_COLUMNS, TILESET_ROWS);
+        } else {
+            root.font(options.font.available().file(), FontLayout::Tcod);
+        }
+        let mut root = root
             .font_type(FontType::Greyscale)
-            .size(SCREEN_WIDTH, SCREEN_HEIGHT)
+            .size(layout.screen_width, layout.screen_height)
+            .fullscreen(options.fullscreen)
             .title("Rust/libtcod tutorial")
             .init();
+        if tiles {
+            map_tiles(&mut root);
+        }
 
         Tcod {
             root,
             con: Offscreen::new(MAP_WIDTH, MAP_HEIGHT),
-            panel: Offscreen::new(SCREEN_WIDTH, PANEL_HEIGHT),
+            panel: Offscreen::new(layout.screen_width, layout.panel_height),
             fov: FovMap::new(MAP_WIDTH, MAP_HEIGHT),
             key: Default::default(),
             mouse: Default::default(),
+            cursor: None,
+            aim: None,
+            scratch: Default::default(),
+            options,
+            tiles,
+            layout,
         }
     }
 }
@@ -0,0 +1,134 @@
+//! The look of a level: the colors its walls and floors are drawn in, and the
+//! glyph strewn over its floors. Each branch, and each band of depths of the main
+//! dungeon, is built in a theme of its own, which the palette the player picked
+//! then draws the map in.
+use serde::{Deserialize, Serialize};
+use tcod::colors::Color;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Theme {
+    /// the plain stone of the town and the upper dungeon
+    #[default]
+    Stone,
+    /// the cold blue caves of the mines
+    Ice,
+    /// the grey, dusty halls of the crypt
+    Bone,
+    /// the scorched red depths at the bottom of the dungeon and below
+    Hell,
+}
+
+/// The wall and floor colors of a theme, out of sight and in it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ThemeColors {
+    pub dark_wall: Color,
+    pub light_wall: Color,
+    pub dark_ground: Color,
+    pub light_ground: Color,
+}
+
+const STONE_COLORS: ThemeColors = ThemeColors {
+    dark_wall: Color { r: 0, g: 0, b: 100 },
+    light_wall: Color {
+        r: 130,
+        g: 110,
+        b: 50,
+    },
+    dark_ground: Color {
+        r: 50,
+        g: 50,
+        b: 150,
+    },
+    light_ground: Color {
+        r: 200,
+        g: 180,
+        b: 50,
+    },
+};
+
+const ICE_COLORS: ThemeColors = ThemeColors {
+    dark_wall: Color {
+        r: 10,
+        g: 40,
+        b: 90,
+    },
+    light_wall: Color {
+        r: 90,
+        g: 150,
+        b: 200,
+    },
+    dark_ground: Color {
+        r: 40,
+        g: 70,
+        b: 120,
+    },
+    light_ground: Color {
+        r: 180,
+        g: 220,
+        b: 240,
+    },
+};
+
+const BONE_COLORS: ThemeColors = ThemeColors {
+    dark_wall: Color {
+        r: 30,
+        g: 30,
+        b: 40,
+    },
+    light_wall: Color {
+        r: 110,
+        g: 105,
+        b: 95,
+    },
+    dark_ground: Color {
+        r: 55,
+        g: 55,
+        b: 70,
+    },
+    light_ground: Color {
+        r: 190,
+        g: 180,
+        b: 160,
+    },
+};
+
+const HELL_COLORS: ThemeColors = ThemeColors {
+    dark_wall: Color { r: 60, g: 0, b: 0 },
+    light_wall: Color {
+        r: 140,
+        g: 30,
+        b: 20,
+    },
+    dark_ground: Color {
+        r: 90,
+        g: 30,
+        b: 30,
+    },
+    light_ground: Color {
+        r: 210,
+        g: 110,
+        b: 60,
+    },
+};
+
+impl Theme {
+    pub fn colors(self) -> &'static ThemeColors {
+        match self {
+            Theme::Stone => &STONE_COLORS,
+            Theme::Ice => &ICE_COLORS,
+            Theme::Bone => &BONE_COLORS,
+            Theme::Hell => &HELL_COLORS,
+        }
+    }
+
+    /// the glyph drawn on the floors of the theme, telling the themes apart
+    /// without their colors, if any
+    pub fn floor_glyph(self) -> Option<char> {
+        match self {
+            Theme::Stone => None,
+            Theme::Ice => Some('.'),
+            Theme::Bone => Some(','),
+            Theme::Hell => Some('`'),
+        }
+    }
+}
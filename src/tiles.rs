@@ -0,0 +1,86 @@
+//! Graphical tiles, for whoever would rather not play in ASCII. The tiles come in
+//! an image font of their own, laid out like the regular font with the tiles in
+//! the rows below the ASCII characters. Each glyph that has a tile gets mapped to
+//! a character code past the ASCII range, and while tiles are on, objects are
+//! drawn with that code instead of their character. Glyphs without a tile,
+//! and the whole game when no tileset is found, stay ASCII.
+use tcod::console::Root;
+
+pub const TILESET_FILE: &str = "tiles.png";
+// the tileset's size in characters, the tiles filling the rows below the ASCII ones
+pub const TILESET_COLUMNS: i32 = 32;
+pub const TILESET_ROWS: i32 = 10;
+const ASCII_ROWS: i32 = 8;
+// the character code of the first tile
+const TILE_CODE_BASE: u32 = 256;
+
+// the tile of every glyph that has one, numbered from the start of the tile rows
+const TILES: &[(char, u32)] = &[
+    ('@', 0),
+    ('o', 1),
+    ('a', 2),
+    ('T', 3),
+    ('z', 4),
+    ('g', 5),
+    ('b', 6),
+    ('r', 7),
+    ('s', 8),
+    ('S', 9),
+    ('H', 10),
+    ('K', 11),
+    ('E', 12),
+    ('%', 13),
+    ('!', 14),
+    ('#', 15),
+    ('/', 16),
+    ('"', 17),
+    ('[', 18),
+    ('(', 19),
+    ('-', 20),
+    (')', 21),
+    ('{', 22),
+    ('$', 23),
+    ('&', 24),
+    (':', 25),
+    ('>', 26),
+    ('<', 27),
+    ('+', 28),
+    ('\'', 29),
+];
+
+/// tiles of their own for objects that share a glyph with others, numbered after
+/// the tiles of the glyphs
+pub const TILE_ORC_CHIEF: u32 = 30;
+
+/// the tile a glyph is drawn as, if it has one
+pub fn tile_index(glyph: char) -> Option<u32> {
+    TILES
+        .iter()
+        .find(|&&(tiled, _)| tiled == glyph)
+        .map(|&(_, index)| index)
+}
+
+/// the character to draw for a tile
+pub fn tile_char(index: u32) -> Option<char> {
+    std::char::from_u32(TILE_CODE_BASE + index)
+}
+
+/// the character to draw for a glyph, the code of its tile while tiles are on
+pub fn glyph(glyph: char, tiles: bool) -> char {
+    match tile_index(glyph).and_then(tile_char) {
+        Some(tile) if tiles => tile,
+        _ => glyph,
+    }
+}
+
+/// point the tiles' character codes at where the tiles are in the tileset
+pub fn map_tiles(root: &mut Root) {
+    let count = TILESET_COLUMNS * (TILESET_ROWS - ASCII_ROWS);
+    for index in 0..count {
+        root.map_ascii_code_to_font(
+            TILE_CODE_BASE as i32 + index,
+            index % TILESET_COLUMNS,
+            ASCII_ROWS + index / TILESET_COLUMNS,
+        );
+    }
+}
@@ -0,0 +1,67 @@
+//! The screen the player passes through on the way down the stairs: the level
+//! reached, how deep it lies and what it feels like, faded in from black. It is
+//! drawn straight on the root console once the new level is ready, and a key
+//! press cuts it short.
+use tcod::colors::{BLACK, LIGHT_CYAN, LIGHT_GREY, WHITE};
+use tcod::console::{Console, Root};
+use tcod::input::{self, Event};
+use tcod::{BackgroundFlag, TextAlignment};
+
+// frames to fade in over, and frames to hold the screen for after
+const FADE_FRAMES: i32 = 8;
+const HOLD_FRAMES: i32 = 24;
+
+/// show the screen for the level descended to, with the feelings it gives or
+/// the flavor line when there are none, fading in if `fade`
+pub fn descent_screen(
+    root: &mut Root,
+    level: &str,
+    depth: u32,
+    feelings: &[&str],
+    flavor: &str,
+    fade: bool,
+) {
+    let (x, y) = (root.width() / 2, root.height() / 2);
+    root.set_default_background(BLACK);
+    root.clear();
+    root.set_default_foreground(WHITE);
+    root.print_ex(x, y - 3, BackgroundFlag::None, TextAlignment::Center, level);
+    root.set_default_foreground(LIGHT_GREY);
+    let depth = format!("Depth {}", depth);
+    root.print_ex(x, y - 2, BackgroundFlag::None, TextAlignment::Center, depth);
+    root.set_default_foreground(LIGHT_CYAN);
+    let lines = if feelings.is_empty() {
+        vec![flavor]
+    } else {
+        feelings.to_vec()
+    };
+    for (row, line) in lines.iter().enumerate() {
+        let line_y = y + row as i32;
+        root.print_ex(
+            x,
+            line_y,
+            BackgroundFlag::None,
+            TextAlignment::Center,
+            *line,
+        );
+    }
+
+    let fade_frames = if fade { FADE_FRAMES } else { 0 };
+    for frame in 0..fade_frames + HOLD_FRAMES {
+        let amount = if frame < fade_frames {
+            255 * (frame + 1) / fade_frames
+        } else {
+            255
+        };
+        root.set_fade(amount as u8, BLACK);
+        root.flush();
+        let key = matches!(
+            input::check_for_event(input::KEY_PRESS),
+            Some((_, Event::Key(_)))
+        );
+        if key || root.window_closed() {
+            break;
+        }
+    }
+    root.set_fade(255, BLACK);
+}
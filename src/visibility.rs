@@ -0,0 +1,29 @@
+//! What the player sees, and what the monsters see of an invisible player. Every
+//! question of whether something is in sight goes through here, so that the
+//! invisible stay unseen everywhere alike: an invisible monster is only made
+//! out once it is right next to the player, or by a player who sees invisible,
+//! and an invisible player is only noticed by a monster they stand next to.
+use crate::{effects::EffectKind, object::Object};
+use tcod::map::Map as FovMap;
+
+// the invisible are sensed closer than this, a step away diagonals included
+const SENSE_DISTANCE: f32 = 2.0;
+
+/// whether the player sees the tile
+pub fn in_sight(fov: &FovMap, x: i32, y: i32) -> bool {
+    fov.is_in_fov(x, y)
+}
+
+/// whether the player sees the object on its tile
+pub fn sees(fov: &FovMap, player: &Object, object: &Object) -> bool {
+    in_sight(fov, object.x, object.y)
+        && (!object.is_invisible()
+            || object.is_player()
+            || player.has_effect(EffectKind::SeeInvisible)
+            || player.distance_to(object) < SENSE_DISTANCE)
+}
+
+/// whether an invisible player is too far off for the monster to notice
+pub fn hidden_from(player: &Object, monster: &Object) -> bool {
+    player.is_invisible() && player.distance_to(monster) >= SENSE_DISTANCE
+}
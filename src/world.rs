@@ -0,0 +1,328 @@
+//! The world the dungeon lies in. A town sits on top of the level graph, its
+//! stairs leading down to the first level of the dungeon, and the player can
+//! climb back up to it between dives to buy and sell, get healed, and leave
+//! what they don't want to carry in a stash chest.
+use crate::{
+    blessings::visit_altar,
+    dungeon::{Branch, LevelId},
+    game::{Game, INVENTORY_SIZE, PLAYER},
+    inventoryui::inventory_menu,
+    item::{identify, item_object, take_off, Item},
+    map::{create_room, Map, Tile},
+    mapbuilder::create_stairs,
+    menu,
+    menuui::Menu,
+    object::Object,
+    quests::visit_elder,
+    rect::Rect,
+    tcoder::{Tcod, INVENTORY_WIDTH, MAP_HEIGHT, MAP_WIDTH},
+};
+use serde::{Deserialize, Serialize};
+use tcod::colors::{LIGHT_BLUE, LIGHT_CYAN, LIGHT_GREEN, RED, SEPIA, YELLOW};
+use tcod::Color;
+
+pub const TOWN: LevelId = LevelId {
+    branch: Branch::Town,
+    depth: 0,
+};
+
+// the size of the town square, walls included
+const SQUARE_WIDTH: i32 = 30;
+const SQUARE_HEIGHT: i32 = 14;
+
+// what the shop always has in stock
+const SHOP_WARES: &[Item] = &[
+    Item::Heal,
+    Item::Torch,
+    Item::Lantern,
+    Item::Identify,
+    Item::LeatherArmor,
+    Item::ChainMail,
+    Item::Bow,
+    Item::Arrows,
+    Item::Dagger,
+    Item::Shield,
+];
+// the shop buys items back for this share of their price, and unknown ones for
+// a pittance
+const SELL_PERCENT: i32 = 50;
+const UNKNOWN_SELL_PRICE: i32 = 5;
+// what each affix adds to the price of an artifact
+const AFFIX_PRICE: i32 = 100;
+
+// gold the healer asks for each hit point restored
+const HEAL_PRICE_PER_HP: i32 = 2;
+
+/// What is done for the player who bumps into one of the townsfolk or the stash
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Service {
+    Shop,
+    Healer,
+    Stash,
+    /// the elder hands out quests and rewards them
+    Quests,
+    /// an altar in the dungeon, telling blessed from cursed and blessing for gold
+    Altar,
+}
+
+/// the town: a walled square with the townsfolk along its north side, the elder
+/// to the south and the stairs down in the middle
+pub fn build_town(objects: &mut Vec<Object>) -> Map {
+    let mut map = vec![vec![Tile::wall(); MAP_HEIGHT as usize]; MAP_WIDTH as usize];
+    // the player is the first object, remove everything else
+    objects.truncate(1);
+
+    let square = Rect::new(
+        (MAP_WIDTH - SQUARE_WIDTH) / 2,
+        (MAP_HEIGHT - SQUARE_HEIGHT) / 2,
+        SQUARE_WIDTH,
+        SQUARE_HEIGHT,
+    );
+    create_room(square, &mut map);
+    let (center_x, center_y) = square.center();
+    let north = square.y1 + 1;
+    objects.push(townsperson(
+        square.x1 + 3,
+        north,
+        '@',
+        "shopkeeper",
+        YELLOW,
+        Service::Shop,
+    ));
+    objects.push(townsperson(
+        center_x,
+        north,
+        '@',
+        "healer",
+        LIGHT_GREEN,
+        Service::Healer,
+    ));
+    objects.push(townsperson(
+        square.x2 - 3,
+        north,
+        '&',
+        "stash chest",
+        SEPIA,
+        Service::Stash,
+    ));
+    objects.push(townsperson(
+        center_x,
+        square.y2 - 1,
+        '@',
+        "elder",
+        LIGHT_CYAN,
+        Service::Quests,
+    ));
+    objects.push(create_stairs(
+        center_x,
+        center_y,
+        LevelId::new(Branch::Main, 1),
+        '>',
+        "stairs down",
+    ));
+    map
+}
+
+fn townsperson(x: i32, y: i32, char: char, name: &str, color: Color, service: Service) -> Object {
+    let mut object = Object::new(x, y, char, name, color, true);
+    object.always_visible = true;
+    object.service = Some(service);
+    object
+}
+
+/// what the shop asks for an item
+fn price(item: Item) -> i32 {
+    match item {
+        Item::Heal => 20,
+        Item::Torch => 15,
+        Item::Lantern => 60,
+        Item::LeatherArmor => 40,
+        Item::ChainMail => 100,
+        Item::PlateArmor => 250,
+        Item::Key => 5,
+        Item::Bow => 50,
+        Item::Arrows => 10,
+        Item::Dagger => 30,
+        Item::Shield => 60,
+        // the rest are worth four times what a sage asks to tell what they are
+        item => 4 * item.appraisal_fee(),
+    }
+}
+
+/// what the shop pays for an object, more for an artifact and only a pittance if
+/// it isn't identified
+fn sell_price(object: &Object) -> i32 {
+    match object.item {
+        Some(_) if object.unidentified => UNKNOWN_SELL_PRICE,
+        Some(item) => {
+            (price(item) + object.affixes.len() as i32 * AFFIX_PRICE) * SELL_PERCENT / 100
+        }
+        None => 0,
+    }
+}
+
+pub fn offer_service(service: Service, game: &mut Game, tcod: &mut Tcod) {
+    match service {
+        Service::Shop => visit_shop(game, tcod),
+        Service::Healer => visit_healer(game, tcod),
+        Service::Stash => open_stash(game, tcod),
+        Service::Quests => visit_elder(game, tcod),
+        Service::Altar => visit_altar(game, tcod),
+    }
+}
+
+fn visit_shop(game: &mut Game, tcod: &mut Tcod) {
+    let header = format!(
+        "\"Have a look around!\" the shopkeeper says. You have {} gold.\n",
+        game.gold
+    );
+    let choices = &["Buy", "Sell", "Leave"];
+    match menu(&header, choices, INVENTORY_WIDTH, &mut tcod.root) {
+        Some(0) => buy(game, tcod),
+        Some(1) => sell(game, tcod),
+        _ => {}
+    }
+}
+
+fn buy(game: &mut Game, tcod: &mut Tcod) {
+    // what the player can't afford is shown, but can't be picked
+    let header = format!("Buy what? You have {} gold.\n", game.gold);
+    let menu = SHOP_WARES
+        .iter()
+        .fold(Menu::new(&header, INVENTORY_WIDTH), |menu, &ware| {
+            let text = format!("{} ({} gold)", ware.name(), price(ware));
+            menu.entry_if(text, price(ware) <= game.gold)
+        });
+    let ware = match menu.show(&mut tcod.root) {
+        Some(index) => SHOP_WARES[index],
+        None => return,
+    };
+    let cost = price(ware);
+    if game.inventory.len() >= INVENTORY_SIZE {
+        game.messages
+            .add("Your inventory is full, you can't carry any more.", RED);
+    } else {
+        game.gold -= cost;
+        let mut object = item_object(ware, 0, 0, 0, &mut rand::thread_rng());
+        identify(&mut object);
+        game.messages.add(
+            format!("You buy a {} for {} gold.", object.name, cost),
+            LIGHT_BLUE,
+        );
+        game.inventory.push(object);
+    }
+}
+
+fn sell(game: &mut Game, tcod: &mut Tcod) {
+    let inventory_index =
+        inventory_menu(&game.inventory, "Which item do you sell?\n", &mut tcod.root);
+    let inventory_index = match inventory_index {
+        Some(index) => index,
+        None => return,
+    };
+    if game.inventory[inventory_index].item == Some(Item::Amulet) {
+        game.messages.add(
+            "\"All the gold in town couldn't buy that,\" the shopkeeper gasps.",
+            LIGHT_BLUE,
+        );
+        return;
+    }
+    let offer = sell_price(&game.inventory[inventory_index]);
+    let header = format!(
+        "\"I'll give you {} gold for your {}.\"\n",
+        offer, game.inventory[inventory_index].name
+    );
+    let choices = &["Sell it", "Keep it"];
+    if menu(&header, choices, INVENTORY_WIDTH, &mut tcod.root) != Some(0) {
+        return;
+    }
+    if !take_off(game, inventory_index) {
+        return;
+    }
+    let sold = game.inventory.remove(inventory_index);
+    game.gold += offer;
+    game.messages.add(
+        format!("You sell your {} for {} gold.", sold.name, offer),
+        LIGHT_BLUE,
+    );
+}
+
+fn visit_healer(game: &mut Game, tcod: &mut Tcod) {
+    let missing_hp = game.objects[PLAYER]
+        .fighter
+        .map_or(0, |fighter| fighter.max_hp - fighter.hp);
+    if missing_hp <= 0 {
+        game.messages.add(
+            "\"You look hale enough to me,\" the healer says.",
+            LIGHT_BLUE,
+        );
+        return;
+    }
+
+    let fee = missing_hp * HEAL_PRICE_PER_HP;
+    let header = format!(
+        "\"For {} gold I will mend your wounds.\" You have {} gold.\n",
+        fee, game.gold
+    );
+    let choices = &["Pay the healer", "Decline"];
+    if menu(&header, choices, INVENTORY_WIDTH, &mut tcod.root) != Some(0) {
+        game.messages
+            .add("The healer goes back to their herbs.", LIGHT_BLUE);
+    } else if game.gold < fee {
+        game.messages.add(
+            "\"Come back when your purse is heavier,\" the healer says.",
+            RED,
+        );
+    } else {
+        game.gold -= fee;
+        game.heal_fully(PLAYER);
+        game.messages
+            .add("The healer's salves close your wounds.", LIGHT_BLUE);
+    }
+}
+
+/// put items in the chest, or take them out, to be found when the player returns
+fn open_stash(game: &mut Game, tcod: &mut Tcod) {
+    let header = format!("The stash chest holds {} items.\n", game.stash.len());
+    let choices = &["Put an item in", "Take an item out", "Close the chest"];
+    match menu(&header, choices, INVENTORY_WIDTH, &mut tcod.root) {
+        Some(0) => {
+            let inventory_index = inventory_menu(
+                &game.inventory,
+                "Which item do you put in the chest?\n",
+                &mut tcod.root,
+            );
+            if let Some(inventory_index) = inventory_index.filter(|&id| take_off(game, id)) {
+                let object = game.inventory.remove(inventory_index);
+                game.messages.add(
+                    format!("You put your {} in the chest.", object.name),
+                    LIGHT_BLUE,
+                );
+                game.stash.push(object);
+            }
+        }
+        Some(1) if game.stash.is_empty() => {
+            game.messages.add("The chest is empty.", LIGHT_BLUE);
+        }
+        Some(1) if game.inventory.len() >= INVENTORY_SIZE => {
+            game.messages
+                .add("Your inventory is full, you can't carry any more.", RED);
+        }
+        Some(1) => {
+            let stash_index = inventory_menu(
+                &game.stash,
+                "Which item do you take out of the chest?\n",
+                &mut tcod.root,
+            );
+            if let Some(stash_index) = stash_index {
+                let object = game.stash.remove(stash_index);
+                game.messages.add(
+                    format!("You take the {} out of the chest.", object.name),
+                    LIGHT_BLUE,
+                );
+                game.inventory.push(object);
+            }
+        }
+        _ => {}
+    }
+}